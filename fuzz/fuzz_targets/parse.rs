@@ -0,0 +1,8 @@
+//! Feeds arbitrary bytes to the parser, which must return for any input without panicking or
+//! overflowing the stack. Run with `cargo fuzz run parse`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| lang::spec::fuzz_parse(data));
@@ -0,0 +1,14 @@
+use thiserror::Error;
+
+use crate::emit::Emit;
+
+/// Represents any error in choosing or running a backend.
+#[derive(Error, Debug)]
+pub enum BackendError {
+    #[error("no backend named `{0}` is registered")]
+    UnknownBackend(String),
+    #[error("the {backend} backend can not produce {emit:?} output")]
+    UnsupportedEmit { backend: &'static str, emit: Emit },
+    #[error("no backend can produce {0:?} output")]
+    NoBackendFor(Emit),
+}
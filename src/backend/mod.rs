@@ -0,0 +1,292 @@
+pub mod error;
+
+use std::path::PathBuf;
+
+use inkwell::{context::Context, module::Module as CodegenModule, targets::TargetMachine};
+
+use crate::{
+    backend::error::BackendError,
+    cgen::generate_c_module,
+    codegen::generate_codegen_program,
+    emit::{Emit, OptLevel, emit_llvm, target_machine},
+    interp::{error::InterpError, run},
+    load::link,
+    lower::{literals::lower_literals, mangle::mangle_program},
+    spec::ast::Module,
+};
+
+/// What a program does when it panics, such as when it reaches `unreachable()`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PanicStrategy {
+    /// Calls `abort`, like a C program would.
+    #[default]
+    Abort,
+    /// Executes a trap instruction, without relying on the C runtime.
+    Trap,
+    /// Prints what went wrong to stderr and exits with the given code.
+    Exit(i32),
+}
+
+/// The message a program panics with when it reaches `unreachable()`.
+pub const UNREACHABLE: &str = "entered unreachable code";
+
+/// Returns the message a program panics with when the condition of an `assert` is 0.
+pub fn assertion_failed(message: &str) -> String {
+    format!("assertion failed: {message}")
+}
+
+/// Options shared by every backend.
+#[derive(Clone, Debug, Default)]
+pub struct CodegenOptions {
+    /// Every artifact the backend should write, along with where to write it.
+    pub outputs: Vec<(Emit, PathBuf)>,
+    /// Every module that the compiled module imports, directly or not.
+    pub imports: Vec<Module>,
+    /// What the compiled program does when it panics.
+    pub panic: PanicStrategy,
+    /// The target triple that assembly and object files are generated for, or `None` for the
+    /// host.
+    pub target: Option<String>,
+    /// How much LLVM optimizes assembly and object files.
+    pub opt_level: OptLevel,
+    /// The arguments `main` is called with by backends that run the program, starting with the
+    /// name of the program.
+    pub args: Vec<String>,
+}
+
+/// What running a backend produced.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Artifact {
+    /// The backend wrote these files.
+    Files(Vec<PathBuf>),
+    /// The backend ran the program, which exited with this code.
+    Exited(i32),
+}
+
+/// Turns a checked module into something that runs. Every backend starts from the same module
+/// that sema has accepted.
+pub trait Backend {
+    /// The name the backend is selected by with `--backend`.
+    fn name(&self) -> &'static str;
+
+    /// Returns whether the backend can produce the given kind of artifact.
+    fn supports(&self, emit: Emit) -> bool;
+
+    fn compile(&self, module: &Module, opts: &CodegenOptions) -> anyhow::Result<Artifact>;
+}
+
+/// Checks that a backend can produce every requested artifact.
+fn check_supported(backend: &dyn Backend, opts: &CodegenOptions) -> Result<(), BackendError> {
+    match opts
+        .outputs
+        .iter()
+        .find(|(emit, _)| !backend.supports(*emit))
+    {
+        Some((emit, _)) => Err(BackendError::UnsupportedEmit {
+            backend: backend.name(),
+            emit: *emit,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Compiles through LLVM to bitcode, IR, assembly or object files.
+pub struct LlvmBackend;
+
+impl Backend for LlvmBackend {
+    fn name(&self) -> &'static str {
+        "llvm"
+    }
+
+    fn supports(&self, emit: Emit) -> bool {
+        emit.needs_llvm()
+    }
+
+    fn compile(&self, module: &Module, opts: &CodegenOptions) -> anyhow::Result<Artifact> {
+        check_supported(self, opts)?;
+
+        with_llvm_module(module, opts, |codegen_module, machine| {
+            for (emit, path) in &opts.outputs {
+                emit_llvm(codegen_module, Some(machine), *emit, path)?;
+            }
+
+            Ok(Artifact::Files(
+                opts.outputs.iter().map(|(_, path)| path.clone()).collect(),
+            ))
+        })
+    }
+}
+
+/// Generates the LLVM module of a program for the target of `opts`, and hands it to `f` along
+/// with the machine of that target.
+fn with_llvm_module<T>(
+    module: &Module,
+    opts: &CodegenOptions,
+    f: impl FnOnce(&CodegenModule<'_>, &TargetMachine) -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    // The target decides how wide `usize` and `isize` are, so it is set up before any code is
+    // generated, and the module is laid out for it so that every artifact agrees with it.
+    let machine = target_machine(opts.target.as_deref(), opts.opt_level)?;
+    let context = Context::create();
+    let codegen_module = generate_codegen_program(
+        &context,
+        &machine.get_target_data(),
+        module,
+        &opts.imports,
+        opts.panic,
+    )?;
+
+    codegen_module.set_triple(&machine.get_triple());
+
+    f(&codegen_module, &machine)
+}
+
+/// Returns the LLVM IR of a program, as the LLVM backend would write it with `--emit ir`.
+pub fn generate_llvm_ir(module: &Module, opts: &CodegenOptions) -> anyhow::Result<String> {
+    with_llvm_module(module, opts, |codegen_module, _| {
+        Ok(codegen_module.print_to_string().to_string())
+    })
+}
+
+/// Lowers to portable C99 source.
+pub struct CBackend;
+
+impl Backend for CBackend {
+    fn name(&self) -> &'static str {
+        "c"
+    }
+
+    fn supports(&self, emit: Emit) -> bool {
+        emit == Emit::C
+    }
+
+    fn compile(&self, module: &Module, opts: &CodegenOptions) -> anyhow::Result<Artifact> {
+        check_supported(self, opts)?;
+
+        let (module, imports) = mangle_program(module, &opts.imports);
+        let source = generate_c_module(&link(&module, &imports), opts.panic)?;
+
+        for (_, path) in &opts.outputs {
+            std::fs::write(path, &source)?;
+        }
+
+        Ok(Artifact::Files(
+            opts.outputs.iter().map(|(_, path)| path.clone()).collect(),
+        ))
+    }
+}
+
+/// Runs the program right away with the interpreter, writing nothing.
+pub struct InterpBackend;
+
+impl Backend for InterpBackend {
+    fn name(&self) -> &'static str {
+        "interp"
+    }
+
+    fn supports(&self, _: Emit) -> bool {
+        false
+    }
+
+    fn compile(&self, module: &Module, opts: &CodegenOptions) -> anyhow::Result<Artifact> {
+        // Functions are mangled like they are for the other backends, so that functions of the
+        // same name in different modules are told apart the same way.
+        let (module, imports) = mangle_program(module, &opts.imports);
+        let result = run(
+            &lower_literals(&link(&module, &imports), &[]),
+            std::io::stdout().lock(),
+            opts.panic,
+            &opts.args,
+        );
+
+        let code = match result {
+            Ok(value) => value.bits().unwrap_or_default() as i32,
+            Err(InterpError::Panicked { message, code }) => {
+                eprintln!("panicked: {message}");
+                code
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        Ok(Artifact::Exited(code))
+    }
+}
+
+/// Returns every registered backend. When no backend is chosen explicitly, each artifact is
+/// produced by the first backend in this list that supports it.
+pub fn backends() -> Vec<Box<dyn Backend>> {
+    vec![
+        Box::new(LlvmBackend),
+        Box::new(CBackend),
+        Box::new(InterpBackend),
+    ]
+}
+
+/// Looks a registered backend up by name.
+pub fn find_backend(name: &str) -> Result<Box<dyn Backend>, BackendError> {
+    backends()
+        .into_iter()
+        .find(|backend| backend.name() == name)
+        .ok_or_else(|| BackendError::UnknownBackend(name.to_string()))
+}
+
+/// A backend along with the artifacts it was asked to produce.
+pub type Assignment = (Box<dyn Backend>, CodegenOptions);
+
+/// Splits the requested artifacts between the registered backends, keeping the order in which
+/// backends were registered. Every backend is given the rest of `opts`, such as the imported
+/// modules, as it is.
+pub fn assign_backends(
+    outputs: Vec<(Emit, PathBuf)>,
+    opts: CodegenOptions,
+) -> Result<Vec<Assignment>, BackendError> {
+    let mut assigned: Vec<Assignment> = backends()
+        .into_iter()
+        .map(|backend| {
+            let opts = CodegenOptions {
+                outputs: vec![],
+                ..opts.clone()
+            };
+
+            (backend, opts)
+        })
+        .collect();
+
+    for (emit, path) in outputs {
+        let (_, opts) = assigned
+            .iter_mut()
+            .find(|(backend, _)| backend.supports(emit))
+            .ok_or(BackendError::NoBackendFor(emit))?;
+
+        opts.outputs.push((emit, path));
+    }
+
+    assigned.retain(|(_, opts)| !opts.outputs.is_empty());
+
+    Ok(assigned)
+}
+
+#[test]
+fn artifacts_are_assigned_to_the_first_backend_supporting_them() {
+    let assigned = assign_backends(
+        vec![
+            (Emit::C, PathBuf::from("main.c")),
+            (Emit::Obj, PathBuf::from("main.o")),
+        ],
+        CodegenOptions::default(),
+    )
+    .unwrap();
+
+    let names: Vec<_> = assigned
+        .iter()
+        .map(|(backend, opts)| (backend.name(), opts.outputs.len()))
+        .collect();
+
+    assert_eq!(names, vec![("llvm", 1), ("c", 1)]);
+}
+
+#[test]
+fn backends_are_found_by_name() {
+    assert_eq!(find_backend("interp").unwrap().name(), "interp");
+    assert!(find_backend("wasm").is_err());
+}
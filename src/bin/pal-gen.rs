@@ -0,0 +1,45 @@
+use clap::Parser;
+
+use lang::spec::generate::{Config, Generator};
+
+/// Generates random, valid pal programs for benchmarks, differential testing and fuzzing.
+#[derive(Parser, Debug)]
+struct Args {
+    /// The seed to generate from. The same seed and options always give the same program.
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// The amount of functions to generate, not counting `main`.
+    #[arg(long, default_value_t = 4)]
+    functions: usize,
+
+    /// The largest amount of statements in any block.
+    #[arg(long, default_value_t = 4)]
+    statements: usize,
+
+    /// How deep blocks, loops and calls may be nested.
+    #[arg(long, default_value_t = 2)]
+    depth: usize,
+
+    /// Do not generate `for` loops.
+    #[arg(long)]
+    no_loops: bool,
+
+    /// Do not generate calls between functions.
+    #[arg(long)]
+    no_calls: bool,
+}
+
+fn main() {
+    let args = Args::parse();
+
+    let config = Config {
+        functions: args.functions,
+        statements: args.statements,
+        depth: args.depth,
+        loops: !args.no_loops,
+        calls: !args.no_calls,
+    };
+
+    print!("{}", Generator::new(args.seed, config).module("main"));
+}
@@ -0,0 +1,12 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+/// Represents any error in reading a C header to generate bindings from.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum BindgenError {
+    #[error("failed to read {}: {message}", .path.display())]
+    Read { path: PathBuf, message: String },
+    #[error("failed to preprocess {} with `cc -E`: {message}", .path.display())]
+    Preprocess { path: PathBuf, message: String },
+}
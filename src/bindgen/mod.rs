@@ -0,0 +1,756 @@
+pub mod error;
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    process::Command,
+};
+
+use crate::{
+    bindgen::error::BindgenError,
+    runtime,
+    spec::{
+        KEYWORDS,
+        ast::{Abi, Item, Symbol, Type, Visibility},
+    },
+};
+
+/// The integer types of `stdint.h` and `stddef.h`, along with the type of pal that is passed the
+/// same way, assuming a target where `long` is as wide as a pointer. Signed integers are given the
+/// unsigned type of their width, except those as wide as a pointer, which are `isize`.
+const C_INTEGERS: [(&str, &str); 14] = [
+    ("int8_t", "u8"),
+    ("uint8_t", "u8"),
+    ("int16_t", "u16"),
+    ("uint16_t", "u16"),
+    ("int32_t", "u32"),
+    ("uint32_t", "u32"),
+    ("int64_t", "u64"),
+    ("uint64_t", "u64"),
+    ("size_t", "usize"),
+    ("uintptr_t", "usize"),
+    ("ssize_t", "isize"),
+    ("intptr_t", "isize"),
+    ("ptrdiff_t", "isize"),
+    ("off_t", "isize"),
+];
+
+/// Words that change nothing about how a declaration is passed, and are skipped.
+const QUALIFIERS: [&str; 16] = [
+    "extern",
+    "const",
+    "volatile",
+    "restrict",
+    "register",
+    "inline",
+    "__const",
+    "__restrict",
+    "__restrict__",
+    "__inline",
+    "__inline__",
+    "__extension__",
+    "__THROW",
+    "__wur",
+    "_Noreturn",
+    "noreturn",
+];
+
+/// Words that are followed by arguments in parentheses that say nothing about how a declaration
+/// is passed, such as `__attribute__((nonnull))`, and are skipped along with them.
+const ATTRIBUTES: [&str; 7] = [
+    "__attribute__",
+    "__attribute",
+    "__declspec",
+    "__asm__",
+    "__asm",
+    "asm",
+    "__nonnull",
+];
+
+/// A token of C, as far as declarations need them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Identifier(String),
+    Punctuation(char),
+    /// The `...` of a variadic function.
+    Ellipsis,
+    /// A string literal, without its quotes or any escapes resolved.
+    Str(String),
+    /// Any other literal, such as a number.
+    Literal,
+    /// Everything between braces, such as the fields of a struct, which declarations skip.
+    Braced,
+}
+
+/// A type of C, as it is being built from a declaration.
+#[derive(Clone, Debug, PartialEq)]
+enum CType {
+    Void,
+    Pal(Type),
+    /// A type pal has nothing passed the same way as, by how C spells it.
+    Foreign(String),
+    /// A function, taking arguments, by name when they have one, and returning a type. Functions
+    /// are only ever passed by pointer.
+    Function(Vec<(Option<String>, Type)>, Type, bool),
+}
+
+/// What a declarator does to the type it is applied to.
+#[derive(Clone, Debug, PartialEq)]
+enum Op {
+    Pointer,
+    /// An array, which is passed as a pointer to its first element.
+    Array,
+    /// A function, taking each parameter, by name and type, and any number of arguments after them
+    /// when it is variadic.
+    Function(Vec<(Option<String>, CType, Vec<Op>)>, bool),
+}
+
+/// Splits C source into tokens, skipping comments and preprocessor directives.
+fn tokenize(source: &str) -> Vec<Token> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = vec![];
+    let mut line_start = true;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        match c {
+            '\n' => {
+                line_start = true;
+                i += 1;
+                continue;
+            }
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            // Directives run up to the end of the line, unless it ends in `\`.
+            '#' if line_start => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += if chars[i] == '\\' { 2 } else { 1 };
+                }
+            }
+            '/' if next == Some('*') => {
+                i += 2;
+
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    i += 1;
+                }
+
+                i += 2;
+            }
+            '/' if next == Some('/') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '"' | '\'' => {
+                let start = i + 1;
+                i += 1;
+
+                while i < chars.len() && chars[i] != c {
+                    i += if chars[i] == '\\' { 2 } else { 1 };
+                }
+
+                let end = i.min(chars.len());
+                i += 1;
+
+                tokens.push(match c {
+                    '"' => Token::Str(chars[start..end].iter().collect()),
+                    _ => Token::Literal,
+                });
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let start = i;
+
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+
+                tokens.push(Token::Identifier(chars[start..i].iter().collect()));
+            }
+            c if c.is_ascii_digit() => {
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '.') {
+                    i += 1;
+                }
+
+                tokens.push(Token::Literal);
+            }
+            '.' if next == Some('.') && chars.get(i + 2) == Some(&'.') => {
+                i += 3;
+                tokens.push(Token::Ellipsis);
+            }
+            c => {
+                i += 1;
+                tokens.push(Token::Punctuation(c));
+            }
+        }
+
+        line_start = false;
+    }
+
+    tokens
+}
+
+/// Splits tokens into top-level declarations, each of which ends with a `;`. Bodies in braces
+/// become a single [`Token::Braced`], and definitions of functions are dropped whole, as there
+/// is nothing to link against in a header. The braces of `extern "C" { ... }` are skipped.
+fn declarations(tokens: &[Token]) -> Vec<Vec<Token>> {
+    let mut declarations = vec![];
+    let mut current = vec![];
+    let mut i = 0;
+
+    while i < tokens.len() {
+        match &tokens[i] {
+            Token::Punctuation(';') => {
+                if !current.is_empty() {
+                    declarations.push(std::mem::take(&mut current));
+                }
+            }
+            Token::Punctuation('{') if matches!(&current[..], [Token::Identifier(word), Token::Str(_)] if word == "extern") =>
+            {
+                current.clear();
+            }
+            Token::Punctuation('{') => {
+                let mut depth = 0;
+
+                while i < tokens.len() {
+                    match tokens[i] {
+                        Token::Punctuation('{') => depth += 1,
+                        Token::Punctuation('}') => depth -= 1,
+                        _ => {}
+                    }
+
+                    if depth == 0 {
+                        break;
+                    }
+
+                    i += 1;
+                }
+
+                match current.last() {
+                    Some(Token::Punctuation(')')) => current.clear(),
+                    _ => current.push(Token::Braced),
+                }
+            }
+            // The end of an `extern "C"` block.
+            Token::Punctuation('}') => {}
+            token => current.push(token.clone()),
+        }
+
+        i += 1;
+    }
+
+    declarations
+}
+
+/// Drops the qualifiers and attributes of a declaration, see [`QUALIFIERS`] and [`ATTRIBUTES`],
+/// returning what is left, along with whether any of them said the function never returns.
+fn clean(declaration: &[Token]) -> (Vec<Token>, bool) {
+    let mut cleaned = vec![];
+    let mut diverges = false;
+    let mut i = 0;
+
+    while i < declaration.len() {
+        match &declaration[i] {
+            Token::Identifier(word) if ATTRIBUTES.contains(&&word[..]) => {
+                i += 1;
+
+                if declaration.get(i) == Some(&Token::Punctuation('(')) {
+                    let mut depth = 0;
+
+                    while i < declaration.len() {
+                        match &declaration[i] {
+                            Token::Punctuation('(') => depth += 1,
+                            Token::Punctuation(')') => depth -= 1,
+                            Token::Identifier(word) if word.contains("noreturn") => {
+                                diverges = true;
+                            }
+                            _ => {}
+                        }
+
+                        i += 1;
+
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                }
+
+                continue;
+            }
+            Token::Identifier(word) if QUALIFIERS.contains(&&word[..]) => {
+                diverges |= word.contains("oreturn");
+            }
+            token => cleaned.push(token.clone()),
+        }
+
+        i += 1;
+    }
+
+    (cleaned, diverges)
+}
+
+/// Reads the types of declarations out of their tokens.
+struct Parser<'t> {
+    tokens: &'t [Token],
+    position: usize,
+    /// The type every `typedef` seen so far names.
+    typedefs: &'t HashMap<String, CType>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn identifier(&self) -> Option<&str> {
+        match self.peek() {
+            Some(Token::Identifier(word)) => Some(word),
+            _ => None,
+        }
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        let eaten = self.peek() == Some(token);
+        self.position += eaten as usize;
+        eaten
+    }
+
+    /// Reads the specifiers a declaration starts with, such as `unsigned long` or `struct tm`,
+    /// and returns the type they spell.
+    fn specifiers(&mut self) -> CType {
+        let mut words = vec![];
+        let mut named = None;
+
+        while let Some(word) = self.identifier().map(str::to_string) {
+            match &word[..] {
+                "void" | "char" | "short" | "int" | "long" | "signed" | "unsigned" | "float"
+                | "double" | "_Bool" | "bool" => words.push(word),
+                "struct" | "union" | "enum" => {
+                    self.position += 1;
+
+                    let tag = match self.identifier() {
+                        Some(tag) => format!("{word} {tag}"),
+                        None => word.clone(),
+                    };
+
+                    self.position += self.identifier().is_some() as usize;
+                    self.eat(&Token::Braced);
+
+                    named = Some(match &word[..] {
+                        "enum" => CType::Pal(atomic("u32")),
+                        _ => CType::Foreign(tag),
+                    });
+                    continue;
+                }
+                // The first word that is not a keyword names a type, and any after it name what
+                // is declared.
+                _ if words.is_empty() && named.is_none() => {
+                    named = Some(self.typedef(&word));
+                }
+                _ => break,
+            }
+
+            self.position += 1;
+        }
+
+        if let Some(named) = named {
+            return named;
+        }
+
+        let has = |name: &str| words.iter().any(|word| word == name);
+        let longs = words.iter().filter(|word| *word == "long").count();
+
+        if has("void") {
+            CType::Void
+        } else if has("float") || has("double") {
+            CType::Foreign(words.join(" "))
+        } else if has("_Bool") || has("bool") || (has("char") && has("unsigned")) {
+            CType::Pal(atomic("u8"))
+        } else if has("char") {
+            CType::Pal(atomic("char"))
+        } else if has("short") {
+            CType::Pal(atomic("u16"))
+        } else if longs >= 2 {
+            CType::Pal(atomic("u64"))
+        } else if longs == 1 && has("unsigned") {
+            CType::Pal(atomic("usize"))
+        } else if longs == 1 {
+            CType::Pal(atomic("isize"))
+        } else {
+            CType::Pal(atomic("u32"))
+        }
+    }
+
+    /// Returns the type a name declared with `typedef` stands for.
+    fn typedef(&self, name: &str) -> CType {
+        if let Some(ctype) = self.typedefs.get(name) {
+            return ctype.clone();
+        }
+
+        match C_INTEGERS.iter().find(|(c, _)| *c == name) {
+            Some((_, pal)) => CType::Pal(atomic(pal)),
+            None => CType::Foreign(name.to_string()),
+        }
+    }
+
+    /// Reads a declarator, such as `*name`, `(*name)(int)` or `name[4]`, along with the operations
+    /// it applies to the type of its specifiers, in the order they are applied. The name is `None`
+    /// for an abstract declarator, such as those of unnamed parameters.
+    fn declarator(&mut self) -> (Option<String>, Vec<Op>) {
+        let mut pointers = vec![];
+
+        while self.eat(&Token::Punctuation('*')) {
+            pointers.push(Op::Pointer);
+        }
+
+        let (name, inner) = match self.peek() {
+            Some(Token::Identifier(name)) => {
+                let name = name.clone();
+                self.position += 1;
+                (Some(name), vec![])
+            }
+            // A parenthesized declarator, unless the parentheses hold the parameters of an
+            // abstract one.
+            Some(Token::Punctuation('('))
+                if matches!(
+                    self.tokens.get(self.position + 1),
+                    Some(Token::Punctuation('*' | '('))
+                ) =>
+            {
+                self.position += 1;
+                let declarator = self.declarator();
+                self.eat(&Token::Punctuation(')'));
+                declarator
+            }
+            _ => (None, vec![]),
+        };
+
+        let mut suffixes = vec![];
+
+        loop {
+            if self.eat(&Token::Punctuation('[')) {
+                while !matches!(self.peek(), Some(Token::Punctuation(']')) | None) {
+                    self.position += 1;
+                }
+
+                self.eat(&Token::Punctuation(']'));
+                suffixes.push(Op::Array);
+            } else if self.eat(&Token::Punctuation('(')) {
+                suffixes.push(self.parameters());
+            } else {
+                break;
+            }
+        }
+
+        suffixes.reverse();
+
+        let ops = pointers.into_iter().chain(suffixes).chain(inner).collect();
+
+        (name, ops)
+    }
+
+    /// Reads the parameters of a function, after its `(`, up to and including its `)`.
+    fn parameters(&mut self) -> Op {
+        let mut parameters = vec![];
+        let mut variadic = false;
+
+        while !matches!(self.peek(), Some(Token::Punctuation(')')) | None) {
+            if self.eat(&Token::Ellipsis) {
+                variadic = true;
+            } else {
+                let ctype = self.specifiers();
+                let (name, ops) = self.declarator();
+                parameters.push((name, ctype, ops));
+            }
+
+            if !self.eat(&Token::Punctuation(',')) {
+                break;
+            }
+        }
+
+        self.eat(&Token::Punctuation(')'));
+
+        // `(void)` is how C writes that a function takes no arguments.
+        if let [(None, CType::Void, ops)] = &parameters[..]
+            && ops.is_empty()
+        {
+            parameters.clear();
+        }
+
+        Op::Function(parameters, variadic)
+    }
+}
+
+fn atomic(name: &str) -> Type {
+    Type::Atomic(Symbol::from(name))
+}
+
+/// Applies the operations of a declarator to a type, see [`Parser::declarator`]. Fails with why
+/// the type has no equivalent in pal.
+fn apply(ctype: CType, ops: &[Op]) -> Result<CType, String> {
+    ops.iter().try_fold(ctype, |ctype, op| match op {
+        Op::Pointer | Op::Array => Ok(match ctype {
+            CType::Pal(typ) => CType::Pal(Type::Pointer(Box::new(typ))),
+            // Pointers to anything pal has no equivalent of are taken as pointers to bytes.
+            CType::Void | CType::Foreign(_) => CType::Pal(Type::Pointer(Box::new(atomic("char")))),
+            CType::Function(arguments, returns, _) => CType::Pal(Type::Function(
+                arguments.into_iter().map(|(_, typ)| typ).collect(),
+                Box::new(returns),
+            )),
+        }),
+        Op::Function(parameters, variadic) => {
+            // Functions that return nothing are taken to return a `u32`, which is ignored.
+            let returns = match ctype {
+                CType::Void => atomic("u32"),
+                CType::Pal(typ) => typ,
+                CType::Foreign(name) => return Err(format!("returns `{name}`")),
+                CType::Function(..) => return Err("returns a function".to_string()),
+            };
+
+            let arguments = parameters
+                .iter()
+                .map(|(name, ctype, ops)| {
+                    let typ = match apply(ctype.clone(), ops)? {
+                        CType::Pal(typ) => typ,
+                        // A parameter of a function type is a pointer to such a function.
+                        function @ CType::Function(..) => match apply(function, &[Op::Pointer])? {
+                            CType::Pal(typ) => typ,
+                            _ => unreachable!("pointers to functions are types of pal"),
+                        },
+                        CType::Void => return Err("takes `void`".to_string()),
+                        CType::Foreign(name) => return Err(format!("takes `{name}`")),
+                    };
+
+                    Ok((name.clone(), typ))
+                })
+                .collect::<Result<_, String>>()?;
+
+            Ok(CType::Function(arguments, returns, *variadic))
+        }
+    })
+}
+
+/// Returns a name that can be declared in pal for a name of C, which is the name itself unless
+/// it is a keyword of pal.
+fn pal_name(name: &str) -> String {
+    match KEYWORDS.contains(&name) {
+        true => format!("{name}_"),
+        false => name.to_string(),
+    }
+}
+
+/// Generates the `ext fn` item of every function a C header declares, as pal source. Types are
+/// mapped to the type of pal that is passed the same way, see [`C_INTEGERS`], and pointers to
+/// anything pal has no equivalent of are `*char`. Functions that return nothing return a `u32`
+/// in pal, and those marked as never returning return `!`.
+///
+/// Variadic functions are declared with their fixed arguments alone. Functions that pal cannot
+/// declare, such as those taking a `double` or a struct by value, or whose name is a keyword of
+/// pal, are left out, with a comment saying why. Only declarations are read, so the header
+/// should be preprocessed first when it relies on macros, see [`read_header`].
+pub fn generate_bindings(header: &str) -> String {
+    let mut typedefs = HashMap::new();
+    let mut declared = HashSet::new();
+    let mut out = String::new();
+
+    for declaration in declarations(&tokenize(header)) {
+        let (tokens, diverges) = clean(&declaration);
+
+        let is_typedef = tokens.first() == Some(&Token::Identifier("typedef".to_string()));
+        let is_static = tokens.contains(&Token::Identifier("static".to_string()));
+
+        let mut parser = Parser {
+            tokens: &tokens[is_typedef as usize..],
+            position: 0,
+            typedefs: &typedefs,
+        };
+        let ctype = parser.specifiers();
+        let (name, ops) = parser.declarator();
+
+        let Some(name) = name else {
+            continue;
+        };
+
+        let function = match (is_typedef, is_static, ops.last()) {
+            (true, ..) => {
+                // Foreign types are spelled by their typedef, as the struct may have no tag.
+                let typ = match apply(ctype, &ops) {
+                    Ok(CType::Foreign(_)) | Err(_) => CType::Foreign(name.clone()),
+                    Ok(typ) => typ,
+                };
+                typedefs.insert(name, typ);
+                continue;
+            }
+            // Static functions have nothing to link against, and pal has no external variables.
+            (_, true, _) | (_, _, None | Some(Op::Pointer | Op::Array)) => continue,
+            (false, false, Some(Op::Function(..))) => apply(ctype, &ops),
+        };
+
+        if !declared.insert(name.clone()) {
+            continue;
+        }
+
+        if KEYWORDS.contains(&&name[..]) || runtime::is_runtime(&name) {
+            out.push_str(&format!(
+                "// `{name}` is not declared, as pal reserves its name.\n"
+            ));
+            continue;
+        }
+
+        let (arguments, returns, variadic) = match function {
+            Ok(CType::Function(arguments, returns, variadic)) => (arguments, returns, variadic),
+            Ok(_) => continue,
+            Err(reason) => {
+                out.push_str(&format!("// `{name}` is not declared, as it {reason}.\n"));
+                continue;
+            }
+        };
+
+        let arguments = arguments
+            .into_iter()
+            .enumerate()
+            .map(|(index, (arg, typ))| {
+                let arg = arg.map_or_else(|| format!("arg{index}"), |arg| pal_name(&arg));
+                (Symbol::from(arg), typ.into())
+            })
+            .collect();
+        let returns = match diverges {
+            true => Type::Never,
+            false => returns,
+        };
+
+        let item = Item::ExternFunctionDefinition(
+            Symbol::from(name),
+            arguments,
+            returns.into(),
+            Abi::C,
+            vec![],
+            Visibility::Private,
+        );
+
+        match variadic {
+            true => out.push_str(&format!("{item} // Takes more arguments in C.\n")),
+            false => out.push_str(&format!("{item}\n")),
+        }
+    }
+
+    out
+}
+
+/// Reads a C header to generate bindings from. When `preprocess` is set, the header is run
+/// through `cc -E` first, so that its macros are expanded and the types of the headers it
+/// includes are known, and only what the header itself declares is kept.
+pub fn read_header(path: &Path, preprocess: bool) -> Result<String, BindgenError> {
+    if !preprocess {
+        return std::fs::read_to_string(path).map_err(|error| BindgenError::Read {
+            path: path.to_path_buf(),
+            message: error.to_string(),
+        });
+    }
+
+    let failed = |message: String| BindgenError::Preprocess {
+        path: path.to_path_buf(),
+        message,
+    };
+
+    let output = Command::new("cc")
+        .arg("-E")
+        .arg(path)
+        .output()
+        .map_err(|error| failed(error.to_string()))?;
+
+    if !output.status.success() {
+        return Err(failed(String::from_utf8_lossy(&output.stderr).to_string()));
+    }
+
+    Ok(own_lines(
+        &String::from_utf8_lossy(&output.stdout),
+        &path.to_string_lossy(),
+    ))
+}
+
+/// Keeps the lines of preprocessed C that come from the file at `path`, along with the typedefs
+/// of every other file, which the declarations of the file may use. `cc -E` says which file the
+/// lines after it come from with markers such as `# 1 "stdio.h"`.
+fn own_lines(preprocessed: &str, path: &str) -> String {
+    let mut own = true;
+    let mut out = String::new();
+
+    for line in preprocessed.lines() {
+        if let Some(marker) = line.strip_prefix("# ") {
+            own = marker
+                .split('"')
+                .nth(1)
+                .is_some_and(|file| file == path || file.ends_with(&format!("/{path}")));
+            continue;
+        }
+
+        if own || line.trim_start().starts_with("typedef") {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+
+    out
+}
+
+#[test]
+fn c_declarations_become_ext_functions() {
+    let bindings = generate_bindings(
+        "#include <stddef.h>
+        #define MAX 4
+        /* Comments are skipped. */
+        typedef struct file FILE;
+        typedef int (*compare_t)(const void *, const void *);
+        #ifdef __cplusplus
+        extern \"C\" {
+        #endif
+        extern int puts(const char *s);
+        size_t strlen(const char *__restrict s) __attribute__((pure));
+        FILE *fopen(const char *path, const char *mode);
+        void qsort(void *base, size_t count, size_t size, compare_t compare);
+        int atexit(void (*)(void));
+        unsigned long long strtoull(const char *, char **end, int base);
+        _Noreturn void abort(void);
+        int printf(const char *format, ...);
+        int isnan(double x);
+        struct tm gmtime(long t);
+        void free(void *ptr);
+        static inline int helper(void) { return 0; }
+        extern int errno;
+        #ifdef __cplusplus
+        }
+        #endif",
+    );
+
+    assert_eq!(
+        bindings.lines().collect::<Vec<_>>(),
+        [
+            "ext fn puts(s: *char) -> u32;",
+            "ext fn strlen(s: *char) -> usize;",
+            "ext fn fopen(path: *char, mode: *char) -> *char;",
+            "ext fn qsort(base: *char, count: usize, size: usize, compare: fn(*char, *char) -> u32) -> u32;",
+            "ext fn atexit(arg0: fn() -> u32) -> u32;",
+            "ext fn strtoull(arg0: *char, end: **char, base: u32) -> u64;",
+            "ext fn abort() -> !;",
+            "ext fn printf(format: *char) -> u32; // Takes more arguments in C.",
+            "// `isnan` is not declared, as it takes `double`.",
+            "// `gmtime` is not declared, as it returns `struct tm`.",
+            "// `free` is not declared, as pal reserves its name.",
+        ]
+    );
+}
+
+#[test]
+fn preprocessed_headers_keep_their_own_declarations() {
+    let preprocessed = "# 1 \"lib.h\"\n# 1 \"/usr/include/stddef.h\" 1 3 4\ntypedef unsigned long size_t;\nint hidden(void);\n# 2 \"lib.h\" 2\nsize_t count(void);\n";
+
+    assert_eq!(
+        own_lines(preprocessed, "lib.h"),
+        "typedef unsigned long size_t;\nsize_t count(void);\n"
+    );
+}
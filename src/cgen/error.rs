@@ -0,0 +1,20 @@
+use thiserror::Error;
+
+use crate::mono::error::MonoError;
+
+/// Represents any error found while lowering a module to C.
+#[derive(Error, Debug)]
+pub enum CgenError {
+    #[error("`{0}` is not a function")]
+    NotAFunction(String),
+    #[error("no such enum variant `{0}` was found")]
+    VariantDoesNotExist(String),
+    #[error("`!` can only be used as the return type of a function")]
+    NeverValue,
+    #[error("arrays can only be declared with `const`")]
+    ArrayValue,
+    #[error(transparent)]
+    Mono(#[from] MonoError),
+    #[error("failed to format C source")]
+    Format(#[from] std::fmt::Error),
+}
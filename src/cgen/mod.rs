@@ -0,0 +1,996 @@
+pub mod error;
+
+use std::{borrow::Cow, fmt::Write};
+
+use crate::{
+    backend::{PanicStrategy, UNREACHABLE, assertion_failed},
+    cgen::error::CgenError,
+    lower::{
+        MATCHED, constructor_name, field_name,
+        format::lower_format,
+        literals::lower_literals,
+        lower_enums,
+        strings::{STR_CONCAT, STR_LEN, STR_NEW, STR_PTR, lower_strings},
+        tag_name,
+    },
+    mono::monomorphize,
+    parser::Spanned,
+    runtime,
+    spec::ast::*,
+};
+
+/// The string a nested block is indented with.
+const INDENT: &str = "    ";
+
+/// Returns the C name of a pal function. `main` is renamed, so that the C entry point can wrap it
+/// with the signature C requires. The functions generated for enums with fields have the `::`
+/// in their names spelled `__`, as do instances of generic functions the `$` in theirs, whose
+/// pointer types are spelled `ptr_`.
+pub fn c_name(name: &str) -> Cow<'_, str> {
+    match name {
+        "main" => Cow::Borrowed("pal_main"),
+        name if name.contains(['$', ':']) => Cow::Owned(
+            name.replace("::", "__")
+                .replace('$', "__")
+                .replace('*', "ptr_"),
+        ),
+        name => Cow::Borrowed(name),
+    }
+}
+
+/// Generates the C spelling of a value type.
+pub fn generate_c_type(typ: &Type) -> Result<String, CgenError> {
+    match typ {
+        Type::Atomic(name) => match &name[..] {
+            "u8" => Ok("uint8_t".to_string()),
+            "u16" => Ok("uint16_t".to_string()),
+            "u32" => Ok("uint32_t".to_string()),
+            "u64" => Ok("uint64_t".to_string()),
+            "usize" => Ok("uintptr_t".to_string()),
+            "isize" => Ok("intptr_t".to_string()),
+            "char" => Ok("char".to_string()),
+            "str" => Ok("pal_str".to_string()),
+            // Sema has checked every type, and the only others left after lowering are enums
+            // with fields, which are declared as structs of the same name.
+            name => Ok(name.to_string()),
+        },
+        Type::Pointer(pointee) => Ok(format!("{}*", generate_c_type(pointee)?)),
+        // C spells arrays around the declared name, see [`generate_c_const`].
+        Type::Array(..) => Err(CgenError::ArrayValue),
+        Type::Never => Err(CgenError::NeverValue),
+        // C spells function pointers around the declared name too, so each is given a name with
+        // a `typedef`, see [`generate_c_function_types`].
+        Type::Function(arguments, returns) => {
+            let mut name = "pal_fn".to_string();
+
+            for argument in arguments.iter() {
+                name.push('_');
+                name.push_str(&generate_c_type(argument)?.replace('*', "_ptr"));
+            }
+
+            let returns = match &**returns {
+                Type::Never => "void".to_string(),
+                typ => generate_c_type(typ)?,
+            };
+
+            Ok(format!("{name}_to_{}", returns.replace('*', "_ptr")))
+        }
+    }
+}
+
+/// Collects every function pointer type a type refers to, including itself, the types it refers
+/// to coming first.
+fn collect_function_types(typ: &Type, found: &mut Vec<Type>) {
+    match typ {
+        Type::Pointer(inner) | Type::Array(inner, _) => collect_function_types(inner, found),
+        Type::Function(arguments, returns) => {
+            for argument in arguments {
+                collect_function_types(argument, found);
+            }
+
+            collect_function_types(returns, found);
+
+            if !found.contains(typ) {
+                found.push(typ.clone());
+            }
+        }
+        Type::Atomic(_) | Type::Never => {}
+    }
+}
+
+fn collect_block_function_types(body: &[Spanned<Statement>], found: &mut Vec<Type>) {
+    for statement in body {
+        match &statement.node {
+            Statement::Let(_, typ, _) => collect_function_types(typ, found),
+            Statement::Block(body) | Statement::For(_, _, _, body) => {
+                collect_block_function_types(body, found)
+            }
+            Statement::Match(_, arms) => {
+                for (_, body) in arms {
+                    collect_block_function_types(body, found);
+                }
+            }
+            Statement::FunctionCall(..)
+            | Statement::Return(_)
+            | Statement::Free(_)
+            | Statement::Assert(..)
+            | Statement::Unreachable
+            | Statement::Asm(_)
+            | Statement::Error(_) => {}
+        }
+    }
+}
+
+/// Writes a `typedef` for every function pointer type a module uses, named the way
+/// [`generate_c_type`] spells it.
+pub fn generate_c_function_types(out: &mut String, module: &Module) -> Result<(), CgenError> {
+    let mut found = vec![];
+
+    for item in &module.1 {
+        for (_, typ) in item.arguments() {
+            collect_function_types(typ, &mut found);
+        }
+
+        match &item.node {
+            Item::Enum(_, variants, _) => {
+                for field in variants.iter().flat_map(|(_, fields)| fields) {
+                    collect_function_types(field, &mut found);
+                }
+            }
+            Item::FunctionDeclaration(_, _, _, returns, body, ..) => {
+                collect_function_types(returns, &mut found);
+                collect_block_function_types(body, &mut found);
+            }
+            Item::ExternFunctionDefinition(_, _, returns, ..) | Item::Const(_, returns, _, _) => {
+                collect_function_types(returns, &mut found);
+            }
+        }
+    }
+
+    for typ in &found {
+        let Type::Function(arguments, returns) = typ else {
+            continue;
+        };
+
+        let arguments = arguments
+            .iter()
+            .map(generate_c_type)
+            .collect::<Result<Vec<_>, _>>()?;
+        let arguments = match arguments.is_empty() {
+            true => "void".to_string(),
+            false => arguments.join(", "),
+        };
+        let returns = match &**returns {
+            Type::Never => "void".to_string(),
+            typ => generate_c_type(typ)?,
+        };
+
+        writeln!(
+            out,
+            "typedef {returns} (*{})({arguments});",
+            generate_c_type(typ)?
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Generates a C expression. Every integer is a `uint32_t`, so literals are truncated the same
+/// way they are by the LLVM backend.
+pub fn generate_c_expression(expression: &Expression) -> Result<String, CgenError> {
+    match expression {
+        Expression::NumericLiteral(value) => Ok(format!("{value}u")),
+        // Pal prints string and character literals with C compatible escapes.
+        Expression::StringLiteral(_) | Expression::CharLiteral(_) => Ok(expression.to_string()),
+        Expression::Variable(name) => Ok(name.to_string()),
+        Expression::Call(name, args) => generate_c_call(name, args),
+        Expression::Index(name, index) => Ok(format!("{name}[{}]", generate_c_expression(index)?)),
+        // The address of anything but a local variable is the address of a function.
+        Expression::Unary(UnaryOp::AddressOf, operand)
+            if let Expression::Variable(name) = &operand.node =>
+        {
+            Ok(format!("(&{})", c_name(name)))
+        }
+        // Operators are parenthesized, so that C's own precedence rules never come into play.
+        Expression::Unary(op, operand) => Ok(format!("({op}{})", generate_c_expression(operand)?)),
+        Expression::Binary(op, left, right) => Ok(format!(
+            "({} {op} {})",
+            generate_c_expression(left)?,
+            generate_c_expression(right)?
+        )),
+        Expression::If(condition, then, otherwise) => Ok(format!(
+            "({} ? {} : {})",
+            generate_c_expression(condition)?,
+            generate_c_expression(then)?,
+            generate_c_expression(otherwise)?
+        )),
+        // Blocks become statement expressions, whose value is that of their last statement. They
+        // are not part of C99, but both GCC and Clang support them.
+        Expression::Block(body, value) => {
+            let mut statements = String::new();
+
+            for statement in body {
+                generate_c_statement(&mut statements, statement, 0)?;
+            }
+
+            let statements: Vec<_> = statements.lines().map(str::trim).collect();
+
+            Ok(format!(
+                "({{ {} {}; }})",
+                statements.join(" "),
+                generate_c_expression(value)?
+            ))
+        }
+        Expression::Cast(value, typ) => Ok(format!(
+            "(({}){})",
+            generate_c_type(typ)?,
+            generate_c_expression(value)?
+        )),
+        // Enums are lowered to their discriminants before any C is generated.
+        Expression::Variant(..) => Err(CgenError::VariantDoesNotExist(expression.to_string())),
+        // The value is copied out of a one element array, which can be initialized with a value
+        // of any type, unlike a plain compound literal.
+        Expression::New(typ, value) => {
+            let typ = generate_c_type(typ)?;
+
+            Ok(format!(
+                "(({typ}*)pal_new(sizeof({typ}), ({typ}[1]){{ {} }}))",
+                generate_c_expression(value)?
+            ))
+        }
+        Expression::Alloc(typ, count) => {
+            let typ = generate_c_type(typ)?;
+
+            Ok(format!(
+                "(({typ}*)malloc(sizeof({typ}) * {}))",
+                generate_c_expression(count)?
+            ))
+        }
+    }
+}
+
+fn generate_c_call(name: &str, args: &[Spanned<Expression>]) -> Result<String, CgenError> {
+    let args = args
+        .iter()
+        .map(|arg| generate_c_expression(arg))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(format!("{}({})", c_name(name), args.join(", ")))
+}
+
+/// Writes a single C statement nested `depth` levels deep, including its indentation.
+pub fn generate_c_statement(
+    out: &mut String,
+    statement: &Statement,
+    depth: usize,
+) -> Result<(), CgenError> {
+    let indent = INDENT.repeat(depth);
+
+    match statement {
+        Statement::FunctionCall(name, args) => {
+            writeln!(out, "{indent}{};", generate_c_call(name, args)?)?;
+        }
+        Statement::Return(expression) => {
+            writeln!(
+                out,
+                "{indent}return {};",
+                generate_c_expression(expression)?
+            )?;
+        }
+        Statement::Let(name, typ, expression) => {
+            writeln!(
+                out,
+                "{indent}{} {name} = {};",
+                generate_c_type(typ)?,
+                generate_c_expression(expression)?
+            )?;
+        }
+        Statement::Block(body) => {
+            writeln!(out, "{indent}{{")?;
+            generate_c_block(out, body, depth + 1)?;
+            writeln!(out, "{indent}}}")?;
+        }
+        Statement::For(name, start, end, body) => {
+            // The end bound is evaluated once, before the loop is entered. It is stored under `in`,
+            // which is a keyword in pal and so can never clash with a pal identifier.
+            writeln!(
+                out,
+                "{indent}for (uint32_t {name} = {}, in = {}; {name} < in; {name}++) {{",
+                generate_c_expression(start)?,
+                generate_c_expression(end)?
+            )?;
+            generate_c_block(out, body, depth + 1)?;
+            writeln!(out, "{indent}}}")?;
+        }
+        // A `match` with only a wildcard arm can be over an enum with fields, which C can not
+        // switch on.
+        Statement::Match(value, arms)
+            if arms
+                .iter()
+                .all(|(pattern, _)| pattern.node == Pattern::Wildcard) =>
+        {
+            writeln!(out, "{indent}(void)({});", generate_c_expression(value)?)?;
+
+            if let Some((_, body)) = arms.first() {
+                writeln!(out, "{indent}{{")?;
+                generate_c_block(out, body, depth + 1)?;
+                writeln!(out, "{indent}}}")?;
+            }
+        }
+        Statement::Match(value, arms) => {
+            writeln!(out, "{indent}switch ({}) {{", generate_c_expression(value)?)?;
+
+            for (pattern, body) in arms {
+                let label = match pattern.node {
+                    Pattern::Number(value) => format!("case {}u", value as u32),
+                    Pattern::Char(value) => format!("case {}", Expression::CharLiteral(value)),
+                    Pattern::Variant(..) => {
+                        return Err(CgenError::VariantDoesNotExist(pattern.to_string()));
+                    }
+                    Pattern::Wildcard => "default".to_string(),
+                };
+
+                writeln!(out, "{indent}{INDENT}{label}: {{")?;
+                generate_c_block(out, body, depth + 2)?;
+                writeln!(out, "{indent}{INDENT}{INDENT}break;")?;
+                writeln!(out, "{indent}{INDENT}}}")?;
+            }
+
+            writeln!(out, "{indent}}}")?;
+        }
+        Statement::Unreachable => writeln!(out, "{indent}pal_panic(\"{UNREACHABLE}\");")?,
+        Statement::Error(_) => {}
+        Statement::Asm(template) => writeln!(
+            out,
+            "{indent}__asm__ volatile (\"{}\");",
+            template.escape_default()
+        )?,
+        Statement::Assert(condition, message) => writeln!(
+            out,
+            "{indent}if (!({})) pal_panic(\"{}\");",
+            generate_c_expression(condition)?,
+            assertion_failed(message).escape_default()
+        )?,
+        Statement::Free(pointer) => {
+            writeln!(out, "{indent}free({});", generate_c_expression(pointer)?)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes every statement of a block, nested `depth` levels deep.
+pub fn generate_c_block(
+    out: &mut String,
+    body: &[Spanned<Statement>],
+    depth: usize,
+) -> Result<(), CgenError> {
+    for statement in body {
+        generate_c_statement(out, statement, depth)?;
+    }
+
+    Ok(())
+}
+
+/// Generates the C definition of a constant array, without a trailing `;`.
+pub fn generate_c_const(
+    name: &str,
+    typ: &Type,
+    elements: &[Spanned<Expression>],
+) -> Result<String, CgenError> {
+    let Type::Array(element, length) = typ else {
+        return Err(CgenError::ArrayValue);
+    };
+
+    let elements = elements
+        .iter()
+        .map(|element| generate_c_expression(element))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(format!(
+        "static {} const {name}[{length}] = {{{}}}",
+        generate_c_type(element)?,
+        elements.join(", ")
+    ))
+}
+
+/// Generates the C declaration of an enum with fields: a struct holding its discriminant and a
+/// union of the fields of each variant, along with the functions lowering calls to build its
+/// variants and to read its discriminant and fields, see [`lower_enums`]. The struct is named with
+/// a `typedef` of its own, see [`generate_c_module`].
+pub fn generate_c_enum(
+    out: &mut String,
+    name: &str,
+    variants: &[Variant],
+) -> Result<(), CgenError> {
+    writeln!(out, "struct {name} {{")?;
+    writeln!(out, "{INDENT}uint32_t tag;")?;
+    writeln!(out, "{INDENT}union {{")?;
+
+    for (variant, fields) in variants.iter().filter(|(_, fields)| !fields.is_empty()) {
+        let fields = fields
+            .iter()
+            .enumerate()
+            .map(|(index, field)| Ok(format!("{} _{index};", generate_c_type(field)?)))
+            .collect::<Result<Vec<_>, CgenError>>()?;
+
+        writeln!(
+            out,
+            "{INDENT}{INDENT}struct {{ {} }} {variant};",
+            fields.join(" ")
+        )?;
+    }
+
+    writeln!(out, "{INDENT}}} fields;")?;
+    writeln!(out, "}};")?;
+
+    writeln!(
+        out,
+        "static inline uint32_t {}({name} {MATCHED}) {{ return {MATCHED}.tag; }}",
+        c_name(&tag_name(name))
+    )?;
+
+    for (tag, (variant, fields)) in variants.iter().enumerate() {
+        let params = fields
+            .iter()
+            .enumerate()
+            .map(|(index, field)| Ok(format!("{} _{index}", generate_c_type(field)?)))
+            .collect::<Result<Vec<_>, CgenError>>()?;
+        let params = match params.is_empty() {
+            true => "void".to_string(),
+            false => params.join(", "),
+        };
+
+        write!(
+            out,
+            "static inline {name} {}({params}) {{ {name} value = {{0}}; value.tag = {tag}u;",
+            c_name(&constructor_name(name, variant))
+        )?;
+
+        for index in 0..fields.len() {
+            write!(out, " value.fields.{variant}._{index} = _{index};")?;
+        }
+
+        writeln!(out, " return value; }}")?;
+
+        for (index, field) in fields.iter().enumerate() {
+            let read = format!("{MATCHED}.fields.{variant}._{index}");
+
+            writeln!(
+                out,
+                "static inline {} {}({name} {MATCHED}) {{ return {read}; }}",
+                generate_c_type(field)?,
+                c_name(&field_name(name, variant, index))
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Generates the C prototype of a top-level item, without a trailing `;`. Functions declared with
+/// a calling convention other than C have it written as a GCC attribute.
+pub fn generate_c_prototype(item: &Item) -> Result<String, CgenError> {
+    let returns = item
+        .return_type()
+        .ok_or_else(|| CgenError::NotAFunction(item.name().to_string()))?;
+    let returns = match &returns.node {
+        Type::Never => "void".to_string(),
+        typ => generate_c_type(typ)?,
+    };
+
+    let args = item
+        .arguments()
+        .iter()
+        .map(|(name, typ)| Ok(format!("{} {name}", generate_c_type(typ)?)))
+        .collect::<Result<Vec<_>, CgenError>>()?;
+
+    let args = match args.is_empty() {
+        true => "void".to_string(),
+        false => args.join(", "),
+    };
+
+    let abi = match item.abi() {
+        Abi::C => "",
+        Abi::Stdcall => "__attribute__((stdcall)) ",
+        Abi::Fastcall => "__attribute__((fastcall)) ",
+        Abi::Win64 => "__attribute__((ms_abi)) ",
+        Abi::Sysv64 => "__attribute__((sysv_abi)) ",
+    };
+
+    Ok(format!("{returns} {abi}{}({args})", c_name(item.name())))
+}
+
+/// Returns whether any of the statements can panic, either by reaching `unreachable()`, by an
+/// `assert` or by calling `pal_panic`.
+fn can_panic(body: &[Spanned<Statement>]) -> bool {
+    block_calls(body, &|name| name == "pal_panic")
+        || block_contains(
+            body,
+            &|expression| matches!(expression, Expression::Block(body, _) if can_panic(body)),
+        )
+        || body.iter().any(|statement| match &statement.node {
+            Statement::Unreachable | Statement::Assert(..) => true,
+            Statement::Block(body) | Statement::For(_, _, _, body) => can_panic(body),
+            Statement::Match(_, arms) => arms.iter().any(|(_, body)| can_panic(body)),
+            _ => false,
+        })
+}
+
+/// Returns whether a type is or refers to `str`.
+fn mentions_str(typ: &Type) -> bool {
+    match typ {
+        Type::Atomic(name) => name == "str",
+        Type::Pointer(inner) | Type::Array(inner, _) => mentions_str(inner),
+        Type::Function(arguments, returns) => {
+            arguments.iter().any(mentions_str) || mentions_str(returns)
+        }
+        Type::Never => false,
+    }
+}
+
+/// Returns whether any of the statements declares a variable whose type is or refers to `str`.
+fn declares_str(body: &[Spanned<Statement>]) -> bool {
+    block_contains(
+        body,
+        &|expression| matches!(expression, Expression::Block(body, _) if declares_str(body)),
+    ) || body.iter().any(|statement| match &statement.node {
+        Statement::Let(_, typ, _) => mentions_str(typ),
+        Statement::Block(body) | Statement::For(_, _, _, body) => declares_str(body),
+        Statement::Match(_, arms) => arms.iter().any(|(_, body)| declares_str(body)),
+        _ => false,
+    })
+}
+
+/// Returns whether an expression, or any expression within it, is one that `found` accepts.
+fn contains(expression: &Expression, found: &dyn Fn(&Expression) -> bool) -> bool {
+    found(expression)
+        || match expression {
+            Expression::Call(_, args) | Expression::Variant(_, _, args) => {
+                args.iter().any(|arg| contains(arg, found))
+            }
+            Expression::Index(_, inner)
+            | Expression::Unary(_, inner)
+            | Expression::New(_, inner)
+            | Expression::Alloc(_, inner)
+            | Expression::Cast(inner, _) => contains(inner, found),
+            Expression::Binary(_, left, right) => contains(left, found) || contains(right, found),
+            Expression::If(condition, then, otherwise) => {
+                contains(condition, found) || contains(then, found) || contains(otherwise, found)
+            }
+            Expression::Block(body, value) => block_contains(body, found) || contains(value, found),
+            Expression::StringLiteral(_)
+            | Expression::NumericLiteral(_)
+            | Expression::CharLiteral(_)
+            | Expression::Variable(_) => false,
+        }
+}
+
+/// Returns whether any of the statements has an expression that `found` accepts. Calls made as
+/// statements are passed to `found` as calls without arguments, which are passed on their own.
+fn block_contains(body: &[Spanned<Statement>], found: &dyn Fn(&Expression) -> bool) -> bool {
+    body.iter().any(|statement| match &statement.node {
+        Statement::Let(_, _, value)
+        | Statement::Return(value)
+        | Statement::Free(value)
+        | Statement::Assert(value, _) => contains(value, found),
+        Statement::FunctionCall(name, args) => {
+            found(&Expression::Call(*name, vec![])) || args.iter().any(|arg| contains(arg, found))
+        }
+        Statement::Block(body) => block_contains(body, found),
+        Statement::For(_, start, end, body) => {
+            contains(start, found) || contains(end, found) || block_contains(body, found)
+        }
+        Statement::Match(value, arms) => {
+            contains(value, found) || arms.iter().any(|(_, body)| block_contains(body, found))
+        }
+        Statement::Unreachable | Statement::Asm(_) | Statement::Error(_) => false,
+    })
+}
+
+/// Returns whether any of the statements calls a function that `called` accepts.
+fn block_calls(body: &[Spanned<Statement>], called: &dyn Fn(&str) -> bool) -> bool {
+    block_contains(
+        body,
+        &|expression| matches!(expression, Expression::Call(name, _) if called(name)),
+    )
+}
+
+/// Generates `pal_str`, the struct a `str` is spelled as, along with the functions lowered code
+/// calls for operations on strings, see [`lower_strings`]. The bytes of a concatenation are
+/// copied by hand, so that no header that could contradict the program is included for `memcpy`.
+pub fn generate_c_strings(out: &mut String) -> Result<(), CgenError> {
+    writeln!(
+        out,
+        "typedef struct pal_str {{ char* ptr; uint32_t len; }} pal_str;"
+    )?;
+    writeln!(out)?;
+    writeln!(
+        out,
+        "static inline pal_str {}(char* ptr, uint32_t len) {{",
+        c_name(STR_NEW)
+    )?;
+    writeln!(out, "{INDENT}pal_str s = {{ ptr, len }};")?;
+    writeln!(out, "{INDENT}return s;")?;
+    writeln!(out, "}}")?;
+    writeln!(out)?;
+    writeln!(
+        out,
+        "static inline char* {}(pal_str s) {{ return s.ptr; }}",
+        c_name(STR_PTR)
+    )?;
+    writeln!(
+        out,
+        "static inline uint32_t {}(pal_str s) {{ return s.len; }}",
+        c_name(STR_LEN)
+    )?;
+    writeln!(out)?;
+    writeln!(
+        out,
+        "static inline pal_str {}(pal_str a, pal_str b) {{",
+        c_name(STR_CONCAT)
+    )?;
+    writeln!(
+        out,
+        "{INDENT}char* ptr = malloc((size_t)a.len + b.len + 1);"
+    )?;
+    writeln!(
+        out,
+        "{INDENT}for (uint32_t i = 0; i < a.len; i++) ptr[i] = a.ptr[i];"
+    )?;
+    writeln!(
+        out,
+        "{INDENT}for (uint32_t i = 0; i < b.len; i++) ptr[a.len + i] = b.ptr[i];"
+    )?;
+    writeln!(out, "{INDENT}ptr[a.len + b.len] = 0;")?;
+    writeln!(
+        out,
+        "{INDENT}return {}(ptr, a.len + b.len);",
+        c_name(STR_NEW)
+    )?;
+    writeln!(out, "}}")?;
+    writeln!(out)?;
+
+    Ok(())
+}
+
+/// Generates `pal_panic`, which generated code calls when it panics, following the given strategy.
+pub fn generate_c_panic(out: &mut String, strategy: PanicStrategy) -> Result<(), CgenError> {
+    // Programs declare the C functions they use themselves, so no header that could contradict
+    // them is included for `dprintf`.
+    if let PanicStrategy::Exit(_) = strategy {
+        writeln!(out, "int dprintf(int fd, const char* format, ...);")?;
+    }
+
+    // The attribute and `__builtin_trap` are not part of C99, but both GCC and Clang support them.
+    writeln!(
+        out,
+        "__attribute__((noreturn)) static void pal_panic(const char* message) {{"
+    )?;
+
+    match strategy {
+        PanicStrategy::Abort => {
+            writeln!(out, "    (void)message;")?;
+            writeln!(out, "    abort();")?;
+        }
+        PanicStrategy::Trap => {
+            writeln!(out, "    (void)message;")?;
+            writeln!(out, "    __builtin_trap();")?;
+        }
+        PanicStrategy::Exit(code) => {
+            writeln!(out, "    dprintf(2, \"panicked: %s\\n\", message);")?;
+            writeln!(out, "    exit({code});")?;
+        }
+    }
+
+    writeln!(out, "}}")?;
+    writeln!(out)?;
+
+    Ok(())
+}
+
+/// Generates a C99 translation unit from an AST module node. Every function is declared before any
+/// is defined, so that functions may be called before they are defined, like they can in pal.
+pub fn generate_c_module(module: &Module, panic: PanicStrategy) -> Result<String, CgenError> {
+    // Enums without fields become plain `uint32_t`s, numbered in the order their variants are
+    // declared in, prints become calls to the runtime, operations on strings become calls to the
+    // functions of [`generate_c_strings`] and literals are converted to the types they were given.
+    let module = lower_format(&monomorphize(module, &[])?, &[]);
+    let module = lower_strings(&module, &[]);
+    let module = &lower_enums(&lower_literals(&module, &[]), &[]);
+    let mut out = String::new();
+
+    writeln!(out, "#include <stdint.h>")?;
+    writeln!(out, "#include <stdlib.h>")?;
+    writeln!(out)?;
+
+    let bodies = || {
+        module.1.iter().filter_map(|item| match &item.node {
+            Item::FunctionDeclaration(.., body, _, _) => Some(&body[..]),
+            _ => None,
+        })
+    };
+
+    if bodies().any(can_panic) {
+        generate_c_panic(&mut out, panic)?;
+    }
+
+    // `pal_panic` is the only function of the runtime that is not part of [`runtime::C_SOURCE`],
+    // while `new` is written as a call to `pal_new`, which is.
+    let uses_runtime = bodies().any(|body| {
+        block_calls(body, &|name| {
+            runtime::is_runtime(name) && name != "pal_panic"
+        }) || block_contains(body, &|expression| {
+            matches!(expression, Expression::New(..))
+        })
+    });
+
+    // Every `str` is either declared with its type or made by one of the functions for strings,
+    // and the runtime prints them.
+    if uses_runtime
+        || module.1.iter().any(|item| {
+            item.arguments().iter().any(|(_, typ)| mentions_str(typ))
+                || item.return_type().is_some_and(|typ| mentions_str(typ))
+        })
+        || bodies()
+            .any(|body| declares_str(body) || block_calls(body, &|name| name.starts_with("str::")))
+    {
+        generate_c_strings(&mut out)?;
+    }
+
+    if uses_runtime {
+        out.push_str(runtime::C_SOURCE);
+        writeln!(out)?;
+    }
+
+    // Enums with fields come first, so that constants and functions can use them. Their names are
+    // declared before function pointer types, which they may both use and be used by.
+    let mut declared = false;
+
+    for item in &module.1 {
+        if let Item::Enum(name, _, _) = &item.node {
+            writeln!(out, "typedef struct {name} {name};")?;
+            declared = true;
+        }
+    }
+
+    let before = out.len();
+    generate_c_function_types(&mut out, module)?;
+
+    if declared || out.len() > before {
+        writeln!(out)?;
+    }
+
+    for item in &module.1 {
+        if let Item::Enum(name, variants, _) = &item.node {
+            generate_c_enum(&mut out, name, variants)?;
+            writeln!(out)?;
+        }
+    }
+
+    // Constants come next, so that every function can read them.
+    for item in &module.1 {
+        if let Item::Const(name, typ, elements, _) = &item.node {
+            writeln!(out, "{};", generate_c_const(name, typ, elements)?)?;
+        }
+    }
+
+    for item in module.1.iter().filter(|item| item.is_function()) {
+        writeln!(out, "{};", generate_c_prototype(item)?)?;
+    }
+
+    for item in &module.1 {
+        if let Item::FunctionDeclaration(_, _, _, _, body, ..) = &item.node {
+            writeln!(out)?;
+            writeln!(out, "{} {{", generate_c_prototype(item)?)?;
+            generate_c_block(&mut out, body, 1)?;
+            writeln!(out, "}}")?;
+        }
+    }
+
+    // Sema has checked that `main` takes either nothing or `argc` and `argv`.
+    match module.1.iter().find(|item| item.name() == "main") {
+        Some(main) if main.arguments().is_empty() => {
+            writeln!(out)?;
+            writeln!(out, "int main(void) {{")?;
+            writeln!(out, "{INDENT}return (int)pal_main();")?;
+            writeln!(out, "}}")?;
+        }
+        Some(_) => {
+            writeln!(out)?;
+            writeln!(out, "int main(int argc, char **argv) {{")?;
+            writeln!(out, "{INDENT}return (int)pal_main((uint32_t)argc, argv);")?;
+            writeln!(out, "}}")?;
+        }
+        None => {}
+    }
+
+    Ok(out)
+}
+
+#[test]
+fn modules_lower_to_c() {
+    let (module, _) = crate::spec::module("main".to_string())
+        .parse(
+            "ext fn printf(ptr: *char) -> u32;
+            fn main() -> u32 {
+                let c: char = '\\n';
+                for i in 0..3 { printf(\"tick\"); }
+                return 0;
+            };",
+        )
+        .unwrap();
+
+    assert_eq!(
+        generate_c_module(&module, PanicStrategy::default()).unwrap(),
+        "#include <stdint.h>
+#include <stdlib.h>
+
+uint32_t printf(char* ptr);
+uint32_t pal_main(void);
+
+uint32_t pal_main(void) {
+    char c = '\\n';
+    for (uint32_t i = 0u, in = 3u; i < in; i++) {
+        printf(\"tick\");
+    }
+    return 0u;
+}
+
+int main(void) {
+    return (int)pal_main();
+}
+"
+    );
+}
+
+#[test]
+fn constant_arrays_lower_to_static_arrays() {
+    let (module, _) = crate::spec::module("main".to_string())
+        .parse("const TABLE: [u32; 2] = [1, 2]; fn main() -> u32 { return TABLE[1]; };")
+        .unwrap();
+
+    let source = generate_c_module(&module, PanicStrategy::default()).unwrap();
+
+    assert!(
+        source.contains("static uint32_t const TABLE[2] = {1u, 2u};\nuint32_t pal_main(void);")
+    );
+    assert!(source.contains("return TABLE[1u];"));
+}
+
+#[test]
+fn panics_call_the_strategy() {
+    let (module, _) = crate::spec::module("main".to_string())
+        .parse("fn main() -> u32 { unreachable(); };")
+        .unwrap();
+
+    let source = generate_c_module(&module, PanicStrategy::Exit(3)).unwrap();
+
+    assert!(source.contains("    dprintf(2, \"panicked: %s\\n\", message);\n    exit(3);"));
+    assert!(source.contains("    pal_panic(\"entered unreachable code\");"));
+}
+
+#[test]
+fn main_receives_the_arguments_of_the_c_entry_point() {
+    let (module, _) = crate::spec::module("main".to_string())
+        .parse("fn main(argc: u32, argv: **char) -> u32 { return *argv[argc]; };")
+        .unwrap();
+
+    let source = generate_c_module(&module, PanicStrategy::default()).unwrap();
+
+    assert!(source.contains("uint32_t pal_main(uint32_t argc, char** argv) {"));
+    assert!(source.contains("    return (*argv[argc]);"));
+    assert!(source.ends_with(
+        "int main(int argc, char **argv) {\n    return (int)pal_main((uint32_t)argc, argv);\n}\n"
+    ));
+}
+
+#[test]
+fn function_pointers_are_named_with_typedefs() {
+    let (module, _) = crate::spec::module("main".to_string())
+        .parse(
+            "fn apply(f: fn(*char) -> u32, s: *char) -> u32 { return f(s); };
+            fn len(s: *char) -> u32 { return 0; };
+            fn main() -> u32 { return apply(&len, \"hi\"); };",
+        )
+        .unwrap();
+
+    let source = generate_c_module(&module, PanicStrategy::default()).unwrap();
+
+    assert!(source.contains("typedef uint32_t (*pal_fn_char_ptr_to_uint32_t)(char*);"));
+    assert!(source.contains("uint32_t apply(pal_fn_char_ptr_to_uint32_t f, char* s);"));
+    assert!(source.contains("return apply((&len), \"hi\");"));
+}
+
+#[test]
+fn the_runtime_is_written_when_it_is_called() {
+    let source = |body: &str| {
+        let (module, _) = crate::spec::module("main".to_string())
+            .parse(format!("fn main() -> u32 {{ {body} }};"))
+            .unwrap();
+
+        generate_c_module(&module, PanicStrategy::default()).unwrap()
+    };
+
+    let used = source("pal_print_int(1); pal_panic(\"done\");");
+    assert!(used.contains("typedef struct pal_str { char* ptr; uint32_t len; } pal_str;"));
+    assert!(used.contains(runtime::C_SOURCE));
+    assert!(used.contains("static void pal_panic(const char* message) {"));
+    assert!(used.contains("    pal_panic(\"done\");"));
+
+    let unused = source("return 0;");
+    assert!(!unused.contains("pal_str") && !unused.contains("pal_print_int"));
+}
+
+#[test]
+fn heap_allocations_are_written_with_the_runtime() {
+    let (module, _) = crate::spec::module("main".to_string())
+        .parse("fn main() -> u32 { let p: *u32 = new u32(7); let a: *char = alloc::<char>(*p); free(a); return *p; };")
+        .unwrap();
+    let source = generate_c_module(&module, PanicStrategy::default()).unwrap();
+
+    assert!(source.contains(runtime::C_SOURCE));
+    assert!(source.contains(
+        "    uint32_t* p = ((uint32_t*)pal_new(sizeof(uint32_t), (uint32_t[1]){ 7u }));"
+    ));
+    assert!(source.contains("    char* a = ((char*)malloc(sizeof(char) * (*p)));"));
+    assert!(source.contains("    free(a);"));
+}
+
+#[test]
+fn assertions_panic_when_they_fail() {
+    let (module, _) = crate::spec::module("main".to_string())
+        .parse("fn main(x: u32) -> u32 { assert(x, \"nonzero\"); return x; };")
+        .unwrap();
+    let source = generate_c_module(&module, PanicStrategy::default()).unwrap();
+
+    assert!(source.contains("    if (!(x)) pal_panic(\"assertion failed: nonzero\");"));
+    assert!(source.contains("static void pal_panic"));
+}
+
+#[test]
+fn calling_conventions_are_attributes() {
+    let (item, _) = crate::spec::item()
+        .parse("ext \"stdcall\" fn MessageBeep(kind: u32) -> u32")
+        .unwrap();
+
+    assert_eq!(
+        generate_c_prototype(&item).unwrap(),
+        "uint32_t __attribute__((stdcall)) MessageBeep(uint32_t kind)"
+    );
+}
+
+#[test]
+fn inline_assembly_is_volatile() {
+    let (module, _) = crate::spec::module("main".to_string())
+        .parse("fn main() -> u32 { asm!(\"nop\\n\\tnop\"); return 0; };")
+        .unwrap();
+    let source = generate_c_module(&module, PanicStrategy::default()).unwrap();
+
+    assert!(source.contains("    __asm__ volatile (\"nop\\n\\tnop\");\n"));
+}
+
+#[test]
+fn blocks_are_statement_expressions() {
+    let (module, _) = crate::spec::module("main".to_string())
+        .parse(
+            "fn main(x: u32) -> u32 { let y: u32 = { let z: u32 = x; assert(z, \"z\"); z }; y };",
+        )
+        .unwrap();
+    let source = generate_c_module(&module, PanicStrategy::default()).unwrap();
+
+    assert!(source.contains(
+        "    uint32_t y = ({ uint32_t z = x; if (!(z)) pal_panic(\"assertion failed: z\"); z; });\n"
+    ));
+}
+
+#[test]
+fn casts_convert_between_integer_types() {
+    let (module, _) = crate::spec::module("main".to_string())
+        .parse("fn main(n: u64) -> u32 { let s: isize = n as isize; return s as u8 as u32; };")
+        .unwrap();
+    let source = generate_c_module(&module, PanicStrategy::default()).unwrap();
+
+    assert!(source.contains("    intptr_t s = ((intptr_t)n);\n"));
+    assert!(source.contains("    return ((uint32_t)((uint8_t)s));\n"));
+}
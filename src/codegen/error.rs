@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+use crate::{mir::error::MirError, parser::Span};
+
 /// Represents any non-LLVM codegen error.
 #[derive(Error, Debug)]
 pub enum CodegenError {
@@ -7,4 +9,100 @@ pub enum CodegenError {
     TypeDoesNotExist,
     #[error("no such function was found")]
     FunctionDoesNotExist,
+    #[error("no such variable was found in the current scope")]
+    VariableDoesNotExist,
+    #[error("the builder is not positioned inside of a function")]
+    NoInsertionBlock,
+    #[error("the bounds of a range must be integers")]
+    NonIntegerRange,
+    #[error("only integers can be matched")]
+    NonIntegerMatch,
+    #[error("the called function does not return a value")]
+    NoReturnValue,
+    #[error("`!` can only be used as the return type of a function")]
+    NeverValue,
+    #[error("function `{0}` is already declared with a different signature")]
+    MismatchedDeclaration(String),
+    #[error("no such intrinsic `{0}` exists")]
+    IntrinsicDoesNotExist(String),
+    #[error("the type behind this pointer is not known")]
+    UnknownPointee,
+    #[error("`&` can only take the address of a variable")]
+    AddressOfValue,
+    #[error("operators can only be applied to integers")]
+    NonIntegerOperand,
+    #[error("no such enum variant was found")]
+    VariantDoesNotExist,
+    #[error("no such constant was found")]
+    ConstDoesNotExist,
+    #[error("array indices must be integers")]
+    NonIntegerIndex,
+    #[error("the elements of a constant must be literals")]
+    NonConstElement,
+    #[error("LLVM rejected the generated code: {0}")]
+    VerificationFailed(String),
+    #[error("failed to write a C header: {0}")]
+    Format(#[from] std::fmt::Error),
+    #[error("failed to link module `{module}`: {message}")]
+    Link { module: String, message: String },
+    /// Wraps an error with the function that was being generated when it happened.
+    #[error("failed to generate function `{function}`")]
+    InFunction {
+        function: String,
+        span: Span,
+        #[source]
+        source: anyhow::Error,
+    },
+    /// Wraps an error with the innermost statement that was being generated when it happened.
+    #[error("failed to generate statement")]
+    InStatement {
+        span: Span,
+        #[source]
+        source: anyhow::Error,
+    },
+}
+
+impl CodegenError {
+    /// Returns the span of the function or statement the error happened in, if it is known.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            CodegenError::InFunction { span, .. } | CodegenError::InStatement { span, .. } => {
+                Some(*span)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Returns the span of the innermost function or statement that a codegen error, possibly wrapped
+/// in other errors, happened in. Errors found while building the MIR of a module know theirs too.
+pub fn innermost_span(error: &anyhow::Error) -> Option<Span> {
+    error
+        .chain()
+        .filter_map(|error| match error.downcast_ref::<CodegenError>() {
+            Some(error) => error.span(),
+            None => error.downcast_ref::<MirError>().and_then(MirError::span),
+        })
+        .last()
+}
+
+#[test]
+fn the_innermost_statement_is_reported() {
+    let error: anyhow::Error = CodegenError::InFunction {
+        function: "broken".to_string(),
+        span: Span::new(0, 40),
+        source: CodegenError::InStatement {
+            span: Span::new(20, 30),
+            source: CodegenError::VariableDoesNotExist.into(),
+        }
+        .into(),
+    }
+    .into();
+
+    assert_eq!(innermost_span(&error), Some(Span::new(20, 30)));
+    assert_eq!(
+        format!("{error:#}"),
+        "failed to generate function `broken`: failed to generate statement: no such variable \
+         was found in the current scope"
+    );
 }
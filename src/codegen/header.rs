@@ -0,0 +1,194 @@
+use std::{collections::HashSet, fmt::Write};
+
+use crate::{
+    codegen::error::CodegenError,
+    lower::mangle::{is_mangled, mangled_name},
+    spec::ast::{Item, Module, Symbol, Type, Visibility},
+};
+
+/// The C spelling of every type of pal that functions pass the way C passes the type it is spelled
+/// as, given the types [`super::generate_codegen_type`] generates for them.
+pub const C_TYPES: [(&str, &str); 7] = [
+    ("u8", "uint8_t"),
+    ("u16", "uint16_t"),
+    ("u32", "uint32_t"),
+    ("u64", "uint64_t"),
+    ("usize", "uintptr_t"),
+    ("isize", "intptr_t"),
+    ("char", "char"),
+];
+
+/// Returns the C declaration of `name` as a value of the given type, such as `uint32_t *p` or
+/// `uint32_t (*f)(uint32_t)`, or `None` when C has no type that is passed the same way. An empty
+/// name gives the type alone. Pointers to types C has no spelling for are `void *`, as every
+/// pointer is passed the same way. Enums without fields are passed as the `u32` they are lowered
+/// to, which is why `plain` holds their names.
+pub fn c_declaration(typ: &Type, name: &str, plain: &HashSet<Symbol>) -> Option<String> {
+    match typ {
+        Type::Atomic(atomic) if plain.contains(atomic) => Some(spaced("uint32_t", name)),
+        Type::Atomic(atomic) => C_TYPES
+            .iter()
+            .find(|(pal, _)| *pal == &atomic[..])
+            .map(|(_, c)| spaced(c, name)),
+        Type::Pointer(pointee) => Some(
+            c_declaration(pointee, &format!("*{name}"), plain)
+                .unwrap_or_else(|| spaced("void", &format!("*{name}"))),
+        ),
+        Type::Function(arguments, returns) => c_function(
+            &format!("(*{name})"),
+            arguments.iter().map(|typ| ("", typ)),
+            returns,
+            plain,
+        ),
+        Type::Array(..) | Type::Never => None,
+    }
+}
+
+/// Joins a type and the name it declares, which may be empty.
+fn spaced(typ: &str, name: &str) -> String {
+    match name.is_empty() {
+        true => typ.to_string(),
+        false => format!("{typ} {name}"),
+    }
+}
+
+/// Returns the C declaration of a function taking the given arguments, by name and type, and
+/// returning the given type, with `!` returning `void`.
+fn c_function<'t>(
+    name: &str,
+    arguments: impl Iterator<Item = (&'t str, &'t Type)>,
+    returns: &Type,
+    plain: &HashSet<Symbol>,
+) -> Option<String> {
+    let arguments = arguments
+        .map(|(name, typ)| c_declaration(typ, name, plain))
+        .collect::<Option<Vec<_>>>()?;
+    let arguments = match arguments.is_empty() {
+        true => "void".to_string(),
+        false => arguments.join(", "),
+    };
+
+    match returns {
+        Type::Never => Some(format!("void {name}({arguments})")),
+        returns => c_declaration(returns, &format!("{name}({arguments})"), plain),
+    }
+}
+
+/// Returns whether a function is part of the interface of a library, which every function that is
+/// public or keeps its name is.
+pub fn is_exported(item: &Item) -> bool {
+    matches!(item, Item::FunctionDeclaration(..))
+        && (item.visibility() == Visibility::Public || !is_mangled(item))
+}
+
+/// Generates a C header declaring every function a library exports, see [`is_exported`], so that
+/// C, and anything that can call C, can link against it. `guard` names the macro that keeps the
+/// header from being included twice.
+///
+/// Functions are declared under the name they are given at link time, and those whose name is
+/// mangled are given the name they are written with as a macro as well. Generic functions have no
+/// code of their own, and C has no spelling for some types, such as `str`, so functions using
+/// them are left out, with a comment saying so.
+pub fn generate_c_header(module: &Module, guard: &str) -> Result<String, CodegenError> {
+    let plain: HashSet<Symbol> = module
+        .1
+        .iter()
+        .filter_map(|item| match &item.node {
+            Item::Enum(name, variants, _)
+                if variants.iter().all(|(_, fields)| fields.is_empty()) =>
+            {
+                Some(*name)
+            }
+            _ => None,
+        })
+        .collect();
+    let guard: String = guard
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c.to_ascii_uppercase(),
+            false => '_',
+        })
+        .collect();
+
+    let mut out = String::new();
+
+    writeln!(out, "/* Generated by pal from module `{}`. */", module.0)?;
+    writeln!(out, "#ifndef {guard}_H")?;
+    writeln!(out, "#define {guard}_H")?;
+    writeln!(out)?;
+    writeln!(out, "#include <stdint.h>")?;
+    writeln!(out)?;
+    writeln!(out, "#ifdef __cplusplus")?;
+    writeln!(out, "extern \"C\" {{")?;
+    writeln!(out, "#endif")?;
+    writeln!(out)?;
+
+    for item in module.1.iter().filter(|item| is_exported(item)) {
+        let Item::FunctionDeclaration(name, generics, args, returns, ..) = &item.node else {
+            continue;
+        };
+
+        if !generics.is_empty() {
+            writeln!(out, "/* `{name}` is generic, so it has no code to call. */")?;
+            continue;
+        }
+
+        let linked = match is_mangled(item) {
+            true => mangled_name(&module.0, name, args),
+            false => *name,
+        };
+        let arguments = args.iter().map(|(arg, typ)| (&arg[..], &typ.node));
+
+        match c_function(&linked, arguments, returns, &plain) {
+            Some(declaration) => writeln!(out, "{declaration};")?,
+            None => {
+                writeln!(
+                    out,
+                    "/* `{name}` takes or returns a type C has no spelling for. */"
+                )?;
+                continue;
+            }
+        }
+
+        if linked != *name {
+            writeln!(out, "#define {name} {linked}")?;
+        }
+    }
+
+    writeln!(out)?;
+    writeln!(out, "#ifdef __cplusplus")?;
+    writeln!(out, "}}")?;
+    writeln!(out, "#endif")?;
+    writeln!(out)?;
+    writeln!(out, "#endif")?;
+
+    Ok(out)
+}
+
+#[test]
+fn headers_declare_exported_functions() {
+    let (module, _) = crate::spec::module("util".to_string())
+        .parse(
+            "enum Color { Red, Green };
+            pub fn add(a: u64, b: *u8) -> u64 { return a; };
+            #[no_mangle] fn apply(f: fn(u32) -> u32, c: Color) -> isize { return 0 as isize; };
+            pub fn stop(p: *str) -> ! { unreachable(); };
+            pub fn greet(s: str) -> u32 { return 0; };
+            pub fn id<T>(x: T) -> T { return x; };
+            fn hidden() -> u32 { return 0; };",
+        )
+        .unwrap();
+
+    let header = generate_c_header(&module, "libutil").unwrap();
+
+    assert!(header.contains("#ifndef LIBUTIL_H"));
+    assert!(header.contains(
+        "uint64_t _P4util3add3u64P2u8(uint64_t a, uint8_t *b);\n#define add _P4util3add3u64P2u8\n"
+    ));
+    assert!(header.contains(
+        "intptr_t apply(uint32_t (*f)(uint32_t), uint32_t c);\nvoid _P4util4stopP3str(void *p);\n#define stop"
+    ));
+    assert!(header.contains("/* `greet` takes or returns a type C has no spelling for. */"));
+    assert!(header.contains("/* `id` is generic, so it has no code to call. */"));
+    assert!(!header.contains("hidden"));
+}
@@ -1,37 +1,405 @@
 pub mod error;
+pub mod header;
+pub mod runtime;
 
 use inkwell::{
-    AddressSpace,
+    AddressSpace, IntPredicate,
+    attributes::{Attribute, AttributeLoc},
+    basic_block::BasicBlock,
     builder::Builder,
     context::Context,
-    module::Module as CodegenModule,
-    types::{BasicType, BasicTypeEnum},
-    values::{BasicValue, BasicValueEnum},
+    module::{Linkage, Module as CodegenModule},
+    targets::TargetData,
+    types::{BasicMetadataTypeEnum, BasicType, BasicTypeEnum, FunctionType, StructType},
+    values::{
+        BasicMetadataValueEnum, BasicValue, BasicValueEnum, FunctionValue, IntValue, PointerValue,
+    },
 };
 
-use crate::{codegen::error::CodegenError, spec::ast::*};
+use crate::{
+    backend::PanicStrategy,
+    codegen::{
+        error::CodegenError,
+        runtime::{RuntimeDecls, define_panic, define_runtime_function},
+    },
+    lower::{
+        constructor_name, field_name, lower_program,
+        mangle::mangle_program,
+        strings::{STR_CONCAT, STR_LEN, STR_NEW, STR_PTR},
+        tag_name,
+    },
+    mir::{
+        BinOp, BlockId, Body, Callee, Constant, Operand, Rvalue, Statement, Terminator, UnOp,
+        build::build,
+    },
+    mono::is_instance,
+    parser::{Span, Spanned},
+    spec::ast::{Abi, Expression, Item, Module, Type, Variant},
+};
+
+/// Wraps an error with the span of the statement it happened in. Errors that already know their
+/// statement keep it, so that the innermost statement is the one reported.
+fn in_statement(span: Span, result: anyhow::Result<()>) -> anyhow::Result<()> {
+    result.map_err(|error| match error.downcast_ref::<CodegenError>() {
+        Some(CodegenError::InStatement { .. }) => error,
+        _ => CodegenError::InStatement {
+            span,
+            source: error,
+        }
+        .into(),
+    })
+}
 
-/// Generates an LLVM Basic Type from a given AST type node.
+/// Generates an LLVM Basic Type from a given AST type node. `usize` and `isize` are as wide as a
+/// pointer under the data layout of the module.
 pub fn generate_codegen_type<'ctx>(
     context: &'ctx Context,
+    module: &CodegenModule<'ctx>,
     typ: &Type,
 ) -> anyhow::Result<BasicTypeEnum<'ctx>> {
     match typ {
         Type::Atomic(ident) => match &ident[..] {
+            "u8" => Ok(context.i8_type().as_basic_type_enum()),
+            "u16" => Ok(context.i16_type().as_basic_type_enum()),
             "u32" => Ok(context.i32_type().as_basic_type_enum()),
+            "u64" => Ok(context.i64_type().as_basic_type_enum()),
+            "usize" | "isize" => {
+                let layout = module.get_data_layout();
+                let target = TargetData::create(&layout.as_str().to_string_lossy());
+
+                Ok(context
+                    .ptr_sized_int_type(&target, None)
+                    .as_basic_type_enum())
+            }
             "char" => Ok(context.i8_type().as_basic_type_enum()),
-            _ => Err(CodegenError::TypeDoesNotExist.into()),
+            "str" => Ok(generate_codegen_str_type(context).as_basic_type_enum()),
+            // Enums with fields are declared as structs of the same name, see
+            // [`generate_codegen_enum`].
+            name => context
+                .get_struct_type(name)
+                .map(|typ| typ.as_basic_type_enum())
+                .ok_or(CodegenError::TypeDoesNotExist.into()),
         },
-        Type::Pointer(_) => Ok(context
+        Type::Pointer(_) | Type::Function(..) => Ok(context
             .ptr_type(AddressSpace::default())
             .as_basic_type_enum()),
+        Type::Array(element, length) => Ok(generate_codegen_type(context, module, element)?
+            .array_type(*length as u32)
+            .as_basic_type_enum()),
+        Type::Never => Err(CodegenError::NeverValue.into()),
+    }
+}
+
+/// Generates the LLVM type of a function from the AST types of its arguments and of the value it
+/// returns. Functions that never return are void as far as LLVM is concerned.
+pub fn generate_codegen_fn_type<'ctx, 't>(
+    context: &'ctx Context,
+    module: &CodegenModule<'ctx>,
+    arguments: impl IntoIterator<Item = &'t Type>,
+    returns: &Type,
+) -> anyhow::Result<FunctionType<'ctx>> {
+    let argument_types = arguments
+        .into_iter()
+        .map(|typ| generate_codegen_type(context, module, typ).map(BasicMetadataTypeEnum::from))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(match returns {
+        Type::Never => context.void_type().fn_type(&argument_types, false),
+        typ => generate_codegen_type(context, module, typ)?.fn_type(&argument_types, false),
+    })
+}
+
+/// Returns the LLVM type of the function that a value of the given AST type points to, if it is a
+/// function pointer.
+pub fn generate_codegen_signature<'ctx>(
+    context: &'ctx Context,
+    module: &CodegenModule<'ctx>,
+    typ: &Type,
+) -> Option<FunctionType<'ctx>> {
+    match typ {
+        Type::Function(arguments, returns) => {
+            generate_codegen_fn_type(context, module, arguments, returns).ok()
+        }
+        _ => None,
+    }
+}
+
+/// Returns whether the block the [`Builder`] is positioned in already ends in a terminator, such as
+/// a `ret` or an `unreachable`, after which no more instructions may be added.
+pub fn is_terminated(builder: &Builder<'_>) -> bool {
+    builder
+        .get_insert_block()
+        .is_some_and(|block| block.get_terminator().is_some())
+}
+
+/// The stack slot of every local of the function being generated, along with the type that is
+/// stored in it, by the position of the local in [`Body::locals`].
+pub type Slots<'ctx> = [(PointerValue<'ctx>, BasicTypeEnum<'ctx>)];
+
+/// Returns the function with the given name, defining it first when it is one of those that
+/// lowered code or the runtime expect every backend to provide.
+/// Returns the LLVM calling convention that a function declared with the given one is called
+/// with. The numbers are those of `llvm::CallingConv`.
+pub fn call_convention(abi: Abi) -> u32 {
+    match abi {
+        Abi::C => 0,
+        Abi::Stdcall => 64,
+        Abi::Fastcall => 65,
+        Abi::Sysv64 => 78,
+        Abi::Win64 => 79,
+    }
+}
+
+fn generate_codegen_function_reference<'ctx>(
+    context: &'ctx Context,
+    module: &CodegenModule<'ctx>,
+    name: &str,
+) -> anyhow::Result<FunctionValue<'ctx>> {
+    match module.get_function(name) {
+        Some(function) => Ok(function),
+        None => match generate_codegen_string_builtin(context, module, name)? {
+            Some(function) => Ok(function),
+            None => Ok(define_runtime_function(context, module, name)?
+                .ok_or(CodegenError::FunctionDoesNotExist)?),
+        },
+    }
+}
+
+/// Generates an LLVM Basic Value from a MIR operand, reading it from its stack slot when it is a
+/// local.
+pub fn generate_codegen_operand<'ctx>(
+    context: &'ctx Context,
+    module: &CodegenModule<'ctx>,
+    builder: &Builder<'ctx>,
+    slots: &Slots<'ctx>,
+    operand: &Operand,
+) -> anyhow::Result<BasicValueEnum<'ctx>> {
+    match operand {
+        Operand::Copy(local) => {
+            let (pointer, typ) = slots[local.0];
+            Ok(builder.build_load(typ, pointer, "")?)
+        }
+        Operand::Constant(Constant::Int(value, typ)) => {
+            match generate_codegen_type(context, module, typ)? {
+                BasicTypeEnum::IntType(typ) => {
+                    Ok(typ.const_int(*value, false).as_basic_value_enum())
+                }
+                _ => Err(CodegenError::NonIntegerOperand.into()),
+            }
+        }
+        Operand::Constant(Constant::Char(value)) => Ok(context
+            .i8_type()
+            .const_int(*value as u64, false)
+            .as_basic_value_enum()),
+        Operand::Constant(Constant::Str(value)) => Ok(builder
+            .build_global_string_ptr(value, "")?
+            .as_basic_value_enum()),
+        Operand::Constant(Constant::Function(name)) => {
+            Ok(generate_codegen_function_reference(context, module, name)?
+                .as_global_value()
+                .as_pointer_value()
+                .as_basic_value_enum())
+        }
+    }
+}
+
+/// Generates an LLVM Basic Value from a MIR rvalue.
+pub fn generate_codegen_rvalue<'ctx>(
+    context: &'ctx Context,
+    module: &CodegenModule<'ctx>,
+    builder: &Builder<'ctx>,
+    body: &Body,
+    slots: &Slots<'ctx>,
+    rvalue: &Rvalue,
+) -> anyhow::Result<BasicValueEnum<'ctx>> {
+    let operand =
+        |operand: &Operand| generate_codegen_operand(context, module, builder, slots, operand);
+    // LLVM pointers are opaque, so what they point to is read from the MIR.
+    let pointee = |typ: Option<Type>| -> anyhow::Result<BasicTypeEnum<'ctx>> {
+        generate_codegen_type(context, module, &typ.ok_or(CodegenError::UnknownPointee)?)
+    };
+
+    match rvalue {
+        Rvalue::Use(value) => operand(value),
+        Rvalue::Unary(op, value) => {
+            let BasicValueEnum::IntValue(value) = operand(value)? else {
+                return Err(CodegenError::NonIntegerOperand.into());
+            };
+
+            let result = match op {
+                UnOp::Neg => builder.build_int_neg(value, "")?,
+                UnOp::BitNot => builder.build_not(value, "")?,
+                UnOp::Not => {
+                    let zero = value.get_type().const_zero();
+                    let is_zero = builder.build_int_compare(IntPredicate::EQ, value, zero, "")?;
+
+                    builder.build_int_z_extend(is_zero, value.get_type(), "")?
+                }
+            };
+
+            Ok(result.as_basic_value_enum())
+        }
+        Rvalue::Binary(op, left, right) => {
+            let signed = is_signed(body, left);
+            let (BasicValueEnum::IntValue(left), BasicValueEnum::IntValue(right)) =
+                (operand(left)?, operand(right)?)
+            else {
+                return Err(CodegenError::NonIntegerOperand.into());
+            };
+
+            // Shifts take an amount of any integer type, which LLVM wants as wide as the value.
+            let right = builder.build_int_cast(right, left.get_type(), "")?;
+
+            let result = match op {
+                BinOp::BitAnd => builder.build_and(left, right, "")?,
+                BinOp::BitOr => builder.build_or(left, right, "")?,
+                BinOp::BitXor => builder.build_xor(left, right, "")?,
+                BinOp::Shl => builder.build_left_shift(left, right, "")?,
+                // Right shifts are arithmetic for `isize`, the only signed integer type.
+                BinOp::Shr => builder.build_right_shift(left, right, signed, "")?,
+                BinOp::Add => builder.build_int_add(left, right, "")?,
+                // Comparisons give a `u32`, whatever the type of their operands.
+                BinOp::Eq | BinOp::Ne | BinOp::Lt => {
+                    let predicate = match op {
+                        BinOp::Eq => IntPredicate::EQ,
+                        BinOp::Ne => IntPredicate::NE,
+                        _ => IntPredicate::ULT,
+                    };
+                    let result = builder.build_int_compare(predicate, left, right, "")?;
+
+                    builder.build_int_z_extend(result, context.i32_type(), "")?
+                }
+            };
+
+            Ok(result.as_basic_value_enum())
+        }
+        Rvalue::Deref(value) => {
+            let pointee = pointee(body.pointee(value))?;
+            let BasicValueEnum::PointerValue(pointer) = operand(value)? else {
+                return Err(CodegenError::UnknownPointee.into());
+            };
+
+            Ok(builder.build_load(pointee, pointer, "")?)
+        }
+        Rvalue::AddressOf(local) => Ok(slots[local.0].0.as_basic_value_enum()),
+        // Indexing a local pointer reads the element at that offset from where it points.
+        Rvalue::Index(local, index) => {
+            let pointee = pointee(body.pointee(&Operand::Copy(*local)))?;
+            let pointer = operand(&Operand::Copy(*local))?.into_pointer_value();
+            let BasicValueEnum::IntValue(index) = operand(index)? else {
+                return Err(CodegenError::NonIntegerIndex.into());
+            };
+
+            let element = unsafe { builder.build_gep(pointee, pointer, &[index], "")? };
+
+            Ok(builder.build_load(pointee, element, "")?)
+        }
+        Rvalue::ConstIndex(name, index) => {
+            let global = module
+                .get_global(name)
+                .ok_or(CodegenError::ConstDoesNotExist)?;
+            let array_type = global.get_value_type().into_array_type();
+
+            // A constant index into a constant defined in this module is folded into the element
+            // itself, since the builder folds extracting from a constant.
+            if let Operand::Constant(Constant::Int(value, _)) = index
+                && let Some(BasicValueEnum::ArrayValue(array)) = global.get_initializer()
+            {
+                return Ok(builder.build_extract_value(array, *value as u32, name)?);
+            }
+
+            let BasicValueEnum::IntValue(index) = operand(index)? else {
+                return Err(CodegenError::NonIntegerIndex.into());
+            };
+
+            let zero = context.i32_type().const_zero();
+            // Sema has checked that the index has the type of an index, and constant indices
+            // against the length of the array.
+            let pointer = unsafe {
+                builder.build_in_bounds_gep(
+                    array_type,
+                    global.as_pointer_value(),
+                    &[zero, index],
+                    "",
+                )?
+            };
+
+            Ok(builder.build_load(array_type.get_element_type(), pointer, name)?)
+        }
+        Rvalue::New(typ, value) => {
+            let typ = generate_codegen_type(context, module, typ)?;
+            let value = operand(value)?;
+            let pointer = generate_codegen_malloc(context, module, builder, typ, None)?;
+
+            builder.build_store(pointer, value)?;
+
+            Ok(pointer.as_basic_value_enum())
+        }
+        Rvalue::Alloc(typ, count) => {
+            let typ = generate_codegen_type(context, module, typ)?;
+            let count = operand(count)?;
+            let pointer = generate_codegen_malloc(
+                context,
+                module,
+                builder,
+                typ,
+                Some(count.into_int_value()),
+            )?;
+
+            Ok(pointer.as_basic_value_enum())
+        }
+        Rvalue::Cast(value, typ) => {
+            let signed = is_signed(body, value);
+            let BasicValueEnum::IntValue(value) = operand(value)? else {
+                return Err(CodegenError::NonIntegerOperand.into());
+            };
+            let BasicTypeEnum::IntType(typ) = generate_codegen_type(context, module, typ)? else {
+                return Err(CodegenError::NonIntegerOperand.into());
+            };
+
+            Ok(builder
+                .build_int_cast_sign_flag(value, typ, signed, "")?
+                .as_basic_value_enum())
+        }
+    }
+}
+
+/// Returns whether an operand is an `isize`, which is extended and shifted with its sign.
+fn is_signed(body: &Body, operand: &Operand) -> bool {
+    matches!(operand, Operand::Copy(local) if body.locals[local.0].typ == Type::Atomic("isize".into()))
+}
+
+/// Allocates room on the heap for a number of values of a type, or for a single one when no
+/// number is given, returning a pointer to the first of them. LLVM works the size of the type out
+/// from the data layout of the target.
+fn generate_codegen_malloc<'ctx>(
+    context: &'ctx Context,
+    module: &CodegenModule<'ctx>,
+    builder: &Builder<'ctx>,
+    typ: BasicTypeEnum<'ctx>,
+    count: Option<IntValue<'ctx>>,
+) -> anyhow::Result<PointerValue<'ctx>> {
+    let runtime = RuntimeDecls::new(context, module);
+    let mut size = typ.size_of().ok_or(CodegenError::TypeDoesNotExist)?;
+
+    if let Some(count) = count {
+        let count = builder.build_int_z_extend(count, size.get_type(), "")?;
+        size = builder.build_int_mul(size, count, "")?;
     }
+
+    Ok(builder
+        .build_call(runtime.malloc()?, &[size.into()], "")?
+        .try_as_basic_value()
+        .basic()
+        .ok_or(CodegenError::NoReturnValue)?
+        .into_pointer_value())
 }
 
-/// Generates an LLVM Basic Value from a given AST expression node.
-pub fn generate_codegen_expression<'ctx>(
+/// Generates the constant value of a literal, for use in the initializer of a global. String
+/// literals are stored in globals of their own.
+pub fn generate_codegen_constant<'ctx>(
     context: &'ctx Context,
-    builder: &'ctx Builder,
+    module: &CodegenModule<'ctx>,
     expression: &Expression,
 ) -> anyhow::Result<BasicValueEnum<'ctx>> {
     match expression {
@@ -39,88 +407,521 @@ pub fn generate_codegen_expression<'ctx>(
             .i32_type()
             .const_int(*value, false)
             .as_basic_value_enum()),
-        Expression::StringLiteral(value) => Ok(builder
-            .build_global_string_ptr(&value, "")?
+        // Literals that were given a type other than `u32` are constants of that type.
+        Expression::Cast(value, typ) if let Expression::NumericLiteral(value) = value.node => {
+            match generate_codegen_type(context, module, typ)? {
+                BasicTypeEnum::IntType(typ) => {
+                    Ok(typ.const_int(value, false).as_basic_value_enum())
+                }
+                _ => Err(CodegenError::NonConstElement.into()),
+            }
+        }
+        Expression::CharLiteral(value) => Ok(context
+            .i8_type()
+            .const_int(*value as u64, false)
             .as_basic_value_enum()),
+        Expression::StringLiteral(value) => {
+            let string = context.const_string(value.as_bytes(), true);
+            let global = module.add_global(string.get_type(), None, "");
+
+            global.set_initializer(&string);
+            global.set_constant(true);
+            global.set_linkage(Linkage::Private);
+
+            Ok(global.as_pointer_value().as_basic_value_enum())
+        }
+        _ => Err(CodegenError::NonConstElement.into()),
     }
 }
 
-/// Generates LLVM instruction values via the [`Builder`] depending on the type of statement
-/// parsed.
-pub fn generate_codegen_statement(
-    context: &Context,
-    module: &CodegenModule,
+/// Generates LLVM instruction values via the [`Builder`] for a MIR statement.
+pub fn generate_codegen_statement<'ctx>(
+    context: &'ctx Context,
+    module: &CodegenModule<'ctx>,
+    builder: &Builder<'ctx>,
+    body: &Body,
+    slots: &Slots<'ctx>,
     statement: &Statement,
-    builder: &Builder,
 ) -> anyhow::Result<()> {
+    let operand =
+        |operand: &Operand| generate_codegen_operand(context, module, builder, slots, operand);
+
     match statement {
-        Statement::FunctionCall(name, expression) => {
-            let fn_reference = module
-                .get_function(&name)
-                .ok_or(CodegenError::FunctionDoesNotExist)?;
+        Statement::Assign(local, rvalue) => {
+            let value = generate_codegen_rvalue(context, module, builder, body, slots, rvalue)?;
+            builder.build_store(slots[local.0].0, value)?;
+        }
+        Statement::Call(destination, callee, args) => {
+            let args = args
+                .iter()
+                .map(|arg| operand(arg).map(BasicMetadataValueEnum::from))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+
+            let call = match callee {
+                Callee::Function(name) => {
+                    let function = generate_codegen_function_reference(context, module, name)?;
+                    let call = builder.build_call(function, &args, "")?;
+
+                    // A call must use the convention of the function it calls, or its behaviour
+                    // is undefined.
+                    call.set_call_convention(function.get_call_conventions());
+                    call
+                }
+                Callee::Pointer(local) => {
+                    let signature =
+                        generate_codegen_signature(context, module, &body.locals[local.0].typ)
+                            .ok_or(CodegenError::FunctionDoesNotExist)?;
+                    let pointer = operand(&Operand::Copy(*local))?.into_pointer_value();
+
+                    builder.build_indirect_call(signature, pointer, &args, "")?
+                }
+            };
 
-            let exprs: Vec<_> = expression
+            if let Some(destination) = destination {
+                let value = call
+                    .try_as_basic_value()
+                    .basic()
+                    .ok_or(CodegenError::NoReturnValue)?;
+
+                builder.build_store(slots[destination.0].0, value)?;
+            }
+        }
+        Statement::Free(pointer) => {
+            let runtime = RuntimeDecls::new(context, module);
+            builder.build_call(runtime.free()?, &[operand(pointer)?.into()], "")?;
+        }
+        Statement::Asm(template) => {
+            // Without operands or clobbers, the assembly is a call to a function taking and
+            // returning nothing, which is marked as having side effects so that it is kept.
+            let fn_type = context.void_type().fn_type(&[], false);
+            let asm = context.create_inline_asm(
+                fn_type,
+                template.clone(),
+                String::new(),
+                true,
+                false,
+                None,
+                false,
+            );
+
+            builder.build_indirect_call(fn_type, asm, &[], "")?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Generates the LLVM terminator of a basic block from a MIR terminator, given the LLVM basic
+/// block of every MIR block.
+pub fn generate_codegen_terminator<'ctx>(
+    context: &'ctx Context,
+    module: &CodegenModule<'ctx>,
+    builder: &Builder<'ctx>,
+    slots: &Slots<'ctx>,
+    blocks: &[BasicBlock<'ctx>],
+    terminator: &Terminator,
+) -> anyhow::Result<()> {
+    let operand =
+        |operand: &Operand| generate_codegen_operand(context, module, builder, slots, operand);
+
+    match terminator {
+        Terminator::Goto(block) => {
+            builder.build_unconditional_branch(blocks[block.0])?;
+        }
+        Terminator::Switch(value, cases, default) => {
+            let BasicValueEnum::IntValue(value) = operand(value)? else {
+                return Err(CodegenError::NonIntegerMatch.into());
+            };
+
+            let cases: Vec<_> = cases
                 .iter()
-                .map(|expression| {
-                    generate_codegen_expression(context, builder, expression)
-                        .unwrap()
-                        .into()
-                })
+                .map(|(case, block)| (value.get_type().const_int(*case, false), blocks[block.0]))
                 .collect();
 
-            builder.build_call(fn_reference, &exprs, "")?;
+            builder.build_switch(value, blocks[default.0], &cases)?;
         }
-        Statement::Return(expression) => {
-            builder.build_return(Some(&generate_codegen_expression(
-                context, builder, expression,
-            )?))?;
+        Terminator::Return(value) => {
+            builder.build_return(Some(&operand(value)?))?;
+        }
+        Terminator::Unreachable => {
+            builder.build_unreachable()?;
         }
     }
 
     Ok(())
 }
 
-/// Generates LLVM top-level items like functions and blocks.
-/// This means language features like function declarations, their implementations and extern
-/// definitions.
+/// Generates the body of a function from its MIR. Every local is given a stack slot in the entry
+/// block, which keeps slots of locals declared inside loop bodies from growing the stack, and the
+/// arguments are spilled into theirs so that they can be read like any other local.
+pub fn generate_codegen_body<'ctx>(
+    context: &'ctx Context,
+    module: &CodegenModule<'ctx>,
+    body: &Body,
+) -> anyhow::Result<()> {
+    let function = module
+        .get_function(&body.name)
+        .ok_or(CodegenError::FunctionDoesNotExist)?;
+    let builder = context.create_builder();
+    builder.position_at_end(context.append_basic_block(function, &body.name));
+
+    let slots = body
+        .locals
+        .iter()
+        .map(|local| {
+            let typ = generate_codegen_type(context, module, &local.typ)?;
+            let name = local.name.as_deref().unwrap_or_default();
+
+            Ok((builder.build_alloca(typ, name)?, typ))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    for ((pointer, _), param) in slots.iter().zip(function.get_param_iter()) {
+        builder.build_store(*pointer, param)?;
+    }
+
+    let blocks: Vec<_> = (0..body.blocks.len())
+        .map(|index| context.append_basic_block(function, &BlockId(index).to_string()))
+        .collect();
+
+    builder.build_unconditional_branch(blocks[0])?;
+
+    for (block, mir) in blocks.iter().zip(&body.blocks) {
+        builder.position_at_end(*block);
+
+        for statement in &mir.statements {
+            in_statement(
+                statement.span,
+                generate_codegen_statement(context, module, &builder, body, &slots, statement),
+            )?;
+        }
+
+        in_statement(
+            mir.terminator.span,
+            generate_codegen_terminator(
+                context,
+                module,
+                &builder,
+                &slots,
+                &blocks,
+                &mir.terminator,
+            ),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Generates the LLVM struct type of an enum with fields, which holds its discriminant and, for
+/// each field of the variant with the most fields, an `i64` slot wide enough for any field. Along
+/// with it, private functions are defined in the module for lowered code to build its variants and
+/// to read its discriminant and fields, see [`crate::lower::lower_enums`]. The struct type is
+/// shared by every module of the context, but each module defines the functions it uses itself.
+pub fn generate_codegen_enum<'ctx>(
+    context: &'ctx Context,
+    module: &CodegenModule<'ctx>,
+    name: &str,
+    variants: &[Variant],
+) -> anyhow::Result<()> {
+    let slot = context.i64_type();
+    let slots = variants
+        .iter()
+        .map(|(_, fields)| fields.len())
+        .max()
+        .unwrap_or_default();
+
+    let struct_type = context.get_struct_type(name).unwrap_or_else(|| {
+        let struct_type = context.opaque_struct_type(name);
+        let tag = context.i32_type().as_basic_type_enum();
+
+        struct_type.set_body(&[tag, slot.array_type(slots as u32).into()], false);
+        struct_type
+    });
+
+    if module.get_function(&tag_name(name)).is_some() {
+        return Ok(());
+    }
+
+    let builder = context.create_builder();
+    let define = |name: &str, fn_type| {
+        let function = module.add_function(name, fn_type, Some(Linkage::Private));
+        builder.position_at_end(context.append_basic_block(function, "entry"));
+        function
+    };
+
+    let tag = define(
+        &tag_name(name),
+        context.i32_type().fn_type(&[struct_type.into()], false),
+    );
+    let value = tag.get_nth_param(0).ok_or(CodegenError::NoInsertionBlock)?;
+    builder.build_return(Some(&builder.build_extract_value(
+        value.into_struct_value(),
+        0,
+        "",
+    )?))?;
+
+    for (discriminant, (variant, fields)) in variants.iter().enumerate() {
+        let field_types = fields
+            .iter()
+            .map(|field| generate_codegen_type(context, module, field))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let params: Vec<_> = field_types.iter().map(|typ| (*typ).into()).collect();
+
+        // Fields are widened into their slots, and narrowed back when they are read.
+        let constructor = define(
+            &constructor_name(name, variant),
+            struct_type.fn_type(&params, false),
+        );
+        let mut slots = slot.array_type(slots as u32).get_undef();
+
+        for (index, param) in constructor.get_param_iter().enumerate() {
+            let widened = match param {
+                BasicValueEnum::PointerValue(pointer) => {
+                    builder.build_ptr_to_int(pointer, slot, "")?
+                }
+                value => builder.build_int_z_extend(value.into_int_value(), slot, "")?,
+            };
+
+            slots = builder
+                .build_insert_value(slots, widened, index as u32, "")?
+                .into_array_value();
+        }
+
+        let tag = context.i32_type().const_int(discriminant as u64, false);
+        let value = builder.build_insert_value(struct_type.get_undef(), tag, 0, "")?;
+        let value = builder.build_insert_value(value, slots, 1, "")?;
+        builder.build_return(Some(&value.into_struct_value()))?;
+
+        for (index, typ) in field_types.iter().enumerate() {
+            let reader = define(
+                &field_name(name, variant, index),
+                typ.fn_type(&[struct_type.into()], false),
+            );
+            let value = reader
+                .get_nth_param(0)
+                .ok_or(CodegenError::NoInsertionBlock)?;
+
+            let slots = builder
+                .build_extract_value(value.into_struct_value(), 1, "")?
+                .into_array_value();
+            let field = builder
+                .build_extract_value(slots, index as u32, "")?
+                .into_int_value();
+
+            let narrowed = match typ {
+                BasicTypeEnum::PointerType(pointer) => builder
+                    .build_int_to_ptr(field, *pointer, "")?
+                    .as_basic_value_enum(),
+                typ => builder
+                    .build_int_truncate(field, typ.into_int_type(), "")?
+                    .as_basic_value_enum(),
+            };
+
+            builder.build_return(Some(&narrowed))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Generates the LLVM type of a `str`, a pointer to its bytes along with their number.
+pub fn generate_codegen_str_type(context: &Context) -> StructType<'_> {
+    context.struct_type(
+        &[
+            context.ptr_type(AddressSpace::default()).into(),
+            context.i32_type().into(),
+        ],
+        false,
+    )
+}
+
+/// Defines the function lowered code calls for an operation on strings, see
+/// [`crate::lower::strings::lower_strings`], if it has not been defined yet. Like the functions of
+/// enums, each module defines those it uses itself as private functions. Returns `None` when the
+/// name is not one of them.
+pub fn generate_codegen_string_builtin<'ctx>(
+    context: &'ctx Context,
+    module: &CodegenModule<'ctx>,
+    name: &str,
+) -> anyhow::Result<Option<FunctionValue<'ctx>>> {
+    let str_type = generate_codegen_str_type(context);
+    let ptr = context.ptr_type(AddressSpace::default());
+    let u32 = context.i32_type();
+
+    let fn_type = match name {
+        STR_NEW => str_type.fn_type(&[ptr.into(), u32.into()], false),
+        STR_PTR => ptr.fn_type(&[str_type.into()], false),
+        STR_LEN => u32.fn_type(&[str_type.into()], false),
+        STR_CONCAT => str_type.fn_type(&[str_type.into(), str_type.into()], false),
+        _ => return Ok(None),
+    };
+
+    let function = module.add_function(name, fn_type, Some(Linkage::Private));
+    let builder = context.create_builder();
+    builder.position_at_end(context.append_basic_block(function, "entry"));
+
+    let param = |index| {
+        function
+            .get_nth_param(index)
+            .ok_or(CodegenError::NoInsertionBlock)
+    };
+
+    let returned = match name {
+        STR_NEW => {
+            let value = builder.build_insert_value(str_type.get_undef(), param(0)?, 0, "")?;
+            builder
+                .build_insert_value(value, param(1)?, 1, "")?
+                .as_basic_value_enum()
+        }
+        STR_PTR => builder.build_extract_value(param(0)?.into_struct_value(), 0, "")?,
+        STR_LEN => builder.build_extract_value(param(0)?.into_struct_value(), 1, "")?,
+        _ => {
+            // The bytes of both strings are copied into a new allocation, followed by a NUL so
+            // that the result can still be passed to C.
+            let runtime = RuntimeDecls::new(context, module);
+            let (left, right) = (param(0)?.into_struct_value(), param(1)?.into_struct_value());
+            let i64 = context.i64_type();
+
+            let left_len = builder.build_extract_value(left, 1, "")?.into_int_value();
+            let right_len = builder.build_extract_value(right, 1, "")?.into_int_value();
+            let len = builder.build_int_add(left_len, right_len, "")?;
+
+            let size = builder.build_int_z_extend(len, i64, "")?;
+            let size = builder.build_int_add(size, i64.const_int(1, false), "")?;
+            let bytes = builder
+                .build_call(runtime.malloc()?, &[size.into()], "")?
+                .try_as_basic_value()
+                .basic()
+                .ok_or(CodegenError::NoInsertionBlock)?
+                .into_pointer_value();
+
+            for (value, len, offset) in [
+                (left, left_len, u32.const_zero()),
+                (right, right_len, left_len),
+            ] {
+                let source = builder.build_extract_value(value, 0, "")?;
+                // SAFETY: the allocation is one byte longer than both strings together.
+                let destination =
+                    unsafe { builder.build_gep(context.i8_type(), bytes, &[offset], "")? };
+                let len = builder.build_int_z_extend(len, i64, "")?;
+
+                builder.build_call(
+                    runtime.memcpy()?,
+                    &[destination.into(), source.into(), len.into()],
+                    "",
+                )?;
+            }
+
+            // SAFETY: as above, the last byte of the allocation is the one after both strings.
+            let end = unsafe { builder.build_gep(context.i8_type(), bytes, &[len], "")? };
+            builder.build_store(end, context.i8_type().const_zero())?;
+
+            let value = builder.build_insert_value(str_type.get_undef(), bytes, 0, "")?;
+            builder
+                .build_insert_value(value, len, 1, "")?
+                .as_basic_value_enum()
+        }
+    };
+
+    builder.build_return(Some(&returned))?;
+
+    Ok(Some(function))
+}
+
+/// Declares the LLVM function for a top-level item, without generating its body. Every item is
+/// declared before any body is generated, so that functions may be called before they are defined.
+pub fn generate_codegen_declaration<'a>(
+    context: &'a Context,
+    runtime: &RuntimeDecls<'_, 'a>,
+    item: &Item,
+) -> anyhow::Result<()> {
+    match item {
+        // Enums without fields have been lowered away, and those with fields are generated by
+        // [`generate_codegen_enum`] before anything else.
+        Item::Enum(..) => {}
+        // Constants are declared without a value, which is only given to them in the module that
+        // defines them.
+        Item::Const(name, typ, _, _) => {
+            let module = runtime.module();
+            let global =
+                module.add_global(generate_codegen_type(context, module, typ)?, None, name);
+
+            global.set_constant(true);
+        }
+        Item::ExternFunctionDefinition(_, args, typ, ..)
+        | Item::FunctionDeclaration(_, _, args, typ, ..) => {
+            let fn_type = generate_codegen_fn_type(
+                context,
+                runtime.module(),
+                args.iter().map(|(_, typ)| &typ.node),
+                typ,
+            )?;
+
+            let function = runtime.declare(item.name(), fn_type)?;
+            function.set_call_conventions(call_convention(item.abi()));
+
+            // Every module that calls a generic function has its own instances of it.
+            if is_instance(item.name()) {
+                function.set_linkage(Linkage::Private);
+            }
+
+            if item.diverges() {
+                let noreturn = Attribute::get_named_enum_kind_id("noreturn");
+
+                function.add_attribute(
+                    AttributeLoc::Function,
+                    context.create_enum_attribute(noreturn, 0),
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Generates the value of a top-level item that is not a function, which are generated from their
+/// MIR instead. The item must already have been declared with [`generate_codegen_declaration`].
 pub fn generate_codegen_item<'a>(
     context: &'a Context,
     module: &CodegenModule<'a>,
     item: &Item,
 ) -> anyhow::Result<()> {
     match item {
-        Item::ExternFunctionDefinition(name, args, typ) => {
-            let argument_types: Vec<_> = args
-                .iter()
-                .map(|(_, typ)| generate_codegen_type(context, typ).unwrap().into())
-                .collect();
+        Item::ExternFunctionDefinition(..) | Item::Enum(..) => {}
+        Item::Const(name, typ, elements, _) => {
+            let global = module
+                .get_global(name)
+                .ok_or(CodegenError::ConstDoesNotExist)?;
 
-            module.add_function(
-                name,
-                generate_codegen_type(context, typ)?.fn_type(&argument_types, false),
-                None,
-            );
-        }
-        Item::FunctionDeclaration(name, args, typ, body) => {
-            let argument_types: Vec<_> = args
+            let elements = elements
                 .iter()
-                .map(|(_, typ)| generate_codegen_type(context, typ).unwrap().into())
-                .collect();
+                .map(|element| generate_codegen_constant(context, module, element))
+                .collect::<anyhow::Result<Vec<_>>>()?;
 
-            let fn_decl = module.add_function(
-                &name,
-                generate_codegen_type(context, typ)?.fn_type(&argument_types, false),
-                None,
-            );
-            let fn_block = context.append_basic_block(fn_decl, &name);
+            let Type::Array(element_type, _) = &typ.node else {
+                return Err(CodegenError::NonConstElement.into());
+            };
 
-            let builder = context.create_builder();
-            builder.position_at_end(fn_block);
+            let array = match generate_codegen_type(context, module, element_type)? {
+                BasicTypeEnum::IntType(typ) => typ.const_array(
+                    &elements
+                        .iter()
+                        .map(|element| element.into_int_value())
+                        .collect::<Vec<_>>(),
+                ),
+                BasicTypeEnum::PointerType(typ) => typ.const_array(
+                    &elements
+                        .iter()
+                        .map(|element| element.into_pointer_value())
+                        .collect::<Vec<_>>(),
+                ),
+                _ => return Err(CodegenError::NonConstElement.into()),
+            };
 
-            for statement in body {
-                generate_codegen_statement(context, module, statement, &builder)?;
-            }
+            global.set_initializer(&array);
         }
+        // Functions are generated from their MIR, see [`generate_codegen_body`].
+        Item::FunctionDeclaration(..) => {}
     }
 
     Ok(())
@@ -129,13 +930,141 @@ pub fn generate_codegen_item<'a>(
 /// Generates an LLVM Module from an AST module node.
 pub fn generate_codegen_module<'a>(
     context: &'a Context,
+    target: &TargetData,
+    module: &Module,
+) -> anyhow::Result<CodegenModule<'a>> {
+    generate_codegen_module_with_imports(context, target, module, &[])
+}
+
+/// Generates an LLVM Module from an AST module node, in which the items of other modules are
+/// declared so that they can be called. Items declared in the module itself take precedence. The
+/// module is laid out like `target` says, which decides how wide `usize` and `isize` are.
+pub fn generate_codegen_module_with_imports<'a>(
+    context: &'a Context,
+    target: &TargetData,
     module: &Module,
+    imports: &[&Module],
 ) -> anyhow::Result<CodegenModule<'a>> {
     let codegen_module = context.create_module(&module.0);
+    codegen_module.set_data_layout(&target.get_data_layout());
+    let runtime = RuntimeDecls::new(context, &codegen_module);
+
+    // Enums with fields come first, as declaring any function may need their types.
+    for item in module
+        .1
+        .iter()
+        .chain(imports.iter().flat_map(|import| &import.1))
+    {
+        if let Item::Enum(name, variants, _) = &item.node {
+            generate_codegen_enum(context, &codegen_module, name, variants)?;
+        }
+    }
 
     for item in &module.1 {
-        generate_codegen_item(context, &codegen_module, item)?;
+        generate_codegen_declaration(context, &runtime, item)?;
+    }
+
+    for item in imports.iter().flat_map(|import| &import.1) {
+        if !is_instance(item.name())
+            && codegen_module.get_function(item.name()).is_none()
+            && codegen_module.get_global(item.name()).is_none()
+        {
+            generate_codegen_declaration(context, &runtime, item)?;
+        }
+    }
+
+    // Constants are given their values first, so that indexing them can be folded in every
+    // function.
+    let in_function = |item: &Spanned<Item>, source| CodegenError::InFunction {
+        function: item.name().to_string(),
+        span: item.span,
+        source,
+    };
+
+    for item in module.1.iter().filter(|item| !item.is_function()) {
+        generate_codegen_item(context, &codegen_module, item)
+            .map_err(|source| in_function(item, source))?;
+    }
+
+    let functions = module
+        .1
+        .iter()
+        .filter(|item| matches!(item.node, Item::FunctionDeclaration(..)));
+
+    for (item, body) in functions.zip(build(module, imports)?) {
+        generate_codegen_body(context, &codegen_module, &body)
+            .map_err(|source| in_function(item, source))?;
     }
 
     Ok(codegen_module)
 }
+
+/// Generates an LLVM Module for every AST module of a program, each of which can call into the
+/// others, and links them all into the module of the entry point. Functions are given their
+/// mangled names first, see [`mangle_program`]. Panics are handled the way `panic` says, and
+/// every module is laid out like `target` says.
+pub fn generate_codegen_program<'a>(
+    context: &'a Context,
+    target: &TargetData,
+    module: &Module,
+    imports: &[Module],
+    panic: PanicStrategy,
+) -> anyhow::Result<CodegenModule<'a>> {
+    let (module, imports) = mangle_program(module, imports);
+    let (module, imports) = lower_program(&module, &imports)?;
+
+    let every: Vec<&Module> = std::iter::once(&module).chain(&imports).collect();
+    let linked = generate_codegen_module_with_imports(context, target, &module, &every[1..])?;
+
+    for (index, import) in imports.iter().enumerate() {
+        let others: Vec<&Module> = every
+            .iter()
+            .enumerate()
+            .filter(|(other, _)| *other != index + 1)
+            .map(|(_, module)| *module)
+            .collect();
+
+        let codegen_module =
+            generate_codegen_module_with_imports(context, target, import, &others)?;
+
+        linked
+            .link_in_module(codegen_module)
+            .map_err(|error| CodegenError::Link {
+                module: import.0.clone(),
+                message: error.to_string(),
+            })?;
+    }
+
+    define_panic(context, &linked, panic)?;
+    verify_program(&linked, &every)?;
+
+    Ok(linked)
+}
+
+/// Checks that LLVM accepts a linked program. When it does not, the error is attributed to the
+/// first function it rejects, if that function was generated from one of the given modules.
+fn verify_program(codegen_module: &CodegenModule, modules: &[&Module]) -> Result<(), CodegenError> {
+    let Err(message) = codegen_module.verify() else {
+        return Ok(());
+    };
+
+    let error = CodegenError::VerificationFailed(message.to_string());
+    let rejected = modules
+        .iter()
+        .flat_map(|module| &module.1)
+        .filter(|item| matches!(item.node, Item::FunctionDeclaration(..)))
+        .find(|item| {
+            codegen_module
+                .get_function(item.name())
+                .is_some_and(|function| !function.verify(false))
+        });
+
+    match rejected {
+        Some(item) => Err(CodegenError::InFunction {
+            function: item.name().to_string(),
+            span: item.span,
+            source: error.into(),
+        }),
+        None => Err(error),
+    }
+}
@@ -0,0 +1,310 @@
+use inkwell::{
+    AddressSpace,
+    attributes::{Attribute, AttributeLoc},
+    context::Context,
+    intrinsics::Intrinsic,
+    module::{Linkage, Module as CodegenModule},
+    types::FunctionType,
+    values::FunctionValue,
+};
+
+use crate::{
+    backend::PanicStrategy,
+    codegen::{error::CodegenError, generate_codegen_str_type, generate_codegen_type},
+    spec::ast::Type,
+};
+
+/// The name of the function generated code calls when it panics.
+const PANIC: &str = "pal.panic";
+
+/// Declares the functions that generated code relies on, such as the C runtime and LLVM
+/// intrinsics, as well as every external function a module declares itself. Declarations are only
+/// added to the module on first use, and a function is never declared twice, so a runtime function
+/// that the program also declares with a different signature is reported rather than silently
+/// renamed by LLVM.
+pub struct RuntimeDecls<'a, 'ctx> {
+    context: &'ctx Context,
+    module: &'a CodegenModule<'ctx>,
+}
+
+impl<'a, 'ctx> RuntimeDecls<'a, 'ctx> {
+    /// Creates the declarations for a module. Nothing is declared until it is asked for.
+    pub fn new(context: &'ctx Context, module: &'a CodegenModule<'ctx>) -> RuntimeDecls<'a, 'ctx> {
+        RuntimeDecls { context, module }
+    }
+
+    /// Returns the module that functions are declared in.
+    pub fn module(&self) -> &'a CodegenModule<'ctx> {
+        self.module
+    }
+
+    /// Returns the function with the given name, declaring it if it has not been declared yet.
+    /// Fails when the function was already declared with another signature.
+    pub fn declare(
+        &self,
+        name: &str,
+        fn_type: FunctionType<'ctx>,
+    ) -> Result<FunctionValue<'ctx>, CodegenError> {
+        match self.module.get_function(name) {
+            Some(function) if function.get_type() == fn_type => Ok(function),
+            Some(_) => Err(CodegenError::MismatchedDeclaration(name.to_string())),
+            None => Ok(self.module.add_function(name, fn_type, None)),
+        }
+    }
+
+    /// Returns `int printf(const char *, ...)`.
+    pub fn printf(&self) -> Result<FunctionValue<'ctx>, CodegenError> {
+        let ptr = self.context.ptr_type(AddressSpace::default());
+
+        self.declare(
+            "printf",
+            self.context.i32_type().fn_type(&[ptr.into()], true),
+        )
+    }
+
+    /// Returns `void *malloc(size_t)`, with `size_t` taken to be 64 bits wide.
+    pub fn malloc(&self) -> Result<FunctionValue<'ctx>, CodegenError> {
+        let ptr = self.context.ptr_type(AddressSpace::default());
+
+        self.declare(
+            "malloc",
+            ptr.fn_type(&[self.context.i64_type().into()], false),
+        )
+    }
+
+    /// Returns `void free(void *)`.
+    pub fn free(&self) -> Result<FunctionValue<'ctx>, CodegenError> {
+        let ptr = self.context.ptr_type(AddressSpace::default());
+
+        self.declare(
+            "free",
+            self.context.void_type().fn_type(&[ptr.into()], false),
+        )
+    }
+
+    /// Returns `void *memcpy(void *, const void *, size_t)`, with `size_t` taken to be 64 bits wide.
+    pub fn memcpy(&self) -> Result<FunctionValue<'ctx>, CodegenError> {
+        let ptr = self.context.ptr_type(AddressSpace::default());
+
+        self.declare(
+            "memcpy",
+            ptr.fn_type(
+                &[ptr.into(), ptr.into(), self.context.i64_type().into()],
+                false,
+            ),
+        )
+    }
+
+    /// Returns `void abort(void)`, which generated code calls when it panics.
+    pub fn abort(&self) -> Result<FunctionValue<'ctx>, CodegenError> {
+        self.declare("abort", self.context.void_type().fn_type(&[], false))
+    }
+
+    /// Returns `void exit(int)`.
+    pub fn exit(&self) -> Result<FunctionValue<'ctx>, CodegenError> {
+        self.declare(
+            "exit",
+            self.context
+                .void_type()
+                .fn_type(&[self.context.i32_type().into()], false),
+        )
+    }
+
+    /// Returns `int dprintf(int, const char *, ...)`, for printing to stderr.
+    pub fn dprintf(&self) -> Result<FunctionValue<'ctx>, CodegenError> {
+        let ptr = self.context.ptr_type(AddressSpace::default());
+
+        self.declare(
+            "dprintf",
+            self.context
+                .i32_type()
+                .fn_type(&[self.context.i32_type().into(), ptr.into()], true),
+        )
+    }
+
+    /// Returns `void pal.panic(const char *message)`, which generated code calls when it panics.
+    /// It is only defined once the whole program is linked, by [`define_panic`], as what it does
+    /// depends on the [`PanicStrategy`]. The name is not a valid identifier, so it can never clash
+    /// with a function the program declares.
+    pub fn panic(&self) -> Result<FunctionValue<'ctx>, CodegenError> {
+        let ptr = self.context.ptr_type(AddressSpace::default());
+        let function = self.declare(
+            PANIC,
+            self.context.void_type().fn_type(&[ptr.into()], false),
+        )?;
+
+        let noreturn = Attribute::get_named_enum_kind_id("noreturn");
+        function.add_attribute(
+            AttributeLoc::Function,
+            self.context.create_enum_attribute(noreturn, 0),
+        );
+
+        Ok(function)
+    }
+
+    /// Returns the declaration of a non-overloaded LLVM intrinsic, such as `llvm.trap`.
+    pub fn intrinsic(&self, name: &str) -> Result<FunctionValue<'ctx>, CodegenError> {
+        Intrinsic::find(name)
+            .and_then(|intrinsic| intrinsic.get_declaration(self.module, &[]))
+            .ok_or_else(|| CodegenError::IntrinsicDoesNotExist(name.to_string()))
+    }
+}
+
+/// Defines the function of the runtime with the given name, see [`crate::runtime`], if it has not
+/// been defined yet. Like the functions for strings, each module defines those it calls itself as
+/// private functions, except for `pal_panic`, which is the function generated code calls when it
+/// panics. Returns `None` when the name is not one of them.
+pub fn define_runtime_function<'ctx>(
+    context: &'ctx Context,
+    module: &CodegenModule<'ctx>,
+    name: &str,
+) -> anyhow::Result<Option<FunctionValue<'ctx>>> {
+    let runtime = RuntimeDecls::new(context, module);
+    let ptr = context.ptr_type(AddressSpace::default());
+    let u32 = context.i32_type();
+    let i64 = context.i64_type();
+    let stdout = u32.const_int(1, false);
+
+    let fn_type = match name {
+        "pal_panic" => return Ok(Some(runtime.panic()?)),
+        "pal_alloc" => ptr.fn_type(&[u32.into()], false),
+        "pal_free" => u32.fn_type(&[ptr.into()], false),
+        "pal_print_int" => u32.fn_type(&[u32.into()], false),
+        "pal_print_u64" => u32.fn_type(&[i64.into()], false),
+        "pal_print_isize" => {
+            let isize = generate_codegen_type(context, module, &Type::Atomic("isize".into()))?;
+            u32.fn_type(&[isize.into()], false)
+        }
+        "pal_print_char" => u32.fn_type(&[context.i8_type().into()], false),
+        "pal_print_cstr" => u32.fn_type(&[ptr.into()], false),
+        "pal_print_str" => u32.fn_type(&[generate_codegen_str_type(context).into()], false),
+        _ => return Ok(None),
+    };
+
+    let function = module.add_function(name, fn_type, Some(Linkage::Private));
+    let builder = context.create_builder();
+    builder.position_at_end(context.append_basic_block(function, "entry"));
+
+    let param = function
+        .get_nth_param(0)
+        .ok_or(CodegenError::NoInsertionBlock)?;
+
+    let returned = match name {
+        "pal_alloc" => {
+            let size = builder.build_int_z_extend(param.into_int_value(), i64, "")?;
+            builder.build_call(runtime.malloc()?, &[size.into()], "")?
+        }
+        "pal_free" => {
+            builder.build_call(runtime.free()?, &[param.into()], "")?;
+            builder.build_return(Some(&u32.const_zero()))?;
+
+            return Ok(Some(function));
+        }
+        "pal_print_int" | "pal_print_u64" | "pal_print_isize" | "pal_print_char"
+        | "pal_print_cstr" => {
+            // Variadic arguments narrower than an `int` are promoted to one, and `isize` is
+            // printed as a `long long` whatever its width.
+            let (format, value) = match name {
+                "pal_print_int" => ("%u", param),
+                "pal_print_u64" => ("%llu", param),
+                "pal_print_isize" => (
+                    "%lld",
+                    builder
+                        .build_int_s_extend_or_bit_cast(param.into_int_value(), i64, "")?
+                        .into(),
+                ),
+                "pal_print_char" => (
+                    "%c",
+                    builder
+                        .build_int_z_extend(param.into_int_value(), u32, "")?
+                        .into(),
+                ),
+                _ => ("%s", param),
+            };
+
+            let format = builder.build_global_string_ptr(format, "")?;
+            builder.build_call(
+                runtime.dprintf()?,
+                &[
+                    stdout.into(),
+                    format.as_pointer_value().into(),
+                    value.into(),
+                ],
+                "",
+            )?
+        }
+        _ => {
+            let format = builder.build_global_string_ptr("%.*s", "")?;
+            let len = builder.build_extract_value(param.into_struct_value(), 1, "")?;
+            let bytes = builder.build_extract_value(param.into_struct_value(), 0, "")?;
+            builder.build_call(
+                runtime.dprintf()?,
+                &[
+                    stdout.into(),
+                    format.as_pointer_value().into(),
+                    len.into(),
+                    bytes.into(),
+                ],
+                "",
+            )?
+        }
+    };
+
+    let returned = returned
+        .try_as_basic_value()
+        .basic()
+        .ok_or(CodegenError::NoReturnValue)?;
+    builder.build_return(Some(&returned))?;
+
+    Ok(Some(function))
+}
+
+/// Defines the function generated code calls when it panics, following the given strategy. Does
+/// nothing when nothing in the module can panic.
+pub fn define_panic<'ctx>(
+    context: &'ctx Context,
+    module: &CodegenModule<'ctx>,
+    strategy: PanicStrategy,
+) -> anyhow::Result<()> {
+    let Some(panic) = module.get_function(PANIC) else {
+        return Ok(());
+    };
+
+    let runtime = RuntimeDecls::new(context, module);
+    let builder = context.create_builder();
+    builder.position_at_end(context.append_basic_block(panic, "entry"));
+
+    match strategy {
+        PanicStrategy::Abort => {
+            builder.build_call(runtime.abort()?, &[], "")?;
+        }
+        PanicStrategy::Trap => {
+            builder.build_call(runtime.intrinsic("llvm.trap")?, &[], "")?;
+        }
+        PanicStrategy::Exit(code) => {
+            let format = builder.build_global_string_ptr("panicked: %s\n", "")?;
+            let message = panic
+                .get_nth_param(0)
+                .ok_or_else(|| CodegenError::MismatchedDeclaration(PANIC.to_string()))?;
+            let stderr = context.i32_type().const_int(2, false);
+
+            builder.build_call(
+                runtime.dprintf()?,
+                &[
+                    stderr.into(),
+                    format.as_pointer_value().into(),
+                    message.into(),
+                ],
+                "",
+            )?;
+
+            let code = context.i32_type().const_int(code as u64, true);
+            builder.build_call(runtime.exit()?, &[code.into()], "")?;
+        }
+    }
+
+    builder.build_unreachable()?;
+    panic.set_linkage(Linkage::Internal);
+
+    Ok(())
+}
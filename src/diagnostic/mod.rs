@@ -0,0 +1,498 @@
+use std::{fmt::Display, path::Path};
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::{
+    parser::{Span, error::ParseError},
+    sema::error::SemaError,
+};
+
+/// How serious a [`Diagnostic`] is.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Stops the program from being compiled.
+    Error,
+    /// Points out something that is likely a mistake, but still compiles.
+    Warning,
+    /// Explains a [`Diagnostic`] it is attached to.
+    Note,
+    /// Suggests how to fix what a [`Diagnostic`] it is attached to points out.
+    Help,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+            Severity::Help => write!(f, "help"),
+        }
+    }
+}
+
+/// How diagnostics are printed.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// As text for people to read, with the line and column each one points at.
+    #[default]
+    Human,
+    /// As an object of JSON on a line of its own, for editors and other tools to read.
+    Json,
+}
+
+/// A change to the source that fixes what a [`Diagnostic`] points out, replacing what a span
+/// covers. An empty span inserts the replacement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fix {
+    pub span: Span,
+    pub replacement: String,
+}
+
+/// A problem found in the source code that is reported to the user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub span: Option<Span>,
+    pub severity: Severity,
+    /// Identifies the kind of problem, such as `missing-return` or `dead-code`, so that tools
+    /// can tell diagnostics apart without matching on their messages.
+    pub code: Option<&'static str>,
+    /// Notes and help that explain the [`Diagnostic`], rendered after it.
+    pub children: Vec<Diagnostic>,
+    /// A change that can be made to the source without looking at it first, which only help
+    /// that suggests how to fix its parent has, see [`Diagnostic::with_fix`].
+    pub fix: Option<Fix>,
+}
+
+impl Diagnostic {
+    /// Creates an error that is not attached to any particular place in the source.
+    pub fn new(message: impl ToString) -> Diagnostic {
+        Diagnostic {
+            message: message.to_string(),
+            span: None,
+            severity: Severity::Error,
+            code: None,
+            children: vec![],
+            fix: None,
+        }
+    }
+
+    /// Creates a warning that is not attached to any particular place in the source.
+    pub fn warning(message: impl ToString) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            ..Diagnostic::new(message)
+        }
+    }
+
+    /// Returns the [`Diagnostic`] with a different severity.
+    pub fn with_severity(self, severity: Severity) -> Diagnostic {
+        Diagnostic { severity, ..self }
+    }
+
+    /// Attaches a code that identifies the kind of problem to the [`Diagnostic`].
+    pub fn with_code(self, code: &'static str) -> Diagnostic {
+        Diagnostic {
+            code: Some(code),
+            ..self
+        }
+    }
+
+    /// Attaches a note that explains the [`Diagnostic`].
+    pub fn with_note(mut self, message: impl ToString) -> Diagnostic {
+        self.children
+            .push(Diagnostic::new(message).with_severity(Severity::Note));
+        self
+    }
+
+    /// Attaches help that suggests how to fix what the [`Diagnostic`] points out.
+    pub fn with_help(mut self, message: impl ToString) -> Diagnostic {
+        self.children
+            .push(Diagnostic::new(message).with_severity(Severity::Help));
+        self
+    }
+
+    /// Attaches help that suggests replacing what a span covers, along with the [`Fix`] that
+    /// does so, see [`apply_fixes`].
+    pub fn with_fix(
+        mut self,
+        message: impl ToString,
+        span: Span,
+        replacement: impl ToString,
+    ) -> Diagnostic {
+        let help = Diagnostic {
+            fix: Some(Fix {
+                span,
+                replacement: replacement.to_string(),
+            }),
+            ..Diagnostic::new(message)
+                .with_severity(Severity::Help)
+                .with_span(span)
+        };
+
+        self.children.push(help);
+        self
+    }
+
+    /// Turns a [`ParseError`] into a [`Diagnostic`] like [`Diagnostic::from`] does, with a
+    /// [`Fix`] that inserts the token that was expected when it is the only one. The token is
+    /// inserted right after what comes before it, rather than where the error is, which may be on
+    /// the line after. A `;` is always inserted, since a statement that is missing one ends where
+    /// the next begins. Any other token is only inserted when it ends a line: one expected in the
+    /// middle of a line is likely missing because something else is wrong.
+    pub fn from_parse_error(error: ParseError, source: &str) -> Diagnostic {
+        let token = match &error {
+            ParseError::Expected { expected, span, .. } => match &expected[..] {
+                [label] => [";", ")", "]", ":"]
+                    .into_iter()
+                    .find(|token| *label == format!("'{token}'"))
+                    .map(|token| (token, span.start)),
+                _ => None,
+            },
+            _ => None,
+        };
+        let diagnostic = Diagnostic::from(error);
+
+        let before = |start: usize| source[..start.min(source.len())].trim_end();
+
+        match token {
+            Some((token, start))
+                if token == ";" || source[before(start).len()..start].contains('\n') =>
+            {
+                let end = before(start).len();
+
+                diagnostic.with_fix(
+                    format!("insert the missing `{token}` here"),
+                    Span::new(end, end),
+                    token,
+                )
+            }
+            _ => diagnostic,
+        }
+    }
+
+    /// Returns whether the [`Diagnostic`] stops the program from being compiled.
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Attaches a [`Span`] to the [`Diagnostic`].
+    pub fn with_span(self, span: Span) -> Diagnostic {
+        Diagnostic {
+            span: Some(span),
+            ..self
+        }
+    }
+
+    /// Renders the [`Diagnostic`] as `path:line:column: severity[code]: message`, using the source
+    /// to work out the line and column the span starts at. Notes and help follow on lines of their
+    /// own, as `  = note: message`.
+    pub fn render(&self, path: &Path, source: &str) -> String {
+        let severity = match self.code {
+            Some(code) => format!("{}[{code}]", self.severity),
+            None => self.severity.to_string(),
+        };
+
+        let mut rendered = match self.span {
+            Some(span) => {
+                let (line, column) = line_column(source, span.start);
+                format!(
+                    "{}:{}:{}: {}: {}",
+                    path.display(),
+                    line,
+                    column,
+                    severity,
+                    self.message
+                )
+            }
+            None => format!("{}: {}: {}", path.display(), severity, self.message),
+        };
+
+        for child in &self.children {
+            rendered.push_str(&format!("\n  = {}: {}", child.severity, child.message));
+        }
+
+        rendered
+    }
+
+    /// Renders the [`Diagnostic`] in the given format.
+    pub fn render_as(&self, format: ErrorFormat, path: &Path, source: &str) -> String {
+        match format {
+            ErrorFormat::Human => self.render(path, source),
+            ErrorFormat::Json => self.to_json(path, source),
+        }
+    }
+
+    /// Renders the [`Diagnostic`] as an object of JSON, on one line, holding the same as
+    /// [`Diagnostic::render`] does: its `code`, or `null`, its `severity` and `message`, the
+    /// `spans` it points at, with their byte offsets and one-based lines and columns, its `notes`
+    /// and its `suggestions`, which are its help, with the `replacement` that fixes it when there
+    /// is one, see [`Diagnostic::with_fix`]. What [`Diagnostic::render`] gives is kept as
+    /// `rendered`, for tools that show it as is.
+    pub fn to_json(&self, path: &Path, source: &str) -> String {
+        let file = path.display().to_string();
+        let spans = |diagnostic: &Diagnostic| -> Vec<JsonSpan> {
+            diagnostic
+                .span
+                .map(|span| {
+                    let (line_start, column_start) = line_column(source, span.start);
+                    let (line_end, column_end) = line_column(source, span.end);
+
+                    JsonSpan {
+                        file: file.clone(),
+                        byte_start: span.start,
+                        byte_end: span.end,
+                        line_start,
+                        column_start,
+                        line_end,
+                        column_end,
+                    }
+                })
+                .into_iter()
+                .collect()
+        };
+        let children = |severity: Severity| -> Vec<JsonChild> {
+            self.children
+                .iter()
+                .filter(|child| child.severity == severity)
+                .map(|child| JsonChild {
+                    message: &child.message,
+                    spans: spans(child),
+                    replacement: child.fix.as_ref().map(|fix| &fix.replacement[..]),
+                })
+                .collect()
+        };
+
+        let json = JsonDiagnostic {
+            code: self.code,
+            severity: self.severity,
+            message: &self.message,
+            spans: spans(self),
+            notes: children(Severity::Note),
+            suggestions: children(Severity::Help),
+            rendered: self.render(path, source),
+        };
+
+        serde_json::to_string(&json).expect("diagnostics serialize to JSON")
+    }
+}
+
+/// Applies the [`Fix`] of every help of the given diagnostics to the source they were reported
+/// against, returning the fixed source and how many fixes were applied. A fix that overlaps one
+/// that comes before it is left out, as applying both would garble the source, as is one that
+/// repeats another.
+pub fn apply_fixes(source: &str, diagnostics: &[Diagnostic]) -> (String, usize) {
+    let mut fixes: Vec<&Fix> = diagnostics
+        .iter()
+        .flat_map(|diagnostic| &diagnostic.children)
+        .filter_map(|child| child.fix.as_ref())
+        .filter(|fix| fix.span.start <= fix.span.end && fix.span.end <= source.len())
+        .collect();
+    fixes.sort_by_key(|fix| (fix.span.start, fix.span.end));
+    fixes.dedup();
+
+    let mut fixed = String::with_capacity(source.len());
+    let mut offset = 0;
+    let mut applied = 0;
+
+    for fix in fixes {
+        if fix.span.start < offset {
+            continue;
+        }
+
+        fixed.push_str(&source[offset..fix.span.start]);
+        fixed.push_str(&fix.replacement);
+        offset = fix.span.end;
+        applied += 1;
+    }
+
+    fixed.push_str(&source[offset..]);
+
+    (fixed, applied)
+}
+
+/// A [`Diagnostic`] as [`Diagnostic::to_json`] writes it.
+#[derive(Serialize)]
+struct JsonDiagnostic<'d> {
+    code: Option<&'static str>,
+    severity: Severity,
+    message: &'d str,
+    spans: Vec<JsonSpan>,
+    notes: Vec<JsonChild<'d>>,
+    suggestions: Vec<JsonChild<'d>>,
+    rendered: String,
+}
+
+/// A span of a [`Diagnostic`] as [`Diagnostic::to_json`] writes it, with one-based lines and
+/// columns.
+#[derive(Serialize)]
+struct JsonSpan {
+    file: String,
+    byte_start: usize,
+    byte_end: usize,
+    line_start: usize,
+    column_start: usize,
+    line_end: usize,
+    column_end: usize,
+}
+
+/// A note or help of a [`Diagnostic`] as [`Diagnostic::to_json`] writes it.
+#[derive(Serialize)]
+struct JsonChild<'d> {
+    message: &'d str,
+    spans: Vec<JsonSpan>,
+    replacement: Option<&'d str>,
+}
+
+impl From<ParseError> for Diagnostic {
+    fn from(error: ParseError) -> Diagnostic {
+        let diagnostic = Diagnostic::new(&error).with_code(error.code());
+
+        match error.span() {
+            Some(span) => diagnostic.with_span(span),
+            None => diagnostic,
+        }
+    }
+}
+
+impl From<SemaError> for Diagnostic {
+    fn from(error: SemaError) -> Diagnostic {
+        Diagnostic::new(&error).with_code(error.code())
+    }
+}
+
+/// Returns the one-based line and column of a byte offset into the source.
+pub fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rfind('\n')
+        .map_or(before, |newline| &before[newline + 1..])
+        .chars()
+        .count()
+        + 1;
+
+    (line, column)
+}
+
+#[test]
+fn warnings_are_rendered_as_warnings() {
+    let path = Path::new("main.pal");
+
+    assert_eq!(
+        Diagnostic::warning("unused").render(path, ""),
+        "main.pal: warning: unused"
+    );
+    assert_eq!(
+        Diagnostic::new("bad")
+            .with_span(Span::new(3, 4))
+            .render(path, "ab\ncd"),
+        "main.pal:2:1: error: bad"
+    );
+}
+
+#[test]
+fn codes_and_notes_are_rendered() {
+    let diagnostic = Diagnostic::warning("unused variable `x`")
+        .with_span(Span::new(0, 1))
+        .with_code("unused-variables")
+        .with_note("`-W unused-variables` is on by default")
+        .with_help("prefix it with an underscore");
+
+    assert_eq!(
+        diagnostic.render(Path::new("main.pal"), "x"),
+        "main.pal:1:1: warning[unused-variables]: unused variable `x`\n  = note: `-W unused-variables` is on by default\n  = help: prefix it with an underscore"
+    );
+    assert_eq!(
+        Diagnostic::from(SemaError::MissingMain).code,
+        Some("missing-main")
+    );
+}
+
+#[test]
+fn json_mirrors_the_human_rendering() {
+    let diagnostic = Diagnostic::warning("unused variable `x`")
+        .with_span(Span::new(3, 4))
+        .with_code("unused-variables")
+        .with_note("a \"note\"");
+
+    assert_eq!(
+        diagnostic.to_json(Path::new("main.pal"), "ab\nx"),
+        r#"{"code":"unused-variables","severity":"warning","message":"unused variable `x`","spans":[{"file":"main.pal","byte_start":3,"byte_end":4,"line_start":2,"column_start":1,"line_end":2,"column_end":2}],"notes":[{"message":"a \"note\"","spans":[],"replacement":null}],"suggestions":[],"rendered":"main.pal:2:1: warning[unused-variables]: unused variable `x`\n  = note: a \"note\""}"#
+    );
+    assert_eq!(
+        Diagnostic::new("bad\tthing").to_json(Path::new("main.pal"), ""),
+        r#"{"code":null,"severity":"error","message":"bad\tthing","spans":[],"notes":[],"suggestions":[],"rendered":"main.pal: error: bad\tthing"}"#
+    );
+
+    let fixed = Diagnostic::new("bad")
+        .with_fix("quote it", Span::new(0, 1), "\"x\"\n")
+        .to_json(Path::new("main.pal"), "x");
+    let json: serde_json::Value = serde_json::from_str(&fixed).unwrap();
+
+    assert_eq!(json["suggestions"][0]["replacement"], "\"x\"\n");
+    assert_eq!(json["suggestions"][0]["spans"][0]["column_end"], 2);
+}
+
+#[test]
+fn missing_tokens_are_inserted_after_what_they_end() {
+    let source = "fn main() -> u32 {\n    let y: u32 = 1 return y;\n    f(x;\n    let x: u32 = f(1)\n    return x;\n};";
+    let (_, errors) = crate::spec::recovering_module("main".to_string())
+        .parse(source)
+        .unwrap()
+        .0;
+    let diagnostics: Vec<Diagnostic> = errors
+        .into_iter()
+        .map(|error| Diagnostic::from_parse_error(error, source))
+        .collect();
+
+    assert_eq!(
+        diagnostics[2].render(Path::new("main.pal"), source),
+        "main.pal:5:5: error[syntax-error]: expected \';\', found \'r\'\n  = help: insert the missing `;` here"
+    );
+    assert!(diagnostics[1].children.is_empty());
+
+    let json: serde_json::Value =
+        serde_json::from_str(&diagnostics[0].to_json(Path::new("main.pal"), source)).unwrap();
+
+    assert_eq!(json["suggestions"][0]["replacement"], ";");
+    assert_eq!(json["suggestions"][0]["spans"][0]["line_start"], 2);
+    assert_eq!(json["suggestions"][0]["spans"][0]["column_start"], 19);
+    assert_eq!(
+        apply_fixes(source, &diagnostics),
+        (
+            "fn main() -> u32 {\n    let y: u32 = 1; return y;\n    f(x;\n    let x: u32 = f(1);\n    return x;\n};"
+                .to_string(),
+            2
+        )
+    );
+}
+
+#[test]
+fn overlapping_fixes_are_left_out() {
+    let fix = |start, end, replacement| {
+        Diagnostic::new("bad").with_fix("fix", Span::new(start, end), replacement)
+    };
+
+    assert_eq!(
+        apply_fixes(
+            "abcdef",
+            &[
+                fix(4, 6, "EF"),
+                fix(0, 2, "AB"),
+                fix(1, 3, "x"),
+                fix(0, 2, "AB")
+            ]
+        ),
+        ("ABcdEF".to_string(), 2)
+    );
+}
+
+#[test]
+fn line_columns_are_one_based() {
+    assert_eq!(line_column("abc", 0), (1, 1));
+    assert_eq!(line_column("ab\ncd", 4), (2, 2));
+}
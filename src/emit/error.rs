@@ -0,0 +1,12 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+/// Represents any error that occurs while writing compiled artifacts.
+#[derive(Error, Debug)]
+pub enum EmitError {
+    #[error("could not set up the target: {0}")]
+    Target(String),
+    #[error("failed to write {}: {message}", .path.display())]
+    Write { path: PathBuf, message: String },
+}
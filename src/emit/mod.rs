@@ -0,0 +1,252 @@
+pub mod error;
+
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use inkwell::{
+    OptimizationLevel,
+    module::Module as CodegenModule,
+    targets::{
+        CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetMachine, TargetTriple,
+    },
+};
+
+use crate::emit::error::EmitError;
+
+/// The kinds of artifact the compiler can write.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Emit {
+    /// LLVM bitcode.
+    #[value(name = "bc")]
+    Bitcode,
+    /// Textual LLVM IR.
+    Ir,
+    /// Assembly for the target.
+    Asm,
+    /// An object file for the target.
+    Obj,
+    /// Portable C99 source. LLVM is not used to produce it.
+    C,
+}
+
+impl Emit {
+    /// Returns the file extension artifacts of this kind are written with.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Emit::Bitcode => "bc",
+            Emit::Ir => "ll",
+            Emit::Asm => "s",
+            Emit::Obj => "o",
+            Emit::C => "c",
+        }
+    }
+
+    /// Returns whether producing this kind of artifact goes through LLVM.
+    pub fn needs_llvm(self) -> bool {
+        self != Emit::C
+    }
+}
+
+/// How much LLVM optimizes the machine code it generates for assembly and object files, like the
+/// `-O` levels of C compilers.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OptLevel {
+    /// No optimization, which keeps the machine code closest to the IR.
+    #[value(name = "0")]
+    None,
+    /// Quick optimizations only.
+    #[value(name = "1")]
+    Less,
+    /// Most optimizations.
+    #[default]
+    #[value(name = "2")]
+    Default,
+    /// Every optimization, even those that make the code larger.
+    #[value(name = "3")]
+    Aggressive,
+}
+
+impl From<OptLevel> for OptimizationLevel {
+    fn from(level: OptLevel) -> OptimizationLevel {
+        match level {
+            OptLevel::None => OptimizationLevel::None,
+            OptLevel::Less => OptimizationLevel::Less,
+            OptLevel::Default => OptimizationLevel::Default,
+            OptLevel::Aggressive => OptimizationLevel::Aggressive,
+        }
+    }
+}
+
+/// Works out where every requested artifact is written. With a single artifact, `output` is the
+/// path of the file itself. With several, `output` is either a directory to put them in, named
+/// after the input, or a basename that each artifact adds its own extension to. Without `output`,
+/// artifacts are written next to the input.
+pub fn output_paths(input: &Path, output: Option<&Path>, emits: &[Emit]) -> Vec<(Emit, PathBuf)> {
+    let stem = input.file_stem().map(PathBuf::from).unwrap_or_default();
+
+    let base = match output {
+        Some(output) if emits.len() == 1 && !output.is_dir() => {
+            return vec![(emits[0], output.to_path_buf())];
+        }
+        Some(output) if output.is_dir() => output.join(stem),
+        Some(output) => output.to_path_buf(),
+        None => input.with_file_name(stem),
+    };
+
+    emits
+        .iter()
+        .map(|emit| (*emit, base.with_extension(emit.extension())))
+        .collect()
+}
+
+/// Returns the directory `--save-temps` writes to when it is not given one. It only depends on the
+/// input, so that rerunning the compiler overwrites the same files.
+pub fn default_temps_dir(input: &Path) -> PathBuf {
+    let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+
+    std::env::temp_dir().join(format!("pal-{stem}"))
+}
+
+/// Creates the directory for `--save-temps` and works out where every kind of artifact is saved in
+/// it. Artifacts are named after the input, like they are with a directory passed to `-o`.
+pub fn temps_paths(input: &Path, dir: &Path) -> Result<Vec<(Emit, PathBuf)>, EmitError> {
+    std::fs::create_dir_all(dir).map_err(|error| EmitError::Write {
+        path: dir.to_path_buf(),
+        message: error.to_string(),
+    })?;
+
+    Ok(output_paths(input, Some(dir), Emit::value_variants()))
+}
+
+/// Creates a target machine for a target triple, such as `aarch64-unknown-linux-gnu`, generating
+/// code at the given level of optimization. Without a triple, the machine is for the host the
+/// compiler is running on, and may use every feature of its CPU.
+pub fn target_machine(triple: Option<&str>, level: OptLevel) -> Result<TargetMachine, EmitError> {
+    let (triple, cpu, features) = match triple {
+        Some(triple) => {
+            Target::initialize_all(&InitializationConfig::default());
+
+            (
+                TargetTriple::create(triple),
+                "generic".to_string(),
+                String::new(),
+            )
+        }
+        None => {
+            Target::initialize_native(&InitializationConfig::default())
+                .map_err(EmitError::Target)?;
+
+            (
+                TargetMachine::get_default_triple(),
+                TargetMachine::get_host_cpu_name().to_string(),
+                TargetMachine::get_host_cpu_features().to_string(),
+            )
+        }
+    };
+
+    let target =
+        Target::from_triple(&triple).map_err(|error| EmitError::Target(error.to_string()))?;
+
+    target
+        .create_target_machine(
+            &triple,
+            &cpu,
+            &features,
+            level.into(),
+            RelocMode::Default,
+            CodeModel::Default,
+        )
+        .ok_or(EmitError::Target(format!(
+            "no target machine for `{}`",
+            triple.as_str().to_string_lossy()
+        )))
+}
+
+/// Writes an LLVM module as the given kind of artifact. The target machine is only needed for
+/// assembly and object files.
+pub fn emit_llvm(
+    module: &CodegenModule<'_>,
+    machine: Option<&TargetMachine>,
+    emit: Emit,
+    path: &Path,
+) -> Result<(), EmitError> {
+    let failed = |message: String| EmitError::Write {
+        path: path.to_path_buf(),
+        message,
+    };
+
+    let file_type = match emit {
+        Emit::Bitcode => {
+            return match module.write_bitcode_to_path(path) {
+                true => Ok(()),
+                false => Err(failed("could not write bitcode".to_string())),
+            };
+        }
+        Emit::Ir => {
+            return module
+                .print_to_file(path)
+                .map_err(|error| failed(error.to_string()));
+        }
+        Emit::Asm => FileType::Assembly,
+        Emit::Obj => FileType::Object,
+        Emit::C => unreachable!("C source is not produced by LLVM"),
+    };
+
+    machine
+        .ok_or(EmitError::Target(
+            "no target machine was set up".to_string(),
+        ))?
+        .write_to_file(module, file_type, path)
+        .map_err(|error| failed(error.to_string()))
+}
+
+#[test]
+fn single_artifacts_are_written_to_the_output() {
+    assert_eq!(
+        output_paths(
+            Path::new("src/main.pal"),
+            Some(Path::new("out.o")),
+            &[Emit::Obj]
+        ),
+        vec![(Emit::Obj, PathBuf::from("out.o"))]
+    );
+
+    assert_eq!(
+        output_paths(Path::new("src/main.pal"), None, &[Emit::Bitcode]),
+        vec![(Emit::Bitcode, PathBuf::from("src/main.bc"))]
+    );
+}
+
+#[test]
+fn multiple_artifacts_share_a_basename() {
+    assert_eq!(
+        output_paths(
+            Path::new("main.pal"),
+            Some(Path::new("build/app")),
+            &[Emit::Obj, Emit::Ir]
+        ),
+        vec![
+            (Emit::Obj, PathBuf::from("build/app.o")),
+            (Emit::Ir, PathBuf::from("build/app.ll")),
+        ]
+    );
+
+    let dir = std::env::temp_dir();
+
+    assert_eq!(
+        output_paths(Path::new("src/main.pal"), Some(&dir), &[Emit::Asm]),
+        vec![(Emit::Asm, dir.join("main.s"))]
+    );
+}
+
+#[test]
+fn temps_are_named_after_the_input() {
+    let dir = std::env::temp_dir().join(format!("pal-temps-{}", std::process::id()));
+    let paths = temps_paths(Path::new("src/loop.pal"), &dir).unwrap();
+
+    assert_eq!(paths.len(), Emit::value_variants().len());
+    assert!(paths.contains(&(Emit::Ir, dir.join("loop.ll"))));
+    assert!(default_temps_dir(Path::new("src/loop.pal")).ends_with("pal-loop"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,51 @@
+use thiserror::Error;
+
+/// Represents any error that stops the interpreter.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum InterpError {
+    #[error("no such function `{0}` was found")]
+    FunctionDoesNotExist(String),
+    #[error("no such variable `{0}` was found in the current scope")]
+    VariableDoesNotExist(String),
+    #[error("external function `{0}` is not available in the interpreter")]
+    UnknownBuiltin(String),
+    #[error("`{function}` expected a {expected} argument")]
+    InvalidArgument {
+        function: String,
+        expected: &'static str,
+    },
+    #[error("the bounds of a range must be integers")]
+    NonIntegerRange,
+    #[error("`{0}` can not be applied to this value")]
+    InvalidOperand(String),
+    #[error("the interpreter can not take the address of a variable")]
+    AddressOf,
+    #[error("the interpreter can not allocate or free memory")]
+    Allocation,
+    #[error("the interpreter can not run inline assembly")]
+    InlineAsm,
+    #[error("array indices must be integers")]
+    NonIntegerIndex,
+    #[error("index {index} is out of bounds for `{name}`, which has {length} element(s)")]
+    IndexOutOfBounds {
+        name: String,
+        index: u32,
+        length: usize,
+    },
+    #[error("entered unreachable code")]
+    Unreachable,
+    /// The program called `pal_panic` while running with a [`crate::backend::PanicStrategy`] that
+    /// stops it without a message.
+    #[error("panicked: {0}")]
+    Aborted(String),
+    /// The program panicked while running with [`crate::backend::PanicStrategy::Exit`].
+    #[error("panicked: {message}")]
+    Panicked { message: String, code: i32 },
+    #[error("function `{0}` finished without returning a value")]
+    MissingReturn(String),
+    #[error("failed to write output: {0}")]
+    Output(String),
+    /// Not a failure: raised by the `exit` builtin to unwind out of every call.
+    #[error("the program exited with code {0}")]
+    Exit(u32),
+}
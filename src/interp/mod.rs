@@ -0,0 +1,837 @@
+pub mod error;
+pub mod scope;
+pub mod value;
+
+use std::{collections::HashMap, io::Write};
+
+use crate::{
+    backend::{PanicStrategy, UNREACHABLE, assertion_failed},
+    interp::{error::InterpError, scope::Frame, value::Value},
+    parser::Spanned,
+    sema::{
+        format::{FORMAT_FUNCTIONS, Piece, parse_format},
+        prelude,
+    },
+    spec::ast::*,
+};
+
+/// What happens after a statement has been executed.
+enum Flow {
+    /// Carry on with the next statement.
+    Next,
+    /// The function returned the given value.
+    Return(Value),
+}
+
+/// Evaluates a module by walking its AST directly, without going through LLVM. External functions
+/// are served by a small set of builtins, and everything they print is written to `out`.
+pub struct Interpreter<'a, W: Write> {
+    functions: HashMap<&'a str, &'a Item>,
+    out: W,
+    panic: PanicStrategy,
+}
+
+impl<'a, W: Write> Interpreter<'a, W> {
+    /// Creates an interpreter for the items of a module.
+    pub fn new(module: &'a Module, out: W) -> Interpreter<'a, W> {
+        Interpreter {
+            functions: module
+                .1
+                .iter()
+                .map(|item| (item.name(), &item.node))
+                .collect(),
+            out,
+            panic: PanicStrategy::default(),
+        }
+    }
+
+    /// Sets what happens when the program panics. Aborting and trapping both stop the
+    /// interpreter with an error, as neither can be told apart from it.
+    pub fn with_panic(mut self, panic: PanicStrategy) -> Interpreter<'a, W> {
+        self.panic = panic;
+        self
+    }
+
+    /// Calls a function by name with already evaluated arguments. Functions of the prelude are
+    /// found when the module declares none of the same name.
+    pub fn call(&mut self, name: &str, args: Vec<Value>) -> Result<Value, InterpError> {
+        if FORMAT_FUNCTIONS.contains(&name) && !self.functions.contains_key(name) {
+            return self.print(name, args);
+        }
+
+        let item = self
+            .functions
+            .get(name)
+            .copied()
+            .or_else(|| {
+                prelude()
+                    .iter()
+                    .find(|item| item.name() == name)
+                    .map(|item| &item.node)
+            })
+            .ok_or_else(|| InterpError::FunctionDoesNotExist(name.to_string()))?;
+
+        match item {
+            Item::ExternFunctionDefinition(..) => self.builtin(name, args),
+            Item::Const(..) | Item::Enum(..) => {
+                Err(InterpError::FunctionDoesNotExist(name.to_string()))
+            }
+            Item::FunctionDeclaration(_, _, params, _, body, ..) => {
+                let mut frame = Frame::new();
+
+                for ((param, _), arg) in params.iter().zip(args) {
+                    frame.insert(param, arg);
+                }
+
+                match self.block(body, &mut frame)? {
+                    Flow::Return(value) => Ok(value),
+                    Flow::Next => Err(InterpError::MissingReturn(name.to_string())),
+                }
+            }
+        }
+    }
+
+    /// Runs one of the external functions the interpreter provides itself.
+    fn builtin(&mut self, name: &str, args: Vec<Value>) -> Result<Value, InterpError> {
+        let invalid = |expected| InterpError::InvalidArgument {
+            function: name.to_string(),
+            expected,
+        };
+
+        let written = match (name, &args[..]) {
+            ("printf", [Value::Str(text)]) => self.write(text)?,
+            ("puts", [Value::Str(text)]) => self.write(&format!("{text}\n"))?,
+            ("putchar", [Value::Char(byte)]) => self.write(&(*byte as char).to_string())?,
+            ("putchar", [Value::U32(value)]) => self.write(&(*value as u8 as char).to_string())?,
+            ("exit", [Value::U32(code)]) => return Err(InterpError::Exit(*code)),
+            ("pal_print_int", [Value::U32(value)]) => self.write(&value.to_string())?,
+            ("pal_print_u64", [value @ Value::U64(_)])
+            | ("pal_print_isize", [value @ Value::Isize(_)])
+            | ("pal_print_char", [value @ Value::Char(_)]) => self.write(&value.to_string())?,
+            ("pal_print_str" | "pal_print_cstr", [Value::Str(text)]) => self.write(text)?,
+            ("pal_panic", [Value::Str(message)]) => return Err(self.panicked(message)),
+            ("len", [Value::Str(text)]) => return Ok(Value::U32(text.len() as u32)),
+            ("concat", [Value::Str(left), Value::Str(right)]) => {
+                return Ok(Value::Str(format!("{left}{right}").into()));
+            }
+            ("printf" | "puts", _) => return Err(invalid("string")),
+            ("putchar", _) => return Err(invalid("character")),
+            ("len" | "concat" | "pal_print_str" | "pal_print_cstr" | "pal_panic", _) => {
+                return Err(invalid("string"));
+            }
+            ("pal_print_int" | "pal_print_u64" | "pal_print_isize", _) => {
+                return Err(invalid("integer"));
+            }
+            ("pal_print_char", _) => return Err(invalid("character")),
+            ("exit", _) => return Err(invalid("integer")),
+            _ => return Err(InterpError::UnknownBuiltin(name.to_string())),
+        };
+
+        Ok(Value::U32(written as u32))
+    }
+
+    /// Runs a call to `print` or `println`, which sema has checked, printing each argument in
+    /// place of a `{}` of the format it is given first.
+    fn print(&mut self, name: &str, args: Vec<Value>) -> Result<Value, InterpError> {
+        let invalid = || InterpError::InvalidArgument {
+            function: name.to_string(),
+            expected: "format string",
+        };
+
+        let mut args = args.into_iter();
+        let Some(Value::Str(format)) = args.next() else {
+            return Err(invalid());
+        };
+
+        let mut text = String::new();
+
+        for piece in parse_format(&format).ok_or_else(invalid)? {
+            match piece {
+                Piece::Text(piece) => text.push_str(&piece),
+                Piece::Argument => text.push_str(&args.next().ok_or_else(invalid)?.to_string()),
+            }
+        }
+
+        if name == "println" {
+            text.push('\n');
+        }
+
+        Ok(Value::U32(self.write(&text)? as u32))
+    }
+
+    /// Returns the error that stops the interpreter when the program panics with a message.
+    fn panicked(&self, message: &str) -> InterpError {
+        match self.panic {
+            PanicStrategy::Exit(code) => InterpError::Panicked {
+                message: message.to_string(),
+                code,
+            },
+            PanicStrategy::Abort | PanicStrategy::Trap => InterpError::Aborted(message.to_string()),
+        }
+    }
+
+    /// Writes text to the output, returning how many bytes were written.
+    fn write(&mut self, text: &str) -> Result<usize, InterpError> {
+        self.out
+            .write_all(text.as_bytes())
+            .map_err(|error| InterpError::Output(error.to_string()))?;
+
+        Ok(text.len())
+    }
+
+    /// Executes every statement of a block inside of its own scope.
+    fn block(
+        &mut self,
+        body: &[Spanned<Statement>],
+        frame: &mut Frame,
+    ) -> Result<Flow, InterpError> {
+        frame.push();
+
+        let mut flow = Flow::Next;
+
+        for statement in body {
+            flow = self.statement(statement, frame)?;
+
+            if let Flow::Return(_) = flow {
+                break;
+            }
+        }
+
+        frame.pop();
+
+        Ok(flow)
+    }
+
+    fn statement(&mut self, statement: &Statement, frame: &mut Frame) -> Result<Flow, InterpError> {
+        match statement {
+            Statement::FunctionCall(name, args) => {
+                let args = self.arguments(args, frame)?;
+                self.call(&callee(name, frame), args)?;
+            }
+            Statement::Return(expression) => {
+                return Ok(Flow::Return(self.expression(expression, frame)?));
+            }
+            Statement::Let(name, _, expression) => {
+                let value = self.expression(expression, frame)?;
+                frame.insert(name, value);
+            }
+            Statement::Block(body) => return self.block(body, frame),
+            Statement::For(name, start, end, body) => {
+                // Both bounds are evaluated once, before the loop is entered.
+                let (Value::U32(start), Value::U32(end)) =
+                    (self.expression(start, frame)?, self.expression(end, frame)?)
+                else {
+                    return Err(InterpError::NonIntegerRange);
+                };
+
+                // The induction variable lives in its own scope around the body.
+                frame.push();
+                frame.insert(name, Value::U32(start));
+
+                let mut flow = Flow::Next;
+
+                while let Some(Value::U32(current)) = frame.get(name)
+                    && current < end
+                {
+                    flow = self.block(body, frame)?;
+
+                    if let Flow::Return(_) = flow {
+                        break;
+                    }
+
+                    frame.set(name, Value::U32(current.wrapping_add(1)));
+                }
+
+                frame.pop();
+
+                return Ok(flow);
+            }
+            Statement::Match(value, arms) => {
+                let value = self.expression(value, frame)?;
+
+                for (pattern, body) in arms {
+                    let matches = match &pattern.node {
+                        Pattern::Number(number) => value.bits() == Some(*number),
+                        Pattern::Char(c) => value == Value::Char(*c as u8),
+                        Pattern::Variant(_, variant, _)
+                            if let Value::Variant(_, found, _) = &value =>
+                        {
+                            variant == found
+                        }
+                        Pattern::Variant(name, variant, _) => {
+                            let variant = Expression::Variant(*name, *variant, vec![]);
+                            value == self.expression(&Spanned::new(variant, pattern.span), frame)?
+                        }
+                        Pattern::Wildcard => true,
+                    };
+
+                    if !matches {
+                        continue;
+                    }
+
+                    // Fields bound by the pattern live in a scope of their own around the arm.
+                    frame.push();
+
+                    if let (Pattern::Variant(_, _, bindings), Value::Variant(_, _, fields)) =
+                        (&pattern.node, &value)
+                    {
+                        for (binding, field) in bindings.iter().zip(fields.iter()) {
+                            if &binding[..] != "_" {
+                                frame.insert(binding, field.clone());
+                            }
+                        }
+                    }
+
+                    let flow = self.block(body, frame);
+                    frame.pop();
+
+                    return flow;
+                }
+            }
+            Statement::Unreachable => {
+                return Err(match self.panic {
+                    PanicStrategy::Exit(_) => self.panicked(UNREACHABLE),
+                    PanicStrategy::Abort | PanicStrategy::Trap => InterpError::Unreachable,
+                });
+            }
+            Statement::Free(_) => return Err(InterpError::Allocation),
+            Statement::Asm(_) => return Err(InterpError::InlineAsm),
+            Statement::Error(_) => {}
+            Statement::Assert(condition, message) => {
+                if self.expression(condition, frame)? == Value::U32(0) {
+                    return Err(self.panicked(&assertion_failed(message)));
+                }
+            }
+        }
+
+        Ok(Flow::Next)
+    }
+
+    /// Evaluates call arguments from left to right.
+    fn arguments(
+        &mut self,
+        args: &[Spanned<Expression>],
+        frame: &mut Frame,
+    ) -> Result<Vec<Value>, InterpError> {
+        args.iter().map(|arg| self.expression(arg, frame)).collect()
+    }
+
+    fn expression(
+        &mut self,
+        expression: &Expression,
+        frame: &mut Frame,
+    ) -> Result<Value, InterpError> {
+        match expression {
+            Expression::StringLiteral(value) => Ok(Value::Str(value.as_str().into())),
+            // Literals are truncated to 32 bits, like they are by codegen.
+            Expression::NumericLiteral(value) => Ok(Value::U32(*value as u32)),
+            Expression::CharLiteral(value) => Ok(Value::Char(*value as u8)),
+            Expression::Variable(name) => frame
+                .get(name)
+                .ok_or_else(|| InterpError::VariableDoesNotExist(name.to_string())),
+            Expression::Call(name, args) => {
+                let args = self.arguments(args, frame)?;
+                self.call(&callee(name, frame), args)
+            }
+            Expression::Index(name, index)
+                if let Some(Value::Strings(values)) = frame.get(name) =>
+            {
+                let Value::U32(index) = self.expression(index, frame)? else {
+                    return Err(InterpError::NonIntegerIndex);
+                };
+
+                match values.get(index as usize) {
+                    Some(value) => Ok(Value::Str(value.clone())),
+                    None => Err(InterpError::IndexOutOfBounds {
+                        name: name.to_string(),
+                        index,
+                        length: values.len(),
+                    }),
+                }
+            }
+            Expression::Index(name, index) => {
+                let Some(Item::Const(_, _, elements, _)) = self.functions.get(&name[..]).copied()
+                else {
+                    return Err(InterpError::VariableDoesNotExist(name.to_string()));
+                };
+
+                let Value::U32(index) = self.expression(index, frame)? else {
+                    return Err(InterpError::NonIntegerIndex);
+                };
+
+                match elements.get(index as usize) {
+                    Some(element) => self.expression(element, frame),
+                    None => Err(InterpError::IndexOutOfBounds {
+                        name: name.to_string(),
+                        index,
+                        length: elements.len(),
+                    }),
+                }
+            }
+            // The address of anything but a local variable is the address of a function.
+            Expression::Unary(UnaryOp::AddressOf, operand)
+                if let Expression::Variable(name) = &operand.node
+                    && frame.get(name).is_none() =>
+            {
+                Ok(Value::Function(*name))
+            }
+            Expression::Unary(op, operand) => match (op, self.expression(operand, frame)?) {
+                (UnaryOp::Neg, Value::U32(value)) => Ok(Value::U32(value.wrapping_neg())),
+                (UnaryOp::Neg, Value::Char(value)) => Ok(Value::Char(value.wrapping_neg())),
+                (UnaryOp::Not, Value::U32(value)) => Ok(Value::U32((value == 0) as u32)),
+                (UnaryOp::Not, Value::Char(value)) => Ok(Value::Char((value == 0) as u8)),
+                (UnaryOp::BitNot, Value::U32(value)) => Ok(Value::U32(!value)),
+                (UnaryOp::BitNot, Value::Char(value)) => Ok(Value::Char(!value)),
+                (UnaryOp::Neg, value) if let Some(bits) = value.bits() => {
+                    Ok(value.with_bits(bits.wrapping_neg()))
+                }
+                (UnaryOp::Not, value) if let Some(bits) = value.bits() => {
+                    Ok(value.with_bits((bits == 0) as u64))
+                }
+                (UnaryOp::BitNot, value) if let Some(bits) = value.bits() => {
+                    Ok(value.with_bits(!bits))
+                }
+                // Strings are the only pointers the interpreter knows about, and reading through
+                // one gives its first byte, or the terminating NUL of an empty string.
+                (UnaryOp::Deref, Value::Str(value)) => {
+                    Ok(Value::Char(value.bytes().next().unwrap_or(0)))
+                }
+                (UnaryOp::Deref, Value::Strings(values)) if !values.is_empty() => {
+                    Ok(Value::Str(values[0].clone()))
+                }
+                (UnaryOp::AddressOf, _) => Err(InterpError::AddressOf),
+                (op, _) => Err(InterpError::InvalidOperand(op.to_string())),
+            },
+            Expression::Cast(value, typ) => {
+                // A literal that was given a type keeps every bit of its value, even those that
+                // would not fit in the `u32` it otherwise is.
+                let bits = match value.node {
+                    Expression::NumericLiteral(value) => Some(value),
+                    _ => self.expression(value, frame)?.bits(),
+                };
+
+                match (bits, &typ.node) {
+                    (Some(bits), Type::Atomic(name)) => Value::integer(name, bits)
+                        .ok_or_else(|| InterpError::InvalidOperand("as".to_string())),
+                    _ => Err(InterpError::InvalidOperand("as".to_string())),
+                }
+            }
+            Expression::New(..) | Expression::Alloc(..) => Err(InterpError::Allocation),
+            Expression::Binary(op, left, right) => {
+                let left = self.expression(left, frame)?;
+                let right = self.expression(right, frame)?;
+
+                match (left, right) {
+                    // Comparisons give a `u32`, whatever the type of their operands.
+                    (left, right) if *op == BinaryOp::Eq => Ok(Value::U32((left == right) as u32)),
+                    (left, right) if *op == BinaryOp::Ne => Ok(Value::U32((left != right) as u32)),
+                    (Value::U32(left), Value::U32(right)) => {
+                        Ok(Value::U32(binary(*op, left, right)))
+                    }
+                    (Value::U32(left), Value::Char(right)) => {
+                        Ok(Value::U32(binary(*op, left, right as u32)))
+                    }
+                    (Value::Char(left), Value::Char(right)) => {
+                        Ok(Value::Char(binary(*op, left as u32, right as u32) as u8))
+                    }
+                    (Value::Char(left), Value::U32(right)) => {
+                        Ok(Value::Char(binary(*op, left as u32, right) as u8))
+                    }
+                    (left, right) if let (Some(bits), Some(by)) = (left.bits(), right.bits()) => {
+                        Ok(left.with_bits(wide_binary(*op, &left, bits, by)))
+                    }
+                    _ => Err(InterpError::InvalidOperand(op.to_string())),
+                }
+            }
+            Expression::If(condition, then, otherwise) => {
+                match self.expression(condition, frame)? == Value::U32(0) {
+                    true => self.expression(otherwise, frame),
+                    false => self.expression(then, frame),
+                }
+            }
+            // Sema rejects a `return` in a block expression, so its statements always run to the
+            // end.
+            Expression::Block(body, value) => {
+                frame.push();
+
+                for statement in body {
+                    self.statement(statement, frame)?;
+                }
+
+                let value = self.expression(value, frame);
+                frame.pop();
+
+                value
+            }
+            // A variant of an enum with fields evaluates to itself, with its fields evaluated.
+            Expression::Variant(name, variant, fields)
+                if self
+                    .functions
+                    .get(&name[..])
+                    .is_some_and(|item| item.has_fields()) =>
+            {
+                let fields = self.arguments(fields, frame)?;
+                Ok(Value::Variant(*name, *variant, fields.into()))
+            }
+            // Any other variant evaluates to its discriminant, which is its position in the enum.
+            Expression::Variant(name, variant, _) => match self.functions.get(&name[..]) {
+                Some(Item::Enum(_, variants, _)) => variants
+                    .iter()
+                    .position(|(other, _)| other.node == *variant)
+                    .map(|index| Value::U32(index as u32))
+                    .ok_or_else(|| InterpError::VariableDoesNotExist(expression.to_string())),
+                _ => Err(InterpError::VariableDoesNotExist(expression.to_string())),
+            },
+        }
+    }
+}
+
+/// Returns the name of the function a call calls, which is the one a local function pointer of
+/// that name points to, if there is one.
+fn callee(name: &Symbol, frame: &Frame) -> Symbol {
+    match frame.get(name) {
+        Some(Value::Function(target)) => target,
+        _ => *name,
+    }
+}
+
+/// Applies a binary operator to two integers. Shifting by the width of the type or more gives 0,
+/// as every bit has been shifted out.
+pub fn binary(op: BinaryOp, left: u32, right: u32) -> u32 {
+    match op {
+        BinaryOp::BitAnd => left & right,
+        BinaryOp::BitOr => left | right,
+        BinaryOp::BitXor => left ^ right,
+        BinaryOp::Shl => left.checked_shl(right).unwrap_or(0),
+        BinaryOp::Shr => left.checked_shr(right).unwrap_or(0),
+        BinaryOp::Eq => (left == right) as u32,
+        BinaryOp::Ne => (left != right) as u32,
+    }
+}
+
+/// Applies a binary operator to integers wider or narrower than a `u32`, given as their bits, and
+/// `left` itself for its type. Shifting by the width of `left` or more gives 0, or -1 when an
+/// `isize` shifted right is negative, and the result is truncated by [`Value::with_bits`].
+fn wide_binary(op: BinaryOp, left: &Value, bits: u64, by: u64) -> u64 {
+    let width = left.width() as u64;
+
+    match op {
+        BinaryOp::Shl if by >= width => 0,
+        BinaryOp::Shl => bits << by,
+        BinaryOp::Shr if let Value::Isize(value) = left => (value >> by.min(63)) as u64,
+        BinaryOp::Shr if by >= width => 0,
+        BinaryOp::Shr => bits >> by,
+        BinaryOp::BitAnd => bits & by,
+        BinaryOp::BitOr => bits | by,
+        BinaryOp::BitXor => bits ^ by,
+        BinaryOp::Eq => (bits == by) as u64,
+        BinaryOp::Ne => (bits != by) as u64,
+    }
+}
+
+/// Runs the `main` function of a module, returning the value it returns, or the code it exited
+/// with. When `main` takes `argc` and `argv`, it is given `args`.
+pub fn run(
+    module: &Module,
+    out: impl Write,
+    panic: PanicStrategy,
+    args: &[String],
+) -> Result<Value, InterpError> {
+    let takes_args = module
+        .1
+        .iter()
+        .any(|item| item.name() == "main" && !item.arguments().is_empty());
+    let args = match takes_args {
+        true => vec![
+            Value::U32(args.len() as u32),
+            Value::Strings(args.iter().map(|arg| arg.as_str().into()).collect()),
+        ],
+        false => vec![],
+    };
+
+    match Interpreter::new(module, out)
+        .with_panic(panic)
+        .call("main", args)
+    {
+        Err(InterpError::Exit(code)) => Ok(Value::U32(code)),
+        result => result,
+    }
+}
+
+#[cfg(test)]
+fn run_source(source: &str) -> (Result<Value, InterpError>, String) {
+    let (module, _) = crate::spec::module("main".to_string())
+        .parse(source)
+        .unwrap();
+    let mut out = vec![];
+    let result = run(&module, &mut out, PanicStrategy::default(), &[]);
+
+    (result, String::from_utf8(out).unwrap())
+}
+
+#[test]
+fn programs_are_evaluated() {
+    let (result, out) = run_source(
+        "ext fn printf(ptr: *char) -> u32;
+        ext fn putchar(c: char) -> u32;
+        fn identity(x: u32) -> u32 { return x; };
+        fn main() -> u32 {
+            for i in 0..3 { printf(\"tick\"); }
+            putchar('\\n');
+            return identity(7);
+        };",
+    );
+
+    assert_eq!(result, Ok(Value::U32(7)));
+    assert_eq!(out, "tickticktick\n");
+}
+
+#[test]
+fn scopes_shadow_and_returns_leave_loops() {
+    let (result, _) = run_source(
+        "fn main() -> u32 {
+            let x: u32 = 1;
+            { let x: u32 = 2; }
+            for i in x..10 { return i; }
+            return 0;
+        };",
+    );
+
+    assert_eq!(result, Ok(Value::U32(1)));
+}
+
+#[test]
+fn unknown_externs_fail() {
+    let (result, _) = run_source("ext fn abort() -> u32; fn main() -> u32 { abort(); return 0; };");
+
+    assert_eq!(
+        result,
+        Err(InterpError::UnknownBuiltin("abort".to_string()))
+    );
+}
+
+#[test]
+fn exit_unwinds_every_call() {
+    let (result, _) = run_source(
+        "ext fn exit(code: u32) -> !;
+        fn fail() -> ! { exit(3); };
+        fn main() -> u32 { fail(); return 0; };",
+    );
+
+    assert_eq!(result, Ok(Value::U32(3)));
+}
+
+#[test]
+fn constant_arrays_are_indexed() {
+    let source = "const TABLE: [u32; 3] = [4, 5, 6];
+        fn main() -> u32 { let i: u32 = 2; return TABLE[i]; };";
+
+    assert_eq!(run_source(source).0, Ok(Value::U32(6)));
+    assert_eq!(
+        run_source(&source.replace("= 2", "= 3")).0,
+        Err(InterpError::IndexOutOfBounds {
+            name: "TABLE".to_string(),
+            index: 3,
+            length: 3,
+        })
+    );
+}
+
+#[test]
+fn unary_operators_are_evaluated() {
+    assert_eq!(
+        run_source("fn main() -> u32 { return -~5; };").0,
+        Ok(Value::U32(6))
+    );
+    assert_eq!(
+        run_source("fn main() -> u32 { let x: u32 = 5; return !!x; };").0,
+        Ok(Value::U32(1))
+    );
+    assert_eq!(
+        run_source("fn main() -> char { return *\"hi\"; };").0,
+        Ok(Value::Char(b'h'))
+    );
+}
+
+#[test]
+fn binary_operators_are_evaluated() {
+    assert_eq!(
+        run_source("fn main() -> u32 { return 6 & 3 | 1 << 4 ^ 8; };").0,
+        Ok(Value::U32(26))
+    );
+    assert_eq!(
+        run_source("fn main() -> u32 { return 1 << 32; };").0,
+        Ok(Value::U32(0))
+    );
+}
+
+#[test]
+fn panics_follow_the_strategy() {
+    let (module, _) = crate::spec::module("main".to_string())
+        .parse("fn main() -> u32 { unreachable(); };")
+        .unwrap();
+
+    assert_eq!(
+        run(&module, vec![], PanicStrategy::Abort, &[]),
+        Err(InterpError::Unreachable)
+    );
+    assert_eq!(
+        run(&module, vec![], PanicStrategy::Exit(3), &[]),
+        Err(InterpError::Panicked {
+            message: "entered unreachable code".to_string(),
+            code: 3,
+        })
+    );
+}
+
+#[test]
+fn main_is_given_its_arguments() {
+    let (module, _) = crate::spec::module("main".to_string())
+        .parse(include_str!("../../examples/args.pal"))
+        .unwrap();
+    let args = ["args", "hello", "world"].map(str::to_string);
+    let mut out = vec![];
+
+    assert_eq!(
+        run(&module, &mut out, PanicStrategy::default(), &args),
+        Ok(Value::U32(0))
+    );
+    assert_eq!(String::from_utf8(out).unwrap(), "hello\nworld\n");
+}
+
+#[test]
+fn enums_compare_by_variant() {
+    assert_eq!(
+        run_source(
+            "enum Color { Red, Green, Blue };
+            fn main() -> u32 {
+                let c: Color = Color::Blue;
+                return (c == Color::Blue) << 1 | (c != Color::Red) << 2 | (c == Color::Green);
+            };"
+        )
+        .0,
+        Ok(Value::U32(6))
+    );
+}
+
+#[test]
+fn matches_run_the_first_matching_arm() {
+    assert_eq!(
+        run_source(
+            "enum Color { Red, Green, Blue };
+            fn code(c: Color) -> u32 {
+                match c { Color::Red => { return 1; } Color::Green => { return 2; } _ => { return 3; } }
+            };
+            fn main() -> u32 {
+                match 'b' { 'a' => { return 0; } 'b' => { return code(Color::Green) << 4 | code(Color::Blue); } }
+                return 0;
+            };"
+        )
+        .0,
+        Ok(Value::U32(0x23))
+    );
+}
+
+#[test]
+fn patterns_bind_the_fields_of_variants() {
+    assert_eq!(
+        run_source(
+            "enum Shape { Circle(u32), Rect(u32, u32), Empty };
+            fn area(s: Shape) -> u32 {
+                match s { Shape::Circle(r) => { return r << 1; } Shape::Rect(w, h) => { return w | h; } _ => { return 0; } }
+            };
+            fn main() -> u32 { return area(Shape::Circle(1)) | area(Shape::Rect(4, 8)) | area(Shape::Empty); };"
+        )
+        .0,
+        Ok(Value::U32(14))
+    );
+}
+
+#[test]
+fn generic_functions_run_for_any_type() {
+    assert_eq!(
+        run_source(
+            "fn first<T>(a: T, b: T) -> T { return a; };
+            fn main() -> u32 { let c: char = first('a', 'b'); return first(c, 'z') == 'a'; };"
+        )
+        .0,
+        Ok(Value::U32(1))
+    );
+}
+
+#[test]
+fn function_pointers_are_called_through() {
+    assert_eq!(
+        run_source(
+            "fn double(x: u32) -> u32 { return x << 1; };
+            fn apply(f: fn(u32) -> u32, x: u32) -> u32 { return f(x); };
+            fn main() -> u32 { let f: fn(u32) -> u32 = &double; return apply(f, 3) | f(1); };"
+        )
+        .0,
+        Ok(Value::U32(6))
+    );
+}
+
+#[test]
+fn strings_know_their_length() {
+    let (result, out) = run_source(
+        "ext fn puts(s: *char) -> u32;
+        fn main() -> u32 { let s: str = concat(\"hello\", \"pal\"); puts(s); return len(s); };",
+    );
+
+    assert_eq!(result, Ok(Value::U32(8)));
+    assert_eq!(out, "hellopal\n");
+}
+
+#[test]
+fn the_runtime_prints_and_panics() {
+    let (result, out) = run_source(
+        "fn main() -> u32 { pal_print_str(\"answer\"); pal_print_int(42); pal_panic(\"done\"); };",
+    );
+
+    assert_eq!(result, Err(InterpError::Aborted("done".to_string())));
+    assert_eq!(out, "answer42");
+}
+
+#[test]
+fn prints_are_formatted() {
+    let (result, out) = run_source(
+        "fn main(n: u64) -> u32 { let c: char = 'x'; print(\"{} = {{{}}}\", c, 300 as u16); println(\"!\"); return 0; };",
+    );
+
+    assert_eq!(result, Ok(Value::U32(0)));
+    assert_eq!(out, "x = {300}!\n");
+}
+
+#[test]
+fn integers_wrap_at_their_width() {
+    assert_eq!(
+        run_source("fn main() -> u32 { return (255 as u8 | 1 as u8) as u32; };").0,
+        Ok(Value::U32(255))
+    );
+    assert_eq!(
+        run_source("fn main() -> u32 { return (~(0 as u8) << 4 as u8) as u32; };").0,
+        Ok(Value::U32(0xf0))
+    );
+    assert_eq!(
+        run_source("fn main() -> u32 { return ((1 as u64 << 40) >> 38) as u32; };").0,
+        Ok(Value::U32(4))
+    );
+    assert_eq!(
+        run_source("fn main() -> u32 { return (-(8 as isize) >> 1) as u32; };").0,
+        Ok(Value::U32(-4i32 as u32))
+    );
+}
+
+#[test]
+fn literals_keep_the_width_they_are_given() {
+    let (module, _) = crate::spec::module("main".to_string())
+        .parse("fn main() -> u32 { let big: u64 = 5000000000; return (big >> 30) as u32; };")
+        .unwrap();
+    let module = crate::lower::literals::lower_literals(&module, &[]);
+
+    assert_eq!(
+        run(&module, vec![], PanicStrategy::default(), &[]),
+        Ok(Value::U32(4))
+    );
+}
@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+
+use crate::interp::value::Value;
+
+/// The local bindings of a single call. Every block pushes a new scope on entry and pops it on
+/// exit, mirroring the symbol table used by codegen.
+#[derive(Debug, Default)]
+pub struct Frame {
+    scopes: Vec<HashMap<String, Value>>,
+}
+
+impl Frame {
+    /// Creates a frame with a single, empty scope.
+    pub fn new() -> Frame {
+        Frame {
+            scopes: vec![HashMap::new()],
+        }
+    }
+
+    /// Enters a new innermost scope.
+    pub fn push(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    /// Leaves the innermost scope, dropping every binding declared in it.
+    pub fn pop(&mut self) {
+        self.scopes.pop();
+    }
+
+    /// Binds a name in the innermost scope, shadowing any binding of the same name.
+    pub fn insert(&mut self, name: impl ToString, value: Value) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), value);
+        }
+    }
+
+    /// Overwrites the innermost binding of a name.
+    pub fn set(&mut self, name: &str, value: Value) {
+        if let Some(slot) = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name))
+        {
+            *slot = value;
+        }
+    }
+
+    /// Looks a name up, starting from the innermost scope and working outwards.
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name).cloned())
+    }
+}
@@ -0,0 +1,100 @@
+use std::{
+    fmt::{Display, Formatter, Result},
+    rc::Rc,
+};
+
+use crate::spec::intern::Symbol;
+
+/// A value computed by the interpreter. Integers wrap like they do in compiled code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Value {
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    /// A `usize`, which is as wide as a pointer of the host.
+    Usize(u64),
+    /// An `isize`, which is as wide as a pointer of the host.
+    Isize(i64),
+    Char(u8),
+    Str(Rc<str>),
+    /// A pointer to strings, which is how `main` receives its arguments.
+    Strings(Rc<[Rc<str>]>),
+    /// A variant of an enum with fields, along with the values of its fields. Variants of enums
+    /// without fields are their discriminants instead.
+    Variant(Symbol, Symbol, Rc<[Value]>),
+    /// A pointer to the function declared under the given name.
+    Function(Symbol),
+}
+
+impl Value {
+    /// Returns the integer of the given type whose bits are the lowest bits of `bits`.
+    pub fn integer(typ: &str, bits: u64) -> Option<Value> {
+        match typ {
+            "u8" => Some(Value::U8(bits as u8)),
+            "u16" => Some(Value::U16(bits as u16)),
+            "u32" => Some(Value::U32(bits as u32)),
+            "u64" => Some(Value::U64(bits)),
+            "usize" => Some(Value::Usize(bits)),
+            "isize" => Some(Value::Isize(bits as i64)),
+            "char" => Some(Value::Char(bits as u8)),
+            _ => None,
+        }
+    }
+
+    /// Returns the bits of an integer, sign extended when it is an `isize`.
+    pub fn bits(&self) -> Option<u64> {
+        match self {
+            Value::U8(value) | Value::Char(value) => Some(*value as u64),
+            Value::U16(value) => Some(*value as u64),
+            Value::U32(value) => Some(*value as u64),
+            Value::U64(value) | Value::Usize(value) => Some(*value),
+            Value::Isize(value) => Some(*value as u64),
+            _ => None,
+        }
+    }
+
+    /// Returns the integer of the same type as this one whose bits are the lowest bits of `bits`.
+    pub fn with_bits(&self, bits: u64) -> Value {
+        match self {
+            Value::U8(_) => Value::U8(bits as u8),
+            Value::U16(_) => Value::U16(bits as u16),
+            Value::U32(_) => Value::U32(bits as u32),
+            Value::U64(_) => Value::U64(bits),
+            Value::Usize(_) => Value::Usize(bits),
+            Value::Isize(_) => Value::Isize(bits as i64),
+            Value::Char(_) => Value::Char(bits as u8),
+            value => value.clone(),
+        }
+    }
+
+    /// Returns how many bits wide an integer is.
+    pub fn width(&self) -> u32 {
+        match self {
+            Value::U8(_) | Value::Char(_) => 8,
+            Value::U16(_) => 16,
+            Value::U32(_) => 32,
+            _ => 64,
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Value::U8(value) => write!(f, "{value}"),
+            Value::U16(value) => write!(f, "{value}"),
+            Value::U32(value) => write!(f, "{value}"),
+            Value::U64(value) | Value::Usize(value) => write!(f, "{value}"),
+            Value::Isize(value) => write!(f, "{value}"),
+            Value::Char(value) => write!(f, "{}", *value as char),
+            Value::Str(value) => write!(f, "{value}"),
+            Value::Strings(values) => write!(f, "{}", values.join(" ")),
+            Value::Variant(name, variant, fields) => {
+                let fields: Vec<_> = fields.iter().map(ToString::to_string).collect();
+                write!(f, "{name}::{variant}({})", fields.join(", "))
+            }
+            Value::Function(name) => write!(f, "&{name}"),
+        }
+    }
+}
@@ -0,0 +1,21 @@
+pub mod backend;
+pub mod bindgen;
+pub mod cgen;
+pub mod codegen;
+pub mod diagnostic;
+pub mod emit;
+pub mod interp;
+pub mod lint;
+pub mod load;
+pub mod lower;
+pub mod lsp;
+pub mod manifest;
+pub mod mir;
+pub mod mono;
+pub mod optimize;
+pub mod parser;
+pub mod repl;
+pub mod runtime;
+pub mod sema;
+pub mod snapshot;
+pub mod spec;
@@ -0,0 +1,35 @@
+use thiserror::Error;
+
+use crate::{diagnostic::Diagnostic, lint::Lint, parser::Span};
+
+/// Represents any warning a lint can give about a module that sema has accepted.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum LintWarning {
+    #[error("unused variable `{0}`; prefix it with an underscore if this is intended")]
+    UnusedVariable(String),
+    #[error("unused parameter `{0}`; prefix it with an underscore if this is intended")]
+    UnusedParameter(String),
+    #[error("unreachable statement; control never gets past the statement before it")]
+    UnreachableStatement,
+    #[error("function `{0}` is never called from `main`")]
+    DeadFunction(String),
+}
+
+impl LintWarning {
+    /// Returns the lint that gives the warning.
+    pub fn lint(&self) -> Lint {
+        match self {
+            LintWarning::UnusedVariable(_) => Lint::UnusedVariables,
+            LintWarning::UnusedParameter(_) => Lint::UnusedParameters,
+            LintWarning::UnreachableStatement => Lint::UnreachableCode,
+            LintWarning::DeadFunction(_) => Lint::DeadCode,
+        }
+    }
+
+    /// Turns the warning into a [`Diagnostic`] pointing at the given span.
+    pub fn at(self, span: Span) -> Diagnostic {
+        Diagnostic::warning(&self)
+            .with_code(self.lint().code())
+            .with_span(span)
+    }
+}
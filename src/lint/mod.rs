@@ -0,0 +1,467 @@
+pub mod error;
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
+
+use crate::{
+    diagnostic::{Diagnostic, Severity},
+    lint::error::LintWarning,
+    parser::{Span, Spanned},
+    sema::{Globals, always_returns},
+    spec::{
+        ast::*,
+        visit::{Visit, walk_expression, walk_statement},
+    },
+};
+
+// Lints run on modules that sema has accepted, and point out code that compiles but is likely a
+// mistake. Unlike sema, they never stop a program from being compiled.
+
+/// A kind of warning, which can be allowed or warned about on its own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Lint {
+    /// A `let`, a `for` loop or an arm of a `match` binds a variable that is never used.
+    UnusedVariables,
+    /// A function never uses one of its parameters.
+    UnusedParameters,
+    /// A statement follows one that returns or never returns, so it can never run.
+    UnreachableCode,
+    /// A function is never called from `main`, directly or through other functions.
+    DeadCode,
+}
+
+impl Lint {
+    /// Every lint, in the order they are documented in.
+    pub const ALL: [Lint; 4] = [
+        Lint::UnusedVariables,
+        Lint::UnusedParameters,
+        Lint::UnreachableCode,
+        Lint::DeadCode,
+    ];
+}
+
+impl Lint {
+    /// Returns the name of the lint, which is the code of the diagnostics it gives and what it is
+    /// called on the command line.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Lint::UnusedVariables => "unused-variables",
+            Lint::UnusedParameters => "unused-parameters",
+            Lint::UnreachableCode => "unreachable-code",
+            Lint::DeadCode => "dead-code",
+        }
+    }
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+/// What is done with the warnings of a lint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+    /// The lint gives no warnings.
+    Allow,
+    /// The lint gives warnings, which do not stop the program from being compiled.
+    Warn,
+    /// The lint gives errors, which stop the program from being compiled.
+    Deny,
+}
+
+/// Which lints are reported, and how. Every lint is warned about unless it has been allowed or
+/// denied.
+#[derive(Clone, Debug, Default)]
+pub struct Levels {
+    levels: HashMap<Lint, Level>,
+}
+
+impl Levels {
+    /// Stops warning about a lint.
+    pub fn allow(&mut self, lint: Lint) {
+        self.levels.insert(lint, Level::Allow);
+    }
+
+    /// Warns about a lint again, after it has been allowed or denied.
+    pub fn warn(&mut self, lint: Lint) {
+        self.levels.insert(lint, Level::Warn);
+    }
+
+    /// Reports the warnings of a lint as errors.
+    pub fn deny(&mut self, lint: Lint) {
+        self.levels.insert(lint, Level::Deny);
+    }
+
+    /// Returns what is done with the warnings of a lint.
+    pub fn level(&self, lint: Lint) -> Level {
+        self.levels.get(&lint).copied().unwrap_or(Level::Warn)
+    }
+
+    /// Returns whether a lint is allowed, so that it gives no warnings.
+    pub fn is_allowed(&self, lint: Lint) -> bool {
+        self.level(lint) == Level::Allow
+    }
+}
+
+/// Lints a module that sema has accepted along with the modules it imports, returning a warning
+/// for every lint that is not allowed, in the order they appear in the source. Lints that are
+/// denied give errors instead.
+///
+/// Variables and parameters whose name starts with an underscore are never reported as unused,
+/// and only a module that declares `main` or `_start` has functions that can be dead.
+pub fn lint(module: &Module, imports: &[&Module], levels: &Levels) -> Vec<Diagnostic> {
+    let globals = Globals::collect_with_imports(module, imports);
+    let mut linter = Linter {
+        items: &|name| globals.item(name),
+        scopes: vec![],
+        warnings: dead_functions(module),
+    };
+
+    linter.visit_module(module);
+
+    let mut warnings = linter.warnings;
+    warnings.sort_by_key(|(_, span)| span.start);
+
+    warnings
+        .into_iter()
+        .filter_map(|(warning, span)| {
+            let lint = warning.lint();
+
+            match levels.level(lint) {
+                Level::Allow => None,
+                Level::Warn => Some(warning.at(span)),
+                Level::Deny => Some(
+                    warning
+                        .at(span)
+                        .with_severity(Severity::Error)
+                        .with_note(format!("`{lint}` is denied with `-D`")),
+                ),
+            }
+        })
+        .collect()
+}
+
+/// A variable or parameter in scope, and whether it has been used since it was bound.
+struct Binding {
+    name: Symbol,
+    span: Span,
+    used: bool,
+    parameter: bool,
+}
+
+/// Walks the functions of a module, keeping track of the variables in scope.
+struct Linter<'a> {
+    items: &'a dyn Fn(&str) -> Option<&'a Item>,
+    scopes: Vec<Vec<Binding>>,
+    warnings: Vec<(LintWarning, Span)>,
+}
+
+impl Linter<'_> {
+    fn push_scope(&mut self) {
+        self.scopes.push(vec![]);
+    }
+
+    /// Leaves the innermost scope, warning about every binding of it that was never used.
+    fn pop_scope(&mut self) {
+        let bindings = self.scopes.pop().unwrap_or_default();
+
+        for binding in bindings {
+            if binding.used || binding.name.starts_with('_') {
+                continue;
+            }
+
+            let name = binding.name.to_string();
+            let warning = match binding.parameter {
+                true => LintWarning::UnusedParameter(name),
+                false => LintWarning::UnusedVariable(name),
+            };
+
+            self.warnings.push((warning, binding.span));
+        }
+    }
+
+    fn bind(&mut self, name: Symbol, span: Span, parameter: bool) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push(Binding {
+                name,
+                span,
+                used: false,
+                parameter,
+            });
+        }
+    }
+
+    /// Marks the innermost binding of a name as used. Names that are not bound refer to items.
+    fn use_name(&mut self, name: &str) {
+        let binding = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .flat_map(|scope| scope.iter_mut().rev())
+            .find(|binding| binding.name == name);
+
+        if let Some(binding) = binding {
+            binding.used = true;
+        }
+    }
+
+    /// Lints the statements of a block in the innermost scope, warning about the first one that
+    /// follows a statement that never finishes.
+    fn lint_statements(&mut self, body: &[Spanned<Statement>]) {
+        let mut diverged = false;
+        let mut reported = false;
+
+        for statement in body {
+            if diverged && !reported {
+                self.warnings
+                    .push((LintWarning::UnreachableStatement, statement.span));
+                reported = true;
+            }
+
+            self.visit_statement(statement);
+            diverged = diverged || always_returns(std::slice::from_ref(statement), self.items);
+        }
+    }
+}
+
+impl Visit<'_> for Linter<'_> {
+    fn visit_item(&mut self, item: &Spanned<Item>) {
+        if let Item::FunctionDeclaration(_, _, args, _, body, ..) = &item.node {
+            self.push_scope();
+
+            for (name, typ) in args {
+                self.bind(*name, typ.span, true);
+            }
+
+            self.visit_block(body);
+            self.pop_scope();
+        }
+    }
+
+    /// Lints the statements of a block in a scope of their own, warning about the first one that
+    /// follows a statement that control never gets past.
+    fn visit_block(&mut self, body: &[Spanned<Statement>]) {
+        self.push_scope();
+        self.lint_statements(body);
+        self.pop_scope();
+    }
+
+    fn visit_statement(&mut self, statement: &Spanned<Statement>) {
+        match &statement.node {
+            Statement::FunctionCall(name, _) => {
+                self.use_name(name);
+                walk_statement(self, statement);
+            }
+            Statement::Let(name, _, value) => {
+                self.visit_expression(value);
+                self.bind(*name, statement.span, false);
+            }
+            Statement::For(name, start, end, body) => {
+                self.visit_expression(start);
+                self.visit_expression(end);
+
+                self.push_scope();
+                self.bind(*name, statement.span, false);
+                self.visit_block(body);
+                self.pop_scope();
+            }
+            Statement::Match(value, arms) => {
+                self.visit_expression(value);
+
+                for (pattern, body) in arms {
+                    self.push_scope();
+
+                    if let Pattern::Variant(_, _, fields) = &pattern.node {
+                        for field in fields {
+                            self.bind(*field, pattern.span, false);
+                        }
+                    }
+
+                    self.visit_block(body);
+                    self.pop_scope();
+                }
+            }
+            _ => walk_statement(self, statement),
+        }
+    }
+
+    fn visit_expression(&mut self, expression: &Spanned<Expression>) {
+        match &expression.node {
+            Expression::Variable(name) => self.use_name(name),
+            Expression::Call(name, _) => {
+                // A call may go through a function pointer held by a variable.
+                self.use_name(name);
+                walk_expression(self, expression);
+            }
+            Expression::Block(body, value) => {
+                self.push_scope();
+                self.lint_statements(body);
+                self.visit_expression(value);
+                self.pop_scope();
+            }
+            _ => walk_expression(self, expression),
+        }
+    }
+}
+
+/// Returns a warning for every function of a module that can not be reached from its `main` or
+/// `_start`, following calls and functions whose address is taken. A module that declares neither
+/// is a library, whose functions are all meant to be called from elsewhere.
+fn dead_functions(module: &Module) -> Vec<(LintWarning, Span)> {
+    let functions: HashMap<Symbol, &Spanned<Item>> = module
+        .1
+        .iter()
+        .filter(|item| matches!(item.node, Item::FunctionDeclaration(..)))
+        .map(|item| (Symbol::intern(item.name()), item))
+        .collect();
+
+    let mut pending: Vec<Symbol> = ["main", "_start"]
+        .into_iter()
+        .map(Symbol::intern)
+        .filter(|name| functions.contains_key(name))
+        .collect();
+
+    if pending.is_empty() {
+        return vec![];
+    }
+
+    let mut reached: HashSet<Symbol> = pending.iter().copied().collect();
+
+    while let Some(name) = pending.pop() {
+        let Item::FunctionDeclaration(.., body, _, _) = &functions[&name].node else {
+            continue;
+        };
+
+        let mut names = vec![];
+        block_names(body, &mut names);
+
+        for name in names {
+            if functions.contains_key(&name) && reached.insert(name) {
+                pending.push(name);
+            }
+        }
+    }
+
+    module
+        .1
+        .iter()
+        .filter(|item| matches!(item.node, Item::FunctionDeclaration(..)))
+        .filter(|item| !reached.contains(item.name()))
+        .map(|item| {
+            (
+                LintWarning::DeadFunction(item.name().to_string()),
+                item.span,
+            )
+        })
+        .collect()
+}
+
+/// Collects every name that the statements of a block refer to, whether they are variables or
+/// items.
+fn block_names(body: &[Spanned<Statement>], names: &mut Vec<Symbol>) {
+    Names(names).visit_block(body);
+}
+
+/// Collects the names a visited node refers to, see [`block_names`].
+struct Names<'n>(&'n mut Vec<Symbol>);
+
+impl Visit<'_> for Names<'_> {
+    fn visit_statement(&mut self, statement: &Spanned<Statement>) {
+        if let Statement::FunctionCall(name, _) = &statement.node {
+            self.0.push(*name);
+        }
+
+        walk_statement(self, statement);
+    }
+
+    fn visit_expression(&mut self, expression: &Spanned<Expression>) {
+        if let Expression::Variable(name) | Expression::Call(name, _) = &expression.node {
+            self.0.push(*name);
+        }
+
+        walk_expression(self, expression);
+    }
+}
+
+#[cfg(test)]
+fn lint_source(source: &str, levels: &Levels) -> Vec<String> {
+    let (module, _) = crate::spec::module("main".to_string())
+        .parse(source)
+        .unwrap();
+
+    lint(&module, &[], levels)
+        .into_iter()
+        .map(|diagnostic| format!("{} [{}]", diagnostic.message, diagnostic.code.unwrap_or("")))
+        .collect()
+}
+
+#[test]
+fn unused_bindings_are_reported() {
+    let warnings = lint_source(
+        "fn main(x: u32, _y: u32, z: u32) -> u32 {
+            let a: u32 = x;
+            let b: u32 = 1;
+            let _c: u32 = 2;
+            for i in 0..a { }
+            return z;
+        };",
+        &Levels::default(),
+    );
+
+    assert_eq!(
+        warnings,
+        [
+            "unused variable `b`; prefix it with an underscore if this is intended [unused-variables]",
+            "unused variable `i`; prefix it with an underscore if this is intended [unused-variables]",
+        ]
+    );
+}
+
+#[test]
+fn unreachable_statements_and_dead_functions_are_reported() {
+    let source = "fn main(x: u32) -> u32 {
+            match x { 0 => { return 1; exit(2); exit(3); } }
+            return helper(x);
+        };
+        fn helper(x: u32) -> u32 { return x; };
+        fn unused(x: u32) -> u32 { return x; };";
+
+    assert_eq!(
+        lint_source(source, &Levels::default()),
+        [
+            "unreachable statement; control never gets past the statement before it [unreachable-code]",
+            "function `unused` is never called from `main` [dead-code]",
+        ]
+    );
+
+    let mut levels = Levels::default();
+    levels.allow(Lint::DeadCode);
+    levels.allow(Lint::UnreachableCode);
+    levels.warn(Lint::UnreachableCode);
+
+    assert_eq!(lint_source(source, &levels).len(), 1);
+}
+
+#[test]
+fn denied_lints_give_errors() {
+    let (module, _) = crate::spec::module("main".to_string())
+        .parse("fn main() -> u32 { let x: u32 = 1; return 0; }; fn unused() -> u32 { return 0; };")
+        .unwrap();
+
+    let mut levels = Levels::default();
+    levels.deny(Lint::UnusedVariables);
+
+    let diagnostics = lint(&module, &[], &levels);
+
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.code, diagnostic.is_error()))
+            .collect::<Vec<_>>(),
+        [(Some("unused-variables"), true), (Some("dead-code"), false)]
+    );
+    assert_eq!(diagnostics[0].children.len(), 1);
+}
@@ -0,0 +1,16 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+/// Represents any error in loading the modules a program imports.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum LoadError {
+    #[error("failed to read module `{name}` from {}: {message}", .path.display())]
+    Read {
+        name: String,
+        path: PathBuf,
+        message: String,
+    },
+    #[error("failed to parse module `{name}`: {message}")]
+    Parse { name: String, message: String },
+}
@@ -0,0 +1,230 @@
+pub mod error;
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    load::error::LoadError,
+    parser::error::ParseError,
+    spec::{ast::*, recovering_module},
+};
+
+/// A module that was loaded because it is imported, along with the source it was parsed from.
+#[derive(Clone, Debug)]
+pub struct Source {
+    /// Where the module was read from. Every path is canonical, so that a module imported from
+    /// several places is only loaded once.
+    pub path: PathBuf,
+    pub text: String,
+    pub module: Module,
+    /// Every item that failed to parse, which was skipped over.
+    pub parse_errors: Vec<ParseError>,
+}
+
+/// Returns the canonical form of a path, or the path itself when it does not exist.
+fn canonical(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Returns the path that `import name;` refers to in the file at `importer`.
+pub fn import_path(importer: &Path, name: &str) -> PathBuf {
+    canonical(
+        &importer
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(format!("{name}.pal")),
+    )
+}
+
+/// Loads every module that the module at `entry` imports, directly or not. Each module is loaded
+/// once, even when it is imported from several places or imports are cyclic.
+pub fn load_imports(entry: &Path, module: &Module) -> Result<Vec<Source>, LoadError> {
+    load_sources(entry, module, &[])
+}
+
+/// Loads the modules of a program made of several files: every file in `inputs`, each as a module
+/// named after its file, and every module that the module at `entry` or any of the inputs
+/// imports. The inputs come first, in the order they are given.
+pub fn load_sources(
+    entry: &Path,
+    module: &Module,
+    inputs: &[PathBuf],
+) -> Result<Vec<Source>, LoadError> {
+    let mut loaded = HashSet::from([canonical(entry)]);
+    let mut pending: Vec<_> = module
+        .2
+        .iter()
+        .rev()
+        .map(|name| (import_path(entry, name), name.node.clone()))
+        .chain(inputs.iter().rev().map(|path| {
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            (canonical(path), name.into_owned())
+        }))
+        .collect();
+    let mut sources = vec![];
+
+    while let Some((path, name)) = pending.pop() {
+        if !loaded.insert(path.clone()) {
+            continue;
+        }
+
+        let text = std::fs::read_to_string(&path).map_err(|error| LoadError::Read {
+            name: name.clone(),
+            path: path.clone(),
+            message: error.to_string(),
+        })?;
+
+        let ((module, parse_errors), _) = recovering_module(name.clone())
+            .parse_memoized(&text)
+            .map_err(|error| LoadError::Parse {
+                name,
+                message: error.to_string(),
+            })?;
+
+        pending.extend(
+            module
+                .2
+                .iter()
+                .rev()
+                .map(|name| (import_path(&path, name), name.node.clone())),
+        );
+
+        sources.push(Source {
+            path,
+            text,
+            module,
+            parse_errors,
+        });
+    }
+
+    Ok(sources)
+}
+
+/// Returns the modules that the module at `path` imports directly, out of those loaded.
+pub fn imports_of<'a>(path: &Path, module: &Module, sources: &'a [Source]) -> Vec<&'a Module> {
+    module
+        .2
+        .iter()
+        .filter_map(|name| {
+            let path = import_path(path, name);
+
+            sources
+                .iter()
+                .find(|source| source.path == path)
+                .map(|source| &source.module)
+        })
+        .collect()
+}
+
+/// Links modules at the level of the AST, for backends that compile a single module. Items are
+/// taken from `module` first and then from each of `imports`, skipping any item whose name was
+/// already taken. A function declared with `ext fn` is skipped when some module defines it,
+/// so that a module can call a function defined in another file without importing it.
+pub fn link(module: &Module, imports: &[Module]) -> Module {
+    let every = || {
+        module
+            .1
+            .iter()
+            .chain(imports.iter().flat_map(|import| &import.1))
+    };
+
+    let defined: HashSet<_> = every()
+        .filter(|item| matches!(item.node, Item::FunctionDeclaration(..)))
+        .map(|item| item.name())
+        .collect();
+    let mut names = HashSet::new();
+
+    let items = every()
+        .filter(|item| {
+            let declared_elsewhere = matches!(item.node, Item::ExternFunctionDefinition(..))
+                && defined.contains(item.name());
+
+            !declared_elsewhere && names.insert(item.name().to_string())
+        })
+        .cloned()
+        .collect();
+
+    Module(module.0.clone(), items, vec![])
+}
+
+#[test]
+fn imports_are_loaded_once() {
+    let dir = std::env::temp_dir().join(format!("pal-load-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    std::fs::write(
+        dir.join("a.pal"),
+        "import b; fn a() -> u32 { return b(); };",
+    )
+    .unwrap();
+    std::fs::write(dir.join("b.pal"), "import a; fn b() -> u32 { return 0; };").unwrap();
+
+    let entry = dir.join("main.pal");
+    let (module, _) = crate::spec::module("main".to_string())
+        .parse("import a; import b; fn main() -> u32 { return a(); };")
+        .unwrap();
+
+    let sources = load_imports(&entry, &module).unwrap();
+    let names: Vec<_> = sources.iter().map(|source| &source.module.0[..]).collect();
+
+    assert_eq!(names, vec!["a", "b"]);
+    assert_eq!(imports_of(&entry, &module, &sources).len(), 2);
+
+    let modules: Vec<_> = sources.into_iter().map(|source| source.module).collect();
+    assert_eq!(link(&module, &modules).1.len(), 3);
+
+    assert!(matches!(
+        load_imports(&dir.join("a.pal"), &modules[0]),
+        Ok(sources) if sources.len() == 1
+    ));
+    assert!(matches!(
+        load_imports(
+            &entry,
+            &crate::spec::module("main".to_string())
+                .parse("import c;")
+                .unwrap()
+                .0
+        ),
+        Err(LoadError::Read { .. })
+    ));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn extern_declarations_yield_to_definitions() {
+    let parse = |name: &str, source: &str| {
+        crate::spec::module(name.to_string())
+            .parse(source)
+            .unwrap()
+            .0
+    };
+
+    let entry = parse(
+        "main",
+        "ext fn twice(x: u32) -> u32; ext fn puts(s: *char) -> u32; fn main() -> u32 { return twice(1); };",
+    );
+    let other = parse(
+        "other",
+        "ext fn puts(s: *char) -> u32; fn twice(x: u32) -> u32 { return x << 1; };",
+    );
+
+    let linked = link(&entry, &[other]);
+    let kinds: Vec<_> = linked
+        .1
+        .iter()
+        .map(|item| {
+            (
+                item.name(),
+                matches!(item.node, Item::FunctionDeclaration(..)),
+            )
+        })
+        .collect();
+
+    assert_eq!(
+        kinds,
+        vec![("puts", false), ("main", true), ("twice", true)]
+    );
+}
@@ -0,0 +1,51 @@
+use std::path::Path;
+
+use crate::{
+    diagnostic::line_column,
+    parser::Spanned,
+    spec::{
+        ast::*,
+        visit::{VisitMut, walk_statement_mut},
+    },
+};
+
+/// Rewrites the message of every `assert` of a module to end with where the assertion is, as
+/// `message at path:line:column`, so that a program that fails one says which. Only the driver
+/// knows the path and source a module was read from, so it runs this pass before handing the
+/// module to a backend, which otherwise panics with the message alone.
+pub fn locate_asserts(module: &Module, path: &Path, source: &str) -> Module {
+    let mut module = module.clone();
+    Locator { path, source }.visit_module_mut(&mut module);
+
+    module
+}
+
+/// Rewrites the message of every `assert` it visits, see [`locate_asserts`].
+struct Locator<'a> {
+    path: &'a Path,
+    source: &'a str,
+}
+
+impl VisitMut for Locator<'_> {
+    fn visit_statement_mut(&mut self, statement: &mut Spanned<Statement>) {
+        if let Statement::Assert(_, message) = &mut statement.node {
+            let (line, column) = line_column(self.source, statement.span.start);
+            *message = format!("{message} at {}:{line}:{column}", self.path.display());
+        }
+
+        walk_statement_mut(self, statement);
+    }
+}
+
+#[test]
+fn asserts_are_located() {
+    let source = "fn main() -> u32 {\n    assert(1, \"first\");\n    { assert(0, \"second\"); }\n    return 0;\n};";
+    let (module, _) = crate::spec::module("main".to_string())
+        .parse(source)
+        .unwrap();
+
+    let located = locate_asserts(&module, Path::new("main.pal"), source).to_string();
+
+    assert!(located.contains("assert(1, \"first at main.pal:2:5\");"));
+    assert!(located.contains("assert(0, \"second at main.pal:3:7\");"));
+}
@@ -0,0 +1,322 @@
+use crate::{
+    parser::{Span, Spanned},
+    sema::{
+        Globals,
+        format::{Piece, is_format_call, parse_format},
+        scope::Scopes,
+        ty::TyKind,
+        type_of,
+    },
+    spec::ast::*,
+};
+
+/// Rewrites a module that sema has accepted so that every call to `print` and `println` becomes a
+/// block of calls to the functions of the runtime that print each piece of its format, see
+/// [`crate::runtime`]. Each argument is printed by the function for its type, widened to the type
+/// that function takes when it is narrower, so `println("{} of {}", b, n)`, with `b: u8` and
+/// `n: u64`, becomes:
+///
+/// ```text
+/// {
+///     pal_print_int(b as u32);
+///     pal_print_cstr(" of ");
+///     pal_print_u64(n);
+///     pal_print_cstr("\n");
+/// }
+/// ```
+///
+/// Arguments are evaluated as they are printed. Generic functions must already have been
+/// instantiated, so that the type of every argument is known.
+pub fn lower_format(module: &Module, imports: &[Module]) -> Module {
+    let imports: Vec<&Module> = imports.iter().collect();
+    let globals = Globals::collect_with_imports(module, &imports);
+
+    let items = module
+        .1
+        .iter()
+        .map(|item| {
+            let node = match &item.node {
+                Item::FunctionDeclaration(
+                    name,
+                    generics,
+                    args,
+                    returns,
+                    body,
+                    attributes,
+                    visibility,
+                ) => {
+                    let mut scopes = Scopes::returning(globals.tcx.lower(returns).ok());
+
+                    for (arg, typ) in args {
+                        if let Ok(ty) = globals.tcx.lower(typ) {
+                            scopes.insert(arg, ty);
+                        }
+                    }
+
+                    Item::FunctionDeclaration(
+                        *name,
+                        generics.clone(),
+                        args.clone(),
+                        returns.clone(),
+                        lower_block(body, &globals, &mut scopes),
+                        attributes.clone(),
+                        *visibility,
+                    )
+                }
+                node => node.clone(),
+            };
+
+            Spanned::new(node, item.span)
+        })
+        .collect();
+
+    Module(module.0.clone(), items, module.2.clone())
+}
+
+fn lower_block(
+    body: &[Spanned<Statement>],
+    globals: &Globals,
+    scopes: &mut Scopes,
+) -> Vec<Spanned<Statement>> {
+    scopes.push();
+
+    let body = body
+        .iter()
+        .map(|statement| {
+            let node = lower_statement(statement, globals, scopes);
+            Spanned::new(node, statement.span)
+        })
+        .collect();
+
+    scopes.pop();
+
+    body
+}
+
+fn lower_statement(
+    statement: &Spanned<Statement>,
+    globals: &Globals,
+    scopes: &mut Scopes,
+) -> Statement {
+    match &statement.node {
+        Statement::FunctionCall(name, args) if is_format_call(name, globals, scopes) => {
+            Statement::Block(lower_print(name, args, statement.span, globals, scopes))
+        }
+        Statement::FunctionCall(name, args) => Statement::FunctionCall(
+            *name,
+            args.iter()
+                .map(|arg| lower_expression(arg, globals, scopes))
+                .collect(),
+        ),
+        Statement::Return(value) => Statement::Return(lower_expression(value, globals, scopes)),
+        Statement::Let(name, typ, value) => {
+            let value = lower_expression(value, globals, scopes);
+
+            if let Ok(ty) = globals.tcx.lower(typ) {
+                scopes.insert(name, ty);
+            }
+
+            Statement::Let(*name, typ.clone(), value)
+        }
+        Statement::Block(body) => Statement::Block(lower_block(body, globals, scopes)),
+        Statement::For(name, start, end, body) => {
+            let start = lower_expression(start, globals, scopes);
+            let end = lower_expression(end, globals, scopes);
+
+            scopes.push();
+            scopes.insert(name, globals.tcx.u32());
+            let body = lower_block(body, globals, scopes);
+            scopes.pop();
+
+            Statement::For(*name, start, end, body)
+        }
+        Statement::Match(value, arms) => {
+            let value = lower_expression(value, globals, scopes);
+            let arms = arms
+                .iter()
+                .map(|(pattern, body)| {
+                    scopes.push();
+                    bind(&pattern.node, globals, scopes);
+                    let body = lower_block(body, globals, scopes);
+                    scopes.pop();
+
+                    (pattern.clone(), body)
+                })
+                .collect();
+
+            Statement::Match(value, arms)
+        }
+        Statement::Assert(condition, message) => Statement::Assert(
+            lower_expression(condition, globals, scopes),
+            message.clone(),
+        ),
+        Statement::Free(pointer) => Statement::Free(lower_expression(pointer, globals, scopes)),
+        Statement::Unreachable | Statement::Asm(_) | Statement::Error(_) => statement.node.clone(),
+    }
+}
+
+/// Binds the fields a pattern binds in the innermost scope, like sema does.
+fn bind(pattern: &Pattern, globals: &Globals, scopes: &mut Scopes) {
+    let Pattern::Variant(name, variant, bindings) = pattern else {
+        return;
+    };
+
+    let fields = globals
+        .variants(name)
+        .and_then(|variants| variants.iter().find(|(other, _)| other.node == *variant))
+        .map(|(_, fields)| &fields[..])
+        .unwrap_or_default();
+
+    for (binding, field) in bindings.iter().zip(fields) {
+        if let Ok(ty) = globals.tcx.lower(field) {
+            scopes.insert(binding, ty);
+        }
+    }
+}
+
+/// Returns the calls that print the pieces of the format of a call to `print` or `println`, which
+/// sema has checked.
+fn lower_print(
+    name: &str,
+    args: &[Spanned<Expression>],
+    span: Span,
+    globals: &Globals,
+    scopes: &Scopes,
+) -> Vec<Spanned<Statement>> {
+    let tcx = &globals.tcx;
+    let call = |function: &str, arg: Expression, span| {
+        let arg = Spanned::new(arg, span);
+        Spanned::new(Statement::FunctionCall(function.into(), vec![arg]), span)
+    };
+    let widened = |arg: &Spanned<Expression>, typ: &str| {
+        let typ = Spanned::new(Type::Atomic(typ.into()), arg.span);
+        Expression::Cast(Box::new(arg.clone()), typ)
+    };
+
+    let Some((Expression::StringLiteral(format), args)) = args
+        .split_first()
+        .map(|(format, args)| (&format.node, args))
+    else {
+        return vec![];
+    };
+
+    let mut pieces = parse_format(format).unwrap_or_default();
+
+    if name == "println" {
+        match pieces.last_mut() {
+            Some(Piece::Text(text)) => text.push('\n'),
+            _ => pieces.push(Piece::Text("\n".to_string())),
+        }
+    }
+
+    let mut args = args.iter();
+
+    pieces
+        .into_iter()
+        .filter_map(|piece| match piece {
+            Piece::Text(text) => Some(call(
+                "pal_print_cstr",
+                Expression::StringLiteral(text),
+                span,
+            )),
+            Piece::Argument => {
+                let arg = lower_expression(args.next()?, globals, scopes);
+                let ty = type_of(&arg, globals, scopes, &mut vec![])?;
+
+                let (function, value) = match tcx.kind(ty) {
+                    TyKind::U8 | TyKind::U16 => ("pal_print_int", widened(&arg, "u32")),
+                    TyKind::U32 => ("pal_print_int", arg.node),
+                    TyKind::U64 => ("pal_print_u64", arg.node),
+                    TyKind::Usize => ("pal_print_u64", widened(&arg, "u64")),
+                    TyKind::Isize => ("pal_print_isize", arg.node),
+                    TyKind::Char => ("pal_print_char", arg.node),
+                    TyKind::Str => ("pal_print_str", arg.node),
+                    _ => ("pal_print_cstr", arg.node),
+                };
+
+                Some(call(function, value, arg.span))
+            }
+        })
+        .collect()
+}
+
+/// Lowers the calls to `print` and `println` in the blocks an expression may contain.
+fn lower_expression(
+    expression: &Spanned<Expression>,
+    globals: &Globals,
+    scopes: &Scopes,
+) -> Spanned<Expression> {
+    let lower = |expression| Box::new(lower_expression(expression, globals, scopes));
+    let lower_all = |expressions: &[Spanned<Expression>]| {
+        expressions
+            .iter()
+            .map(|expression| lower_expression(expression, globals, scopes))
+            .collect()
+    };
+
+    let lowered = match &expression.node {
+        Expression::Call(name, args) => Expression::Call(*name, lower_all(args)),
+        Expression::Index(name, index) => Expression::Index(*name, lower(index)),
+        Expression::Unary(op, operand) => Expression::Unary(*op, lower(operand)),
+        Expression::Binary(op, left, right) => Expression::Binary(*op, lower(left), lower(right)),
+        Expression::If(condition, then, otherwise) => {
+            Expression::If(lower(condition), lower(then), lower(otherwise))
+        }
+        Expression::Block(body, value) => {
+            let mut scopes = scopes.clone();
+            scopes.push();
+
+            let body = body
+                .iter()
+                .map(|statement| {
+                    let node = lower_statement(statement, globals, &mut scopes);
+                    Spanned::new(node, statement.span)
+                })
+                .collect();
+            let value = lower_expression(value, globals, &scopes);
+
+            Expression::Block(body, Box::new(value))
+        }
+        Expression::Variant(name, variant, args) => {
+            Expression::Variant(*name, *variant, lower_all(args))
+        }
+        Expression::New(typ, value) => Expression::New(typ.clone(), lower(value)),
+        Expression::Alloc(typ, count) => Expression::Alloc(typ.clone(), lower(count)),
+        Expression::Cast(value, typ) => Expression::Cast(lower(value), typ.clone()),
+        Expression::StringLiteral(_)
+        | Expression::NumericLiteral(_)
+        | Expression::CharLiteral(_)
+        | Expression::Variable(_) => expression.node.clone(),
+    };
+
+    Spanned::new(lowered, expression.span)
+}
+
+#[test]
+fn prints_are_lowered_to_the_runtime() {
+    let (module, _) = crate::spec::module("main".to_string())
+        .parse(
+            "fn main(b: u8, n: usize, c: char, s: str) -> u32 { println(\"{} of {}: {}{{{}}}\", b, n, c, s); print(\"{}\", 7); return 0; };",
+        )
+        .unwrap();
+
+    assert_eq!(
+        lower_format(&module, &[]).1[0].to_string(),
+        "fn main(b: u8, n: usize, c: char, s: str) -> u32 {
+    {
+        pal_print_int(b as u32);
+        pal_print_cstr(\" of \");
+        pal_print_u64(n as u64);
+        pal_print_cstr(\": \");
+        pal_print_char(c);
+        pal_print_cstr(\"{\");
+        pal_print_str(s);
+        pal_print_cstr(\"}\\n\");
+    }
+    {
+        pal_print_int(7);
+    }
+    return 0;
+};"
+    );
+}
@@ -0,0 +1,329 @@
+use crate::{
+    parser::Spanned,
+    sema::{
+        Globals,
+        scope::Scopes,
+        ty::{Ty, TyKind},
+        type_of_expected,
+    },
+    spec::ast::*,
+};
+
+/// Rewrites a module that sema has accepted so that every numeric literal that sema gave a type
+/// other than `u32` says so, as a conversion of the literal to its type. `let x: u64 = 5;` becomes
+/// `let x: u64 = 5 as u64;`, which backends generate as a constant of the width of `u64` rather
+/// than as a `u32` that is then widened, so that literals too large for a `u32` keep their value.
+///
+/// Literals take their types the way sema gives them: from the type their context expects, or from
+/// the other operand of a binary operator.
+pub fn lower_literals(module: &Module, imports: &[Module]) -> Module {
+    let imports: Vec<&Module> = imports.iter().collect();
+    let globals = Globals::collect_with_imports(module, &imports);
+    let tcx = &globals.tcx;
+
+    let items = module
+        .1
+        .iter()
+        .map(|item| {
+            let node = match &item.node {
+                Item::FunctionDeclaration(
+                    name,
+                    generics,
+                    args,
+                    returns,
+                    body,
+                    attributes,
+                    visibility,
+                ) => {
+                    let mut scopes = Scopes::returning(tcx.lower(returns).ok());
+
+                    for (arg, typ) in args {
+                        if let Ok(ty) = tcx.lower(typ) {
+                            scopes.insert(arg, ty);
+                        }
+                    }
+
+                    Item::FunctionDeclaration(
+                        *name,
+                        generics.clone(),
+                        args.clone(),
+                        returns.clone(),
+                        lower_block(body, &globals, &mut scopes),
+                        attributes.clone(),
+                        *visibility,
+                    )
+                }
+                Item::Const(name, typ, elements, visibility) => {
+                    let element = match tcx.lower(typ).map(|ty| tcx.kind(ty)) {
+                        Ok(TyKind::Array(element, _)) => Some(element),
+                        _ => None,
+                    };
+                    let elements = elements
+                        .iter()
+                        .map(|value| lower_expression(value, element, &globals, &Scopes::new()))
+                        .collect();
+
+                    Item::Const(*name, typ.clone(), elements, *visibility)
+                }
+                node => node.clone(),
+            };
+
+            Spanned::new(node, item.span)
+        })
+        .collect();
+
+    Module(module.0.clone(), items, module.2.clone())
+}
+
+fn lower_block(
+    body: &[Spanned<Statement>],
+    globals: &Globals,
+    scopes: &mut Scopes,
+) -> Vec<Spanned<Statement>> {
+    scopes.push();
+
+    let body = body
+        .iter()
+        .map(|statement| {
+            let node = lower_statement(&statement.node, globals, scopes);
+            Spanned::new(node, statement.span)
+        })
+        .collect();
+
+    scopes.pop();
+
+    body
+}
+
+fn lower_statement(statement: &Statement, globals: &Globals, scopes: &mut Scopes) -> Statement {
+    let tcx = &globals.tcx;
+
+    match statement {
+        Statement::FunctionCall(name, args) => {
+            Statement::FunctionCall(*name, lower_arguments(*name, args, globals, scopes))
+        }
+        Statement::Return(value) => Statement::Return(lower_expression(
+            value,
+            scopes.return_type(),
+            globals,
+            scopes,
+        )),
+        Statement::Let(name, typ, value) => {
+            let ty = tcx.lower(typ).ok();
+            let value = lower_expression(value, ty, globals, scopes);
+
+            if let Some(ty) = ty {
+                scopes.insert(name, ty);
+            }
+
+            Statement::Let(*name, typ.clone(), value)
+        }
+        Statement::Block(body) => Statement::Block(lower_block(body, globals, scopes)),
+        Statement::For(name, start, end, body) => {
+            let start = lower_expression(start, None, globals, scopes);
+            let end = lower_expression(end, None, globals, scopes);
+
+            scopes.push();
+            scopes.insert(name, tcx.u32());
+            let body = lower_block(body, globals, scopes);
+            scopes.pop();
+
+            Statement::For(*name, start, end, body)
+        }
+        Statement::Match(value, arms) => {
+            let value = lower_expression(value, None, globals, scopes);
+            let arms = arms
+                .iter()
+                .map(|(pattern, body)| {
+                    scopes.push();
+                    bind(&pattern.node, globals, scopes);
+                    let body = lower_block(body, globals, scopes);
+                    scopes.pop();
+
+                    (pattern.clone(), body)
+                })
+                .collect();
+
+            Statement::Match(value, arms)
+        }
+        Statement::Assert(condition, message) => Statement::Assert(
+            lower_expression(condition, None, globals, scopes),
+            message.clone(),
+        ),
+        Statement::Free(pointer) => {
+            Statement::Free(lower_expression(pointer, None, globals, scopes))
+        }
+        Statement::Unreachable | Statement::Asm(_) | Statement::Error(_) => statement.clone(),
+    }
+}
+
+/// Binds the fields a pattern binds in the innermost scope, like sema does.
+fn bind(pattern: &Pattern, globals: &Globals, scopes: &mut Scopes) {
+    let Pattern::Variant(name, variant, bindings) = pattern else {
+        return;
+    };
+
+    let fields = globals
+        .variants(name)
+        .and_then(|variants| variants.iter().find(|(other, _)| other.node == *variant))
+        .map(|(_, fields)| &fields[..])
+        .unwrap_or_default();
+
+    for (binding, field) in bindings.iter().zip(fields) {
+        if let Ok(ty) = globals.tcx.lower(field) {
+            scopes.insert(binding, ty);
+        }
+    }
+}
+
+/// Lowers the arguments of a call, each of which is expected to have the type of its parameter.
+fn lower_arguments(
+    name: Symbol,
+    args: &[Spanned<Expression>],
+    globals: &Globals,
+    scopes: &Scopes,
+) -> Vec<Spanned<Expression>> {
+    let tcx = &globals.tcx;
+
+    // A variable holding a function pointer shadows any function of the same name, like in sema.
+    let parameters: Vec<Option<Ty>> = match scopes.get(&name).map(|ty| tcx.kind(ty)) {
+        Some(TyKind::Function(parameters, _)) => parameters.into_iter().map(Some).collect(),
+        _ => globals
+            .functions
+            .get(&name[..])
+            .map(|function| function.arguments())
+            .unwrap_or_default()
+            .iter()
+            .map(|(_, typ)| tcx.lower(typ).ok())
+            .collect(),
+    };
+
+    args.iter()
+        .enumerate()
+        .map(|(index, arg)| {
+            let expected = parameters.get(index).copied().flatten();
+            lower_expression(arg, expected, globals, scopes)
+        })
+        .collect()
+}
+
+/// Lowers an expression, given the type its context expects.
+fn lower_expression(
+    expression: &Spanned<Expression>,
+    expected: Option<Ty>,
+    globals: &Globals,
+    scopes: &Scopes,
+) -> Spanned<Expression> {
+    let tcx = &globals.tcx;
+    let lower = |expression, expected| lower_expression(expression, expected, globals, scopes);
+    let type_of =
+        |expression, expected| type_of_expected(expression, expected, globals, scopes, &mut vec![]);
+    let is_literal =
+        |operand: &Spanned<Expression>| matches!(operand.node, Expression::NumericLiteral(_));
+
+    let lowered = match &expression.node {
+        Expression::NumericLiteral(_)
+            if let Some(ty) = expected
+                && tcx.kind(ty).is_integer()
+                && ty != tcx.u32() =>
+        {
+            let typ = Spanned::new(tcx.to_type(ty), expression.span);
+            Expression::Cast(Box::new(expression.clone()), typ)
+        }
+        Expression::Call(name, args) => {
+            Expression::Call(*name, lower_arguments(*name, args, globals, scopes))
+        }
+        Expression::Index(name, index) => Expression::Index(*name, Box::new(lower(index, None))),
+        Expression::Unary(op @ (UnaryOp::Neg | UnaryOp::Not | UnaryOp::BitNot), operand) => {
+            Expression::Unary(*op, Box::new(lower(operand, expected)))
+        }
+        Expression::Unary(op, operand) => Expression::Unary(*op, Box::new(lower(operand, None))),
+        // Operands are expected to have the type sema gives them, see
+        // [`crate::sema::type_of_expected`].
+        Expression::Binary(op, left, right) => {
+            let expected = expected.filter(|_| !matches!(op, BinaryOp::Eq | BinaryOp::Ne));
+
+            let (left, right) = match op {
+                BinaryOp::Shl | BinaryOp::Shr => (lower(left, expected), lower(right, None)),
+                _ if is_literal(left) && !is_literal(right) => (
+                    lower(left, type_of(right, expected)),
+                    lower(right, expected),
+                ),
+                _ if is_literal(left) => (lower(left, expected), lower(right, expected)),
+                _ => (lower(left, expected), lower(right, type_of(left, expected))),
+            };
+
+            Expression::Binary(*op, Box::new(left), Box::new(right))
+        }
+        // Both branches give the value of the whole expression, so they expect the same type.
+        Expression::If(condition, then, otherwise) => Expression::If(
+            Box::new(lower(condition, None)),
+            Box::new(lower(then, expected)),
+            Box::new(lower(otherwise, expected)),
+        ),
+        Expression::Block(body, value) => {
+            let mut scopes = scopes.clone();
+            scopes.push();
+
+            let body = body
+                .iter()
+                .map(|statement| {
+                    let node = lower_statement(&statement.node, globals, &mut scopes);
+                    Spanned::new(node, statement.span)
+                })
+                .collect();
+            let value = lower_expression(value, expected, globals, &scopes);
+
+            Expression::Block(body, Box::new(value))
+        }
+        Expression::Variant(name, variant, args) => {
+            let fields = globals
+                .variants(name)
+                .and_then(|variants| variants.iter().find(|(other, _)| other.node == *variant))
+                .map(|(_, fields)| &fields[..])
+                .unwrap_or_default();
+            let args = args
+                .iter()
+                .enumerate()
+                .map(|(index, arg)| {
+                    let expected = fields.get(index).and_then(|field| tcx.lower(field).ok());
+                    lower(arg, expected)
+                })
+                .collect();
+
+            Expression::Variant(*name, *variant, args)
+        }
+        Expression::New(typ, value) => {
+            Expression::New(typ.clone(), Box::new(lower(value, tcx.lower(typ).ok())))
+        }
+        Expression::Alloc(typ, count) => {
+            Expression::Alloc(typ.clone(), Box::new(lower(count, None)))
+        }
+        Expression::Cast(value, typ) => Expression::Cast(Box::new(lower(value, None)), typ.clone()),
+        Expression::StringLiteral(_)
+        | Expression::NumericLiteral(_)
+        | Expression::CharLiteral(_)
+        | Expression::Variable(_) => expression.node.clone(),
+    };
+
+    Spanned::new(lowered, expression.span)
+}
+
+#[test]
+fn literals_are_given_their_types() {
+    let (module, _) = crate::spec::module("main".to_string())
+        .parse(
+            "fn f(b: u8) -> u8 { return b; };
+            fn main(n: u64) -> u32 { let big: u64 = 5000000000 | 1 << n; let b: u8 = f(~0); return (n & 1) as u32 | b as u32 | 2; };",
+        )
+        .unwrap();
+
+    assert_eq!(
+        lower_literals(&module, &[]).1[1].to_string(),
+        "fn main(n: u64) -> u32 {
+    let big: u64 = (5000000000 as u64) | ((1 as u64) << n);
+    let b: u8 = f(~(0 as u8));
+    return (((n & (1 as u64)) as u32) | (b as u32)) | 2;
+};"
+    );
+}
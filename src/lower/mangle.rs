@@ -0,0 +1,366 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{parser::Spanned, spec::ast::*};
+
+/// What every mangled name starts with. Identifiers of pal can not start with it followed by a
+/// digit unless written so on purpose, and neither can those of C code following the usual rules
+/// for reserved names.
+pub const PREFIX: &str = "_P";
+
+/// Returns the name a function is given at link time: [`PREFIX`], the module it is declared in and
+/// its name, each preceded by its length, followed by the types of its arguments, or `v` when it
+/// has none. `fn add(a: u32, b: *char)` in module `math` becomes `_P4math3add3u32P4char`.
+///
+/// Types are encoded as follows: named types by their length and name, pointers as `P` followed by
+/// the type they point to, arrays as `A`, their length and `_` followed by the type of their
+/// elements, function pointers as `F`, their arguments and return type and `E`, and `!` as `z`.
+pub fn mangled_name(module: &str, name: &str, args: &[(Symbol, Spanned<Type>)]) -> Symbol {
+    let module: String = module
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c,
+            false => '_',
+        })
+        .collect();
+
+    let mut mangled = format!("{PREFIX}{}{module}{}{name}", module.len(), name.len());
+
+    if args.is_empty() {
+        mangled.push('v');
+    }
+
+    for (_, typ) in args {
+        mangle_type(&mut mangled, &typ.node);
+    }
+
+    Symbol::from(mangled)
+}
+
+fn mangle_type(out: &mut String, typ: &Type) {
+    match typ {
+        Type::Atomic(name) => out.push_str(&format!("{}{name}", name.len())),
+        Type::Pointer(pointee) => {
+            out.push('P');
+            mangle_type(out, pointee);
+        }
+        Type::Array(element, length) => {
+            out.push_str(&format!("A{length}_"));
+            mangle_type(out, element);
+        }
+        Type::Function(arguments, returns) => {
+            out.push('F');
+
+            for argument in arguments {
+                mangle_type(out, argument);
+            }
+
+            mangle_type(out, returns);
+            out.push('E');
+        }
+        Type::Never => out.push('z'),
+    }
+}
+
+/// Returns whether an item is renamed by [`mangle_program`]. Only functions defined in pal are:
+/// functions declared with `ext fn` or marked `#[no_mangle]` keep the name they are written with,
+/// as do `main` and `_start`, which the runtime and the linker look for.
+pub fn is_mangled(item: &Item) -> bool {
+    matches!(item, Item::FunctionDeclaration(..))
+        && !matches!(item.name(), "main" | "_start")
+        && !item.attributes().contains(&Attribute::NoMangle)
+}
+
+/// Renames every function of a program, along with every call and reference to it, to its
+/// [`mangled_name`], so that functions of the same name in different modules no longer collide
+/// once the modules are linked. This runs before [`super::lower_program`], so that the instances
+/// of a generic function are named after its mangled name.
+///
+/// A name in a module refers to the items the module declares first, and then to the public items
+/// of the modules it imports, like it does in sema. `imports` holds every module of the program.
+pub fn mangle_program(module: &Module, imports: &[Module]) -> (Module, Vec<Module>) {
+    let every: Vec<&Module> = std::iter::once(module).chain(imports).collect();
+
+    let mangle = |module: &Module| {
+        let mut names = HashMap::new();
+
+        for import in &module.2 {
+            let Some(import) = every.iter().find(|other| other.0 == import.node) else {
+                continue;
+            };
+
+            for item in &import.1 {
+                if item.visibility() == Visibility::Public {
+                    names.insert(Symbol::from(item.name()), renamed(import, item));
+                }
+            }
+        }
+
+        for item in &module.1 {
+            names.insert(Symbol::from(item.name()), renamed(module, item));
+        }
+
+        mangle_module(module, &names)
+    };
+
+    (mangle(module), imports.iter().map(mangle).collect())
+}
+
+/// Returns the name an item of a module has once mangled.
+fn renamed(module: &Module, item: &Item) -> Symbol {
+    match item {
+        Item::FunctionDeclaration(name, _, args, ..) if is_mangled(item) => {
+            mangled_name(&module.0, name, args)
+        }
+        _ => Symbol::from(item.name()),
+    }
+}
+
+fn mangle_module(module: &Module, names: &HashMap<Symbol, Symbol>) -> Module {
+    let items = module
+        .1
+        .iter()
+        .map(|item| {
+            let node = match &item.node {
+                Item::FunctionDeclaration(
+                    name,
+                    generics,
+                    args,
+                    returns,
+                    body,
+                    attributes,
+                    visibility,
+                ) => {
+                    let mut mangler = Mangler {
+                        names,
+                        locals: vec![args.iter().map(|(arg, _)| *arg).collect()],
+                    };
+
+                    Item::FunctionDeclaration(
+                        names.get(name).copied().unwrap_or(*name),
+                        generics.clone(),
+                        args.clone(),
+                        returns.clone(),
+                        mangler.block(body),
+                        attributes.clone(),
+                        *visibility,
+                    )
+                }
+                node => node.clone(),
+            };
+
+            Spanned::new(node, item.span)
+        })
+        .collect();
+
+    Module(module.0.clone(), items, module.2.clone())
+}
+
+/// Renames the references to functions in a body, keeping track of the variables in scope, which
+/// shadow functions of the same name.
+struct Mangler<'n> {
+    names: &'n HashMap<Symbol, Symbol>,
+    locals: Vec<HashSet<Symbol>>,
+}
+
+impl Mangler<'_> {
+    fn resolve(&self, name: Symbol) -> Symbol {
+        if self.locals.iter().any(|scope| scope.contains(&name)) {
+            return name;
+        }
+
+        self.names.get(&name).copied().unwrap_or(name)
+    }
+
+    fn scoped(
+        &mut self,
+        bound: impl IntoIterator<Item = Symbol>,
+        body: &[Spanned<Statement>],
+    ) -> Vec<Spanned<Statement>> {
+        self.locals.push(bound.into_iter().collect());
+        let body = self.block(body);
+        self.locals.pop();
+
+        body
+    }
+
+    fn block(&mut self, body: &[Spanned<Statement>]) -> Vec<Spanned<Statement>> {
+        body.iter()
+            .map(|statement| Spanned::new(self.statement(&statement.node), statement.span))
+            .collect()
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Statement {
+        match statement {
+            Statement::FunctionCall(name, args) => {
+                Statement::FunctionCall(self.resolve(*name), self.expressions(args))
+            }
+            Statement::Return(value) => Statement::Return(self.expression(value)),
+            Statement::Let(name, typ, value) => {
+                let value = self.expression(value);
+
+                if let Some(scope) = self.locals.last_mut() {
+                    scope.insert(*name);
+                }
+
+                Statement::Let(*name, typ.clone(), value)
+            }
+            Statement::Block(body) => Statement::Block(self.scoped([], body)),
+            Statement::For(name, start, end, body) => Statement::For(
+                *name,
+                self.expression(start),
+                self.expression(end),
+                self.scoped([*name], body),
+            ),
+            Statement::Match(value, arms) => Statement::Match(
+                self.expression(value),
+                arms.iter()
+                    .map(|(pattern, body)| {
+                        let bound = match &pattern.node {
+                            Pattern::Variant(_, _, bindings) => bindings.clone(),
+                            _ => vec![],
+                        };
+
+                        (pattern.clone(), self.scoped(bound, body))
+                    })
+                    .collect(),
+            ),
+            Statement::Free(pointer) => Statement::Free(self.expression(pointer)),
+            Statement::Assert(condition, message) => {
+                Statement::Assert(self.expression(condition), message.clone())
+            }
+            Statement::Unreachable | Statement::Asm(_) | Statement::Error(_) => statement.clone(),
+        }
+    }
+
+    fn expressions(&mut self, expressions: &[Spanned<Expression>]) -> Vec<Spanned<Expression>> {
+        expressions
+            .iter()
+            .map(|expression| self.expression(expression))
+            .collect()
+    }
+
+    fn expression(&mut self, expression: &Spanned<Expression>) -> Spanned<Expression> {
+        let mangled = match &expression.node {
+            Expression::Variable(name) => Expression::Variable(self.resolve(*name)),
+            Expression::Call(name, args) => {
+                Expression::Call(self.resolve(*name), self.expressions(args))
+            }
+            Expression::Index(name, index) => {
+                Expression::Index(*name, Box::new(self.expression(index)))
+            }
+            Expression::Unary(op, operand) => {
+                Expression::Unary(*op, Box::new(self.expression(operand)))
+            }
+            Expression::Binary(op, left, right) => Expression::Binary(
+                *op,
+                Box::new(self.expression(left)),
+                Box::new(self.expression(right)),
+            ),
+            Expression::If(condition, then, otherwise) => Expression::If(
+                Box::new(self.expression(condition)),
+                Box::new(self.expression(then)),
+                Box::new(self.expression(otherwise)),
+            ),
+            Expression::Block(body, value) => {
+                self.locals.push(HashSet::new());
+                let body = self.block(body);
+                let value = self.expression(value);
+                self.locals.pop();
+
+                Expression::Block(body, Box::new(value))
+            }
+            Expression::Variant(name, variant, fields) => {
+                Expression::Variant(*name, *variant, self.expressions(fields))
+            }
+            Expression::New(typ, value) => {
+                Expression::New(typ.clone(), Box::new(self.expression(value)))
+            }
+            Expression::Alloc(typ, count) => {
+                Expression::Alloc(typ.clone(), Box::new(self.expression(count)))
+            }
+            Expression::Cast(value, typ) => {
+                Expression::Cast(Box::new(self.expression(value)), typ.clone())
+            }
+            Expression::StringLiteral(_)
+            | Expression::NumericLiteral(_)
+            | Expression::CharLiteral(_) => expression.node.clone(),
+        };
+
+        Spanned::new(mangled, expression.span)
+    }
+}
+
+#[cfg(test)]
+fn parse(name: &str, source: &str) -> Module {
+    crate::spec::module(name.to_string())
+        .parse(source)
+        .unwrap()
+        .0
+}
+
+#[test]
+fn names_are_mangled() {
+    let module = parse(
+        "math",
+        "fn add(a: u32, b: *char) -> u32 { return a; }; fn zero() -> u32 { return 0; }; fn apply(f: fn([u32; 2]) -> !) -> u32 { return 0; };",
+    );
+    let names: Vec<_> = module
+        .1
+        .iter()
+        .map(|item| match &item.node {
+            Item::FunctionDeclaration(name, _, args, ..) => mangled_name("math", name, args),
+            _ => unreachable!(),
+        })
+        .collect();
+
+    assert_eq!(
+        names,
+        [
+            "_P4math3add3u32P4char",
+            "_P4math4zerov",
+            "_P4math5applyFA2_3u32zE"
+        ]
+        .map(Symbol::from)
+    );
+    assert_eq!(mangled_name("a-b", "f", &[]), Symbol::from("_P3a_b1fv"));
+}
+
+#[test]
+fn functions_of_different_modules_do_not_collide() {
+    let main = parse(
+        "main",
+        "import util; ext fn puts(s: *char) -> u32; fn helper() -> u32 { return 1; }; fn main() -> u32 { let helper2: u32 = helper(); puts(\"hi\"); return twice(helper2); };",
+    );
+    let util = parse(
+        "util",
+        "fn helper() -> u32 { return 2; }; pub fn twice(x: u32) -> u32 { let f: fn() -> u32 = helper; let helper: u32 = x; return helper; }; #[no_mangle] pub fn raw() -> u32 { return helper(); };",
+    );
+
+    let (main, imports) = mangle_program(&main, &[util]);
+    let util = &imports[0];
+
+    let names = |module: &Module| {
+        module
+            .1
+            .iter()
+            .map(|item| item.name().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(names(&main), ["puts", "_P4main6helperv", "main"]);
+    assert_eq!(names(util), ["_P4util6helperv", "_P4util5twice3u32", "raw"]);
+    assert!(
+        main.to_string()
+            .contains("let helper2: u32 = _P4main6helperv();")
+    );
+    assert!(main.to_string().contains("puts(\"hi\");"));
+    assert!(
+        main.to_string()
+            .contains("return _P4util5twice3u32(helper2);")
+    );
+    assert!(
+        util.to_string()
+            .contains("let f: fn() -> u32 = _P4util6helperv;")
+    );
+    assert!(util.to_string().contains("return helper;"));
+}
@@ -0,0 +1,442 @@
+pub mod asserts;
+pub mod format;
+pub mod literals;
+pub mod mangle;
+pub mod strings;
+
+use std::collections::HashMap;
+
+use crate::{
+    lower::{format::lower_format, literals::lower_literals, strings::lower_strings},
+    mono::{error::MonoError, monomorphize},
+    parser::{Span, Spanned},
+    sema::missing_variants,
+    spec::ast::*,
+};
+
+/// The variants of every enum a module can refer to, by the name of the enum.
+type Enums<'a> = HashMap<Symbol, &'a [Variant]>;
+
+/// The variable that a lowered `match` over an enum with fields keeps its value in. `match` is a
+/// keyword, so it can never clash with a variable of the program.
+pub const MATCHED: &str = "match";
+
+/// Returns the name of the function that builds a variant of an enum with fields.
+pub fn constructor_name(name: &str, variant: &str) -> Symbol {
+    Symbol::from(format!("{name}::{variant}"))
+}
+
+/// Returns the name of the function that reads a field of a variant of an enum with fields.
+pub fn field_name(name: &str, variant: &str, index: usize) -> Symbol {
+    Symbol::from(format!("{name}::{variant}::{index}"))
+}
+
+/// Returns the name of the function that reads the discriminant of a value of an enum with
+/// fields. `match` is a keyword, so it can never clash with the name of a variant.
+pub fn tag_name(name: &str) -> Symbol {
+    Symbol::from(format!("{name}::{MATCHED}"))
+}
+
+/// Returns whether an enum has no variant with fields, and so can be lowered to `u32`.
+fn is_plain(variants: &[Variant]) -> bool {
+    variants.iter().all(|(_, fields)| fields.is_empty())
+}
+
+/// Runs every pass that a module sema has accepted must go through before a backend can generate
+/// it, along with its imports: generic functions are instantiated, prints become calls to the
+/// runtime, strings are taken apart, literals are given their types and enums are numbered, so
+/// that backends never have to know about them.
+pub fn lower_program(
+    module: &Module,
+    imports: &[Module],
+) -> Result<(Module, Vec<Module>), MonoError> {
+    let lower = |module| -> Result<Module, MonoError> {
+        let module = lower_format(&monomorphize(module, imports)?, imports);
+        let module = lower_strings(&module, imports);
+        let module = lower_literals(&module, imports);
+        Ok(lower_enums(&module, imports))
+    };
+
+    let lowered = imports.iter().map(lower).collect::<Result<_, _>>()?;
+
+    Ok((lower(module)?, lowered))
+}
+
+/// Rewrites a module that sema has accepted so that it no longer uses enums the way the source
+/// does, which no backend knows about. Enums without fields are erased: their type becomes `u32`,
+/// every variant becomes the literal of its discriminant, and their items are dropped.
+///
+/// Enums with fields are kept, and backends generate a function for each of their variants, whose
+/// fields are its arguments, see [`constructor_name`], and functions reading their discriminant
+/// and fields, see [`tag_name`] and [`field_name`]. Variants become calls to these functions, and
+/// a `match` over such an enum becomes a `match` over its discriminant, whose arms read the bound
+/// fields into variables.
+///
+/// Enums declared in the module itself shadow imported ones of the same name, like they do in
+/// sema.
+pub fn lower_enums(module: &Module, imports: &[Module]) -> Module {
+    let mut enums = Enums::new();
+
+    for item in imports
+        .iter()
+        .rev()
+        .flat_map(|import| &import.1)
+        .chain(&module.1)
+    {
+        if let Item::Enum(name, variants, _) = &item.node {
+            enums.insert(*name, variants);
+        }
+    }
+
+    let items = module
+        .1
+        .iter()
+        .filter(|item| !matches!(item.node, Item::Enum(..)) || item.has_fields())
+        .map(|item| Spanned::new(lower_item(&item.node, &enums), item.span))
+        .collect();
+
+    Module(module.0.clone(), items, module.2.clone())
+}
+
+fn lower_item(item: &Item, enums: &Enums) -> Item {
+    match item {
+        Item::ExternFunctionDefinition(name, args, returns, abi, attributes, visibility) => {
+            Item::ExternFunctionDefinition(
+                *name,
+                lower_arguments(args, enums),
+                lower_spanned_type(returns, enums),
+                *abi,
+                attributes.clone(),
+                *visibility,
+            )
+        }
+        Item::FunctionDeclaration(name, generics, args, returns, body, attributes, visibility) => {
+            Item::FunctionDeclaration(
+                *name,
+                generics.clone(),
+                lower_arguments(args, enums),
+                lower_spanned_type(returns, enums),
+                lower_block(body, enums),
+                attributes.clone(),
+                *visibility,
+            )
+        }
+        Item::Const(name, typ, elements, visibility) => Item::Const(
+            *name,
+            lower_spanned_type(typ, enums),
+            elements.clone(),
+            *visibility,
+        ),
+        Item::Enum(name, variants, visibility) => Item::Enum(
+            *name,
+            variants
+                .iter()
+                .map(|(variant, fields)| {
+                    let fields = fields
+                        .iter()
+                        .map(|field| lower_spanned_type(field, enums))
+                        .collect();
+
+                    (variant.clone(), fields)
+                })
+                .collect(),
+            *visibility,
+        ),
+    }
+}
+
+fn lower_arguments(
+    args: &[(Symbol, Spanned<Type>)],
+    enums: &Enums,
+) -> Vec<(Symbol, Spanned<Type>)> {
+    args.iter()
+        .map(|(name, typ)| (*name, lower_spanned_type(typ, enums)))
+        .collect()
+}
+
+fn lower_type(typ: &Type, enums: &Enums) -> Type {
+    match typ {
+        Type::Atomic(name) if enums.get(name).is_some_and(|variants| is_plain(variants)) => {
+            Type::Atomic("u32".into())
+        }
+        Type::Pointer(pointee) => Type::Pointer(Box::new(lower_type(pointee, enums))),
+        Type::Array(element, length) => Type::Array(Box::new(lower_type(element, enums)), *length),
+        Type::Function(arguments, returns) => Type::Function(
+            arguments
+                .iter()
+                .map(|argument| lower_type(argument, enums))
+                .collect(),
+            Box::new(lower_type(returns, enums)),
+        ),
+        Type::Atomic(_) | Type::Never => typ.clone(),
+    }
+}
+
+fn lower_spanned_type(typ: &Spanned<Type>, enums: &Enums) -> Spanned<Type> {
+    Spanned::new(lower_type(&typ.node, enums), typ.span)
+}
+
+fn lower_block(body: &[Spanned<Statement>], enums: &Enums) -> Vec<Spanned<Statement>> {
+    body.iter()
+        .map(|statement| Spanned::new(lower_statement(&statement.node, enums), statement.span))
+        .collect()
+}
+
+fn lower_statement(statement: &Statement, enums: &Enums) -> Statement {
+    match statement {
+        Statement::FunctionCall(name, args) => {
+            Statement::FunctionCall(*name, lower_expressions(args, enums))
+        }
+        Statement::Return(expression) => Statement::Return(lower_expression(expression, enums)),
+        Statement::Let(name, typ, value) => Statement::Let(
+            *name,
+            lower_spanned_type(typ, enums),
+            lower_expression(value, enums),
+        ),
+        Statement::Block(body) => Statement::Block(lower_block(body, enums)),
+        Statement::For(name, start, end, body) => Statement::For(
+            *name,
+            lower_expression(start, enums),
+            lower_expression(end, enums),
+            lower_block(body, enums),
+        ),
+        Statement::Match(value, arms) => {
+            let value = lower_expression(value, enums);
+            let matched = arms.iter().find_map(|(pattern, _)| match pattern.node {
+                Pattern::Variant(name, ..) if enums.get(&name).is_some_and(|v| !is_plain(v)) => {
+                    Some(name)
+                }
+                _ => None,
+            });
+
+            let Some(name) = matched else {
+                return Statement::Match(value, lower_arms(arms, enums));
+            };
+
+            // The value is kept in a variable, so that it is evaluated once however many fields
+            // the arms read from it.
+            let span = value.span;
+            let tag = Expression::Call(
+                tag_name(&name),
+                vec![Spanned::new(Expression::Variable(MATCHED.into()), span)],
+            );
+
+            Statement::Block(vec![
+                Spanned::new(
+                    Statement::Let(
+                        MATCHED.into(),
+                        Spanned::new(Type::Atomic(name), span),
+                        value,
+                    ),
+                    span,
+                ),
+                Spanned::new(
+                    Statement::Match(Spanned::new(tag, span), lower_arms(arms, enums)),
+                    span,
+                ),
+            ])
+        }
+        Statement::Free(pointer) => Statement::Free(lower_expression(pointer, enums)),
+        Statement::Assert(condition, message) => {
+            Statement::Assert(lower_expression(condition, enums), message.clone())
+        }
+        Statement::Unreachable | Statement::Asm(_) | Statement::Error(_) => statement.clone(),
+    }
+}
+
+/// Lowers the arms of a `match`, turning variant patterns into the numbers of their
+/// discriminants, and the fields they bind into variables read from [`MATCHED`]. A `match` that
+/// covers every variant of an enum gets its last arm turned into a wildcard, so that backends see
+/// that it is exhaustive without knowing about enums.
+fn lower_arms(arms: &[Arm], enums: &Enums) -> Vec<Arm> {
+    let exhaustive = arms.iter().any(|(pattern, _)| match pattern.node {
+        Pattern::Variant(name, ..) => enums
+            .get(&name)
+            .is_some_and(|variants| missing_variants(arms, name, variants).is_empty()),
+        _ => false,
+    });
+
+    arms.iter()
+        .enumerate()
+        .map(|(index, (pattern, body))| {
+            let lowered = match &pattern.node {
+                _ if exhaustive && index == arms.len() - 1 => Pattern::Wildcard,
+                Pattern::Variant(name, variant, _) => match discriminant(enums, *name, *variant) {
+                    Some(value) => Pattern::Number(value),
+                    None => pattern.node.clone(),
+                },
+                _ => pattern.node.clone(),
+            };
+
+            let mut lowered_body = match &pattern.node {
+                Pattern::Variant(name, variant, bindings) => {
+                    lower_bindings(*name, *variant, bindings, pattern.span, enums)
+                }
+                _ => vec![],
+            };
+            lowered_body.extend(lower_block(body, enums));
+
+            (Spanned::new(lowered, pattern.span), lowered_body)
+        })
+        .collect()
+}
+
+fn lower_expressions(
+    expressions: &[Spanned<Expression>],
+    enums: &Enums,
+) -> Vec<Spanned<Expression>> {
+    expressions
+        .iter()
+        .map(|expression| lower_expression(expression, enums))
+        .collect()
+}
+
+fn lower_expression(expression: &Spanned<Expression>, enums: &Enums) -> Spanned<Expression> {
+    let lowered = match &expression.node {
+        Expression::Call(name, args) => Expression::Call(*name, lower_expressions(args, enums)),
+        Expression::Index(name, index) => {
+            Expression::Index(*name, Box::new(lower_expression(index, enums)))
+        }
+        Expression::Unary(op, operand) => {
+            Expression::Unary(*op, Box::new(lower_expression(operand, enums)))
+        }
+        Expression::Binary(op, left, right) => Expression::Binary(
+            *op,
+            Box::new(lower_expression(left, enums)),
+            Box::new(lower_expression(right, enums)),
+        ),
+        Expression::If(condition, then, otherwise) => Expression::If(
+            Box::new(lower_expression(condition, enums)),
+            Box::new(lower_expression(then, enums)),
+            Box::new(lower_expression(otherwise, enums)),
+        ),
+        Expression::Block(body, value) => Expression::Block(
+            lower_block(body, enums),
+            Box::new(lower_expression(value, enums)),
+        ),
+        Expression::Variant(name, variant, fields) => match enums.get(name) {
+            Some(variants) if !is_plain(variants) => Expression::Call(
+                constructor_name(name, variant),
+                lower_expressions(fields, enums),
+            ),
+            _ => match discriminant(enums, *name, *variant) {
+                Some(value) => Expression::NumericLiteral(value),
+                None => expression.node.clone(),
+            },
+        },
+        Expression::New(typ, value) => Expression::New(
+            lower_spanned_type(typ, enums),
+            Box::new(lower_expression(value, enums)),
+        ),
+        Expression::Alloc(typ, count) => Expression::Alloc(
+            lower_spanned_type(typ, enums),
+            Box::new(lower_expression(count, enums)),
+        ),
+        Expression::Cast(value, typ) => Expression::Cast(
+            Box::new(lower_expression(value, enums)),
+            lower_spanned_type(typ, enums),
+        ),
+        Expression::StringLiteral(_)
+        | Expression::NumericLiteral(_)
+        | Expression::CharLiteral(_)
+        | Expression::Variable(_) => expression.node.clone(),
+    };
+
+    Spanned::new(lowered, expression.span)
+}
+
+/// Declares a variable for every field a variant pattern binds, reading it from [`MATCHED`].
+fn lower_bindings(
+    name: Symbol,
+    variant: Symbol,
+    bindings: &[Symbol],
+    span: Span,
+    enums: &Enums,
+) -> Vec<Spanned<Statement>> {
+    let fields = enums
+        .get(&name)
+        .and_then(|variants| variants.iter().find(|(other, _)| other.node == variant))
+        .map(|(_, fields)| &fields[..])
+        .unwrap_or_default();
+
+    bindings
+        .iter()
+        .zip(fields)
+        .enumerate()
+        .filter(|(_, (binding, _))| &binding[..] != "_")
+        .map(|(index, (binding, field))| {
+            let matched = Spanned::new(Expression::Variable(MATCHED.into()), span);
+            let value = Expression::Call(field_name(&name, &variant, index), vec![matched]);
+
+            let statement = Statement::Let(
+                *binding,
+                lower_spanned_type(field, enums),
+                Spanned::new(value, span),
+            );
+
+            Spanned::new(statement, span)
+        })
+        .collect()
+}
+
+/// Returns the discriminant of a variant, which is its position in the enum.
+fn discriminant(enums: &Enums, name: Symbol, variant: Symbol) -> Option<u64> {
+    enums
+        .get(&name)?
+        .iter()
+        .position(|(other, _)| other.node == variant)
+        .map(|index| index as u64)
+}
+
+#[test]
+fn enums_lower_to_integers() {
+    let parse = |source: &str| {
+        crate::spec::module("main".to_string())
+            .parse(source)
+            .unwrap()
+            .0
+    };
+
+    let lowered = lower_enums(
+        &parse("fn main() -> u32 { let c: Color = Color::Blue; return c == Color::Red; };"),
+        &[parse("pub enum Color { Red, Green, Blue };")],
+    );
+
+    assert_eq!(
+        lowered.to_string(),
+        "fn main() -> u32 {\n    let c: u32 = 2;\n    return c == 0;\n};\n"
+    );
+}
+
+#[test]
+fn matches_over_enums_with_fields_lower_to_calls() {
+    let (module, _) = crate::spec::module("main".to_string())
+        .parse(
+            "enum Shape { Circle(u32), Rect(u32, u32) };
+            fn main() -> u32 {
+                match Shape::Rect(1, 2) { Shape::Circle(r) => { return r; } Shape::Rect(_, h) => { return h; } }
+            };",
+        )
+        .unwrap();
+
+    let lowered = lower_enums(&module, &[]);
+
+    assert_eq!(
+        lowered.1[1].to_string(),
+        "fn main() -> u32 {
+    {
+        let match: Shape = Shape::Rect(1, 2);
+        match Shape::match(match) {
+            0 => {
+                let r: u32 = Shape::Circle::0(match);
+                return r;
+            }
+            _ => {
+                let h: u32 = Shape::Rect::1(match);
+                return h;
+            }
+        }
+    }
+};"
+    );
+}
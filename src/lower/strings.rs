@@ -0,0 +1,332 @@
+use crate::{
+    parser::Spanned,
+    sema::{
+        Globals, prelude,
+        scope::Scopes,
+        ty::{Ty, TyKind},
+        type_of,
+    },
+    spec::ast::*,
+};
+
+/// Builds a `str` from a pointer to its bytes and their number, taking `(*char, u32)`.
+pub const STR_NEW: &str = "str::new";
+/// Returns the pointer to the bytes of a `str`, which are followed by a NUL.
+pub const STR_PTR: &str = "str::ptr";
+/// Returns the number of bytes of a `str`, which is what `len` is lowered to.
+pub const STR_LEN: &str = "str::len";
+/// Allocates a `str` holding the bytes of two others, which is what `concat` is lowered to.
+pub const STR_CONCAT: &str = "str::concat";
+
+/// Rewrites a module that sema has accepted so that every operation on a `str` is a call to one
+/// of the functions backends provide for them, whose names have `::` in them so that they can
+/// never clash with a function of the program:
+///
+/// - a string literal that is used as a `str` becomes a call to [`STR_NEW`] with its length,
+/// - a `str` passed to an external function that takes a `*char` is passed as [`STR_PTR`] of it,
+/// - calls to `len` and `concat` from the prelude call [`STR_LEN`] and [`STR_CONCAT`].
+///
+/// Generic functions must already have been instantiated, so that every type is known.
+pub fn lower_strings(module: &Module, imports: &[Module]) -> Module {
+    let imports: Vec<&Module> = imports.iter().collect();
+    let globals = Globals::collect_with_imports(module, &imports);
+
+    let items = module
+        .1
+        .iter()
+        .map(|item| {
+            let node = match &item.node {
+                Item::FunctionDeclaration(
+                    name,
+                    generics,
+                    args,
+                    returns,
+                    body,
+                    attributes,
+                    visibility,
+                ) => {
+                    let mut scopes = Scopes::returning(globals.tcx.lower(returns).ok());
+
+                    for (arg, typ) in args {
+                        if let Ok(ty) = globals.tcx.lower(typ) {
+                            scopes.insert(arg, ty);
+                        }
+                    }
+
+                    Item::FunctionDeclaration(
+                        *name,
+                        generics.clone(),
+                        args.clone(),
+                        returns.clone(),
+                        lower_block(body, &globals, &mut scopes),
+                        attributes.clone(),
+                        *visibility,
+                    )
+                }
+                node => node.clone(),
+            };
+
+            Spanned::new(node, item.span)
+        })
+        .collect();
+
+    Module(module.0.clone(), items, module.2.clone())
+}
+
+fn lower_block(
+    body: &[Spanned<Statement>],
+    globals: &Globals,
+    scopes: &mut Scopes,
+) -> Vec<Spanned<Statement>> {
+    scopes.push();
+
+    let body = body
+        .iter()
+        .map(|statement| {
+            let node = lower_statement(&statement.node, globals, scopes);
+            Spanned::new(node, statement.span)
+        })
+        .collect();
+
+    scopes.pop();
+
+    body
+}
+
+fn lower_statement(statement: &Statement, globals: &Globals, scopes: &mut Scopes) -> Statement {
+    match statement {
+        Statement::FunctionCall(name, args) => {
+            let (name, args) = lower_call(*name, args, globals, scopes);
+            Statement::FunctionCall(name, args)
+        }
+        Statement::Return(value) => Statement::Return(lower_expression(
+            value,
+            scopes.return_type(),
+            globals,
+            scopes,
+        )),
+        Statement::Let(name, typ, value) => {
+            let ty = globals.tcx.lower(typ).ok();
+            let value = lower_expression(value, ty, globals, scopes);
+
+            if let Some(ty) = ty {
+                scopes.insert(name, ty);
+            }
+
+            Statement::Let(*name, typ.clone(), value)
+        }
+        Statement::Block(body) => Statement::Block(lower_block(body, globals, scopes)),
+        Statement::For(name, start, end, body) => {
+            let start = lower_expression(start, None, globals, scopes);
+            let end = lower_expression(end, None, globals, scopes);
+
+            scopes.push();
+            scopes.insert(name, globals.tcx.u32());
+            let body = lower_block(body, globals, scopes);
+            scopes.pop();
+
+            Statement::For(*name, start, end, body)
+        }
+        Statement::Match(value, arms) => {
+            let value = lower_expression(value, None, globals, scopes);
+            let arms = arms
+                .iter()
+                .map(|(pattern, body)| {
+                    scopes.push();
+                    bind(&pattern.node, globals, scopes);
+                    let body = lower_block(body, globals, scopes);
+                    scopes.pop();
+
+                    (pattern.clone(), body)
+                })
+                .collect();
+
+            Statement::Match(value, arms)
+        }
+        Statement::Assert(condition, message) => Statement::Assert(
+            lower_expression(condition, None, globals, scopes),
+            message.clone(),
+        ),
+        Statement::Free(pointer) => {
+            Statement::Free(lower_expression(pointer, None, globals, scopes))
+        }
+        Statement::Unreachable | Statement::Asm(_) | Statement::Error(_) => statement.clone(),
+    }
+}
+
+/// Binds the fields a pattern binds in the innermost scope, like sema does.
+fn bind(pattern: &Pattern, globals: &Globals, scopes: &mut Scopes) {
+    let Pattern::Variant(name, variant, bindings) = pattern else {
+        return;
+    };
+
+    let fields = globals
+        .variants(name)
+        .and_then(|variants| variants.iter().find(|(other, _)| other.node == *variant))
+        .map(|(_, fields)| &fields[..])
+        .unwrap_or_default();
+
+    for (binding, field) in bindings.iter().zip(fields) {
+        if let Ok(ty) = globals.tcx.lower(field) {
+            scopes.insert(binding, ty);
+        }
+    }
+}
+
+/// Lowers a call, returning the name of the function it calls along with its lowered arguments.
+fn lower_call(
+    name: Symbol,
+    args: &[Spanned<Expression>],
+    globals: &Globals,
+    scopes: &Scopes,
+) -> (Symbol, Vec<Spanned<Expression>>) {
+    let tcx = &globals.tcx;
+
+    // A variable holding a function pointer shadows any function of the same name, like in sema.
+    if let Some(ty) = scopes.get(&name)
+        && let TyKind::Function(parameters, _) = tcx.kind(ty)
+    {
+        let args = args
+            .iter()
+            .enumerate()
+            .map(|(index, arg)| {
+                lower_expression(arg, parameters.get(index).copied(), globals, scopes)
+            })
+            .collect();
+
+        return (name, args);
+    }
+
+    let Some(function) = globals.functions.get(&name[..]).copied() else {
+        return (name, args.to_vec());
+    };
+
+    let is_extern = matches!(function.node, Item::ExternFunctionDefinition(..));
+    let c_string = tcx.pointer(tcx.char());
+
+    let args = args
+        .iter()
+        .enumerate()
+        .map(|(index, arg)| {
+            let expected = function
+                .arguments()
+                .get(index)
+                .and_then(|(_, typ)| tcx.lower(typ).ok());
+            let lowered = lower_expression(arg, expected, globals, scopes);
+
+            match expected {
+                Some(expected)
+                    if is_extern
+                        && expected == c_string
+                        && type_of(arg, globals, scopes, &mut vec![]) == Some(tcx.str()) =>
+                {
+                    let span = lowered.span;
+                    Spanned::new(Expression::Call(STR_PTR.into(), vec![lowered]), span)
+                }
+                _ => lowered,
+            }
+        })
+        .collect();
+
+    let name = match &name[..] {
+        "len" if is_prelude(function) => STR_LEN.into(),
+        "concat" if is_prelude(function) => STR_CONCAT.into(),
+        _ => name,
+    };
+
+    (name, args)
+}
+
+/// Returns whether an item is one of those declared by the prelude, rather than one of the
+/// program's own that shadows it.
+fn is_prelude(item: &Spanned<Item>) -> bool {
+    prelude().iter().any(|builtin| std::ptr::eq(builtin, item))
+}
+
+/// Lowers an expression, given the type its context expects.
+fn lower_expression(
+    expression: &Spanned<Expression>,
+    expected: Option<Ty>,
+    globals: &Globals,
+    scopes: &Scopes,
+) -> Spanned<Expression> {
+    let lower = |expression| lower_expression(expression, None, globals, scopes);
+
+    let lowered = match &expression.node {
+        Expression::StringLiteral(value) if expected == Some(globals.tcx.str()) => {
+            let length = Expression::NumericLiteral(value.len() as u64);
+
+            Expression::Call(
+                STR_NEW.into(),
+                vec![expression.clone(), Spanned::new(length, expression.span)],
+            )
+        }
+        Expression::Call(name, args) => {
+            let (name, args) = lower_call(*name, args, globals, scopes);
+            Expression::Call(name, args)
+        }
+        Expression::Index(name, index) => Expression::Index(*name, Box::new(lower(index))),
+        Expression::Unary(op, operand) => Expression::Unary(*op, Box::new(lower(operand))),
+        Expression::Binary(op, left, right) => {
+            Expression::Binary(*op, Box::new(lower(left)), Box::new(lower(right)))
+        }
+        // Both branches give the value of the whole expression, so they expect the same type.
+        Expression::If(condition, then, otherwise) => Expression::If(
+            Box::new(lower(condition)),
+            Box::new(lower_expression(then, expected, globals, scopes)),
+            Box::new(lower_expression(otherwise, expected, globals, scopes)),
+        ),
+        Expression::Block(body, value) => {
+            let mut scopes = scopes.clone();
+            scopes.push();
+
+            let body = body
+                .iter()
+                .map(|statement| {
+                    let node = lower_statement(&statement.node, globals, &mut scopes);
+                    Spanned::new(node, statement.span)
+                })
+                .collect();
+            let value = lower_expression(value, expected, globals, &scopes);
+
+            Expression::Block(body, Box::new(value))
+        }
+        Expression::Variant(name, variant, fields) => {
+            Expression::Variant(*name, *variant, fields.iter().map(lower).collect())
+        }
+        Expression::New(typ, value) => {
+            let expected = globals.tcx.lower(typ).ok();
+            let value = lower_expression(value, expected, globals, scopes);
+
+            Expression::New(typ.clone(), Box::new(value))
+        }
+        Expression::Alloc(typ, count) => Expression::Alloc(typ.clone(), Box::new(lower(count))),
+        Expression::Cast(value, typ) => Expression::Cast(Box::new(lower(value)), typ.clone()),
+        Expression::StringLiteral(_)
+        | Expression::NumericLiteral(_)
+        | Expression::CharLiteral(_)
+        | Expression::Variable(_) => expression.node.clone(),
+    };
+
+    Spanned::new(lowered, expression.span)
+}
+
+#[test]
+fn strings_lower_to_calls() {
+    let (module, _) = crate::spec::module("main".to_string())
+        .parse(
+            "ext fn puts(s: *char) -> u32;
+            fn main() -> u32 { let s: str = concat(\"ab\", \"c\"); puts(s); puts(\"d\"); return len(s); };",
+        )
+        .unwrap();
+
+    assert_eq!(
+        lower_strings(&module, &[]).1[1].to_string(),
+        "fn main() -> u32 {
+    let s: str = str::concat(str::new(\"ab\", 2), str::new(\"c\", 1));
+    puts(str::ptr(s));
+    puts(\"d\");
+    return str::len(s);
+};"
+    );
+}
@@ -0,0 +1,578 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    diagnostic::Diagnostic,
+    lint::{Levels, lint},
+    load::{Source, imports_of, load_sources},
+    parser::{Span, Spanned},
+    sema::{Globals, check_module_with_imports, scope::Scopes, ty::Ty, type_of},
+    spec::{
+        ast::{Expression, Item, Module, Pattern, Statement, Symbol, Type, Visibility},
+        recovering_module,
+    },
+};
+
+/// A source file that is open in an editor, parsed and checked along with the modules it imports.
+/// Imported modules are read from disk, as they were last saved.
+#[derive(Debug)]
+pub struct Document {
+    pub path: PathBuf,
+    pub text: String,
+    module: Module,
+    sources: Vec<Source>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// What a position in a [`Document`] refers to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Target {
+    /// A parameter, a `let`, the variable of a `for` or a field bound by a pattern, either where
+    /// it is declared or where it is used. Its span is where its name is declared.
+    Local {
+        name: Symbol,
+        span: Span,
+        ty: Option<String>,
+    },
+    /// An item, by the name it is declared under, and how it is declared.
+    Item { name: Symbol, signature: String },
+    /// Any other expression, with its type when it has one.
+    Expression { ty: Option<String> },
+}
+
+impl Document {
+    /// Parses and checks a source. Problems are kept as diagnostics, like the compiler reports
+    /// them, except that a missing `main` is not one, as any file may be a module that another
+    /// imports. Lints are only run on a source without errors.
+    pub fn new(path: PathBuf, text: String) -> Document {
+        let mut diagnostics = vec![];
+
+        let module = match recovering_module("main".to_string()).parse(&text) {
+            Ok(((module, errors), _)) => {
+                diagnostics.extend(
+                    errors
+                        .into_iter()
+                        .map(|error| Diagnostic::from_parse_error(error, &text)),
+                );
+
+                module
+            }
+            Err(error) => {
+                diagnostics.push(Diagnostic::from_parse_error(error, &text));
+
+                Module("main".to_string(), vec![], vec![])
+            }
+        };
+
+        let sources = load_sources(&path, &module, &[]).unwrap_or_else(|error| {
+            diagnostics.push(Diagnostic::new(error));
+            vec![]
+        });
+        let imports = imports_of(&path, &module, &sources);
+
+        diagnostics.extend(check_module_with_imports(&module, &imports));
+
+        if !diagnostics.iter().any(Diagnostic::is_error) {
+            diagnostics.extend(lint(&module, &imports, &Levels::default()));
+        }
+
+        Document {
+            path,
+            text,
+            module,
+            sources,
+            diagnostics,
+        }
+    }
+
+    /// Returns every problem found in the document.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Returns what the name or expression at a byte offset refers to, resolving names the way
+    /// the semantic pass does.
+    pub fn target(&self, offset: usize) -> Option<Target> {
+        let imports = imports_of(&self.path, &self.module, &self.sources);
+        let globals = Globals::collect_with_imports(&self.module, &imports);
+        let mut finder = Finder {
+            source: &self.text,
+            offset,
+            globals: &globals,
+            scopes: Scopes::new(),
+            locals: vec![vec![]],
+        };
+
+        self.module.1.iter().find_map(|item| finder.item(item))
+    }
+
+    /// Returns where the name at a byte offset is declared: the path and text of the file, and
+    /// the span of the name in it. Items of imported modules are found in their own files.
+    pub fn definition(&self, offset: usize) -> Option<(&Path, &str, Span)> {
+        match self.target(offset)? {
+            Target::Local { span, .. } => Some((&self.path, &self.text, span)),
+            Target::Item { name, .. } => {
+                let local = self
+                    .module
+                    .1
+                    .iter()
+                    .find(|item| item.name() == &name[..])
+                    .map(|item| (self.path.as_path(), self.text.as_str(), item));
+                let (path, text, item) = local.or_else(|| {
+                    self.sources.iter().find_map(|source| {
+                        source
+                            .module
+                            .1
+                            .iter()
+                            .find(|item| {
+                                item.name() == &name[..] && item.visibility() == Visibility::Public
+                            })
+                            .map(|item| (source.path.as_path(), source.text.as_str(), item))
+                    })
+                })?;
+
+                Some((path, text, occurrences(text, item.span, &name).next()?))
+            }
+            Target::Expression { .. } => None,
+        }
+    }
+
+    /// Returns what to show when hovering over a byte offset: the type of a local binding or an
+    /// expression, or how an item is declared.
+    pub fn hover(&self, offset: usize) -> Option<String> {
+        match self.target(offset)? {
+            Target::Local { name, ty, .. } => Some(match ty {
+                Some(ty) => format!("{name}: {ty}"),
+                None => name.to_string(),
+            }),
+            Target::Item { signature, .. } => Some(signature),
+            Target::Expression { ty } => ty,
+        }
+    }
+}
+
+/// Returns how an item is declared, without the body of a function or the elements of a constant.
+fn signature(item: &Item) -> String {
+    match item {
+        Item::FunctionDeclaration(name, generics, args, returns, ..) => {
+            let generics = match generics.is_empty() {
+                true => String::new(),
+                false => format!(
+                    "<{}>",
+                    generics
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            };
+            let args: Vec<_> = args
+                .iter()
+                .map(|(name, typ)| format!("{name}: {typ}"))
+                .collect();
+
+            format!("fn {name}{generics}({}) -> {returns}", args.join(", "))
+        }
+        Item::Const(name, typ, ..) => format!("const {name}: {typ}"),
+        Item::ExternFunctionDefinition(..) | Item::Enum(..) => item.to_string(),
+    }
+}
+
+/// Returns the spans of every occurrence of a name as a whole word in part of a source. Names that
+/// the AST keeps without a span of their own, like that of a `let`, are found where they are
+/// written this way.
+fn occurrences<'s>(
+    source: &'s str,
+    within: Span,
+    name: &'s str,
+) -> impl Iterator<Item = Span> + 's {
+    let text = &source[within.start.min(source.len())..within.end.min(source.len())];
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+
+    text.match_indices(name)
+        .filter(move |(index, _)| {
+            !text[..*index].ends_with(is_word) && !text[index + name.len()..].starts_with(is_word)
+        })
+        .map(move |(index, _)| Span::new(within.start + index, within.start + index + name.len()))
+}
+
+/// Returns whether a span covers a byte offset. The offset right after its end is covered too,
+/// which is where the cursor is after typing a name.
+fn covers(span: Span, offset: usize) -> bool {
+    span.start <= offset && offset <= span.end
+}
+
+/// Walks the items of a module down to what a byte offset is in, keeping track of the local
+/// bindings in scope on the way like the semantic pass does, so that names resolve to what they
+/// do there and expressions get the types it gives them.
+struct Finder<'s, 'g, 'a> {
+    source: &'s str,
+    offset: usize,
+    globals: &'g Globals<'a>,
+    scopes: Scopes,
+    /// The bindings of every scope, with the spans of their names, alongside `scopes`.
+    locals: Vec<Vec<(Symbol, Span)>>,
+}
+
+impl Finder<'_, '_, '_> {
+    /// Returns whether a name written at the start of a span covers the offset.
+    fn starts(&self, span: Span, name: &str) -> bool {
+        covers(Span::new(span.start, span.start + name.len()), self.offset)
+    }
+
+    fn push(&mut self) {
+        self.scopes.push();
+        self.locals.push(vec![]);
+    }
+
+    fn pop(&mut self) {
+        self.scopes.pop();
+        self.locals.pop();
+    }
+
+    /// Binds a name declared at a span in the innermost scope, returning the binding when the
+    /// offset is on its name.
+    fn bind(&mut self, name: Symbol, span: Option<Span>, ty: Option<Ty>) -> Option<Target> {
+        let span = span?;
+
+        if let Some(ty) = ty {
+            self.scopes.insert(name, ty);
+        }
+
+        if let Some(scope) = self.locals.last_mut() {
+            scope.push((name, span));
+        }
+
+        covers(span, self.offset)
+            .then(|| self.resolve(name))
+            .flatten()
+    }
+
+    /// Resolves a name to the innermost binding of it, or to an item.
+    fn resolve(&self, name: Symbol) -> Option<Target> {
+        let local = self
+            .locals
+            .iter()
+            .rev()
+            .find_map(|scope| scope.iter().rev().find(|(local, _)| *local == name));
+
+        match local {
+            Some((_, span)) => Some(Target::Local {
+                name,
+                span: *span,
+                ty: self
+                    .scopes
+                    .get(&name)
+                    .map(|ty| self.globals.tcx.display(ty).to_string()),
+            }),
+            None => self.globals.item(&name).map(|item| Target::Item {
+                name,
+                signature: signature(item),
+            }),
+        }
+    }
+
+    /// Finds the item a name in a type written at the offset refers to, such as an enum.
+    fn typ(&self, typ: &Spanned<Type>) -> Option<Target> {
+        fn names(typ: &Type, found: &mut Vec<Symbol>) {
+            match typ {
+                Type::Atomic(name) => found.push(*name),
+                Type::Pointer(inner) | Type::Array(inner, _) => names(inner, found),
+                Type::Function(arguments, returns) => {
+                    arguments.iter().for_each(|argument| names(argument, found));
+                    names(returns, found);
+                }
+                Type::Never => {}
+            }
+        }
+
+        if !covers(typ.span, self.offset) {
+            return None;
+        }
+
+        let mut found = vec![];
+        names(&typ.node, &mut found);
+
+        found.into_iter().find_map(|name| {
+            occurrences(self.source, typ.span, &name)
+                .any(|span| covers(span, self.offset))
+                .then(|| self.resolve(name))
+                .flatten()
+        })
+    }
+
+    fn item(&mut self, item: &Spanned<Item>) -> Option<Target> {
+        if !covers(item.span, self.offset) {
+            return None;
+        }
+
+        if occurrences(self.source, item.span, item.name())
+            .next()
+            .is_some_and(|span| covers(span, self.offset))
+        {
+            return self.resolve(Symbol::intern(item.name()));
+        }
+
+        // Type parameters lower like any other type inside of the function declaring them.
+        self.globals.tcx.set_generics(item.generics());
+        let found = self.item_body(item);
+        self.globals.tcx.set_generics(&[]);
+
+        found
+    }
+
+    fn item_body(&mut self, item: &Spanned<Item>) -> Option<Target> {
+        match &item.node {
+            Item::Const(_, typ, elements, _) => self
+                .typ(typ)
+                .or_else(|| elements.iter().find_map(|element| self.expression(element))),
+            Item::Enum(_, variants, _) => variants
+                .iter()
+                .flat_map(|(_, fields)| fields)
+                .find_map(|field| self.typ(field)),
+            Item::ExternFunctionDefinition(_, args, returns, ..)
+            | Item::FunctionDeclaration(_, _, args, returns, ..) => {
+                self.scopes = Scopes::returning(self.globals.tcx.lower(&returns.node).ok());
+                self.locals = vec![vec![]];
+
+                for (name, typ) in args {
+                    // A parameter is written right before its type.
+                    let span = occurrences(
+                        self.source,
+                        Span::new(item.span.start, typ.span.start),
+                        name,
+                    )
+                    .last();
+                    let ty = self.globals.tcx.lower(&typ.node).ok();
+
+                    if let Some(found) = self.typ(typ).or_else(|| self.bind(*name, span, ty)) {
+                        return Some(found);
+                    }
+                }
+
+                match &item.node {
+                    Item::FunctionDeclaration(_, _, _, _, body, ..) => {
+                        self.typ(returns).or_else(|| self.statements(body))
+                    }
+                    _ => self.typ(returns),
+                }
+            }
+        }
+    }
+
+    /// Finds the offset in a list of statements, binding what each of them declares until it
+    /// gets to the one the offset is in.
+    fn statements(&mut self, statements: &[Spanned<Statement>]) -> Option<Target> {
+        let offset = self.offset;
+
+        statements
+            .iter()
+            .take_while(|statement| statement.span.start <= offset)
+            .find_map(|statement| self.statement(statement))
+    }
+
+    fn block(&mut self, statements: &[Spanned<Statement>]) -> Option<Target> {
+        self.push();
+        let found = self.statements(statements);
+        self.pop();
+
+        found
+    }
+
+    fn statement(&mut self, statement: &Spanned<Statement>) -> Option<Target> {
+        let covered = covers(statement.span, self.offset);
+
+        match &statement.node {
+            Statement::Let(name, typ, value) => {
+                let found = covered
+                    .then(|| self.typ(typ).or_else(|| self.expression(value)))
+                    .flatten();
+                let span = occurrences(self.source, statement.span, name).next();
+                let ty = self.globals.lower_value_type(typ, &mut vec![]);
+
+                found.or_else(|| self.bind(*name, span, ty))
+            }
+            _ if !covered => None,
+            Statement::FunctionCall(name, _) if self.starts(statement.span, name) => {
+                self.resolve(*name)
+            }
+            Statement::FunctionCall(_, args) => args.iter().find_map(|arg| self.expression(arg)),
+            Statement::Return(value) | Statement::Free(value) | Statement::Assert(value, _) => {
+                self.expression(value)
+            }
+            Statement::Block(body) => self.block(body),
+            Statement::For(name, start, end, body) => self
+                .expression(start)
+                .or_else(|| self.expression(end))
+                .or_else(|| {
+                    let span = occurrences(self.source, statement.span, name).next();
+
+                    self.push();
+                    let found = self
+                        .bind(*name, span, Some(self.globals.tcx.u32()))
+                        .or_else(|| self.block(body));
+                    self.pop();
+
+                    found
+                }),
+            Statement::Match(value, arms) => self.expression(value).or_else(|| {
+                arms.iter().find_map(|(pattern, body)| {
+                    self.push();
+                    let found = self.pattern(pattern).or_else(|| self.block(body));
+                    self.pop();
+
+                    found
+                })
+            }),
+            Statement::Unreachable | Statement::Asm(_) | Statement::Error(_) => None,
+        }
+    }
+
+    /// Binds the fields a pattern binds, finding the offset in the pattern.
+    fn pattern(&mut self, pattern: &Spanned<Pattern>) -> Option<Target> {
+        let Pattern::Variant(name, variant, bindings) = &pattern.node else {
+            return None;
+        };
+
+        if covers(pattern.span, self.offset) && self.starts(pattern.span, name) {
+            return self.resolve(*name);
+        }
+
+        let fields = self
+            .globals
+            .variants(name)
+            .and_then(|variants| variants.iter().find(|(other, _)| other.node == *variant))
+            .map_or(&[][..], |(_, fields)| &fields[..]);
+
+        bindings
+            .iter()
+            .enumerate()
+            .filter(|(_, binding)| &binding[..] != "_")
+            .find_map(|(index, binding)| {
+                let span = occurrences(self.source, pattern.span, binding).next();
+                let ty = fields
+                    .get(index)
+                    .and_then(|field| self.globals.tcx.lower(&field.node).ok());
+
+                self.bind(*binding, span, ty)
+            })
+    }
+
+    fn expression(&mut self, expression: &Spanned<Expression>) -> Option<Target> {
+        if !covers(expression.span, self.offset) {
+            return None;
+        }
+
+        let found = match &expression.node {
+            Expression::Variable(name) => self.resolve(*name),
+            Expression::Call(name, _)
+            | Expression::Index(name, _)
+            | Expression::Variant(name, _, _)
+                if self.starts(expression.span, name) =>
+            {
+                self.resolve(*name)
+            }
+            Expression::Call(_, args) | Expression::Variant(_, _, args) => {
+                args.iter().find_map(|arg| self.expression(arg))
+            }
+            Expression::Index(_, index) => self.expression(index),
+            Expression::Unary(_, operand) => self.expression(operand),
+            Expression::Binary(_, left, right) => {
+                self.expression(left).or_else(|| self.expression(right))
+            }
+            Expression::New(typ, value) | Expression::Alloc(typ, value) => {
+                self.typ(typ).or_else(|| self.expression(value))
+            }
+            Expression::If(condition, then, otherwise) => self
+                .expression(condition)
+                .or_else(|| self.expression(then))
+                .or_else(|| self.expression(otherwise)),
+            Expression::Block(statements, value) => {
+                self.push();
+                let found = self
+                    .statements(statements)
+                    .or_else(|| self.expression(value));
+                self.pop();
+
+                found
+            }
+            Expression::Cast(value, typ) => self.expression(value).or_else(|| self.typ(typ)),
+            Expression::StringLiteral(_)
+            | Expression::NumericLiteral(_)
+            | Expression::CharLiteral(_) => None,
+        };
+
+        found.or_else(|| {
+            let ty = type_of(expression, self.globals, &self.scopes, &mut vec![]);
+
+            Some(Target::Expression {
+                ty: ty.map(|ty| self.globals.tcx.display(ty).to_string()),
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+fn document(text: &str) -> Document {
+    Document::new(PathBuf::from("main.pal"), text.to_string())
+}
+
+#[test]
+fn problems_are_diagnostics() {
+    let messages = |text| {
+        document(text)
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| diagnostic.message.clone())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        messages("fn main() -> u32 { let x: u32 = ; return y; };"),
+        ["expected expression, found ';'", "variable `y` not found"]
+    );
+    assert_eq!(
+        messages("fn main() -> u32 { let x: u32 = 1; return 0; };"),
+        ["unused variable `x`; prefix it with an underscore if this is intended"]
+    );
+    assert!(messages("fn helper() -> u32 { return 0; };").is_empty());
+}
+
+#[test]
+fn names_resolve_to_their_declarations() {
+    let text = "fn add(a: u32, b: u32) -> u32 { let sum: u32 = add(a, b); return sum; };";
+    let document = document(text);
+    let definition = |needle: &str| {
+        document
+            .definition(text.find(needle).unwrap())
+            .map(|(_, _, span)| span)
+    };
+
+    assert_eq!(definition("add(a"), Some(Span::new(3, 6)));
+    assert_eq!(definition("a, b)"), Some(Span::new(7, 8)));
+    assert_eq!(definition("sum; }"), Some(Span::new(36, 39)));
+    assert_eq!(definition("return"), None);
+}
+
+#[test]
+fn hovers_show_resolved_types() {
+    let text = "enum Color { Red, Green };
+    fn pick<T>(x: T) -> T { return x; };
+    fn main() -> u32 {
+        let c: Color = Color::Red;
+        for i in 0..2 { let p: *u8 = new u8(i as u8); free(p); }
+        match c { Color::Red => { return pick(7); } _ => { return 0; } }
+    };";
+    let document = document(text);
+    let hover = |needle: &str| document.hover(text.find(needle).unwrap());
+
+    assert_eq!(hover("x; }"), Some("x: T".to_string()));
+    assert_eq!(hover("c: Color"), Some("c: Color".to_string()));
+    assert_eq!(hover("i as"), Some("i: u32".to_string()));
+    assert_eq!(hover("new"), Some("*u8".to_string()));
+    assert_eq!(hover("p); }"), Some("p: *u8".to_string()));
+    assert_eq!(hover("pick(7)"), Some("fn pick<T>(x: T) -> T".to_string()));
+    assert_eq!(
+        hover("Color::Red =>"),
+        Some("enum Color { Red, Green };".to_string())
+    );
+}
@@ -0,0 +1,15 @@
+use lsp_server::ProtocolError;
+use thiserror::Error;
+
+/// Represents any error that stops the language server.
+#[derive(Error, Debug)]
+pub enum LspError {
+    #[error(transparent)]
+    Protocol(#[from] ProtocolError),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("the editor disconnected")]
+    Disconnected,
+}
@@ -0,0 +1,376 @@
+pub mod document;
+pub mod error;
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::{
+    DiagnosticSeverity, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverContents,
+    HoverParams, HoverProviderCapability, Location, MarkupContent, MarkupKind, NumberOrString,
+    OneOf, Position, PublishDiagnosticsParams, Range, ServerCapabilities,
+    TextDocumentPositionParams, TextDocumentSyncCapability, TextDocumentSyncKind, Uri,
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, Notification as _,
+        PublishDiagnostics,
+    },
+    request::{GotoDefinition, HoverRequest, Request as _},
+};
+
+use crate::{
+    diagnostic::{Diagnostic, Severity},
+    lsp::{document::Document, error::LspError},
+    parser::Span,
+};
+
+/// Runs a language server that talks to an editor over stdin and stdout, until the editor shuts
+/// it down.
+pub fn run() -> Result<(), LspError> {
+    let (connection, io_threads) = Connection::stdio();
+
+    serve(&connection)?;
+
+    // The thread writing to stdout only stops once every sender is gone.
+    drop(connection);
+    io_threads.join()?;
+
+    Ok(())
+}
+
+/// Serves an editor over a connection until it shuts the server down. Documents are synced in
+/// full on every change, after which their diagnostics are published again, and hovers and
+/// definitions are answered from the last version of a document.
+pub fn serve(connection: &Connection) -> Result<(), LspError> {
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        ..ServerCapabilities::default()
+    };
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+
+    // Keyed by the text of their URIs, which clippy does not trust to hash the same every time.
+    let mut documents: HashMap<String, Document> = HashMap::new();
+
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    return Ok(());
+                }
+
+                let id = request.id.clone();
+                let response = answer(&documents, request).unwrap_or_else(|error| {
+                    Response::new_err(id, ErrorCode::InvalidParams as i32, error.to_string())
+                });
+
+                send(connection, response.into())?;
+            }
+            Message::Notification(notification) => {
+                if let Some(uri) = update(&mut documents, notification)? {
+                    publish(connection, &uri, documents.get(uri.as_str()))?;
+                }
+            }
+            Message::Response(_) => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn send(connection: &Connection, message: Message) -> Result<(), LspError> {
+    connection
+        .sender
+        .send(message)
+        .map_err(|_| LspError::Disconnected)
+}
+
+/// Answers a request about a position in a document.
+fn answer(documents: &HashMap<String, Document>, request: Request) -> serde_json::Result<Response> {
+    let response = match request.method.as_str() {
+        HoverRequest::METHOD => {
+            let params: HoverParams = serde_json::from_value(request.params)?;
+            let hover = at(documents, &params.text_document_position_params)
+                .and_then(|(document, offset)| document.hover(offset))
+                .map(|text| Hover {
+                    contents: HoverContents::Markup(MarkupContent {
+                        kind: MarkupKind::Markdown,
+                        value: format!("```pal\n{text}\n```"),
+                    }),
+                    range: None,
+                });
+
+            Response::new_ok(request.id, hover)
+        }
+        GotoDefinition::METHOD => {
+            let params: GotoDefinitionParams = serde_json::from_value(request.params)?;
+            let position = params.text_document_position_params;
+            let location = at(documents, &position).and_then(|(document, offset)| {
+                let (path, text, span) = document.definition(offset)?;
+                let uri = match path == document.path {
+                    true => position.text_document.uri.clone(),
+                    false => uri_of(path)?,
+                };
+
+                Some(GotoDefinitionResponse::Scalar(Location::new(
+                    uri,
+                    range(text, span),
+                )))
+            });
+
+            Response::new_ok(request.id, location)
+        }
+        method => Response::new_err(
+            request.id,
+            ErrorCode::MethodNotFound as i32,
+            format!("unsupported request `{method}`"),
+        ),
+    };
+
+    Ok(response)
+}
+
+/// Returns the open document a position is in, along with the byte offset of the position.
+fn at<'d>(
+    documents: &'d HashMap<String, Document>,
+    position: &TextDocumentPositionParams,
+) -> Option<(&'d Document, usize)> {
+    let document = documents.get(position.text_document.uri.as_str())?;
+
+    Some((document, offset(&document.text, position.position)))
+}
+
+/// Opens, changes or closes a document as a notification says, returning the URI of the document
+/// when its diagnostics need to be published again.
+fn update(
+    documents: &mut HashMap<String, Document>,
+    notification: Notification,
+) -> serde_json::Result<Option<Uri>> {
+    match notification.method.as_str() {
+        DidOpenTextDocument::METHOD => {
+            let params: DidOpenTextDocumentParams = serde_json::from_value(notification.params)?;
+            let uri = params.text_document.uri;
+
+            documents.insert(
+                uri.to_string(),
+                Document::new(path_of(&uri), params.text_document.text),
+            );
+
+            Ok(Some(uri))
+        }
+        DidChangeTextDocument::METHOD => {
+            let mut params: DidChangeTextDocumentParams =
+                serde_json::from_value(notification.params)?;
+            let uri = params.text_document.uri;
+
+            // Documents are synced in full, so the last change holds the whole text.
+            let Some(change) = params.content_changes.pop() else {
+                return Ok(None);
+            };
+
+            documents.insert(uri.to_string(), Document::new(path_of(&uri), change.text));
+
+            Ok(Some(uri))
+        }
+        DidCloseTextDocument::METHOD => {
+            let params: DidCloseTextDocumentParams = serde_json::from_value(notification.params)?;
+            let uri = params.text_document.uri;
+
+            documents.remove(uri.as_str());
+
+            Ok(Some(uri))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Publishes the diagnostics of a document, or clears them once it is closed.
+fn publish(
+    connection: &Connection,
+    uri: &Uri,
+    document: Option<&Document>,
+) -> Result<(), LspError> {
+    let diagnostics = document.map_or(vec![], |document| {
+        document
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| lsp_diagnostic(diagnostic, &document.text))
+            .collect()
+    });
+    let params = PublishDiagnosticsParams::new(uri.clone(), diagnostics, None);
+
+    send(
+        connection,
+        Notification::new(PublishDiagnostics::METHOD.to_string(), params).into(),
+    )
+}
+
+/// Converts a [`Diagnostic`] to the form LSP sends it in. Notes and help are added to its message
+/// on lines of their own, as they are rendered.
+fn lsp_diagnostic(diagnostic: &Diagnostic, source: &str) -> lsp_types::Diagnostic {
+    let mut message = diagnostic.message.clone();
+
+    for child in &diagnostic.children {
+        message.push_str(&format!("\n{}: {}", child.severity, child.message));
+    }
+
+    lsp_types::Diagnostic {
+        range: range(source, diagnostic.span.unwrap_or_default()),
+        severity: Some(match diagnostic.severity {
+            Severity::Error => DiagnosticSeverity::ERROR,
+            Severity::Warning => DiagnosticSeverity::WARNING,
+            Severity::Note => DiagnosticSeverity::INFORMATION,
+            Severity::Help => DiagnosticSeverity::HINT,
+        }),
+        code: diagnostic
+            .code
+            .map(|code| NumberOrString::String(code.to_string())),
+        source: Some("pal".to_string()),
+        message,
+        ..lsp_types::Diagnostic::default()
+    }
+}
+
+/// Returns the path a `file` URI names.
+fn path_of(uri: &Uri) -> PathBuf {
+    let path = uri.path();
+
+    match path.as_estr().decode().into_string() {
+        Ok(decoded) => PathBuf::from(decoded.into_owned()),
+        Err(_) => PathBuf::from(path.as_str()),
+    }
+}
+
+/// Returns the `file` URI of a path, escaping every byte that can not be written in one as is.
+fn uri_of(path: &Path) -> Option<Uri> {
+    let mut uri = "file://".to_string();
+
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'/' | b'-' | b'.' | b'_' | b'~' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{byte:02X}")),
+        }
+    }
+
+    uri.parse().ok()
+}
+
+fn range(source: &str, span: Span) -> Range {
+    Range::new(position(source, span.start), position(source, span.end))
+}
+
+/// Returns the position of a byte offset as LSP counts it: the line it is on, and the number of
+/// UTF-16 code units before it on that line.
+fn position(source: &str, offset: usize) -> Position {
+    let before = &source[..offset.min(source.len())];
+    let line = before
+        .rfind('\n')
+        .map_or(before, |newline| &before[newline + 1..]);
+
+    Position::new(
+        before.matches('\n').count() as u32,
+        line.encode_utf16().count() as u32,
+    )
+}
+
+/// Returns the byte offset of a position, see [`position`]. A position past the end of its line
+/// is at the end of it.
+fn offset(source: &str, position: Position) -> usize {
+    let start: usize = source
+        .split_inclusive('\n')
+        .take(position.line as usize)
+        .map(str::len)
+        .sum();
+    let line = source[start..].split('\n').next().unwrap_or_default();
+    let mut units = 0;
+
+    for (index, c) in line.char_indices() {
+        if units >= position.character as usize {
+            return start + index;
+        }
+
+        units += c.len_utf16();
+    }
+
+    start + line.len()
+}
+
+#[test]
+fn positions_count_utf16_code_units() {
+    let source = "a\n€x\n";
+
+    assert_eq!(position(source, 5), Position::new(1, 1));
+    assert_eq!(offset(source, Position::new(1, 1)), 5);
+    assert_eq!(offset(source, Position::new(1, 9)), 6);
+    assert_eq!(offset(source, Position::new(9, 0)), source.len());
+}
+
+#[test]
+fn editors_get_diagnostics_hovers_and_definitions() {
+    use serde_json::{Value, json};
+
+    let (server, client) = Connection::memory();
+    let server = std::thread::spawn(move || serve(&server));
+    let request = |id: i32, method: &str, params: Value| {
+        client
+            .sender
+            .send(Request::new(id.into(), method.to_string(), params).into())
+            .unwrap();
+
+        match client.receiver.recv().unwrap() {
+            Message::Response(response) => response.result.unwrap_or_default(),
+            message => panic!("expected a response, got {message:?}"),
+        }
+    };
+    let notify = |method: &str, params: Value| {
+        client
+            .sender
+            .send(Notification::new(method.to_string(), params).into())
+            .unwrap();
+    };
+    let uri = "file:///tmp/main.pal";
+    let at = |line, character| json!({ "textDocument": { "uri": uri }, "position": { "line": line, "character": character } });
+
+    request(1, "initialize", json!({ "capabilities": {} }));
+    notify("initialized", json!({}));
+    notify(
+        "textDocument/didOpen",
+        json!({ "textDocument": {
+            "uri": uri,
+            "languageId": "pal",
+            "version": 1,
+            "text": "fn main() -> u32 {\n    let x: u32 = 1;\n    return x;\n};\nfn f() -> u32 { return y; };",
+        } }),
+    );
+
+    let Message::Notification(published) = client.receiver.recv().unwrap() else {
+        panic!("expected diagnostics to be published");
+    };
+    let published: PublishDiagnosticsParams = serde_json::from_value(published.params).unwrap();
+
+    assert_eq!(published.diagnostics.len(), 1);
+    assert_eq!(published.diagnostics[0].message, "variable `y` not found");
+    assert_eq!(published.diagnostics[0].range.start, Position::new(4, 23));
+
+    assert_eq!(
+        request(2, "textDocument/hover", at(2, 11))["contents"]["value"],
+        "```pal\nx: u32\n```"
+    );
+    assert_eq!(
+        request(3, "textDocument/definition", at(2, 11)),
+        json!({ "uri": uri, "range": {
+            "start": { "line": 1, "character": 8 },
+            "end": { "line": 1, "character": 9 },
+        } })
+    );
+
+    request(4, "shutdown", Value::Null);
+    notify("exit", Value::Null);
+
+    assert!(server.join().unwrap().is_ok());
+}
@@ -1,34 +1,605 @@
-pub mod codegen;
-pub mod parser;
-pub mod spec;
+use std::io::{BufRead, Write};
 
-use clap::Parser;
-use inkwell::context::Context;
+use clap::{Parser, Subcommand, ValueEnum};
 
-use crate::{codegen::generate_codegen_module, spec::module};
+use lang::{
+    backend::{Artifact, CodegenOptions, PanicStrategy, assign_backends, backends, find_backend},
+    bindgen::{generate_bindings, read_header},
+    codegen::{error::innermost_span, header::generate_c_header},
+    diagnostic::{Diagnostic, ErrorFormat, apply_fixes},
+    emit::{Emit, OptLevel, default_temps_dir, output_paths, temps_paths},
+    lint::{Levels, Lint, lint},
+    load::{imports_of, load_sources},
+    lower::{asserts::locate_asserts, literals::lower_literals, lower_program},
+    manifest::{Kind, Manifest},
+    mir::build::build,
+    optimize::optimize,
+    parser::trace,
+    repl::{Repl, error::ReplError},
+    sema::{check_entry, check_module_with_imports, synthesize_main},
+    spec::{recovering_module, recovering_script},
+};
+
+/// Returns the names of every registered backend, for `--backend` to choose from.
+fn backend_names() -> Vec<&'static str> {
+    backends().iter().map(|backend| backend.name()).collect()
+}
+
+/// What a program does when it panics.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Panic {
+    /// Call `abort`.
+    Abort,
+    /// Execute a trap instruction.
+    Trap,
+    /// Print what went wrong and exit with `--panic-exit-code`.
+    Exit,
+}
+
+/// A lint to allow, warn about or deny, or a group of lints.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum LintArg {
+    /// Every lint.
+    Warnings,
+    /// Variables and parameters that are never used.
+    Unused,
+    /// Variables that are never used.
+    #[value(alias = "unused_variables")]
+    UnusedVariables,
+    /// Parameters that are never used.
+    #[value(alias = "unused_parameters")]
+    UnusedParameters,
+    /// Statements that follow one that control never gets past.
+    #[value(alias = "unreachable_code")]
+    UnreachableCode,
+    /// Functions that are never called from `main`.
+    #[value(alias = "dead_code")]
+    DeadCode,
+}
+
+impl LintArg {
+    /// Returns the lints the argument stands for.
+    fn lints(self) -> Vec<Lint> {
+        match self {
+            LintArg::Warnings => Lint::ALL.to_vec(),
+            LintArg::Unused => vec![Lint::UnusedVariables, Lint::UnusedParameters],
+            LintArg::UnusedVariables => vec![Lint::UnusedVariables],
+            LintArg::UnusedParameters => vec![Lint::UnusedParameters],
+            LintArg::UnreachableCode => vec![Lint::UnreachableCode],
+            LintArg::DeadCode => vec![Lint::DeadCode],
+        }
+    }
+}
+
+/// The commands that do something other than compiling a file.
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Start an interactive session that evaluates items, statements and expressions one line at a
+    /// time.
+    Repl,
+    /// Build the package that the current directory is in, as its `pal.toml` describes.
+    Build,
+    /// Build the package that the current directory is in, and run the executable it produces.
+    Run {
+        /// Arguments to pass to the executable, after a `--`.
+        #[arg(last = true)]
+        args: Vec<String>,
+    },
+    /// Print the `ext fn` declarations of the functions a C header declares, so that pal can call
+    /// them.
+    Bindgen {
+        /// The C header to read.
+        header: std::path::PathBuf,
+
+        /// Run the header through `cc -E` first, so that its macros are expanded and the types the
+        /// headers it includes define are known.
+        #[arg(long)]
+        preprocess: bool,
+    },
+    /// Start a language server that reports errors and warnings, types on hover and the
+    /// definitions of names to an editor, which talks to it over stdin and stdout.
+    Lsp,
+}
 
 /// A list of arguments that can be passed to the palc executable.
 #[derive(Parser, Debug)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
-    /// The source file that the compiler should use as an entry point to your program.
-    input: std::path::PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// The source file that the compiler should use as an entry point to your program. Given on
+    /// its own, the program is run with the interpreter; choosing an output with `--emit`,
+    /// `--backend`, `--output` or `--save-temps` compiles it instead.
+    #[arg(required = true)]
+    input: Option<std::path::PathBuf>,
+
+    /// More source files that are compiled along with the input, each into a module named after
+    /// its file. The input can call those of their functions marked `#[no_mangle]` by declaring
+    /// them with `ext fn`, without importing them.
+    modules: Vec<std::path::PathBuf>,
+
+    /// Allow bare statements at the top level of the input, which are wrapped in an implicit
+    /// `main` function.
+    #[arg(long)]
+    script: bool,
+
+    /// Print the parsed AST back out as pal source before compiling it.
+    #[arg(long)]
+    dump_ast: bool,
+
+    /// Fold constant expressions and remove code that can never run before compiling. The AST
+    /// printed by `--dump-ast` is the one that was parsed, before it is optimized.
+    #[arg(short = 'O', long)]
+    optimize: bool,
+
+    /// Print the MIR of every function of the input, once it has been checked and lowered.
+    #[arg(long)]
+    dump_mir: bool,
+
+    /// Stop warning about a lint, even when it is also denied with `-D`. Can be given more than
+    /// once.
+    #[arg(short = 'A', long = "allow", value_enum, value_name = "LINT")]
+    allow: Vec<LintArg>,
+
+    /// Warn about a lint, even when it is also allowed with `-A` or denied with `-D`. Can be given
+    /// more than once.
+    #[arg(short = 'W', long = "warn", value_enum, value_name = "LINT")]
+    warn: Vec<LintArg>,
+
+    /// Report a lint as an error, which stops the input from being compiled. `--deny warnings`
+    /// denies every lint. Can be given more than once.
+    #[arg(short = 'D', long = "deny", value_enum, value_name = "LINT")]
+    deny: Vec<LintArg>,
+
+    /// How errors and warnings are printed, to stderr.
+    #[arg(long, value_enum, default_value_t = ErrorFormat::Human)]
+    error_format: ErrorFormat,
+
+    /// Rewrite the input, and the modules it imports, with every fix that the errors found in them
+    /// suggest, such as inserting a missing `;` or correcting a misspelled name. The errors are
+    /// still reported, so compile again to check what the fixes did.
+    #[arg(long)]
+    apply_fixes: bool,
+
+    /// Print every grammar rule the parser tried on the input, with what it consumed or why it
+    /// failed, to stderr.
+    #[arg(long)]
+    trace_parser: bool,
+
+    /// The backend that runs or compiles the program. By default, every kind of output is produced
+    /// by the first backend that supports it.
+    #[arg(long, value_parser = clap::builder::PossibleValuesParser::new(backend_names()))]
+    backend: Option<String>,
+
+    /// The kinds of output to produce, as a comma separated list. Defaults to `bc` when another
+    /// option asks for the input to be compiled.
+    #[arg(long, value_enum, value_delimiter = ',')]
+    emit: Option<Vec<Emit>>,
+
+    /// The target triple to generate assembly and object files for, such as
+    /// `aarch64-unknown-linux-gnu`. Defaults to the host.
+    #[arg(long)]
+    target: Option<String>,
+
+    /// How much LLVM optimizes the machine code of assembly and object files.
+    #[arg(long, value_enum, default_value_t = OptLevel::Default)]
+    opt_level: OptLevel,
+
+    /// Where to write the output. When several kinds of output are produced, this is either a
+    /// directory or a basename that each output adds its own extension to. Defaults to the input
+    /// without its extension.
+    #[arg(short, long)]
+    output: Option<std::path::PathBuf>,
+
+    /// What the program does when it panics, such as when it reaches `unreachable()`. Also applies
+    /// when the program is run with the interpreter.
+    #[arg(long, value_enum, default_value_t = Panic::Abort)]
+    panic: Panic,
+
+    /// The code a program exits with when it panics with `--panic=exit`.
+    #[arg(long, default_value_t = 101)]
+    panic_exit_code: i32,
+
+    /// Keep every intermediate form of the program, such as the AST, the LLVM IR and the object
+    /// file, in a directory. Defaults to a directory named after the input in the system's
+    /// temporary directory.
+    #[arg(long, value_name = "DIR", num_args = 0..=1, require_equals = true)]
+    save_temps: Option<Option<std::path::PathBuf>>,
+
+    /// Arguments to pass to `main` when the program is run by the interpreter, after a `--`.
+    #[arg(last = true)]
+    args: Vec<String>,
+
+    /// Whether the input is a library, which does not need a `main`. Only packages can be.
+    #[arg(skip)]
+    library: bool,
+
+    /// Where to write a C header declaring the functions the input exports, when it is a library.
+    #[arg(skip)]
+    header: Option<std::path::PathBuf>,
+}
+
+/// Runs a parse, printing every rule it tried to stderr when `enabled` is set.
+fn traced<T>(enabled: bool, parse: impl FnOnce() -> T) -> T {
+    if enabled {
+        trace::start();
+    }
+
+    let result = parse();
+
+    for line in trace::finish() {
+        eprintln!("{line}");
+    }
+
+    result
+}
+
+/// Runs an interactive session on stdin until it is closed.
+fn repl() -> Result<(), anyhow::Error> {
+    let mut repl = Repl::new();
+    let path = std::path::Path::new("<repl>");
+
+    print!("> ");
+    std::io::stdout().flush()?;
+
+    for line in std::io::stdin().lock().lines() {
+        let line = line?;
+
+        match repl.eval(&line, std::io::stdout().lock()) {
+            Ok(Some(value)) => println!("{value}"),
+            Ok(None) => {}
+            Err(ReplError::Diagnostics(diagnostics)) => {
+                for diagnostic in &diagnostics {
+                    eprintln!("{}", diagnostic.render(path, &line));
+                }
+            }
+            Err(ReplError::Interp(lang::interp::error::InterpError::Exit(code))) => {
+                std::process::exit(code as i32)
+            }
+            Err(error) => eprintln!("{}: error: {error}", path.display()),
+        }
+
+        print!("> ");
+        std::io::stdout().flush()?;
+    }
+
+    println!();
+
+    Ok(())
+}
+
+/// Builds the package that the current directory is in, compiling its entry point to an object
+/// file with LLVM and linking that into an executable or a library. Returns the manifest of the
+/// package, along with the path of what was built.
+fn build_package(mut args: Args) -> Result<(Manifest, std::path::PathBuf), anyhow::Error> {
+    let manifest = Manifest::find(&std::env::current_dir()?)?;
+    std::fs::create_dir_all(manifest.build_dir())?;
+
+    args.input = Some(manifest.entry());
+    args.emit = Some(vec![Emit::Obj]);
+    args.output = Some(manifest.object());
+    args.backend = Some("llvm".to_string());
+    args.target = manifest.package.target.clone();
+    args.library = manifest.package.kind == Kind::Lib;
+    args.header = args.library.then(|| manifest.header());
+
+    compile(args)?;
+
+    let artifact = manifest.link()?;
+    eprintln!("built {}", artifact.display());
+
+    Ok((manifest, artifact))
 }
 
 fn main() -> Result<(), anyhow::Error> {
-    let args = Args::parse();
+    let mut args = Args::parse();
 
-    let file = std::fs::read_to_string(args.input)?;
-    let (entry_module, remaining) = module("main".to_string()).parse(&file)?;
+    match args.command.take() {
+        Some(Command::Repl) => repl(),
+        Some(Command::Build) => build_package(args).map(|_| ()),
+        Some(Command::Run { args: program_args }) => {
+            let (manifest, artifact) = build_package(args)?;
 
-    println!("Parsed: {:?}", entry_module);
-    println!("Remaining: {:?}", remaining);
+            if manifest.package.kind == Kind::Lib {
+                anyhow::bail!(
+                    "package `{}` is a library, which can not be run",
+                    manifest.package.name
+                );
+            }
 
-    let codegen_context = Context::create();
-    let codegen_module = generate_codegen_module(&codegen_context, &entry_module)?;
+            let status = std::process::Command::new(&artifact)
+                .args(program_args)
+                .status()?;
 
-    codegen_module.verify().unwrap();
+            std::process::exit(status.code().unwrap_or(1))
+        }
+        Some(Command::Bindgen { header, preprocess }) => {
+            let source = read_header(&header, preprocess)?;
+            print!("{}", generate_bindings(&source));
+            Ok(())
+        }
+        Some(Command::Lsp) => Ok(lang::lsp::run()?),
+        None if runs_by_default(&args) => {
+            args.backend = Some("interp".to_string());
+            args.emit = Some(vec![]);
 
-    codegen_module.write_bitcode_to_path("bitcode.ll");
+            compile(args)
+        }
+        None => compile(args),
+    }
+}
+
+/// Returns whether the arguments say nothing about what to make of the input, in which case it is
+/// run with the interpreter, so that a file with a `#!/usr/bin/env pal` line can be executed.
+fn runs_by_default(args: &Args) -> bool {
+    args.emit.is_none()
+        && args.backend.is_none()
+        && args.output.is_none()
+        && args.save_temps.is_none()
+}
+
+/// Rewrites a file with the fixes its diagnostics suggest, see [`apply_fixes`], saying how many
+/// were applied. A file with nothing to fix is left alone.
+fn fix_file(
+    path: &std::path::Path,
+    source: &str,
+    diagnostics: &[Diagnostic],
+) -> Result<(), anyhow::Error> {
+    let (fixed, applied) = apply_fixes(source, diagnostics);
+
+    if applied > 0 {
+        std::fs::write(path, fixed)?;
+        eprintln!("{}: applied {applied} fix(es)", path.display());
+    }
 
     Ok(())
 }
+
+/// Compiles the input file, as the arguments say.
+fn compile(args: Args) -> Result<(), anyhow::Error> {
+    let Some(input) = args.input else {
+        anyhow::bail!("no input file was given");
+    };
+
+    let file = std::fs::read_to_string(&input)?;
+    let mut diagnostics: Vec<Diagnostic> = vec![];
+
+    let mut entry_module = if args.script {
+        let ((elements, parse_errors), _) = traced(args.trace_parser, || {
+            recovering_script().parse_memoized(&file)
+        })?;
+        diagnostics.extend(
+            parse_errors
+                .into_iter()
+                .map(|error| Diagnostic::from_parse_error(error, &file)),
+        );
+
+        synthesize_main("main".to_string(), elements, &mut diagnostics)
+    } else {
+        let ((module, parse_errors), _) = traced(args.trace_parser, || {
+            recovering_module("main".to_string()).parse_memoized(&file)
+        })?;
+        diagnostics.extend(
+            parse_errors
+                .into_iter()
+                .map(|error| Diagnostic::from_parse_error(error, &file)),
+        );
+
+        module
+    };
+
+    if args.dump_ast {
+        print!("{entry_module}");
+    }
+
+    let sources = load_sources(&input, &entry_module, &args.modules)?;
+
+    diagnostics.extend(check_module_with_imports(
+        &entry_module,
+        &imports_of(&input, &entry_module, &sources),
+    ));
+    if !args.library {
+        diagnostics.extend(check_entry(&entry_module));
+    }
+
+    for diagnostic in &diagnostics {
+        eprintln!("{}", diagnostic.render_as(args.error_format, &input, &file));
+    }
+
+    if args.apply_fixes {
+        fix_file(&input, &file, &diagnostics)?;
+    }
+
+    let mut errors = diagnostics.len();
+
+    // Imported modules are checked on their own, and report problems against their own source.
+    for source in &sources {
+        let imports = imports_of(&source.path, &source.module, &sources);
+        let diagnostics: Vec<Diagnostic> = source
+            .parse_errors
+            .iter()
+            .cloned()
+            .map(|error| Diagnostic::from_parse_error(error, &source.text))
+            .chain(check_module_with_imports(&source.module, &imports))
+            .collect();
+
+        for diagnostic in &diagnostics {
+            eprintln!(
+                "{}",
+                diagnostic.render_as(args.error_format, &source.path, &source.text)
+            );
+            errors += 1;
+        }
+
+        if args.apply_fixes {
+            fix_file(&source.path, &source.text, &diagnostics)?;
+        }
+    }
+
+    if errors > 0 {
+        anyhow::bail!("aborting due to {errors} previous error(s)");
+    }
+
+    let mut levels = Levels::default();
+
+    for lint in args.deny.iter().flat_map(|arg| arg.lints()) {
+        levels.deny(lint);
+    }
+
+    for lint in args.allow.iter().flat_map(|arg| arg.lints()) {
+        levels.allow(lint);
+    }
+
+    for lint in args.warn.iter().flat_map(|arg| arg.lints()) {
+        levels.warn(lint);
+    }
+
+    let entry_imports = imports_of(&input, &entry_module, &sources);
+
+    for warning in lint(&entry_module, &entry_imports, &levels) {
+        eprintln!("{}", warning.render_as(args.error_format, &input, &file));
+        errors += warning.is_error() as usize;
+    }
+
+    for source in &sources {
+        let imports = imports_of(&source.path, &source.module, &sources);
+
+        for warning in lint(&source.module, &imports, &levels) {
+            eprintln!(
+                "{}",
+                warning.render_as(args.error_format, &source.path, &source.text)
+            );
+            errors += warning.is_error() as usize;
+        }
+    }
+
+    if errors > 0 {
+        anyhow::bail!("aborting due to {errors} denied lint(s)");
+    }
+
+    if let Some(path) = &args.header {
+        let guard = path.file_stem().unwrap_or_default().to_string_lossy();
+        std::fs::write(path, generate_c_header(&entry_module, &guard)?)?;
+    }
+
+    entry_module = locate_asserts(&entry_module, &input, &file);
+
+    let mut imports: Vec<_> = sources
+        .iter()
+        .map(|source| locate_asserts(&source.module, &source.path, &source.text))
+        .collect();
+
+    // Literals are given their types before anything is folded, so that they are folded at the
+    // width of their type rather than at that of a `u32`.
+    if args.optimize {
+        entry_module = optimize(&lower_literals(&entry_module, &imports));
+        imports = imports
+            .iter()
+            .map(|import| optimize(&lower_literals(import, &imports)))
+            .collect();
+    }
+
+    if args.dump_mir {
+        let (module, imports) = lower_program(&entry_module, &imports)?;
+        let imports: Vec<_> = imports.iter().collect();
+
+        for body in build(&module, &imports)? {
+            print!("{body}");
+        }
+    }
+
+    let emit = args.emit.unwrap_or_else(|| vec![Emit::Bitcode]);
+    let mut outputs = output_paths(&input, args.output.as_deref(), &emit);
+    let mut temps = vec![];
+
+    if let Some(dir) = args.save_temps {
+        let dir = dir.unwrap_or_else(|| default_temps_dir(&input));
+        temps = temps_paths(&input, &dir)?;
+
+        // The entry module is named after the input, like the other artifacts, and every
+        // imported module after itself.
+        let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+        std::fs::write(
+            dir.join(format!("{stem}.ast")),
+            format!("{entry_module:#?}"),
+        )?;
+
+        for import in &imports {
+            std::fs::write(
+                dir.join(format!("{}.ast", import.0)),
+                format!("{import:#?}"),
+            )?;
+        }
+
+        eprintln!("saving intermediate artifacts to {}", dir.display());
+    }
+
+    let opts = CodegenOptions {
+        outputs: vec![],
+        imports,
+        panic: match args.panic {
+            Panic::Abort => PanicStrategy::Abort,
+            Panic::Trap => PanicStrategy::Trap,
+            Panic::Exit => PanicStrategy::Exit(args.panic_exit_code),
+        },
+        target: args.target,
+        opt_level: args.opt_level,
+        args: std::iter::once(input.display().to_string())
+            .chain(args.args)
+            .collect(),
+    };
+
+    let assigned = match &args.backend {
+        Some(name) => {
+            let backend = find_backend(name)?;
+
+            // Only the intermediate artifacts the chosen backend can produce are kept.
+            outputs.extend(
+                temps
+                    .into_iter()
+                    .filter(|(emit, _)| backend.supports(*emit)),
+            );
+
+            vec![(backend, CodegenOptions { outputs, ..opts })]
+        }
+        None => {
+            outputs.extend(temps);
+
+            assign_backends(outputs, opts)?
+        }
+    };
+
+    for (backend, opts) in assigned {
+        match backend.compile(&entry_module, &opts) {
+            Ok(Artifact::Exited(code)) => std::process::exit(code),
+            Ok(Artifact::Files(_)) => {}
+            // Codegen errors point at the function and statement being generated, which is
+            // rendered like any other diagnostic.
+            Err(error) => match innermost_span(&error) {
+                Some(span) => {
+                    let diagnostic = Diagnostic::new(format!("{error:#}")).with_span(span);
+                    eprintln!("{}", diagnostic.render_as(args.error_format, &input, &file));
+
+                    anyhow::bail!("aborting due to a {} backend error", backend.name());
+                }
+                None => return Err(error),
+            },
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn files_given_on_their_own_are_run() {
+    let parse =
+        |args: &[&str]| Args::try_parse_from(["pal", "main.pal"].iter().chain(args)).unwrap();
+
+    assert!(runs_by_default(&parse(&[])));
+    assert!(runs_by_default(&parse(&["--dump-ast", "--", "x"])));
+    assert!(!runs_by_default(&parse(&["--emit", "ir"])));
+    assert!(!runs_by_default(&parse(&["--backend", "c"])));
+    assert!(!runs_by_default(&parse(&["-o", "main"])));
+    assert!(!runs_by_default(&parse(&["--save-temps"])));
+}
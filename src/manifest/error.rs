@@ -0,0 +1,16 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+/// Represents any error in finding, reading or building the package a manifest describes.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ManifestError {
+    #[error("could not find `pal.toml` in {} or any of its parents", .0.display())]
+    NotFound(PathBuf),
+    #[error("failed to read {}: {message}", .path.display())]
+    Read { path: PathBuf, message: String },
+    #[error("failed to parse {}: {message}", .path.display())]
+    Parse { path: PathBuf, message: String },
+    #[error("failed to run `{command}`: {message}")]
+    Command { command: String, message: String },
+}
@@ -0,0 +1,227 @@
+pub mod error;
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use serde::Deserialize;
+
+use crate::manifest::error::ManifestError;
+
+/// The name of the file that describes a package, which is at the root of the package.
+pub const MANIFEST: &str = "pal.toml";
+
+/// What building a package produces.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    /// An executable, whose entry point is `main.pal`.
+    #[default]
+    Bin,
+    /// A static library, whose entry point is `lib.pal` and which has no `main`.
+    Lib,
+}
+
+/// The `[package]` table of a manifest, such as:
+///
+/// ```toml
+/// [package]
+/// name = "hello"
+/// source = "src"
+/// kind = "bin"
+/// target = "x86_64-unknown-linux-gnu"
+/// linker-flags = ["-lm"]
+/// ```
+///
+/// Only `name` is required. Sources are in `src` by default, packages are executables, and code
+/// is generated for the host.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Package {
+    pub name: String,
+    /// The directory the entry point of the package is in, relative to the manifest.
+    #[serde(default = "default_source")]
+    pub source: PathBuf,
+    #[serde(default)]
+    pub kind: Kind,
+    /// The target triple code is generated for.
+    pub target: Option<String>,
+    /// Extra arguments passed to `cc` when an executable is linked.
+    #[serde(default)]
+    pub linker_flags: Vec<String>,
+}
+
+fn default_source() -> PathBuf {
+    PathBuf::from("src")
+}
+
+/// Everything a manifest can contain.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Document {
+    package: Package,
+}
+
+/// A package, along with the directory its manifest is in, which every path of it is relative to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Manifest {
+    pub root: PathBuf,
+    pub package: Package,
+}
+
+impl Manifest {
+    /// Parses the text of the manifest at `path`.
+    pub fn parse(path: &Path, text: &str) -> Result<Manifest, ManifestError> {
+        let document: Document = toml::from_str(text).map_err(|error| ManifestError::Parse {
+            path: path.to_path_buf(),
+            message: error.message().to_string(),
+        })?;
+
+        Ok(Manifest {
+            root: path.parent().unwrap_or(Path::new("")).to_path_buf(),
+            package: document.package,
+        })
+    }
+
+    /// Reads the manifest at `path`.
+    pub fn read(path: &Path) -> Result<Manifest, ManifestError> {
+        let text = std::fs::read_to_string(path).map_err(|error| ManifestError::Read {
+            path: path.to_path_buf(),
+            message: error.to_string(),
+        })?;
+
+        Manifest::parse(path, &text)
+    }
+
+    /// Reads the manifest of the package that `dir` is in, which is the closest one in `dir` or
+    /// any of its parents.
+    pub fn find(dir: &Path) -> Result<Manifest, ManifestError> {
+        match dir
+            .ancestors()
+            .map(|dir| dir.join(MANIFEST))
+            .find(|path| path.is_file())
+        {
+            Some(path) => Manifest::read(&path),
+            None => Err(ManifestError::NotFound(dir.to_path_buf())),
+        }
+    }
+
+    /// Returns the path of the file the package is compiled from.
+    pub fn entry(&self) -> PathBuf {
+        let file = match self.package.kind {
+            Kind::Bin => "main.pal",
+            Kind::Lib => "lib.pal",
+        };
+
+        self.root.join(&self.package.source).join(file)
+    }
+
+    /// Returns the directory everything built from the package is written to.
+    pub fn build_dir(&self) -> PathBuf {
+        self.root.join("target")
+    }
+
+    /// Returns the path of the object file the package is compiled to, before it is linked.
+    pub fn object(&self) -> PathBuf {
+        self.build_dir().join(format!("{}.o", self.package.name))
+    }
+
+    /// Returns the path of the executable or library that building the package produces.
+    pub fn artifact(&self) -> PathBuf {
+        match self.package.kind {
+            Kind::Bin => self.build_dir().join(&self.package.name),
+            Kind::Lib => self.build_dir().join(format!("lib{}.a", self.package.name)),
+        }
+    }
+
+    /// Returns the path of the C header that building a library writes, which declares the
+    /// functions it exports, see [`crate::codegen::header::generate_c_header`].
+    pub fn header(&self) -> PathBuf {
+        self.build_dir().join(format!("{}.h", self.package.name))
+    }
+
+    /// Returns the command that turns the object file of the package into its artifact: `cc` for
+    /// an executable, and `ar` for a library.
+    pub fn link_command(&self) -> Command {
+        match self.package.kind {
+            Kind::Bin => {
+                let mut command = Command::new("cc");
+                command
+                    .arg(self.object())
+                    .arg("-o")
+                    .arg(self.artifact())
+                    .args(&self.package.linker_flags);
+                command
+            }
+            Kind::Lib => {
+                let mut command = Command::new("ar");
+                command.arg("rcs").arg(self.artifact()).arg(self.object());
+                command
+            }
+        }
+    }
+
+    /// Links the object file of the package into its artifact, returning where it was written.
+    pub fn link(&self) -> Result<PathBuf, ManifestError> {
+        let mut command = self.link_command();
+        let shown = format!("{command:?}");
+        let failed = |message: String| ManifestError::Command {
+            command: shown.clone(),
+            message,
+        };
+
+        let status = command
+            .status()
+            .map_err(|error| failed(error.to_string()))?;
+
+        match status.success() {
+            true => Ok(self.artifact()),
+            false => Err(failed(status.to_string())),
+        }
+    }
+}
+
+#[test]
+fn manifests_have_defaults() {
+    let manifest =
+        Manifest::parse(Path::new("hello/pal.toml"), "[package]\nname = \"hello\"\n").unwrap();
+
+    assert_eq!(manifest.package.kind, Kind::Bin);
+    assert_eq!(manifest.entry(), PathBuf::from("hello/src/main.pal"));
+    assert_eq!(manifest.artifact(), PathBuf::from("hello/target/hello"));
+    assert_eq!(manifest.package.target, None);
+}
+
+#[test]
+fn libraries_are_archived() {
+    let manifest = Manifest::parse(
+        Path::new("pal.toml"),
+        "[package]\nname = \"util\"\nsource = \"lib\"\nkind = \"lib\"\nlinker-flags = [\"-lm\"]\n",
+    )
+    .unwrap();
+
+    assert_eq!(manifest.entry(), PathBuf::from("lib/lib.pal"));
+    assert_eq!(manifest.header(), PathBuf::from("target/util.h"));
+
+    let command = manifest.link_command();
+    let args: Vec<_> = command.get_args().collect();
+
+    assert_eq!(command.get_program(), "ar");
+    assert_eq!(args, ["rcs", "target/libutil.a", "target/util.o"]);
+}
+
+#[test]
+fn invalid_manifests_are_rejected() {
+    let parse = |text| Manifest::parse(Path::new("pal.toml"), text);
+
+    assert!(matches!(
+        parse("[package]\nname = \"a\"\nkind = \"dylib\"\n"),
+        Err(ManifestError::Parse { .. })
+    ));
+    assert!(matches!(
+        parse("[package]\nname = \"a\"\nedition = 1\n"),
+        Err(ManifestError::Parse { .. })
+    ));
+    assert!(matches!(parse(""), Err(ManifestError::Parse { .. })));
+}
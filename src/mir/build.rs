@@ -0,0 +1,796 @@
+use std::collections::HashMap;
+
+use crate::{
+    backend::{UNREACHABLE, assertion_failed},
+    lower::{
+        constructor_name, field_name,
+        strings::{STR_CONCAT, STR_LEN, STR_NEW, STR_PTR},
+        tag_name,
+    },
+    mir::{error::MirError, *},
+    parser::{Span, Spanned},
+    sema::prelude,
+    spec::ast::{self, Expression, Item, Module, Pattern, Type, UnaryOp},
+};
+
+/// The types of the arguments of every function a body can call, and of the value it returns.
+type Signatures = HashMap<Symbol, (Vec<Type>, Type)>;
+
+/// Returns a type that is written as a single name, such as `u32`.
+fn atomic(name: &str) -> Type {
+    Type::Atomic(name.into())
+}
+
+/// Collects the signature of every function the bodies of a module can call: those of the
+/// prelude and the runtime, those of its imports, its own, which shadow the others, and those
+/// that the lowering passes expect backends to provide.
+fn signatures(module: &Module, imports: &[&Module]) -> Signatures {
+    let mut signatures = Signatures::new();
+    let items = prelude()
+        .iter()
+        .chain(imports.iter().rev().flat_map(|import| &import.1))
+        .chain(&module.1);
+
+    for item in items {
+        match &item.node {
+            Item::ExternFunctionDefinition(name, args, returns, ..)
+            | Item::FunctionDeclaration(name, _, args, returns, ..) => {
+                let args = args.iter().map(|(_, typ)| typ.node.clone()).collect();
+                signatures.insert(*name, (args, returns.node.clone()));
+            }
+            Item::Enum(name, variants, _) => {
+                let typ = atomic(name);
+                signatures.insert(tag_name(name), (vec![typ.clone()], atomic("u32")));
+
+                for (variant, fields) in variants {
+                    let fields: Vec<_> = fields.iter().map(|field| field.node.clone()).collect();
+
+                    for (index, field) in fields.iter().enumerate() {
+                        signatures.insert(
+                            field_name(name, variant, index),
+                            (vec![typ.clone()], field.clone()),
+                        );
+                    }
+
+                    signatures.insert(constructor_name(name, variant), (fields, typ.clone()));
+                }
+            }
+            Item::Const(..) => {}
+        }
+    }
+
+    let (str, c_string) = (atomic("str"), Type::Pointer(Box::new(atomic("char"))));
+
+    for (name, args, returns) in [
+        (STR_NEW, vec![c_string.clone(), atomic("u32")], str.clone()),
+        (STR_PTR, vec![str.clone()], c_string),
+        (STR_LEN, vec![str.clone()], atomic("u32")),
+        (STR_CONCAT, vec![str.clone(), str.clone()], str),
+    ] {
+        signatures.insert(name.into(), (args, returns));
+    }
+
+    signatures
+}
+
+/// Builds the MIR of every function with a body that a module declares, in the order they are
+/// declared in. The module must have been accepted by sema, and lowered so that it has no generic
+/// functions, no operations on strings and no enum variants left, see [`crate::lower`].
+pub fn build(module: &Module, imports: &[&Module]) -> Result<Vec<Body>, MirError> {
+    let signatures = signatures(module, imports);
+    let mut consts = HashMap::new();
+
+    for item in imports
+        .iter()
+        .rev()
+        .flat_map(|import| &import.1)
+        .chain(&module.1)
+    {
+        if let Item::Const(name, typ, _, _) = &item.node
+            && let Type::Array(element, _) = &typ.node
+        {
+            consts.insert(*name, (**element).clone());
+        }
+    }
+
+    module
+        .1
+        .iter()
+        .filter_map(|item| match &item.node {
+            Item::FunctionDeclaration(name, _, args, returns, body, ..) => {
+                let mut builder = Builder::new(&signatures, &consts);
+
+                for (arg, typ) in args {
+                    let local = builder.local(Some(*arg), typ.node.clone());
+                    builder.bind(*arg, local);
+                }
+
+                let body = builder
+                    .finish(*name, args.len(), &returns.node, body)
+                    .map_err(|source| MirError::InFunction {
+                        function: name.to_string(),
+                        span: item.span,
+                        source: Box::new(source),
+                    });
+
+                Some(body)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// A basic block that is being built, whose terminator is only known once control leaves it.
+type OpenBlock = (Vec<Spanned<Statement>>, Option<Spanned<Terminator>>);
+
+/// Builds the MIR of a single function, one statement of the source at a time.
+struct Builder<'a> {
+    signatures: &'a Signatures,
+    /// The type of the elements of every constant array, by the name of the constant.
+    consts: &'a HashMap<Symbol, Type>,
+    locals: Vec<LocalDecl>,
+    /// The blocks built so far, the last of which may not have a terminator yet.
+    blocks: Vec<OpenBlock>,
+    /// The block that statements are added to, or `None` when control can not reach them.
+    current: Option<BlockId>,
+    /// The locals that every variable in scope is held in, innermost scope last.
+    scopes: Vec<HashMap<Symbol, Local>>,
+    /// The span of the statement of the source that is being lowered.
+    span: Span,
+}
+
+impl<'a> Builder<'a> {
+    fn new(signatures: &'a Signatures, consts: &'a HashMap<Symbol, Type>) -> Builder<'a> {
+        Builder {
+            signatures,
+            consts,
+            locals: vec![],
+            blocks: vec![(vec![], None)],
+            current: Some(BlockId(0)),
+            scopes: vec![HashMap::new()],
+            span: Span::default(),
+        }
+    }
+
+    /// Lowers the body of a function, ending any block that is left without a terminator with
+    /// [`Terminator::Unreachable`], as sema has checked that control never falls off the end.
+    fn finish(
+        mut self,
+        name: Symbol,
+        arguments: usize,
+        returns: &Type,
+        body: &[Spanned<ast::Statement>],
+    ) -> Result<Body, MirError> {
+        self.block(body)?;
+
+        let blocks = self
+            .blocks
+            .into_iter()
+            .map(|(statements, terminator)| BasicBlock {
+                statements,
+                terminator: terminator
+                    .unwrap_or_else(|| Spanned::new(Terminator::Unreachable, self.span)),
+            })
+            .collect();
+
+        Ok(Body {
+            name,
+            arguments,
+            locals: self.locals,
+            returns: returns.clone(),
+            blocks,
+        })
+    }
+
+    fn local(&mut self, name: Option<Symbol>, typ: Type) -> Local {
+        self.locals.push(LocalDecl { name, typ });
+        Local(self.locals.len() - 1)
+    }
+
+    fn bind(&mut self, name: Symbol, local: Local) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name, local);
+        }
+    }
+
+    fn lookup(&self, name: &Symbol) -> Option<Local> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name).copied())
+    }
+
+    fn new_block(&mut self) -> BlockId {
+        self.blocks.push((vec![], None));
+        BlockId(self.blocks.len() - 1)
+    }
+
+    fn push(&mut self, statement: Statement) {
+        if let Some(block) = self.current {
+            self.blocks[block.0]
+                .0
+                .push(Spanned::new(statement, self.span));
+        }
+    }
+
+    /// Ends the current block, after which no block is current until one is entered.
+    fn terminate(&mut self, terminator: Terminator) {
+        if let Some(block) = self.current.take() {
+            self.blocks[block.0].1 = Some(Spanned::new(terminator, self.span));
+        }
+    }
+
+    fn enter(&mut self, block: BlockId) {
+        self.current = Some(block);
+    }
+
+    /// Ends the current block with a call to `pal_panic`, which never returns.
+    fn panic(&mut self, message: String) {
+        self.push(Statement::Call(
+            None,
+            Callee::Function("pal_panic".into()),
+            vec![Operand::Constant(Constant::Str(message))],
+        ));
+        self.terminate(Terminator::Unreachable);
+    }
+
+    /// Lowers every statement of a block inside of its own scope. Statements after one that
+    /// control never comes back from are dropped, as they can not be reached.
+    fn block(&mut self, body: &[Spanned<ast::Statement>]) -> Result<(), MirError> {
+        self.scopes.push(HashMap::new());
+        self.statements(body)?;
+        self.scopes.pop();
+
+        Ok(())
+    }
+
+    /// Lowers statements into the innermost scope, stopping at the first one that control never
+    /// comes back from.
+    fn statements(&mut self, body: &[Spanned<ast::Statement>]) -> Result<(), MirError> {
+        for statement in body {
+            if self.current.is_none() {
+                break;
+            }
+
+            let outer = std::mem::replace(&mut self.span, statement.span);
+            self.statement(&statement.node)
+                .map_err(|error| error.in_statement(statement.span))?;
+            self.span = outer;
+        }
+
+        Ok(())
+    }
+
+    fn statement(&mut self, statement: &ast::Statement) -> Result<(), MirError> {
+        match statement {
+            ast::Statement::FunctionCall(name, args) => {
+                self.call(name, args, false)?;
+            }
+            ast::Statement::Return(value) => {
+                let value = self.operand(value)?;
+                self.terminate(Terminator::Return(value));
+            }
+            ast::Statement::Let(name, typ, value) => {
+                let value = self.rvalue(value)?;
+                let local = self.local(Some(*name), typ.node.clone());
+
+                self.push(Statement::Assign(local, value));
+                self.bind(*name, local);
+            }
+            ast::Statement::Block(body) => self.block(body)?,
+            ast::Statement::For(name, start, end, body) => {
+                // Both bounds are evaluated once, before the loop is entered.
+                let start = self.operand(start)?;
+                let end = self.operand(end)?;
+
+                self.scopes.push(HashMap::new());
+
+                let induction = self.local(Some(*name), atomic("u32"));
+                self.bind(*name, induction);
+                self.push(Statement::Assign(induction, Rvalue::Use(start)));
+
+                let (cond, body_block, exit) =
+                    (self.new_block(), self.new_block(), self.new_block());
+                self.terminate(Terminator::Goto(cond));
+
+                self.enter(cond);
+                let in_range = self.local(None, atomic("u32"));
+                self.push(Statement::Assign(
+                    in_range,
+                    Rvalue::Binary(BinOp::Lt, Operand::Copy(induction), end),
+                ));
+                self.terminate(Terminator::Switch(
+                    Operand::Copy(in_range),
+                    vec![(0, exit)],
+                    body_block,
+                ));
+
+                self.enter(body_block);
+                self.block(body)?;
+
+                // The body may already have returned, in which case there is nothing to step.
+                self.push(Statement::Assign(
+                    induction,
+                    Rvalue::Binary(
+                        BinOp::Add,
+                        Operand::Copy(induction),
+                        Operand::Constant(Constant::Int(1, atomic("u32"))),
+                    ),
+                ));
+                self.terminate(Terminator::Goto(cond));
+
+                self.scopes.pop();
+                self.enter(exit);
+            }
+            ast::Statement::Match(value, arms) => {
+                let value = self.operand(value)?;
+
+                // The end of the `match` is only reached when no arm matches, or when an arm does
+                // not return, so it is only made once either is known to happen.
+                let mut end = None;
+                let mut cases = vec![];
+                let mut bodies = vec![];
+                let mut default = None;
+
+                for (pattern, body) in arms {
+                    let block = self.new_block();
+
+                    match pattern.node {
+                        Pattern::Number(number) => cases.push((number, block)),
+                        Pattern::Char(c) => cases.push((c as u64, block)),
+                        Pattern::Variant(..) => return Err(MirError::VariantDoesNotExist),
+                        Pattern::Wildcard => default = default.or(Some(block)),
+                    }
+
+                    bodies.push((block, body));
+                }
+
+                // A `match` with only a wildcard arm can be over an enum with fields, which is not
+                // an integer, and so is only switched on when there is a case to switch to.
+                let default = match default {
+                    Some(default) => default,
+                    None => *end.insert(self.new_block()),
+                };
+                match cases.is_empty() {
+                    true => self.terminate(Terminator::Goto(default)),
+                    false => self.terminate(Terminator::Switch(value, cases, default)),
+                }
+
+                for (block, body) in bodies {
+                    self.enter(block);
+                    self.block(body)?;
+
+                    if self.current.is_some() {
+                        let end = *end.get_or_insert_with(|| self.new_block());
+                        self.terminate(Terminator::Goto(end));
+                    }
+                }
+
+                self.current = end;
+            }
+            ast::Statement::Free(pointer) => {
+                let pointer = self.operand(pointer)?;
+                self.push(Statement::Free(pointer));
+            }
+            ast::Statement::Unreachable => self.panic(UNREACHABLE.to_string()),
+            ast::Statement::Asm(template) => self.push(Statement::Asm(template.clone())),
+            ast::Statement::Error(_) => {}
+            ast::Statement::Assert(condition, message) => {
+                let condition = self.operand(condition)?;
+                let (failed, passed) = (self.new_block(), self.new_block());
+
+                self.terminate(Terminator::Switch(condition, vec![(0, failed)], passed));
+                self.enter(failed);
+                self.panic(assertion_failed(message));
+                self.enter(passed);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Lowers a call, evaluating its arguments from left to right. When its value is used, it is
+    /// assigned to a new local, which is returned.
+    fn call(
+        &mut self,
+        name: &Symbol,
+        args: &[Spanned<Expression>],
+        used: bool,
+    ) -> Result<Option<Local>, MirError> {
+        let args = args
+            .iter()
+            .map(|arg| self.operand(arg))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // A local function pointer shadows any function of the same name, like in sema.
+        let (callee, returns) = match self.lookup(name) {
+            Some(local) => match &self.locals[local.0].typ {
+                Type::Function(_, returns) => (Callee::Pointer(local), (**returns).clone()),
+                _ => return Err(MirError::FunctionDoesNotExist(name.to_string())),
+            },
+            None => match self.signatures.get(name) {
+                Some((_, returns)) => (Callee::Function(*name), returns.clone()),
+                None => return Err(MirError::FunctionDoesNotExist(name.to_string())),
+            },
+        };
+
+        if returns == Type::Never {
+            if used {
+                return Err(MirError::NoReturnValue(name.to_string()));
+            }
+
+            self.push(Statement::Call(None, callee, args));
+            self.terminate(Terminator::Unreachable);
+
+            return Ok(None);
+        }
+
+        let destination = used.then(|| self.local(None, returns));
+        self.push(Statement::Call(destination, callee, args));
+
+        Ok(destination)
+    }
+
+    /// Lowers an expression into an operand, assigning its value to a new local unless it is a
+    /// constant or a variable.
+    fn operand(&mut self, expression: &Spanned<Expression>) -> Result<Operand, MirError> {
+        match self.rvalue(expression)? {
+            Rvalue::Use(operand) => Ok(operand),
+            rvalue => {
+                let typ = self.type_of(&rvalue)?;
+                let local = self.local(None, typ);
+
+                self.push(Statement::Assign(local, rvalue));
+
+                Ok(Operand::Copy(local))
+            }
+        }
+    }
+
+    fn rvalue(&mut self, expression: &Spanned<Expression>) -> Result<Rvalue, MirError> {
+        let rvalue = match &expression.node {
+            Expression::NumericLiteral(value) => {
+                Rvalue::Use(Operand::Constant(Constant::Int(*value, atomic("u32"))))
+            }
+            // A literal that was given a type is a constant of that type, rather than a `u32` that
+            // is converted, which could not hold every value of a wider type.
+            Expression::Cast(value, typ) if let Expression::NumericLiteral(value) = value.node => {
+                Rvalue::Use(Operand::Constant(Constant::Int(value, typ.node.clone())))
+            }
+            Expression::CharLiteral(value) => {
+                Rvalue::Use(Operand::Constant(Constant::Char(*value)))
+            }
+            Expression::StringLiteral(value) => {
+                Rvalue::Use(Operand::Constant(Constant::Str(value.clone())))
+            }
+            Expression::Variable(name) => Rvalue::Use(Operand::Copy(
+                self.lookup(name)
+                    .ok_or_else(|| MirError::VariableDoesNotExist(name.to_string()))?,
+            )),
+            Expression::Call(name, args) => {
+                let local = self.call(name, args, true)?;
+                Rvalue::Use(Operand::Copy(
+                    local.ok_or_else(|| MirError::NoReturnValue(name.to_string()))?,
+                ))
+            }
+            Expression::Index(name, index) => {
+                let index = self.operand(index)?;
+
+                match self.lookup(name) {
+                    Some(local) => Rvalue::Index(local, index),
+                    None => Rvalue::ConstIndex(*name, index),
+                }
+            }
+            Expression::Unary(UnaryOp::AddressOf, operand) => match &operand.node {
+                Expression::Variable(name) => match self.lookup(name) {
+                    Some(local) => Rvalue::AddressOf(local),
+                    // Anything else whose address is taken is a function.
+                    None => Rvalue::Use(Operand::Constant(Constant::Function(*name))),
+                },
+                _ => return Err(MirError::AddressOfValue),
+            },
+            Expression::Unary(UnaryOp::Deref, operand) => Rvalue::Deref(self.operand(operand)?),
+            Expression::Unary(op, operand) => {
+                let op = match op {
+                    UnaryOp::Neg => UnOp::Neg,
+                    UnaryOp::Not => UnOp::Not,
+                    _ => UnOp::BitNot,
+                };
+
+                Rvalue::Unary(op, self.operand(operand)?)
+            }
+            Expression::Binary(op, left, right) => {
+                let left = self.operand(left)?;
+                let right = self.operand(right)?;
+
+                Rvalue::Binary((*op).into(), left, right)
+            }
+            Expression::Variant(..) => return Err(MirError::VariantDoesNotExist),
+            Expression::New(typ, value) => Rvalue::New(typ.node.clone(), self.operand(value)?),
+            Expression::Alloc(typ, count) => Rvalue::Alloc(typ.node.clone(), self.operand(count)?),
+            Expression::Cast(value, typ) => Rvalue::Cast(self.operand(value)?, typ.node.clone()),
+            // Each branch assigns its value to the same local, which is read once they join.
+            Expression::If(condition, then, otherwise) => {
+                let condition = self.operand(condition)?;
+                let (then_block, else_block, end) =
+                    (self.new_block(), self.new_block(), self.new_block());
+                let mut result = None;
+
+                self.terminate(Terminator::Switch(
+                    condition,
+                    vec![(0, else_block)],
+                    then_block,
+                ));
+
+                for (block, branch) in [(then_block, then), (else_block, otherwise)] {
+                    self.enter(block);
+
+                    let Some(value) = self.branch(branch)? else {
+                        continue;
+                    };
+
+                    let local = match result {
+                        Some(local) => local,
+                        None => {
+                            let typ = self.operand_type(&value)?;
+                            *result.insert(self.local(None, typ))
+                        }
+                    };
+
+                    self.push(Statement::Assign(local, Rvalue::Use(value)));
+                    self.terminate(Terminator::Goto(end));
+                }
+
+                let local = result.ok_or_else(|| MirError::NoReturnValue("if".to_string()))?;
+                self.enter(end);
+
+                Rvalue::Use(Operand::Copy(local))
+            }
+            // The statements bind names in a scope of their own, which the value is read in.
+            Expression::Block(body, value) => {
+                self.scopes.push(HashMap::new());
+                self.statements(body)?;
+                let value = self.operand(value);
+                self.scopes.pop();
+
+                Rvalue::Use(value?)
+            }
+        };
+
+        Ok(rvalue)
+    }
+
+    /// Lowers a branch of an `if` expression into an operand, or into nothing when the branch is,
+    /// or is a block ending with, a call to a function that never returns, which ends the block
+    /// instead.
+    fn branch(&mut self, expression: &Spanned<Expression>) -> Result<Option<Operand>, MirError> {
+        if let Expression::Block(body, value) = &expression.node {
+            self.scopes.push(HashMap::new());
+            self.statements(body)?;
+            let value = self.branch(value);
+            self.scopes.pop();
+
+            return value;
+        }
+
+        if let Expression::Call(name, args) = &expression.node {
+            let returns = match self.lookup(name) {
+                Some(local) => match &self.locals[local.0].typ {
+                    Type::Function(_, returns) => Some(&**returns),
+                    _ => None,
+                },
+                None => self.signatures.get(name).map(|(_, returns)| returns),
+            };
+
+            if returns == Some(&Type::Never) {
+                self.call(name, args, false)?;
+                return Ok(None);
+            }
+        }
+
+        self.operand(expression).map(Some)
+    }
+
+    fn operand_type(&self, operand: &Operand) -> Result<Type, MirError> {
+        match operand {
+            Operand::Copy(local) => Ok(self.locals[local.0].typ.clone()),
+            Operand::Constant(Constant::Int(_, typ)) => Ok(typ.clone()),
+            Operand::Constant(Constant::Char(_)) => Ok(atomic("char")),
+            Operand::Constant(Constant::Str(_)) => Ok(Type::Pointer(Box::new(atomic("char")))),
+            Operand::Constant(Constant::Function(name)) => match self.signatures.get(name) {
+                Some((args, returns)) => {
+                    Ok(Type::Function(args.clone(), Box::new(returns.clone())))
+                }
+                None => Err(MirError::FunctionDoesNotExist(name.to_string())),
+            },
+        }
+    }
+
+    /// Works out the type of the value of an rvalue, for the local it is assigned to.
+    fn type_of(&self, rvalue: &Rvalue) -> Result<Type, MirError> {
+        let pointee = |typ| match typ {
+            Type::Pointer(pointee) => Ok(*pointee),
+            _ => Err(MirError::UnknownPointee),
+        };
+
+        match rvalue {
+            Rvalue::Use(operand) | Rvalue::Unary(_, operand) => self.operand_type(operand),
+            Rvalue::Binary(BinOp::Eq | BinOp::Ne | BinOp::Lt, ..) => Ok(atomic("u32")),
+            Rvalue::Binary(_, left, _) => self.operand_type(left),
+            Rvalue::Deref(operand) => pointee(self.operand_type(operand)?),
+            Rvalue::AddressOf(local) => {
+                Ok(Type::Pointer(Box::new(self.locals[local.0].typ.clone())))
+            }
+            Rvalue::Index(local, _) => pointee(self.locals[local.0].typ.clone()),
+            Rvalue::ConstIndex(name, _) => self
+                .consts
+                .get(name)
+                .cloned()
+                .ok_or_else(|| MirError::ConstDoesNotExist(name.to_string())),
+            Rvalue::New(typ, _) | Rvalue::Alloc(typ, _) => Ok(Type::Pointer(Box::new(typ.clone()))),
+            Rvalue::Cast(_, typ) => Ok(typ.clone()),
+        }
+    }
+}
+
+#[test]
+fn control_flow_is_lowered_to_blocks() {
+    let (module, _) = crate::spec::module("main".to_string())
+        .parse(
+            "ext fn exit(code: u32) -> !;
+            fn main(n: u32) -> u32 {
+                for i in 0..n { match i { 3 => { return i; } 4 => { exit(~i); } _ => {} } }
+                match n { 0 => { exit(1); } }
+                unreachable();
+            };",
+        )
+        .unwrap();
+
+    assert_eq!(
+        build(&module, &[]).unwrap()[0].to_string(),
+        "fn main(_0 /* n */: u32) -> u32 {
+    let _1 /* i */: u32;
+    let _2: u32;
+    let _3: u32;
+
+    bb0: {
+        _1 = 0;
+        goto bb1;
+    }
+
+    bb1: {
+        _2 = _1 < _0;
+        switch _2 [0 => bb3, _ => bb2];
+    }
+
+    bb2: {
+        switch _1 [3 => bb4, 4 => bb5, _ => bb6];
+    }
+
+    bb3: {
+        switch _0 [0 => bb8, _ => bb9];
+    }
+
+    bb4: {
+        return _1;
+    }
+
+    bb5: {
+        _3 = ~_1;
+        exit(_3);
+        unreachable;
+    }
+
+    bb6: {
+        goto bb7;
+    }
+
+    bb7: {
+        _1 = _1 + 1;
+        goto bb1;
+    }
+
+    bb8: {
+        exit(1);
+        unreachable;
+    }
+
+    bb9: {
+        pal_panic(\"entered unreachable code\");
+        unreachable;
+    }
+}
+"
+    );
+}
+
+#[test]
+fn locals_and_constants_are_typed() {
+    let (module, _) = crate::spec::module("main".to_string())
+        .parse(
+            "const DIGITS: [char; 2] = ['0', '1'];
+            fn first(p: *char) -> char { return p[0]; };
+            fn main() -> u32 {
+                let f: fn(*char) -> char = &first;
+                let c: char = DIGITS[1];
+                let p: *char = new char(c);
+                return f(&c) == *p;
+            };",
+        )
+        .unwrap();
+
+    let bodies = build(&module, &[]).unwrap();
+
+    assert_eq!(
+        bodies[0].to_string(),
+        "fn first(_0 /* p */: *char) -> char {
+    let _1: char;
+
+    bb0: {
+        _1 = _0[0];
+        return _1;
+    }
+}
+"
+    );
+    assert_eq!(
+        bodies[1].to_string(),
+        "fn main() -> u32 {
+    let _0 /* f */: fn(*char) -> char;
+    let _1 /* c */: char;
+    let _2 /* p */: *char;
+    let _3: *char;
+    let _4: char;
+    let _5: char;
+    let _6: u32;
+
+    bb0: {
+        _0 = &first;
+        _1 = DIGITS[1];
+        _2 = new char(_1);
+        _3 = &_1;
+        _4 = (*_0)(_3);
+        _5 = *_2;
+        _6 = _4 == _5;
+        return _6;
+    }
+}
+"
+    );
+}
+
+#[test]
+fn if_expressions_join_in_a_local() {
+    let (module, _) = crate::spec::module("main".to_string())
+        .parse(
+            "ext fn exit(code: u32) -> !;
+            fn main(n: u32) -> u32 { return if n { exit(n) } else { 2 }; };",
+        )
+        .unwrap();
+
+    assert_eq!(
+        build(&module, &[]).unwrap()[0].to_string(),
+        "fn main(_0 /* n */: u32) -> u32 {
+    let _1: u32;
+
+    bb0: {
+        switch _0 [0 => bb2, _ => bb1];
+    }
+
+    bb1: {
+        exit(_0);
+        unreachable;
+    }
+
+    bb2: {
+        _1 = 2;
+        goto bb3;
+    }
+
+    bb3: {
+        return _1;
+    }
+}
+"
+    );
+}
@@ -0,0 +1,60 @@
+use thiserror::Error;
+
+use crate::parser::Span;
+
+/// Represents any error found while building the MIR of a module. Sema has already checked the
+/// module, so these only happen when a lowering pass leaves something behind that it should not.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum MirError {
+    #[error("no such variable `{0}` was found in the current scope")]
+    VariableDoesNotExist(String),
+    #[error("no such function `{0}` was found")]
+    FunctionDoesNotExist(String),
+    #[error("no such constant `{0}` was found")]
+    ConstDoesNotExist(String),
+    #[error("function `{0}` does not return a value")]
+    NoReturnValue(String),
+    #[error("`&` can only take the address of a variable")]
+    AddressOfValue,
+    #[error("the type behind this pointer is not known")]
+    UnknownPointee,
+    #[error("enum variants must be lowered before MIR is built")]
+    VariantDoesNotExist,
+    /// Wraps an error with the function that was being lowered when it happened.
+    #[error("failed to lower function `{function}`")]
+    InFunction {
+        function: String,
+        span: Span,
+        #[source]
+        source: Box<MirError>,
+    },
+    /// Wraps an error with the innermost statement that was being lowered when it happened.
+    #[error("failed to lower statement")]
+    InStatement {
+        span: Span,
+        #[source]
+        source: Box<MirError>,
+    },
+}
+
+impl MirError {
+    /// Returns the span of the function or statement the error happened in, if it is known.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            MirError::InFunction { span, .. } | MirError::InStatement { span, .. } => Some(*span),
+            _ => None,
+        }
+    }
+
+    /// Wraps the error with the statement it happened in, unless it already knows a statement
+    /// nested in that one.
+    pub fn in_statement(self, span: Span) -> MirError {
+        match self {
+            MirError::InStatement { .. } => self,
+            source => MirError::InStatement {
+                span,
+                source: Box::new(source),
+            },
+        }
+    }
+}
@@ -0,0 +1,180 @@
+pub mod build;
+pub mod error;
+pub mod pretty;
+
+use crate::{parser::Spanned, spec::ast::*};
+
+// The MIR of a function is a control flow graph of basic blocks, each of which is a list of
+// statements that run one after the other, followed by a terminator that decides which block runs
+// next. Every intermediate value is held in a local of its own, and every local has a type, so
+// that backends never have to work out what an expression evaluates to.
+
+/// A local of a function, by its position in [`Body::locals`]. The first locals of a function are
+/// its arguments.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Local(pub usize);
+
+/// A basic block of a function, by its position in [`Body::blocks`]. Control enters a function
+/// at its first block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BlockId(pub usize);
+
+/// A local of a function: the variable it was declared as, if it is not a temporary, and its type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LocalDecl {
+    pub name: Option<Symbol>,
+    pub typ: Type,
+}
+
+/// A value that is known without running any code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Constant {
+    /// An integer of the given type, which is `u32` unless the literal it was built from was
+    /// given another one, see [`crate::lower::literals`].
+    Int(u64, Type),
+    Char(char),
+    /// A pointer to the first byte of a string, which is followed by a NUL.
+    Str(String),
+    /// A pointer to a function.
+    Function(Symbol),
+}
+
+/// The input of an operation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Operand {
+    /// The value a local holds when the operand is evaluated.
+    Copy(Local),
+    Constant(Constant),
+}
+
+/// An operator written in front of its operand. Dereferencing is an [`Rvalue`] of its own, as it
+/// needs the type of what is read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnOp {
+    Neg,
+    Not,
+    BitNot,
+}
+
+/// An operator between two operands. Along with those of the source, there are the ones that
+/// desugared `for` loops need.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinOp {
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+    Eq,
+    Ne,
+    /// Unsigned comparison, which gives a `u32` like the other comparisons.
+    Lt,
+    /// Wrapping addition.
+    Add,
+}
+
+impl From<BinaryOp> for BinOp {
+    fn from(op: BinaryOp) -> BinOp {
+        match op {
+            BinaryOp::BitAnd => BinOp::BitAnd,
+            BinaryOp::BitOr => BinOp::BitOr,
+            BinaryOp::BitXor => BinOp::BitXor,
+            BinaryOp::Shl => BinOp::Shl,
+            BinaryOp::Shr => BinOp::Shr,
+            BinaryOp::Eq => BinOp::Eq,
+            BinaryOp::Ne => BinOp::Ne,
+        }
+    }
+}
+
+/// An operation whose value is assigned to a local.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Rvalue {
+    Use(Operand),
+    Unary(UnOp, Operand),
+    Binary(BinOp, Operand, Operand),
+    /// Reads the value a pointer points to.
+    Deref(Operand),
+    /// Takes the address of a local.
+    AddressOf(Local),
+    /// Reads the element at an offset from where a local pointer points.
+    Index(Local, Operand),
+    /// Reads an element of a constant array.
+    ConstIndex(Symbol, Operand),
+    /// Allocates a value on the heap, evaluating to a pointer to it.
+    New(Type, Operand),
+    /// Allocates room for a number of values on the heap, evaluating to a pointer to the first.
+    Alloc(Type, Operand),
+    /// Converts an integer to an integer of another type, truncating or extending it. Only an
+    /// `isize` is sign extended.
+    Cast(Operand, Type),
+}
+
+/// The function a call calls.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Callee {
+    /// A function declared by the program, the prelude or the runtime, or one that a lowering
+    /// pass expects backends to provide.
+    Function(Symbol),
+    /// The function a local function pointer points to.
+    Pointer(Local),
+}
+
+/// An operation that does not end its basic block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Statement {
+    Assign(Local, Rvalue),
+    /// Calls a function with its arguments, assigning what it returns to a local when it is used.
+    /// A call to a function that never returns is always followed by [`Terminator::Unreachable`].
+    Call(Option<Local>, Callee, Vec<Operand>),
+    /// Frees memory allocated with `new` or `alloc`.
+    Free(Operand),
+    /// Runs inline assembly, which takes no operands.
+    Asm(String),
+}
+
+/// The operation that ends a basic block, deciding which one runs next.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Terminator {
+    Goto(BlockId),
+    /// Goes to the block of the first case that an integer is equal to, or to the last block when
+    /// there is none.
+    Switch(Operand, Vec<(u64, BlockId)>, BlockId),
+    Return(Operand),
+    /// Marks the end of a block that control never reaches the end of, such as one that calls a
+    /// function that never returns.
+    Unreachable,
+}
+
+/// A list of statements that always run one after the other, followed by a terminator. Each is
+/// spanned with the statement of the source it was lowered from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BasicBlock {
+    pub statements: Vec<Spanned<Statement>>,
+    pub terminator: Spanned<Terminator>,
+}
+
+/// The MIR of a function with a body.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Body {
+    pub name: Symbol,
+    /// How many of the first [`Body::locals`] are the arguments of the function.
+    pub arguments: usize,
+    pub locals: Vec<LocalDecl>,
+    pub returns: Type,
+    pub blocks: Vec<BasicBlock>,
+}
+
+impl Body {
+    /// Returns the type of the value that a pointer operand points to, if it is a pointer.
+    pub fn pointee(&self, operand: &Operand) -> Option<Type> {
+        match operand {
+            Operand::Copy(local) => match &self.locals[local.0].typ {
+                Type::Pointer(pointee) => Some((**pointee).clone()),
+                _ => None,
+            },
+            Operand::Constant(Constant::Str(_)) => Some(Type::Atomic("char".into())),
+            Operand::Constant(_) => None,
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use std::fmt::{Display, Formatter, Result};
+
+use crate::{
+    mir::*,
+    spec::ast::{Expression, Symbol},
+};
+
+/// The string statements are indented with, once inside of their function and once more inside of
+/// their block.
+const INDENT: &str = "    ";
+
+impl Display for Local {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "_{}", self.0)
+    }
+}
+
+impl Display for BlockId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "bb{}", self.0)
+    }
+}
+
+impl Display for Constant {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Constant::Int(value, Type::Atomic(name)) if name == "u32" => write!(f, "{value}"),
+            Constant::Int(value, typ) => write!(f, "{value}_{typ}"),
+            Constant::Char(value) => write!(f, "{}", Expression::CharLiteral(*value)),
+            Constant::Str(value) => write!(f, "{}", Expression::StringLiteral(value.clone())),
+            Constant::Function(name) => write!(f, "&{name}"),
+        }
+    }
+}
+
+impl Display for Operand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Operand::Copy(local) => write!(f, "{local}"),
+            Operand::Constant(constant) => write!(f, "{constant}"),
+        }
+    }
+}
+
+impl Display for UnOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            UnOp::Neg => write!(f, "-"),
+            UnOp::Not => write!(f, "!"),
+            UnOp::BitNot => write!(f, "~"),
+        }
+    }
+}
+
+impl Display for BinOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            BinOp::BitAnd => write!(f, "&"),
+            BinOp::BitOr => write!(f, "|"),
+            BinOp::BitXor => write!(f, "^"),
+            BinOp::Shl => write!(f, "<<"),
+            BinOp::Shr => write!(f, ">>"),
+            BinOp::Eq => write!(f, "=="),
+            BinOp::Ne => write!(f, "!="),
+            BinOp::Lt => write!(f, "<"),
+            BinOp::Add => write!(f, "+"),
+        }
+    }
+}
+
+impl Display for Rvalue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Rvalue::Use(operand) => write!(f, "{operand}"),
+            Rvalue::Unary(op, operand) => write!(f, "{op}{operand}"),
+            Rvalue::Binary(op, left, right) => write!(f, "{left} {op} {right}"),
+            Rvalue::Deref(operand) => write!(f, "*{operand}"),
+            Rvalue::AddressOf(local) => write!(f, "&{local}"),
+            Rvalue::Index(local, index) => write!(f, "{local}[{index}]"),
+            Rvalue::ConstIndex(name, index) => write!(f, "{name}[{index}]"),
+            Rvalue::New(typ, value) => write!(f, "new {typ}({value})"),
+            Rvalue::Alloc(typ, count) => write!(f, "alloc::<{typ}>({count})"),
+            Rvalue::Cast(value, typ) => write!(f, "{value} as {typ}"),
+        }
+    }
+}
+
+impl Display for Callee {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Callee::Function(name) => write!(f, "{name}"),
+            Callee::Pointer(local) => write!(f, "(*{local})"),
+        }
+    }
+}
+
+impl Display for Statement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Statement::Assign(local, rvalue) => write!(f, "{local} = {rvalue};"),
+            Statement::Call(destination, callee, args) => {
+                if let Some(destination) = destination {
+                    write!(f, "{destination} = ")?;
+                }
+
+                let args: Vec<_> = args.iter().map(ToString::to_string).collect();
+                write!(f, "{callee}({});", args.join(", "))
+            }
+            Statement::Free(pointer) => write!(f, "free({pointer});"),
+            Statement::Asm(template) => write!(f, "asm!({template:?});"),
+        }
+    }
+}
+
+impl Display for Terminator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Terminator::Goto(block) => write!(f, "goto {block};"),
+            Terminator::Switch(value, cases, default) => {
+                write!(f, "switch {value} [")?;
+
+                for (case, block) in cases {
+                    write!(f, "{case} => {block}, ")?;
+                }
+
+                write!(f, "_ => {default}];")
+            }
+            Terminator::Return(value) => write!(f, "return {value};"),
+            Terminator::Unreachable => write!(f, "unreachable;"),
+        }
+    }
+}
+
+/// Writes the name of a local, along with the variable it was declared as, if any.
+fn write_local(f: &mut Formatter<'_>, local: Local, name: Option<Symbol>) -> Result {
+    match name {
+        Some(name) => write!(f, "{local} /* {name} */"),
+        None => write!(f, "{local}"),
+    }
+}
+
+impl Display for Body {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "fn {}(", self.name)?;
+
+        for (index, decl) in self.locals[..self.arguments].iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+
+            write_local(f, Local(index), decl.name)?;
+            write!(f, ": {}", decl.typ)?;
+        }
+
+        writeln!(f, ") -> {} {{", self.returns)?;
+
+        for (index, decl) in self.locals.iter().enumerate().skip(self.arguments) {
+            write!(f, "{INDENT}let ")?;
+            write_local(f, Local(index), decl.name)?;
+            writeln!(f, ": {};", decl.typ)?;
+        }
+
+        for (index, block) in self.blocks.iter().enumerate() {
+            // Blocks are set apart from each other, and from the locals when there are any.
+            if index > 0 || self.locals.len() > self.arguments {
+                writeln!(f)?;
+            }
+
+            writeln!(f, "{INDENT}{}: {{", BlockId(index))?;
+
+            for statement in &block.statements {
+                writeln!(f, "{INDENT}{INDENT}{}", statement.node)?;
+            }
+
+            writeln!(f, "{INDENT}{INDENT}{}", block.terminator.node)?;
+            writeln!(f, "{INDENT}}}")?;
+        }
+
+        writeln!(f, "}}")
+    }
+}
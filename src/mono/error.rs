@@ -0,0 +1,10 @@
+use thiserror::Error;
+
+/// Represents any error found while monomorphizing a module.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum MonoError {
+    #[error(
+        "generic function `{name}` needs more than {limit} instances; does it call itself with an ever larger type?"
+    )]
+    TooManyInstances { name: String, limit: usize },
+}
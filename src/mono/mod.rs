@@ -0,0 +1,419 @@
+pub mod error;
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::{
+    mono::error::MonoError,
+    parser::Spanned,
+    sema::{Globals, scope::Scopes, ty::Ty, type_of, unify},
+    spec::ast::*,
+};
+
+/// The most instances a module may need of generic functions, past which a generic function is
+/// assumed to instantiate itself forever, such as `fn f<T>(x: T) -> u32 { return f(&x); }`.
+pub const MAX_INSTANCES: usize = 1024;
+
+/// Returns the name of the instance of a generic function for the given types, such as `id$u32`.
+/// `$` can not be part of an identifier, so it can never clash with a function of the program.
+pub fn instance_name(name: &str, types: &[Type]) -> Symbol {
+    let types: Vec<_> = types.iter().map(ToString::to_string).collect();
+
+    Symbol::from(format!("{name}${}", types.join("$")))
+}
+
+/// Returns whether a function is an instance of a generic function, see [`instance_name`].
+pub fn is_instance(name: &str) -> bool {
+    name.contains('$')
+}
+
+/// Rewrites a module that sema has accepted so that it no longer has generic functions, which no
+/// backend knows about. Every call to a generic function calls an instance of it instead, in
+/// which its type parameters are replaced with the types inferred for the call. Instances are
+/// private items of the module, named by [`instance_name`], and are generated once per module
+/// however many calls share them, including for generic functions declared in imported modules.
+/// Generic functions themselves are dropped.
+pub fn monomorphize(module: &Module, imports: &[Module]) -> Result<Module, MonoError> {
+    let imports: Vec<&Module> = imports.iter().collect();
+    let globals = Globals::collect_with_imports(module, &imports);
+    let mut mono = Mono {
+        globals: &globals,
+        instances: HashSet::new(),
+        queue: VecDeque::new(),
+    };
+
+    let mut items = vec![];
+
+    for item in module.1.iter().filter(|item| item.generics().is_empty()) {
+        let node = match &item.node {
+            Item::FunctionDeclaration(name, _, args, returns, body, attributes, visibility) => mono
+                .function(*name, args, returns, body, &HashMap::new())?
+                .with_attributes(attributes.clone())
+                .with_visibility(*visibility),
+            node => node.clone(),
+        };
+
+        items.push(Spanned::new(node, item.span));
+    }
+
+    while let Some((name, item, subst)) = mono.queue.pop_front() {
+        let Item::FunctionDeclaration(_, _, args, returns, body, ..) = &item.node else {
+            continue;
+        };
+
+        let node = mono.function(name, args, returns, body, &subst)?;
+
+        items.push(Spanned::new(node, item.span));
+    }
+
+    Ok(Module(module.0.clone(), items, module.2.clone()))
+}
+
+/// The state of [`monomorphize`]: the instances that have been asked for so far, and those of
+/// them that have yet to be generated, along with the types of their type parameters.
+struct Mono<'g, 'a> {
+    globals: &'g Globals<'a>,
+    instances: HashSet<Symbol>,
+    queue: VecDeque<(Symbol, &'a Spanned<Item>, HashMap<Symbol, Type>)>,
+}
+
+impl<'a> Mono<'_, 'a> {
+    /// Generates a private function with the type parameters in its signature and body replaced
+    /// by `subst`, and every call in its body rewritten to call instances.
+    fn function(
+        &mut self,
+        name: Symbol,
+        args: &[(Symbol, Spanned<Type>)],
+        returns: &Spanned<Type>,
+        body: &[Spanned<Statement>],
+        subst: &HashMap<Symbol, Type>,
+    ) -> Result<Item, MonoError> {
+        let args: Vec<_> = args
+            .iter()
+            .map(|(arg, typ)| (*arg, substitute(typ, subst)))
+            .collect();
+
+        let mut scopes = Scopes::new();
+
+        for (arg, typ) in &args {
+            if let Ok(ty) = self.globals.tcx.lower(typ) {
+                scopes.insert(arg, ty);
+            }
+        }
+
+        Ok(Item::FunctionDeclaration(
+            name,
+            vec![],
+            args,
+            substitute(returns, subst),
+            self.block(body, &mut scopes, subst)?,
+            vec![],
+            Visibility::Private,
+        ))
+    }
+
+    fn block(
+        &mut self,
+        body: &[Spanned<Statement>],
+        scopes: &mut Scopes,
+        subst: &HashMap<Symbol, Type>,
+    ) -> Result<Vec<Spanned<Statement>>, MonoError> {
+        scopes.push();
+
+        let body = body
+            .iter()
+            .map(|statement| {
+                let node = self.statement(&statement.node, scopes, subst)?;
+                Ok(Spanned::new(node, statement.span))
+            })
+            .collect();
+
+        scopes.pop();
+
+        body
+    }
+
+    fn statement(
+        &mut self,
+        statement: &Statement,
+        scopes: &mut Scopes,
+        subst: &HashMap<Symbol, Type>,
+    ) -> Result<Statement, MonoError> {
+        Ok(match statement {
+            Statement::FunctionCall(name, args) => Statement::FunctionCall(
+                self.callee(*name, args, scopes)?,
+                self.expressions(args, scopes, subst)?,
+            ),
+            Statement::Return(value) => Statement::Return(self.expression(value, scopes, subst)?),
+            Statement::Let(name, typ, value) => {
+                let typ = substitute(typ, subst);
+                let value = self.expression(value, scopes, subst)?;
+
+                if let Ok(ty) = self.globals.tcx.lower(&typ) {
+                    scopes.insert(name, ty);
+                }
+
+                Statement::Let(*name, typ, value)
+            }
+            Statement::Block(body) => Statement::Block(self.block(body, scopes, subst)?),
+            Statement::For(name, start, end, body) => {
+                let start = self.expression(start, scopes, subst)?;
+                let end = self.expression(end, scopes, subst)?;
+
+                scopes.push();
+                scopes.insert(name, self.globals.tcx.u32());
+                let body = self.block(body, scopes, subst)?;
+                scopes.pop();
+
+                Statement::For(*name, start, end, body)
+            }
+            Statement::Match(value, arms) => {
+                let value = self.expression(value, scopes, subst)?;
+                let mut lowered = vec![];
+
+                for (pattern, body) in arms {
+                    scopes.push();
+                    self.bind(&pattern.node, scopes);
+                    lowered.push((pattern.clone(), self.block(body, scopes, subst)?));
+                    scopes.pop();
+                }
+
+                Statement::Match(value, lowered)
+            }
+            Statement::Free(pointer) => Statement::Free(self.expression(pointer, scopes, subst)?),
+            Statement::Assert(condition, message) => {
+                Statement::Assert(self.expression(condition, scopes, subst)?, message.clone())
+            }
+            Statement::Unreachable | Statement::Asm(_) | Statement::Error(_) => statement.clone(),
+        })
+    }
+
+    /// Binds the fields a pattern binds in the innermost scope, like sema does.
+    fn bind(&self, pattern: &Pattern, scopes: &mut Scopes) {
+        let Pattern::Variant(name, variant, bindings) = pattern else {
+            return;
+        };
+
+        let fields = self
+            .globals
+            .variants(name)
+            .and_then(|variants| variants.iter().find(|(other, _)| other.node == *variant))
+            .map(|(_, fields)| &fields[..])
+            .unwrap_or_default();
+
+        for (binding, field) in bindings.iter().zip(fields) {
+            if let Ok(ty) = self.globals.tcx.lower(field) {
+                scopes.insert(binding, ty);
+            }
+        }
+    }
+
+    fn expressions(
+        &mut self,
+        expressions: &[Spanned<Expression>],
+        scopes: &Scopes,
+        subst: &HashMap<Symbol, Type>,
+    ) -> Result<Vec<Spanned<Expression>>, MonoError> {
+        expressions
+            .iter()
+            .map(|expression| self.expression(expression, scopes, subst))
+            .collect()
+    }
+
+    fn expression(
+        &mut self,
+        expression: &Spanned<Expression>,
+        scopes: &Scopes,
+        subst: &HashMap<Symbol, Type>,
+    ) -> Result<Spanned<Expression>, MonoError> {
+        let node = match &expression.node {
+            Expression::Call(name, args) => Expression::Call(
+                self.callee(*name, args, scopes)?,
+                self.expressions(args, scopes, subst)?,
+            ),
+            Expression::Index(name, index) => {
+                Expression::Index(*name, Box::new(self.expression(index, scopes, subst)?))
+            }
+            Expression::Unary(op, operand) => {
+                Expression::Unary(*op, Box::new(self.expression(operand, scopes, subst)?))
+            }
+            Expression::Binary(op, left, right) => Expression::Binary(
+                *op,
+                Box::new(self.expression(left, scopes, subst)?),
+                Box::new(self.expression(right, scopes, subst)?),
+            ),
+            Expression::If(condition, then, otherwise) => Expression::If(
+                Box::new(self.expression(condition, scopes, subst)?),
+                Box::new(self.expression(then, scopes, subst)?),
+                Box::new(self.expression(otherwise, scopes, subst)?),
+            ),
+            // The statements of a block expression bind names for its value alone.
+            Expression::Block(body, value) => {
+                let mut scopes = scopes.clone();
+                scopes.push();
+
+                let body = body
+                    .iter()
+                    .map(|statement| {
+                        let node = self.statement(&statement.node, &mut scopes, subst)?;
+                        Ok(Spanned::new(node, statement.span))
+                    })
+                    .collect::<Result<_, MonoError>>()?;
+
+                Expression::Block(body, Box::new(self.expression(value, &scopes, subst)?))
+            }
+            Expression::Variant(name, variant, fields) => {
+                Expression::Variant(*name, *variant, self.expressions(fields, scopes, subst)?)
+            }
+            Expression::New(typ, value) => Expression::New(
+                substitute(typ, subst),
+                Box::new(self.expression(value, scopes, subst)?),
+            ),
+            Expression::Alloc(typ, count) => Expression::Alloc(
+                substitute(typ, subst),
+                Box::new(self.expression(count, scopes, subst)?),
+            ),
+            Expression::Cast(value, typ) => Expression::Cast(
+                Box::new(self.expression(value, scopes, subst)?),
+                substitute(typ, subst),
+            ),
+            Expression::StringLiteral(_)
+            | Expression::NumericLiteral(_)
+            | Expression::CharLiteral(_)
+            | Expression::Variable(_) => expression.node.clone(),
+        };
+
+        Ok(Spanned::new(node, expression.span))
+    }
+
+    /// Returns the function a call should call: the function it names, or, when that is generic,
+    /// the instance of it for the types inferred from the arguments, which is queued to be
+    /// generated if it has not been already.
+    fn callee(
+        &mut self,
+        name: Symbol,
+        args: &[Spanned<Expression>],
+        scopes: &Scopes,
+    ) -> Result<Symbol, MonoError> {
+        // Calls through function pointers are left alone, as generic functions have none.
+        let Some(item) = self
+            .globals
+            .functions
+            .get(&name[..])
+            .copied()
+            .filter(|item| !item.generics().is_empty() && scopes.get(&name).is_none())
+        else {
+            return Ok(name);
+        };
+
+        let tcx = &self.globals.tcx;
+        let mut inferred: HashMap<Symbol, Option<Ty>> = item
+            .generics()
+            .iter()
+            .map(|generic| (*generic, None))
+            .collect();
+
+        for ((_, typ), arg) in item.arguments().iter().zip(args) {
+            if let Some(ty) = type_of(arg, self.globals, scopes, &mut vec![]) {
+                unify(tcx, typ, ty, &mut inferred);
+            }
+        }
+
+        // Sema has checked that every type parameter is inferred by the arguments of the call.
+        let Some(types) = item
+            .generics()
+            .iter()
+            .map(|generic| inferred[generic].map(|ty| tcx.to_type(ty)))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return Ok(name);
+        };
+
+        let instance = instance_name(&name, &types);
+
+        if self.instances.insert(instance) {
+            if self.instances.len() > MAX_INSTANCES {
+                return Err(MonoError::TooManyInstances {
+                    name: name.to_string(),
+                    limit: MAX_INSTANCES,
+                });
+            }
+
+            let subst = item.generics().iter().copied().zip(types).collect();
+            self.queue.push_back((instance, item, subst));
+        }
+
+        Ok(instance)
+    }
+}
+
+/// Replaces the type parameters in a type written in the source with the types given for them.
+fn substitute(typ: &Spanned<Type>, subst: &HashMap<Symbol, Type>) -> Spanned<Type> {
+    fn go(typ: &Type, subst: &HashMap<Symbol, Type>) -> Type {
+        match typ {
+            Type::Atomic(name) => subst.get(name).cloned().unwrap_or_else(|| typ.clone()),
+            Type::Pointer(pointee) => Type::Pointer(Box::new(go(pointee, subst))),
+            Type::Array(element, length) => Type::Array(Box::new(go(element, subst)), *length),
+            Type::Function(arguments, returns) => Type::Function(
+                arguments
+                    .iter()
+                    .map(|argument| go(argument, subst))
+                    .collect(),
+                Box::new(go(returns, subst)),
+            ),
+            Type::Never => Type::Never,
+        }
+    }
+
+    Spanned::new(go(&typ.node, subst), typ.span)
+}
+
+#[test]
+fn generic_functions_are_instantiated_once_per_type() {
+    let (module, _) = crate::spec::module("main".to_string())
+        .parse(
+            "fn id<T>(x: T) -> T { let y: T = x; return y; };
+            fn main() -> u32 { let c: char = id('a'); let p: *char = id(&c); return id(id(1)); };",
+        )
+        .unwrap();
+
+    let mono = monomorphize(&module, &[]).unwrap();
+    let items: Vec<_> = mono.1.iter().map(ToString::to_string).collect();
+
+    assert_eq!(
+        items,
+        vec![
+            "fn main() -> u32 {
+    let c: char = id$char('a');
+    let p: *char = id$*char(&c);
+    return id$u32(id$u32(1));
+};",
+            "fn id$char(x: char) -> char {
+    let y: char = x;
+    return y;
+};",
+            "fn id$*char(x: *char) -> *char {
+    let y: *char = x;
+    return y;
+};",
+            "fn id$u32(x: u32) -> u32 {
+    let y: u32 = x;
+    return y;
+};",
+        ]
+    );
+}
+
+#[test]
+fn runaway_instantiation_is_reported() {
+    let (module, _) = crate::spec::module("main".to_string())
+        .parse("fn f<T>(x: T) -> u32 { return f(&x); }; fn main() -> u32 { return f(1); };")
+        .unwrap();
+
+    assert_eq!(
+        monomorphize(&module, &[]).unwrap_err(),
+        MonoError::TooManyInstances {
+            name: "f".to_string(),
+            limit: MAX_INSTANCES,
+        }
+    );
+}
@@ -0,0 +1,284 @@
+use crate::{interp::binary, parser::Spanned, spec::ast::*};
+
+/// Rewrites a module that sema has accepted into a simpler one that does the same thing:
+///
+/// - operators applied to integer literals are replaced with their result, computed like the
+///   interpreter does, except for shifts by the width of `u32` or more, which backends disagree on,
+/// - a `match` over a literal is replaced with the block of the arm that matches it, or removed
+///   when none does, which is the closest pal has to an `if` over a constant,
+/// - a `for` over an empty range of literals is removed,
+/// - an `if` over a literal is replaced with the branch it takes,
+/// - statements that follow a `return` or `unreachable()` in the same block are removed, as they
+///   can never run.
+///
+/// Character literals are only folded by comparisons, as the other operators give a `char` that
+/// may not be representable as a literal.
+pub fn optimize(module: &Module) -> Module {
+    let items = module
+        .1
+        .iter()
+        .map(|item| {
+            let node = match &item.node {
+                Item::FunctionDeclaration(
+                    name,
+                    generics,
+                    args,
+                    returns,
+                    body,
+                    attributes,
+                    visibility,
+                ) => Item::FunctionDeclaration(
+                    *name,
+                    generics.clone(),
+                    args.clone(),
+                    returns.clone(),
+                    optimize_block(body),
+                    attributes.clone(),
+                    *visibility,
+                ),
+                node => node.clone(),
+            };
+
+            Spanned::new(node, item.span)
+        })
+        .collect();
+
+    Module(module.0.clone(), items, module.2.clone())
+}
+
+fn optimize_block(body: &[Spanned<Statement>]) -> Vec<Spanned<Statement>> {
+    let mut optimized = vec![];
+
+    for statement in body {
+        let Some(node) = optimize_statement(&statement.node) else {
+            continue;
+        };
+        let ends = matches!(node, Statement::Return(_) | Statement::Unreachable);
+
+        optimized.push(Spanned::new(node, statement.span));
+
+        if ends {
+            break;
+        }
+    }
+
+    optimized
+}
+
+/// Optimizes a statement, returning `None` when it does nothing.
+fn optimize_statement(statement: &Statement) -> Option<Statement> {
+    let node = match statement {
+        Statement::FunctionCall(name, args) => {
+            Statement::FunctionCall(*name, args.iter().map(optimize_expression).collect())
+        }
+        Statement::Return(value) => Statement::Return(optimize_expression(value)),
+        Statement::Let(name, typ, value) => {
+            Statement::Let(*name, typ.clone(), optimize_expression(value))
+        }
+        Statement::Block(body) => Statement::Block(optimize_block(body)),
+        Statement::For(name, start, end, body) => {
+            let start = optimize_expression(start);
+            let end = optimize_expression(end);
+
+            if let (Expression::NumericLiteral(first), Expression::NumericLiteral(last)) =
+                (&start.node, &end.node)
+                && *first as u32 >= *last as u32
+            {
+                return None;
+            }
+
+            Statement::For(*name, start, end, optimize_block(body))
+        }
+        Statement::Match(value, arms) => {
+            let value = optimize_expression(value);
+
+            // Only literals are known, and they have no effects to keep when no arm matches.
+            if matches!(
+                value.node,
+                Expression::NumericLiteral(_) | Expression::CharLiteral(_)
+            ) {
+                let (_, body) = arms
+                    .iter()
+                    .find(|(pattern, _)| matches_literal(&pattern.node, &value.node))?;
+
+                return Some(Statement::Block(optimize_block(body)));
+            }
+
+            let arms = arms
+                .iter()
+                .map(|(pattern, body)| (pattern.clone(), optimize_block(body)))
+                .collect();
+
+            Statement::Match(value, arms)
+        }
+        Statement::Free(pointer) => Statement::Free(optimize_expression(pointer)),
+        Statement::Assert(condition, message) => {
+            let condition = optimize_expression(condition);
+
+            // An assertion of a literal other than 0 always holds.
+            if matches!(condition.node, Expression::NumericLiteral(value) if value as u32 != 0) {
+                return None;
+            }
+
+            Statement::Assert(condition, message.clone())
+        }
+        Statement::Unreachable | Statement::Asm(_) | Statement::Error(_) => statement.clone(),
+    };
+
+    Some(node)
+}
+
+/// Returns whether a pattern matches a literal. Numbers are compared as the `u32` they are.
+fn matches_literal(pattern: &Pattern, literal: &Expression) -> bool {
+    match (pattern, literal) {
+        (Pattern::Wildcard, _) => true,
+        (Pattern::Number(number), Expression::NumericLiteral(value)) => {
+            *number as u32 == *value as u32
+        }
+        (Pattern::Char(char), Expression::CharLiteral(value)) => char == value,
+        _ => false,
+    }
+}
+
+fn optimize_expression(expression: &Spanned<Expression>) -> Spanned<Expression> {
+    let optimized = match &expression.node {
+        Expression::Call(name, args) => {
+            Expression::Call(*name, args.iter().map(optimize_expression).collect())
+        }
+        Expression::Index(name, index) => {
+            Expression::Index(*name, Box::new(optimize_expression(index)))
+        }
+        Expression::Variant(name, variant, fields) => Expression::Variant(
+            *name,
+            *variant,
+            fields.iter().map(optimize_expression).collect(),
+        ),
+        Expression::New(typ, value) => {
+            Expression::New(typ.clone(), Box::new(optimize_expression(value)))
+        }
+        Expression::Alloc(typ, count) => {
+            Expression::Alloc(typ.clone(), Box::new(optimize_expression(count)))
+        }
+        // Literals are `u32`s, so a conversion of one is kept rather than folded into another.
+        Expression::Cast(value, typ) => {
+            Expression::Cast(Box::new(optimize_expression(value)), typ.clone())
+        }
+        Expression::Unary(op, operand) => {
+            let operand = optimize_expression(operand);
+
+            match (op, &operand.node) {
+                (UnaryOp::Neg, Expression::NumericLiteral(value)) => {
+                    Expression::NumericLiteral((*value as u32).wrapping_neg() as u64)
+                }
+                (UnaryOp::Not, Expression::NumericLiteral(value)) => {
+                    Expression::NumericLiteral((*value as u32 == 0) as u64)
+                }
+                (UnaryOp::BitNot, Expression::NumericLiteral(value)) => {
+                    Expression::NumericLiteral(!(*value as u32) as u64)
+                }
+                _ => Expression::Unary(*op, Box::new(operand)),
+            }
+        }
+        Expression::Binary(op, left, right) => {
+            let left = optimize_expression(left);
+            let right = optimize_expression(right);
+
+            match (op, &left.node, &right.node) {
+                (BinaryOp::Shl | BinaryOp::Shr, _, Expression::NumericLiteral(by))
+                    if *by as u32 >= u32::BITS =>
+                {
+                    Expression::Binary(*op, Box::new(left), Box::new(right))
+                }
+                (_, Expression::NumericLiteral(a), Expression::NumericLiteral(b)) => {
+                    Expression::NumericLiteral(binary(*op, *a as u32, *b as u32) as u64)
+                }
+                (BinaryOp::Eq, Expression::CharLiteral(a), Expression::CharLiteral(b)) => {
+                    Expression::NumericLiteral((a == b) as u64)
+                }
+                (BinaryOp::Ne, Expression::CharLiteral(a), Expression::CharLiteral(b)) => {
+                    Expression::NumericLiteral((a != b) as u64)
+                }
+                _ => Expression::Binary(*op, Box::new(left), Box::new(right)),
+            }
+        }
+        // Only the branch that a literal condition takes is kept.
+        Expression::If(condition, then, otherwise) => {
+            let condition = optimize_expression(condition);
+
+            match condition.node {
+                Expression::NumericLiteral(value) if value as u32 != 0 => {
+                    return optimize_expression(then);
+                }
+                Expression::NumericLiteral(_) => return optimize_expression(otherwise),
+                _ => Expression::If(
+                    Box::new(condition),
+                    Box::new(optimize_expression(then)),
+                    Box::new(optimize_expression(otherwise)),
+                ),
+            }
+        }
+        // A block left with no statements is only its value.
+        Expression::Block(body, value) => {
+            let body = optimize_block(body);
+
+            match body.is_empty() {
+                true => return optimize_expression(value),
+                false => Expression::Block(body, Box::new(optimize_expression(value))),
+            }
+        }
+        Expression::StringLiteral(_)
+        | Expression::NumericLiteral(_)
+        | Expression::CharLiteral(_)
+        | Expression::Variable(_) => expression.node.clone(),
+    };
+
+    Spanned::new(optimized, expression.span)
+}
+
+#[test]
+fn constants_are_folded() {
+    let (module, _) = crate::spec::module("main".to_string())
+        .parse("fn main(x: u32) -> u32 { let y: u32 = (~0 ^ (1 << 4)) == x; return -(2 | 1) & 1 << 32; };")
+        .unwrap();
+
+    assert_eq!(
+        optimize(&module).to_string(),
+        "fn main(x: u32) -> u32 {
+    let y: u32 = 4294967279 == x;
+    return 4294967293 & (1 << 32);
+};
+"
+    );
+}
+
+#[test]
+fn dead_code_is_removed() {
+    let (module, _) = crate::spec::module("main".to_string())
+        .parse(
+            "fn main(x: u32) -> u32 {
+                for i in 3..3 { exit(i); }
+                match 1 ^ 3 { 1 => { exit(1); } 2 => { return 2; exit(3); } _ => { exit(4); } }
+                match 'a' { 'b' => { exit(5); } }
+                match x { 0 => { return 0; unreachable(); } }
+                return x;
+                exit(6);
+            };",
+        )
+        .unwrap();
+
+    assert_eq!(
+        optimize(&module).to_string(),
+        "fn main(x: u32) -> u32 {
+    {
+        return 2;
+    }
+    match x {
+        0 => {
+            return 0;
+        }
+    }
+    return x;
+};
+"
+    );
+}
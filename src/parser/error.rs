@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+use crate::parser::Span;
+
 /// An error type that describes any possible parsing error.
 #[derive(Error, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum ParseError {
@@ -10,4 +12,120 @@ pub enum ParseError {
         expected: Option<char>,
         found: Option<char>,
     },
+    #[error("expected {}, found {}", join_labels(.expected), describe_found(.found))]
+    Expected {
+        expected: Vec<String>,
+        found: Option<char>,
+        span: Span,
+    },
+    #[error("invalid digit {digit:?} in base {radix} literal")]
+    InvalidDigit { digit: char, radix: u32, span: Span },
+    #[error("numeric literal does not fit in 64 bits")]
+    NumberOverflow { span: Span },
+    #[error("unknown character escape {escape:?}")]
+    InvalidEscape { escape: char, span: Span },
+    #[error("character literal {found:?} is not an ASCII character")]
+    NonAsciiCharacter { found: char, span: Span },
+    #[error("nesting is too deep; at most {limit} levels are allowed")]
+    TooDeep { limit: usize, span: Span },
+}
+
+impl ParseError {
+    /// Returns the code that identifies the error in diagnostics. Input that does not match the
+    /// grammar is a `syntax-error`, and malformed literals are told apart by what is wrong with
+    /// them.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseError::Unit
+            | ParseError::CharacterMismatch { .. }
+            | ParseError::Expected { .. } => "syntax-error",
+            ParseError::InvalidDigit { .. } => "invalid-digit",
+            ParseError::NumberOverflow { .. } => "number-overflow",
+            ParseError::InvalidEscape { .. } => "invalid-escape",
+            ParseError::NonAsciiCharacter { .. } => "non-ascii-character",
+            ParseError::TooDeep { .. } => "too-deep",
+        }
+    }
+
+    /// Returns the part of the source the error points at, if it is known.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            ParseError::Unit | ParseError::CharacterMismatch { .. } => None,
+            ParseError::Expected { span, .. }
+            | ParseError::InvalidDigit { span, .. }
+            | ParseError::NumberOverflow { span }
+            | ParseError::InvalidEscape { span, .. }
+            | ParseError::NonAsciiCharacter { span, .. }
+            | ParseError::TooDeep { span, .. } => Some(*span),
+        }
+    }
+
+    /// Returns whether the error ends parsing, rather than letting alternatives be tried. Input
+    /// that nests too deeply would nest as deeply under any other alternative.
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, ParseError::TooDeep { .. })
+    }
+
+    /// Combines the errors of two failed alternatives. Labelled errors win over unlabelled ones,
+    /// the error that got further into the input wins over the other, and labels of errors at
+    /// the same position are merged so that every alternative is reported. Otherwise, the higher
+    /// ordering variant is prioritized, so errors about malformed input win over labels.
+    pub fn merge(self, other: ParseError) -> ParseError {
+        match (self, other) {
+            (
+                ParseError::Expected {
+                    mut expected,
+                    found,
+                    span,
+                },
+                ParseError::Expected {
+                    expected: other_expected,
+                    span: other_span,
+                    ..
+                },
+            ) if span.start == other_span.start => {
+                for label in other_expected {
+                    if !expected.contains(&label) {
+                        expected.push(label);
+                    }
+                }
+
+                ParseError::Expected {
+                    expected,
+                    found,
+                    span,
+                }
+            }
+            (
+                a @ ParseError::Expected { span, .. },
+                b @ ParseError::Expected {
+                    span: other_span, ..
+                },
+            ) => {
+                if span.start > other_span.start {
+                    a
+                } else {
+                    b
+                }
+            }
+            (a, b) => a.max(b),
+        }
+    }
+}
+
+/// Formats a list of labels as `a, b or c`.
+fn join_labels(labels: &[String]) -> String {
+    match labels {
+        [] => "nothing".to_string(),
+        [label] => label.clone(),
+        [init @ .., last] => format!("{} or {}", init.join(", "), last),
+    }
+}
+
+/// Formats the character that was found instead of what was expected.
+fn describe_found(found: &Option<char>) -> String {
+    match found {
+        Some(c) => format!("{c:?}"),
+        None => "end of input".to_string(),
+    }
 }
@@ -1,17 +1,16 @@
-use crate::parser::*;
+use crate::{
+    parser::*,
+    spec::{KEYWORDS, intern::Symbol},
+};
 
 /// Matches exactly one [`char`].
 pub fn char(allowed: char) -> Parser<char> {
-    Parser::new(move |input| {
-        let mut chars = input.chars();
-
-        match chars.next() {
-            Some(c) if c == allowed => Ok((c, chars.collect())),
-            res => Err(ParseError::CharacterMismatch {
-                expected: Some(allowed),
-                found: res,
-            }),
-        }
+    Parser::new(move |input: Input| match input.next_char() {
+        Some(c) if c == allowed => Ok((c, input.advance(c.len_utf8()))),
+        res => Err(ParseError::CharacterMismatch {
+            expected: Some(allowed),
+            found: res,
+        }),
     })
 }
 
@@ -29,6 +28,27 @@ pub fn list(allowed: impl Iterator<Item = char>) -> Parser<char> {
     alt(allowed.map(char))
 }
 
+/// Generates a [`Parser`] that succeeds without consuming any input when the given [`Parser`]
+/// fails, and fails when it succeeds.
+pub fn not<T: 'static>(p: Parser<T>) -> Parser<()> {
+    Parser::new(move |input: Input| match p.parse_input(input.clone()) {
+        Ok(_) => Err(ParseError::Unit),
+        Err(_) => Ok(((), input)),
+    })
+}
+
+/// Generates a [`Parser`] that only succeeds at the end of the input.
+pub fn eof() -> Parser<()> {
+    Parser::new(|input: Input| match input.next_char() {
+        None => Ok(((), input)),
+        found => Err(ParseError::Expected {
+            expected: vec!["end of input".to_string()],
+            found,
+            span: input.next_span(),
+        }),
+    })
+}
+
 /// Generates a [`Parser`] that expects 3 matches in a row, and drops the first and the last.
 pub fn between<T: 'static, I: 'static, O: 'static>(
     a: Parser<T>,
@@ -38,50 +58,129 @@ pub fn between<T: 'static, I: 'static, O: 'static>(
     a.right(b).left(c)
 }
 
+/// Matches exactly one [`char`] for which the predicate holds, in a single step.
+pub fn satisfy(predicate: impl Fn(char) -> bool + 'static) -> Parser<char> {
+    Parser::new(move |input: Input| match input.next_char() {
+        Some(c) if predicate(c) => Ok((c, input.advance(c.len_utf8()))),
+        found => Err(ParseError::CharacterMismatch {
+            expected: None,
+            found,
+        }),
+    })
+}
+
+/// Matches the longest run of [`char`]s for which the predicate holds, in a single step. Always
+/// succeeds, matching an empty [`String`] when the first character does not satisfy the predicate.
+pub fn take_while(predicate: impl Fn(char) -> bool + 'static) -> Parser<String> {
+    Parser::new(move |input: Input| {
+        let len = input
+            .rest()
+            .find(|c| !predicate(c))
+            .unwrap_or(input.rest().len());
+
+        Ok((input.rest()[..len].to_string(), input.advance(len)))
+    })
+}
+
+/// Returns whether the [`char`] is whitespace.
+pub fn is_whitespace(c: char) -> bool {
+    matches!(c, ' ' | '\n' | '\t' | '\r')
+}
+
+/// Returns whether the [`char`] is a letter (or non-alphabetic identifier character).
+pub fn is_letter(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+/// Returns whether the [`char`] is alphanumeric (or a non-alphabetic identifier character).
+pub fn is_alphanum(c: char) -> bool {
+    is_letter(c) || c.is_ascii_digit()
+}
+
 /// Generates a parser for whitespace characters.
 pub fn whitespace() -> Parser<char> {
-    list([' ', '\n', '\t', '\r'].into_iter())
+    satisfy(is_whitespace)
 }
 
 /// Generates a parser that ignores whitespace characters.
 pub fn strip<T: 'static>(p: Parser<T>) -> Parser<T> {
-    between(whitespace().many(), p, whitespace().many())
+    between(take_while(is_whitespace), p, take_while(is_whitespace))
 }
 
 /// Generates a parser that matches on all lowercase alphabetic characters.
 pub fn lowercase() -> Parser<char> {
-    list('a'..='z')
+    satisfy(|c| c.is_ascii_lowercase())
 }
 
 /// Generates a parser that matches on all uppercase alphabetic characters.
 pub fn uppercase() -> Parser<char> {
-    list('A'..='Z')
+    satisfy(|c| c.is_ascii_uppercase())
 }
 
 /// Generates a parser that matches on all non-alphabetic valid identifier characters.
 pub fn other() -> Parser<char> {
-    list(['_'].into_iter())
+    char('_')
 }
 
 /// Generates a parser that matches on any letter (or non-alphabetic identifier character).
 pub fn letter() -> Parser<char> {
-    lowercase().or(uppercase()).or(other())
+    satisfy(is_letter)
 }
 
 /// Generates a parser that matches on all numerical digits.
 pub fn digit() -> Parser<char> {
-    list('0'..='9')
+    satisfy(|c| c.is_ascii_digit())
 }
 
 /// Generates a parser that matches on all alphanumeric characters (or non-alphabetic identifier
 /// characters).
 pub fn alphanum() -> Parser<char> {
-    letter().or(digit())
+    satisfy(is_alphanum)
+}
+
+/// Generates a parser that matches on any possible identifier, interning it straight from the
+/// input. Reserved words from [`crate::spec::KEYWORDS`] are rejected.
+pub fn identifier() -> Parser<Symbol> {
+    strip(
+        Parser::new(move |input: Input| {
+            let rest = input.rest();
+
+            if !rest.starts_with(is_letter) {
+                return Err(ParseError::Unit);
+            }
+
+            let len = rest.find(|c| !is_alphanum(c)).unwrap_or(rest.len());
+            let name = &rest[..len];
+
+            if KEYWORDS.contains(&name) {
+                return Err(ParseError::Unit);
+            }
+
+            Ok((Symbol::intern(name), input.advance(len)))
+        })
+        .label("identifier"),
+    )
 }
 
-/// Generates a parser that matches on any possible identifier.
-pub fn identifier() -> Parser<String> {
-    strip(letter().chain(alphanum().many())).map(|(x, xs)| once(x).chain(xs).collect())
+/// Generates a parser that matches on one exact keyword. Unlike [`symbol`], the keyword must not be
+/// directly followed by another identifier character, so that `returnx` is not read as `return x`.
+/// Ignores whitespace.
+pub fn keyword(word: impl ToString) -> Parser<String> {
+    let word = word.to_string();
+    let label = format!("'{word}'");
+
+    strip(
+        Parser::new(move |input: Input| {
+            let rest = input.rest();
+
+            if !rest.starts_with(&word) || rest[word.len()..].starts_with(is_alphanum) {
+                return Err(ParseError::Unit);
+            }
+
+            Ok((word.clone(), input.advance(word.len())))
+        })
+        .label(label),
+    )
 }
 
 /// Generates a parser that matches on one exact given string-like item. This can be used to parse
@@ -102,7 +201,10 @@ pub fn symbol(input: impl ToString) -> Parser<String> {
         Parser::pure("".to_string())
     }
 
-    strip(symbol_prime(input))
+    let input = input.to_string();
+    let label = format!("'{input}'");
+
+    strip(symbol_prime(input).label(label))
 }
 
 impl Parser<Vec<char>> {
@@ -137,7 +239,7 @@ pub fn alt_parser_parses() {
 pub fn identifiers_parse() {
     assert_eq!(
         identifier().parse("abcdef123 fuck"),
-        Ok(("abcdef123".to_string(), "fuck".to_string()))
+        Ok((Symbol::intern("abcdef123"), "fuck".to_string()))
     );
 
     assert!(identifier().parse("123abc").is_err(),)
@@ -152,3 +254,73 @@ fn symbols_parse() {
 
     assert!(symbol("fn").parse("nf hello").is_err());
 }
+
+#[test]
+fn symbols_report_what_was_expected() {
+    assert_eq!(
+        symbol("fn").parse("  nf").unwrap_err().to_string(),
+        "expected 'fn', found 'n'"
+    );
+}
+
+#[test]
+fn satisfy_parser_parses() {
+    assert_eq!(
+        satisfy(|c| c.is_ascii_digit()).parse("1a"),
+        Ok(('1', "a".to_string()))
+    );
+
+    assert!(satisfy(|c| c.is_ascii_digit()).parse("a1").is_err());
+}
+
+#[test]
+fn take_while_parser_parses() {
+    assert_eq!(
+        take_while(|c| c.is_ascii_digit()).parse("123abc"),
+        Ok(("123".to_string(), "abc".to_string()))
+    );
+
+    assert_eq!(
+        take_while(|c| c.is_ascii_digit()).parse("abc"),
+        Ok(("".to_string(), "abc".to_string()))
+    );
+}
+
+#[test]
+fn identifiers_reject_keywords() {
+    assert!(identifier().parse("return").is_err());
+
+    assert_eq!(
+        identifier().parse("returned"),
+        Ok(("returned".into(), "".to_string()))
+    );
+}
+
+#[test]
+fn keywords_require_a_boundary() {
+    assert_eq!(
+        keyword("return").parse("return x"),
+        Ok(("return".to_string(), "x".to_string()))
+    );
+
+    assert_eq!(
+        keyword("return").parse("return;"),
+        Ok(("return".to_string(), ";".to_string()))
+    );
+
+    assert!(keyword("return").parse("returnx").is_err());
+}
+
+#[test]
+fn not_parser_inverts() {
+    assert_eq!(not(char('a')).parse("bc"), Ok(((), "bc".to_string())));
+
+    assert!(not(char('a')).parse("abc").is_err());
+}
+
+#[test]
+fn eof_parser_parses() {
+    assert_eq!(eof().parse(""), Ok(((), "".to_string())));
+
+    assert!(eof().parse("a").is_err());
+}
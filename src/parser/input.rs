@@ -0,0 +1,233 @@
+use std::{
+    any::Any,
+    cell::RefCell,
+    collections::HashMap,
+    fmt::{Debug, Display, Formatter},
+    ops::Deref,
+    rc::Rc,
+    sync::Arc,
+};
+
+use crate::parser::error::ParseError;
+
+/// A range of bytes in the source that is being parsed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// Creates a span covering the bytes from `start` up to, but not including, `end`.
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+}
+
+/// A value along with the part of the source it was parsed from. Spans are ignored when comparing
+/// values, so that the same program parsed from differently formatted sources compares equal.
+#[derive(Clone, Debug, Default, Eq)]
+pub struct Spanned<T> {
+    pub node: T,
+    pub span: Span,
+}
+
+impl<T> Spanned<T> {
+    /// Attaches a span to a value.
+    pub fn new(node: T, span: Span) -> Spanned<T> {
+        Spanned { node, span }
+    }
+}
+
+impl<T: PartialEq> PartialEq for Spanned<T> {
+    fn eq(&self, other: &Spanned<T>) -> bool {
+        self.node == other.node
+    }
+}
+
+impl<T> Deref for Spanned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.node
+    }
+}
+
+/// Wraps a value that was not parsed from any source, such as a generated node, with an empty
+/// span.
+impl<T> From<T> for Spanned<T> {
+    fn from(node: T) -> Spanned<T> {
+        Spanned::new(node, Span::default())
+    }
+}
+
+impl<T: Display> Display for Spanned<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.node.fmt(f)
+    }
+}
+
+/// The results of memoized rules, keyed by the name of the rule and the offset it started at. The
+/// table is shared by every [`Input`] advanced from the same one.
+#[derive(Clone, Default)]
+pub struct Memo(Rc<RefCell<MemoTable>>);
+
+/// Type-erased results, as every rule produces values of its own type.
+type MemoTable = HashMap<(&'static str, usize), Rc<dyn Any>>;
+
+impl Memo {
+    /// Returns the result a rule gave when it was last run at the given offset.
+    pub fn get(&self, rule: &'static str, offset: usize) -> Option<Rc<dyn Any>> {
+        self.0.borrow().get(&(rule, offset)).cloned()
+    }
+
+    /// Records the result a rule gave at the given offset.
+    pub fn insert(&self, rule: &'static str, offset: usize, result: Rc<dyn Any>) {
+        self.0.borrow_mut().insert((rule, offset), result);
+    }
+}
+
+impl Debug for Memo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Memo({} results)", self.0.borrow().len())
+    }
+}
+
+/// Memoized results never change what an [`Input`] parses to, so they are ignored when comparing.
+impl PartialEq for Memo {
+    fn eq(&self, _: &Memo) -> bool {
+        true
+    }
+}
+
+impl Eq for Memo {}
+
+/// How deeply rules can be nested by default before parsing fails with
+/// [`crate::parser::error::ParseError::TooDeep`], see [`Input::with_depth_limit`]. This leaves
+/// room to spare in the 2 MiB of stack that threads get by default, even in debug builds, and
+/// allows for around 40 levels of parentheses in an expression, or a chain of around 120
+/// operators.
+pub const DEFAULT_DEPTH_LIMIT: usize = 128;
+
+/// The input consumed by a [`crate::parser::Parser`]: the whole source along with how far into it
+/// parsing has progressed. Cloning an [`Input`] is cheap, as the source is shared.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Input {
+    source: Arc<str>,
+    offset: usize,
+    memo: Option<Memo>,
+    depth: usize,
+    limit: usize,
+    recover: bool,
+}
+
+impl Input {
+    /// Creates an [`Input`] positioned at the start of the given source.
+    pub fn new(source: impl ToString) -> Input {
+        Input {
+            source: source.to_string().into(),
+            offset: 0,
+            memo: None,
+            depth: 0,
+            limit: DEFAULT_DEPTH_LIMIT,
+            recover: false,
+        }
+    }
+
+    /// Returns the [`Input`] with a different limit on how deeply rules can be nested, which is
+    /// [`DEFAULT_DEPTH_LIMIT`] unless changed. Every rule that refers to itself, like a
+    /// parenthesized expression or a pointer type, is parsed by recursing, so a limit keeps deeply
+    /// nested input from overflowing the stack.
+    pub fn with_depth_limit(self, limit: usize) -> Input {
+        Input { limit, ..self }
+    }
+
+    /// Returns how many rules are being parsed inside one another, see
+    /// [`crate::parser::Parser::nested`].
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns how deeply rules can be nested, see [`Input::with_depth_limit`].
+    pub fn depth_limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Returns the [`Input`] at a different nesting depth.
+    pub fn at_depth(self, depth: usize) -> Input {
+        Input { depth, ..self }
+    }
+
+    /// Returns the [`Input`] one level of nesting deeper, failing with
+    /// [`ParseError::TooDeep`] when it is already as deep as its [`Input::depth_limit`].
+    pub fn deeper(self) -> Result<Input, ParseError> {
+        if self.depth >= self.limit {
+            return Err(ParseError::TooDeep {
+                limit: self.limit,
+                span: self.next_span(),
+            });
+        }
+
+        let depth = self.depth + 1;
+
+        Ok(self.at_depth(depth))
+    }
+
+    /// Returns the [`Input`] with recovery from errors turned on or off. Rules that can recover,
+    /// like statements, skip past input they fail to parse when it is on, instead of failing.
+    pub fn with_recovery(self, recover: bool) -> Input {
+        Input { recover, ..self }
+    }
+
+    /// Returns whether rules should recover from errors, see [`Input::with_recovery`].
+    pub fn recovers(&self) -> bool {
+        self.recover
+    }
+
+    /// Creates an [`Input`] like [`Input::new`], along with a memo table that lets rules marked
+    /// with [`crate::parser::Parser::memoized`] reuse their results instead of parsing again.
+    pub fn memoized(source: impl ToString) -> Input {
+        Input {
+            memo: Some(Memo::default()),
+            ..Input::new(source)
+        }
+    }
+
+    /// Returns the memo table, if the input has one.
+    pub fn memo(&self) -> Option<&Memo> {
+        self.memo.as_ref()
+    }
+
+    /// Returns the byte offset into the source that has been reached.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the part of the source that has not been consumed yet.
+    pub fn rest(&self) -> &str {
+        &self.source[self.offset..]
+    }
+
+    /// Returns the next unconsumed character, if there is one.
+    pub fn next_char(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    /// Returns the span of the next unconsumed character. At the end of the input this is an
+    /// empty span.
+    pub fn next_span(&self) -> Span {
+        let len = self.next_char().map_or(0, char::len_utf8);
+
+        Span::new(self.offset, self.offset + len)
+    }
+
+    /// Returns a new [`Input`] with the given amount of bytes consumed.
+    pub fn advance(&self, len: usize) -> Input {
+        Input {
+            source: self.source.clone(),
+            offset: self.offset + len,
+            memo: self.memo.clone(),
+            ..*self
+        }
+    }
+}
@@ -1,40 +1,84 @@
 pub mod error;
 pub mod generators;
+pub mod input;
+pub mod trace;
 
 pub use generators::*;
-use std::{iter::once, sync::Arc};
+pub use input::{Input, Span, Spanned};
+use std::{iter::once, rc::Rc, sync::Arc};
 
 use error::ParseError;
 
+/// The result of running a parser: either a value and the rest of the input, or a parsing error.
+pub type ParseResult<T> = Result<(T, Input), ParseError>;
+
 /// A generic parser for pal.
 #[derive(Clone)]
 pub struct Parser<T> {
-    parser: Arc<dyn Fn(String) -> Result<(T, String), ParseError>>,
+    parser: Arc<dyn Fn(Input) -> ParseResult<T>>,
 }
 
 impl<T: 'static> Parser<T> {
-    /// Creates a new parser from a given function, which parses a given [`String`] and returns
+    /// Creates a new parser from a given function, which parses a given [`Input`] and returns
     /// either a result and the rest of the input, or a parsing error.
-    pub fn new(parser: impl Fn(String) -> Result<(T, String), ParseError> + 'static) -> Parser<T> {
+    pub fn new(parser: impl Fn(Input) -> ParseResult<T> + 'static) -> Parser<T> {
         Parser {
             parser: Arc::new(parser),
         }
     }
 
     /// Makes the parser that is moved into the closure lazily evaulated, meaning it only gets
-    /// initialized when you attempt to parse.
+    /// initialized when you attempt to parse. Rules can only refer to themselves through a lazy
+    /// [`Parser`], so every one is [`Parser::nested`].
     pub fn lazy(producer: impl Fn() -> Parser<T> + 'static) -> Parser<T> {
-        Parser::new(move |input| producer().parse(input))
+        Parser::new(move |input| producer().parse_input(input)).nested()
+    }
+
+    /// Counts the [`Parser`] as one level of nesting. Parsing fails with [`ParseError::TooDeep`]
+    /// instead of running it once as many levels as the [`Input::depth_limit`] are already being
+    /// parsed, before the stack can overflow.
+    pub fn nested(self) -> Parser<T> {
+        Parser::new(move |input: Input| {
+            let depth = input.depth();
+
+            self.parse_input(input.deeper()?)
+                .map(|(result, rest)| (result, rest.at_depth(depth)))
+        })
+    }
+
+    /// Parses with a different limit on how deeply rules can be nested, see
+    /// [`Input::with_depth_limit`]. The limit applies to the [`Parser`] alone.
+    pub fn depth_limit(self, limit: usize) -> Parser<T> {
+        Parser::new(move |input: Input| {
+            let outer = input.depth_limit();
+
+            self.parse_input(input.with_depth_limit(limit))
+                .map(|(result, rest)| (result, rest.with_depth_limit(outer)))
+        })
+    }
+
+    /// Parses with recovery from errors turned on, see [`Input::with_recovery`]. Recovery applies
+    /// to the [`Parser`] alone.
+    pub fn with_recovery(self) -> Parser<T> {
+        Parser::new(move |input: Input| {
+            let outer = input.recovers();
+
+            self.parse_input(input.with_recovery(true))
+                .map(|(result, rest)| (result, rest.with_recovery(outer)))
+        })
     }
 
     // Functor
     /// Maps a [`Parser<T>`] to a [`Parser<O>`] with a function f such that `fn(T) -> O`.
     pub fn map<O: 'static>(self, f: impl Fn(T) -> O + 'static) -> Parser<O> {
-        Parser::new(move |input| self.parse(input).map(|(result, input)| (f(result), input)))
+        Parser::new(move |input| {
+            self.parse_input(input)
+                .map(|(result, input)| (f(result), input))
+        })
     }
 
     // Applicative
-    /// Returns a [`Parser<T>`] that always returns `Ok((T, String))`.
+    /// Returns a [`Parser<T>`] that always returns `Ok((T, Input))`.
     pub fn pure(value: T) -> Parser<T>
     where
         T: Clone,
@@ -46,9 +90,9 @@ impl<T: 'static> Parser<T> {
     /// [`Parser<T>`].
     pub fn chain<O: 'static>(self, other: Parser<O>) -> Parser<(T, O)> {
         Parser::new(move |input| {
-            self.parse(input).and_then(|(result_a, input)| {
+            self.parse_input(input).and_then(|(result_a, input)| {
                 other
-                    .parse(input)
+                    .parse_input(input)
                     .map(|(result_b, input)| ((result_a, result_b), input))
             })
         })
@@ -71,21 +115,109 @@ impl<T: 'static> Parser<T> {
     }
 
     /// Creates a [`Parser`] that attempts the given [`Parser`] when the calling [`Parser`] fails.
-    /// Errors are ordered and higher ordering variants are prioritized.
-    /// The choice is as follows:
+    /// When both fail, the errors are combined with [`ParseError::merge`]:
     /// ```rs
-    /// parse_error_a.max(parse_error_b)
+    /// parse_error_a.merge(parse_error_b)
     /// ```
+    /// A [fatal](ParseError::is_fatal) error is returned without trying the other [`Parser`].
     pub fn or(self, other: Parser<T>) -> Parser<T> {
         Parser::new(move |input| {
-            self.parse(input.clone()).or_else(|parse_error_a| {
-                other
-                    .parse(input)
-                    .map_err(|parse_error_b| parse_error_a.max(parse_error_b))
+            self.parse_input(input.clone()).or_else(|parse_error_a| {
+                match parse_error_a.is_fatal() {
+                    true => Err(parse_error_a),
+                    false => other
+                        .parse_input(input)
+                        .map_err(|parse_error_b| parse_error_a.merge(parse_error_b)),
+                }
             })
         })
     }
 
+    /// Names what the [`Parser`] is expected to match, for use in error messages. When the
+    /// [`Parser`] fails without getting past the first character, its error is replaced with a
+    /// [`ParseError::Expected`] carrying the label, so that alternatives report e.g. "expected
+    /// expression or '}'" rather than a character mismatch deep inside one of them. Errors about
+    /// malformed input, like invalid digits, are always kept.
+    pub fn label(self, label: impl ToString) -> Parser<T> {
+        let label = label.to_string();
+
+        Parser::new(move |input: Input| {
+            self.parse_input(input.clone())
+                .map_err(|parse_error| match parse_error {
+                    ParseError::Expected { span, .. } if span.start > input.offset() => parse_error,
+                    ParseError::Unit
+                    | ParseError::CharacterMismatch { .. }
+                    | ParseError::Expected { .. } => ParseError::Expected {
+                        expected: vec![label.clone()],
+                        found: input.next_char(),
+                        span: input.next_span(),
+                    },
+                    _ => parse_error,
+                })
+        })
+    }
+
+    /// Names the grammar rule the [`Parser`] matches. While [`trace`] is on, every attempt to
+    /// parse the rule is logged, along with what it consumed or why it failed.
+    pub fn named(self, rule: impl ToString) -> Parser<T> {
+        let rule = rule.to_string();
+
+        Parser::new(move |input: Input| {
+            if !trace::is_tracing() {
+                return self.parse_input(input);
+            }
+
+            trace::enter(&rule, &input);
+            let result = self.parse_input(input.clone());
+            trace::exit(&rule, &input, result.as_ref().map(|(_, rest)| rest));
+
+            result
+        })
+    }
+
+    /// Memoizes the [`Parser`] under the name of the grammar rule it matches. When the input has a
+    /// memo table (see [`Input::memoized`]), the result of running the rule at a position is
+    /// recorded, and trying the rule at that position again, such as from another alternative,
+    /// returns the recorded result rather than parsing again. Every rule memoized under the same
+    /// name must parse the same way.
+    pub fn memoized(self, rule: &'static str) -> Parser<T>
+    where
+        T: Clone,
+    {
+        Parser::new(move |input: Input| {
+            let Some(memo) = input.memo().cloned() else {
+                return self.parse_input(input);
+            };
+
+            let offset = input.offset();
+            let recorded = memo.get(rule, offset);
+
+            // Results are recorded with the offset the rule stopped at, which is turned back into
+            // an input by advancing the current one.
+            let result = match recorded
+                .as_ref()
+                .and_then(|recorded| recorded.downcast_ref::<Result<(T, usize), ParseError>>())
+            {
+                Some(result) => result.clone(),
+                None => {
+                    let result = self
+                        .parse_input(input.clone())
+                        .map(|(result, rest)| (result, rest.offset()));
+
+                    // Whether input nests too deeply depends on how deeply the rule was nested
+                    // when it ran, which the memo table does not record.
+                    if !result.as_ref().is_err_and(ParseError::is_fatal) {
+                        memo.insert(rule, offset, Rc::new(result.clone()));
+                    }
+
+                    result
+                }
+            };
+
+            result.map(|(result, end)| (result, input.advance(end - offset)))
+        })
+    }
+
     /// Creates a [`Parser`] that wraps a value in [`Option<T>`]. Returns `Some(T)` when the parser
     /// succeeds, otherwise returns `None`.
     pub fn maybe(self) -> Parser<Option<T>>
@@ -95,15 +227,132 @@ impl<T: 'static> Parser<T> {
         self.map(Some).or(Parser::pure(None))
     }
 
-    /// Creates a [`Parser`] that matches on zero or many possibilities.
-    pub fn many(self) -> Parser<Vec<T>>
-    where
-        T: Clone,
-    {
-        self.clone()
-            .chain(Parser::lazy(move || self.clone().many()))
-            .map(|(x, xs)| once(x).chain(xs).collect())
-            .or(Parser::pure(vec![]))
+    /// Creates a [`Parser`] that matches on zero or many possibilities. Matches are collected in a
+    /// loop rather than by recursing once per match, so long runs cannot overflow the stack. A
+    /// match that consumes no input ends the run, as it would otherwise repeat forever, and a
+    /// [fatal](ParseError::is_fatal) error fails it.
+    pub fn many(self) -> Parser<Vec<T>> {
+        Parser::new(move |mut input: Input| {
+            let mut results = vec![];
+
+            loop {
+                let (result, rest) = match self.parse_input(input.clone()) {
+                    Ok(parsed) => parsed,
+                    Err(parse_error) if parse_error.is_fatal() => return Err(parse_error),
+                    Err(_) => break,
+                };
+                let consumed = rest.offset() > input.offset();
+
+                results.push(result);
+                input = rest;
+
+                if !consumed {
+                    break;
+                }
+            }
+
+            Ok((results, input))
+        })
+    }
+
+    /// Like [`Parser::many`], but every match counts as a level of nesting, see
+    /// [`Parser::nested`], for runs that build a tree as deep as they are long, such as the `as`
+    /// of `x as u8 as u32`.
+    pub fn many_nested(self) -> Parser<Vec<T>> {
+        Parser::new(move |mut input: Input| {
+            let depth = input.depth();
+            let mut results = vec![];
+
+            loop {
+                let parsed = input
+                    .clone()
+                    .deeper()
+                    .and_then(|deeper| self.parse_input(deeper));
+                let (result, rest) = match parsed {
+                    Ok(parsed) => parsed,
+                    Err(parse_error) if parse_error.is_fatal() => return Err(parse_error),
+                    Err(_) => break,
+                };
+                let consumed = rest.offset() > input.offset();
+
+                results.push(result);
+                input = rest;
+
+                if !consumed {
+                    break;
+                }
+            }
+
+            Ok((results, input.at_depth(depth)))
+        })
+    }
+
+    /// Creates a [`Parser`] for one or more operands separated by left-associative operators, such
+    /// as `a - b - c`. Each operator parses to the function that combines the operands on either
+    /// side of it, so this is read as `(a - b) - c`. An operator that is not followed by an operand
+    /// ends the chain, unless the operand fails with a [fatal](ParseError::is_fatal) error.
+    ///
+    /// Every operator nests the result one level deeper, so each one counts as a level of
+    /// nesting, see [`Parser::nested`]. This keeps long chains from building trees so deep that
+    /// dropping them overflows the stack.
+    pub fn chainl1(self, op: Parser<fn(T, T) -> T>) -> Parser<T> {
+        Parser::new(move |input: Input| {
+            let depth = input.depth();
+            let (mut result, mut input) = self.parse_input(input)?;
+
+            while let Ok((combine, rest)) = op.parse_input(input.clone()) {
+                let (right, rest) = match rest.deeper().and_then(|rest| self.parse_input(rest)) {
+                    Ok(parsed) => parsed,
+                    Err(parse_error) if parse_error.is_fatal() => return Err(parse_error),
+                    Err(_) => break,
+                };
+
+                result = combine(result, right);
+                input = rest;
+            }
+
+            Ok((result, input.at_depth(depth)))
+        })
+    }
+
+    /// Like [`Parser::chainl1`], but for right-associative operators: `a ^ b ^ c` is read as
+    /// `a ^ (b ^ c)`. Every operator counts as a level of nesting in the same way.
+    pub fn chainr1(self, op: Parser<fn(T, T) -> T>) -> Parser<T> {
+        Parser::new(move |input: Input| {
+            let depth = input.depth();
+            let (first, mut input) = self.parse_input(input)?;
+            let mut rest = vec![];
+
+            while let Ok((combine, after_op)) = op.parse_input(input.clone()) {
+                let parsed = after_op
+                    .deeper()
+                    .and_then(|after_op| self.parse_input(after_op));
+                let (right, after_right) = match parsed {
+                    Ok(parsed) => parsed,
+                    Err(parse_error) if parse_error.is_fatal() => return Err(parse_error),
+                    Err(_) => break,
+                };
+
+                rest.push((combine, right));
+                input = after_right;
+            }
+
+            // Each operator takes the operand before it and everything after it, so the result
+            // is built up from the last operand, which each operator hands to the one before it.
+            let mut left = first;
+            let mut pending = vec![];
+
+            for (combine, right) in rest {
+                pending.push((combine, std::mem::replace(&mut left, right)));
+            }
+
+            let result = pending
+                .into_iter()
+                .rev()
+                .fold(left, |result, (combine, left)| combine(left, result));
+
+            Ok((result, input.at_depth(depth)))
+        })
     }
 
     /// Creates a [`Parser`] that matches on one or many possibilities.
@@ -117,12 +366,61 @@ impl<T: 'static> Parser<T> {
     {
         self.clone()
             .chain(self.many())
-            .map(|(x, xs)| Some(x).into_iter().chain(xs.into_iter()).collect())
+            .map(|(x, xs)| Some(x).into_iter().chain(xs).collect())
+    }
+
+    /// Creates a [`Parser`] that recovers from failure. When the calling [`Parser`] fails, `skip`
+    /// is run from the same position to consume the offending input, and the error is returned
+    /// as a value so that parsing can carry on. Fails only if `skip` fails as well.
+    pub fn recover(self, skip: Parser<()>) -> Parser<Result<T, ParseError>> {
+        Parser::new(move |input: Input| match self.parse_input(input.clone()) {
+            Ok((result, input)) => Ok((Ok(result), input)),
+            Err(parse_error) => skip
+                .parse_input(input)
+                .map(|(_, input)| (Err(parse_error), input)),
+        })
+    }
+
+    /// Creates a [`Parser`] that matches like the calling [`Parser`], but does not consume any
+    /// input when it succeeds.
+    pub fn peek(self) -> Parser<T> {
+        Parser::new(move |input: Input| {
+            self.parse_input(input.clone())
+                .map(|(result, _)| (result, input))
+        })
+    }
+
+    /// Creates a [`Parser`] that records the span of the input the calling [`Parser`] matched.
+    /// Whitespace around the match is not part of the span.
+    pub fn spanned(self) -> Parser<Spanned<T>> {
+        Parser::new(move |input: Input| {
+            let (result, rest) = self.parse_input(input.clone())?;
+
+            let consumed = &input.rest()[..rest.offset() - input.offset()];
+            let trimmed = consumed.trim_start_matches(is_whitespace);
+            let start = rest.offset() - trimmed.len();
+            let end = start + trimmed.trim_end_matches(is_whitespace).len();
+
+            Ok((Spanned::new(result, Span::new(start, end)), rest))
+        })
+    }
+
+    /// Runs a [`Parser`] on an [`Input`], returning the result and the rest of the input.
+    pub fn parse_input(&self, input: Input) -> ParseResult<T> {
+        (self.parser)(input)
     }
 
     /// Consumes a [`Parser`] with any type that implements [`ToString`] and returns the result.
     pub fn parse(&self, input: impl ToString) -> Result<(T, String), ParseError> {
-        (self.parser)(input.to_string())
+        self.parse_input(Input::new(input))
+            .map(|(result, rest)| (result, rest.rest().to_string()))
+    }
+
+    /// Like [`Parser::parse`], but with a memo table, so that memoized rules are parsed at most
+    /// once at every position.
+    pub fn parse_memoized(&self, input: impl ToString) -> Result<(T, String), ParseError> {
+        self.parse_input(Input::memoized(input))
+            .map(|(result, rest)| (result, rest.rest().to_string()))
     }
 }
 
@@ -197,15 +495,12 @@ fn alternative_maybe_exists() {
 
 #[test]
 fn alternative_many() {
-    let char_a = Parser::new(move |input| {
-        let mut chars = input.chars();
-        match chars.next() {
-            Some('a') => Ok(('a', chars.collect())),
-            found => Err(ParseError::CharacterMismatch {
-                expected: Some('a'),
-                found,
-            }),
-        }
+    let char_a = Parser::new(move |input: Input| match input.next_char() {
+        Some('a') => Ok(('a', input.advance(1))),
+        found => Err(ParseError::CharacterMismatch {
+            expected: Some('a'),
+            found,
+        }),
     });
 
     assert_eq!(
@@ -219,17 +514,78 @@ fn alternative_many() {
     );
 }
 
+#[test]
+fn long_runs_do_not_overflow() {
+    let source = "a".repeat(100_000);
+    let (matched, rest) = generators::char('a').many().parse(&source).unwrap();
+
+    assert_eq!(matched.len(), 100_000);
+    assert_eq!(rest, "");
+    assert_eq!(
+        Parser::pure('a').many().parse("b"),
+        Ok((vec!['a'], "b".to_string()))
+    );
+}
+
+#[cfg(test)]
+fn parens() -> Parser<usize> {
+    Parser::lazy(|| {
+        generators::between(generators::char('('), parens(), generators::char(')'))
+            .map(|depth| depth + 1)
+            .or(Parser::pure(0))
+    })
+}
+
+#[test]
+fn nesting_is_limited() {
+    let nested = |depth: usize| format!("{}{}", "(".repeat(depth), ")".repeat(depth));
+
+    assert_eq!(parens().parse(nested(3)), Ok((3, "".to_string())));
+    assert_eq!(
+        parens().depth_limit(4).parse(nested(3)),
+        Ok((3, "".to_string()))
+    );
+    assert_eq!(
+        parens().depth_limit(3).parse(nested(3)),
+        Err(ParseError::TooDeep {
+            limit: 3,
+            span: Span::new(3, 4)
+        })
+    );
+    assert!(matches!(
+        parens().many().parse(nested(100_000)),
+        Err(ParseError::TooDeep {
+            limit: input::DEFAULT_DEPTH_LIMIT,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn operators_chain_left_and_right() {
+    let digit = generators::digit().map(|digit| digit.to_digit(10).unwrap() as i32);
+    let minus = generators::char('-').map(|_| (|a, b| a - b) as fn(i32, i32) -> i32);
+
+    assert_eq!(
+        digit.clone().chainl1(minus.clone()).parse("8-2-1"),
+        Ok((5, "".to_string()))
+    );
+    assert_eq!(
+        digit.clone().chainr1(minus.clone()).parse("8-2-1"),
+        Ok((7, "".to_string()))
+    );
+    // A trailing operator without an operand is left unparsed.
+    assert_eq!(digit.chainl1(minus).parse("8-2-"), Ok((6, "-".to_string())));
+}
+
 #[test]
 fn alternative_some() {
-    let char_a = Parser::new(move |input| {
-        let mut chars = input.chars();
-        match chars.next() {
-            Some('a') => Ok(('a', chars.collect())),
-            found => Err(ParseError::CharacterMismatch {
-                expected: Some('a'),
-                found,
-            }),
-        }
+    let char_a = Parser::new(move |input: Input| match input.next_char() {
+        Some('a') => Ok(('a', input.advance(1))),
+        found => Err(ParseError::CharacterMismatch {
+            expected: Some('a'),
+            found,
+        }),
     });
 
     assert_eq!(
@@ -245,3 +601,134 @@ fn alternative_some() {
         })
     );
 }
+
+#[test]
+fn labels_replace_shallow_errors() {
+    let p = generators::char('a').label("the letter a");
+
+    assert_eq!(
+        p.parse("b"),
+        Err(ParseError::Expected {
+            expected: vec!["the letter a".to_string()],
+            found: Some('b'),
+            span: Span::new(0, 1),
+        })
+    );
+}
+
+#[test]
+fn labels_keep_deeper_errors() {
+    let inner = generators::char('a').right(generators::char('b').label("b"));
+
+    assert_eq!(
+        inner.label("ab").parse("ac"),
+        Err(ParseError::Expected {
+            expected: vec!["b".to_string()],
+            found: Some('c'),
+            span: Span::new(1, 2),
+        })
+    );
+}
+
+#[test]
+fn alternatives_merge_labels() {
+    let p = generators::char('a')
+        .label("a")
+        .or(generators::char('b').label("b"));
+
+    assert_eq!(
+        p.parse("c").unwrap_err().to_string(),
+        "expected a or b, found 'c'"
+    );
+}
+
+#[test]
+fn recovering_skips_failed_input() {
+    let a = generators::char('a');
+    let skip = generators::char('b').map(|_| ());
+
+    assert_eq!(
+        a.clone().recover(skip.clone()).parse("a"),
+        Ok((Ok('a'), "".to_string()))
+    );
+    assert_eq!(
+        a.clone().recover(skip.clone()).parse("bc"),
+        Ok((
+            Err(ParseError::CharacterMismatch {
+                expected: Some('a'),
+                found: Some('b')
+            }),
+            "c".to_string()
+        ))
+    );
+    assert!(a.recover(skip).parse("c").is_err());
+}
+
+#[test]
+fn spans_exclude_surrounding_whitespace() {
+    let (spanned, _) = generators::symbol("fn").spanned().parse("  fn  x").unwrap();
+
+    assert_eq!(spanned.span, Span::new(2, 4));
+}
+
+#[test]
+fn peeking_does_not_consume() {
+    assert_eq!(
+        generators::char('a').peek().parse("abc"),
+        Ok(('a', "abc".to_string()))
+    );
+
+    assert!(generators::char('a').peek().parse("bc").is_err());
+}
+
+#[test]
+fn named_rules_are_traced() {
+    let digits = generators::digit().some().named("digits");
+    let p = digits.clone().left(generators::char('+')).named("sum");
+
+    trace::start();
+    assert!(p.parse("12+").is_ok());
+    assert!(digits.parse("x").is_err());
+
+    assert_eq!(
+        trace::finish(),
+        vec![
+            "sum @ 0",
+            "  digits @ 0",
+            "  digits ok @ 0..2: \"12\"",
+            "sum ok @ 0..3: \"12+\"",
+            "digits @ 0",
+            "digits failed @ 0: invalid character; expected one of None, found Some('x')",
+        ]
+    );
+    assert!(!trace::is_tracing());
+}
+
+#[test]
+fn memoized_rules_are_parsed_once_per_position() {
+    let runs = Rc::new(std::cell::Cell::new(0));
+    let counted = runs.clone();
+
+    let digits = generators::digit()
+        .some()
+        .map(move |digits| {
+            counted.set(counted.get() + 1);
+            digits
+        })
+        .memoized("digits");
+
+    // Both alternatives start with the same rule.
+    let p = digits
+        .clone()
+        .left(generators::char('+'))
+        .or(digits.left(generators::char('-')));
+
+    assert_eq!(p.parse("12-"), Ok((vec!['1', '2'], "".to_string())));
+    assert_eq!(runs.get(), 2);
+
+    assert_eq!(
+        p.parse_memoized("12-"),
+        Ok((vec!['1', '2'], "".to_string()))
+    );
+    assert_eq!(runs.get(), 3);
+}
@@ -0,0 +1,70 @@
+use std::cell::{Cell, RefCell};
+
+use crate::parser::{Input, error::ParseError};
+
+thread_local! {
+    /// The lines traced so far, or [`None`] when tracing is off.
+    static LINES: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+    /// How many named rules are being parsed right now.
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// The most input to show for what a rule consumed, so that lines stay readable.
+const SHOWN: usize = 24;
+
+/// Starts tracing every named rule parsed on this thread, discarding anything traced before.
+pub fn start() {
+    LINES.with_borrow_mut(|lines| *lines = Some(vec![]));
+    DEPTH.set(0);
+}
+
+/// Stops tracing, returning a line for every time a named rule was entered and left.
+pub fn finish() -> Vec<String> {
+    LINES.with_borrow_mut(Option::take).unwrap_or_default()
+}
+
+/// Returns whether rules are being traced on this thread.
+pub fn is_tracing() -> bool {
+    LINES.with_borrow(Option::is_some)
+}
+
+fn push(line: String) {
+    let indent = "  ".repeat(DEPTH.get());
+
+    LINES.with_borrow_mut(|lines| {
+        if let Some(lines) = lines {
+            lines.push(format!("{indent}{line}"));
+        }
+    });
+}
+
+/// Records that a rule started parsing at the given input.
+pub fn enter(rule: &str, input: &Input) {
+    push(format!("{rule} @ {}", input.offset()));
+    DEPTH.set(DEPTH.get() + 1);
+}
+
+/// Records how a rule that started at `input` ended: either with the rest of the input, or with
+/// an error.
+pub fn exit(rule: &str, input: &Input, result: Result<&Input, &ParseError>) {
+    DEPTH.set(DEPTH.get().saturating_sub(1));
+
+    match result {
+        Ok(rest) => {
+            let consumed = &input.rest()[..rest.offset() - input.offset()];
+            let shown: String = consumed.chars().take(SHOWN).collect();
+            let ellipsis = if shown.len() < consumed.len() {
+                "..."
+            } else {
+                ""
+            };
+
+            push(format!(
+                "{rule} ok @ {}..{}: {shown:?}{ellipsis}",
+                input.offset(),
+                rest.offset()
+            ));
+        }
+        Err(parse_error) => push(format!("{rule} failed @ {}: {parse_error}", input.offset())),
+    }
+}
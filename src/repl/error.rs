@@ -0,0 +1,14 @@
+use thiserror::Error;
+
+use crate::{diagnostic::Diagnostic, interp::error::InterpError, parser::error::ParseError};
+
+/// Represents any error in evaluating a single REPL entry. None of them end the session.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ReplError {
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error("the entry has {} error(s)", .0.len())]
+    Diagnostics(Vec<Diagnostic>),
+    #[error(transparent)]
+    Interp(#[from] InterpError),
+}
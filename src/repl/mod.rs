@@ -0,0 +1,270 @@
+pub mod error;
+
+use std::io::Write;
+
+use crate::{
+    interp::{Interpreter, value::Value},
+    lower::literals::lower_literals,
+    parser::{Parser, Spanned, generators::*},
+    repl::error::ReplError,
+    sema::{Globals, check_module, scope::Scopes, type_of},
+    spec::{ast::*, expression, item, statement},
+};
+
+/// The name of the function every entry is evaluated in. It is not a valid identifier, so it can
+/// never clash with a function the user declares.
+const ENTRY: &str = "<repl>";
+
+/// A single line of input to the REPL.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Entry {
+    Item(Spanned<Item>),
+    Statement(Spanned<Statement>),
+    Expression(Spanned<Expression>),
+}
+
+/// Parses a REPL entry. Items are tried first, then statements, and only then expressions, so
+/// that a call followed by `;` runs as a statement rather than printing its value.
+pub fn entry() -> Parser<Entry> {
+    let item = item().spanned().left(symbol(";").maybe()).map(Entry::Item);
+    let statement = statement().spanned().map(Entry::Statement);
+    let expression = expression()
+        .spanned()
+        .left(symbol(";").maybe())
+        .map(Entry::Expression);
+
+    strip(item)
+        .left(eof())
+        .or(strip(statement).left(eof()))
+        .or(strip(expression).left(eof()))
+}
+
+/// An interactive session. Items declared in earlier entries stay declared, and `let` bindings
+/// keep their values, so that later entries can use them.
+#[derive(Debug, Default)]
+pub struct Repl {
+    items: Vec<Spanned<Item>>,
+    bindings: Vec<(Symbol, Spanned<Type>, Value)>,
+}
+
+impl Repl {
+    /// Creates a session in which nothing has been declared yet.
+    pub fn new() -> Repl {
+        Repl::default()
+    }
+
+    /// Evaluates a single line of input, returning the value of an expression entry. Everything
+    /// the entry prints is written to `out`. An entry that fails leaves the session as it was.
+    pub fn eval(&mut self, line: &str, out: impl Write) -> Result<Option<Value>, ReplError> {
+        let (entry, _) = entry().parse(line)?;
+
+        match entry {
+            Entry::Item(item) => {
+                let mut items = self.items.clone();
+                // Declaring an item again replaces the earlier declaration.
+                items.retain(|declared| declared.name() != item.name());
+                items.push(item);
+
+                let diagnostics = check_module(&Module(ENTRY.to_string(), items.clone(), vec![]));
+
+                if !diagnostics.is_empty() {
+                    return Err(ReplError::Diagnostics(diagnostics));
+                }
+
+                self.items = items;
+
+                Ok(None)
+            }
+            Entry::Statement(Spanned {
+                node: Statement::Let(name, typ, expression),
+                ..
+            }) => {
+                let value = self.run(
+                    vec![Statement::Let(name, typ.clone(), expression.clone()).into()],
+                    Expression::Variable(name).into(),
+                    typ.clone(),
+                    out,
+                )?;
+
+                self.bindings.push((name, typ, value));
+
+                Ok(None)
+            }
+            Entry::Statement(statement) => {
+                self.run(
+                    vec![statement],
+                    Expression::NumericLiteral(0).into(),
+                    Type::Atomic("u32".into()).into(),
+                    out,
+                )?;
+
+                Ok(None)
+            }
+            Entry::Expression(expression) => {
+                let returns = self.type_of(&expression)?;
+
+                self.run(vec![], expression, returns, out).map(Some)
+            }
+        }
+    }
+
+    /// Works out the type of the value of an expression entry, given the items declared and the
+    /// bindings made so far.
+    fn type_of(&self, expression: &Spanned<Expression>) -> Result<Spanned<Type>, ReplError> {
+        let module = Module(ENTRY.to_string(), self.items.clone(), vec![]);
+        let globals = Globals::collect(&module);
+        let mut scopes = Scopes::new();
+        let mut diagnostics = vec![];
+
+        for (name, typ, _) in &self.bindings {
+            if let Some(ty) = globals.lower_value_type(typ, &mut diagnostics) {
+                scopes.insert(name, ty);
+            }
+        }
+
+        let ty = type_of(expression, &globals, &scopes, &mut diagnostics);
+
+        match ty {
+            Some(ty) if diagnostics.is_empty() => Ok(globals.tcx.to_type(ty).into()),
+            _ => Err(ReplError::Diagnostics(diagnostics)),
+        }
+    }
+
+    /// Checks and runs `body` followed by a return of `result`, which has the type `returns`,
+    /// inside of a function in which every binding made so far is declared again with its value.
+    fn run(
+        &self,
+        body: Vec<Spanned<Statement>>,
+        result: Spanned<Expression>,
+        returns: Spanned<Type>,
+        out: impl Write,
+    ) -> Result<Value, ReplError> {
+        let mut statements: Vec<Spanned<Statement>> = self
+            .bindings
+            .iter()
+            .map(|(name, typ, value)| {
+                Statement::Let(*name, typ.clone(), literal(value).into()).into()
+            })
+            .collect();
+
+        statements.extend(body);
+        statements.push(Statement::Return(result).into());
+
+        let mut items = self.items.clone();
+        items.push(
+            Item::FunctionDeclaration(
+                ENTRY.into(),
+                vec![],
+                vec![],
+                returns,
+                statements,
+                vec![],
+                Visibility::Private,
+            )
+            .into(),
+        );
+
+        let module = Module(ENTRY.to_string(), items, vec![]);
+        let diagnostics = check_module(&module);
+
+        if !diagnostics.is_empty() {
+            return Err(ReplError::Diagnostics(diagnostics));
+        }
+
+        let module = lower_literals(&module, &[]);
+
+        Ok(Interpreter::new(&module, out).call(ENTRY, vec![])?)
+    }
+}
+
+/// Returns the literal that evaluates to a value.
+fn literal(value: &Value) -> Expression {
+    match value {
+        // Literals take the type of the binding they are assigned to.
+        Value::Isize(value) if *value < 0 => Expression::Unary(
+            UnaryOp::Neg,
+            Box::new(Expression::NumericLiteral(value.unsigned_abs()).into()),
+        ),
+        Value::U8(_)
+        | Value::U16(_)
+        | Value::U32(_)
+        | Value::U64(_)
+        | Value::Usize(_)
+        | Value::Isize(_) => Expression::NumericLiteral(value.bits().unwrap_or_default()),
+        Value::Char(value) => Expression::CharLiteral(*value as char),
+        Value::Str(value) => Expression::StringLiteral(value.to_string()),
+        Value::Variant(name, variant, fields) => {
+            let fields = fields
+                .iter()
+                .map(|field| Spanned::from(literal(field)))
+                .collect();
+
+            Expression::Variant(*name, *variant, fields)
+        }
+        Value::Function(name) => Expression::Unary(
+            UnaryOp::AddressOf,
+            Box::new(Expression::Variable(*name).into()),
+        ),
+        Value::Strings(_) => {
+            unreachable!("only `main` is given strings, and the REPL never calls it")
+        }
+    }
+}
+
+#[test]
+fn entries_build_on_each_other() {
+    let mut repl = Repl::new();
+    let mut out = vec![];
+
+    assert_eq!(
+        repl.eval("fn double(x: u32) -> u32 { return twice(x); };", &mut out),
+        Err(ReplError::Diagnostics(vec![
+            crate::sema::error::SemaError::FunctionNotFound {
+                name: "twice".to_string(),
+                suggestion: None,
+            }
+            .at(crate::parser::Span::new(34, 42))
+        ]))
+    );
+    assert_eq!(
+        repl.eval("fn twice(x: u32) -> u32 { return x; }", &mut out),
+        Ok(None)
+    );
+    assert_eq!(repl.eval("let n: u32 = twice(21);", &mut out), Ok(None));
+    assert_eq!(repl.eval("twice(n)", &mut out), Ok(Some(Value::U32(21))));
+    assert_eq!(
+        repl.eval("ext fn printf(ptr: *char) -> u32;", &mut out),
+        Ok(None)
+    );
+    assert_eq!(repl.eval("printf(\"hi\");", &mut out), Ok(None));
+    assert_eq!(String::from_utf8(out).unwrap(), "hi");
+}
+
+#[test]
+fn values_of_any_type_are_evaluated() {
+    let mut repl = Repl::new();
+    let mut out = vec![];
+
+    assert_eq!(repl.eval("'a'", &mut out), Ok(Some(Value::Char(b'a'))));
+    assert_eq!(repl.eval("let s: str = \"x\";", &mut out), Ok(None));
+    assert_eq!(repl.eval("let b: u8 = 7;", &mut out), Ok(None));
+    assert_eq!(repl.eval("b", &mut out), Ok(Some(Value::U8(7))));
+    assert!(matches!(repl.eval("s", &mut out), Ok(Some(Value::Str(_)))));
+}
+
+#[test]
+fn entries_are_told_apart() {
+    assert!(matches!(
+        entry().parse("fn f() -> u32 { return 0; }"),
+        Ok((Entry::Item(_), _))
+    ));
+    assert!(matches!(
+        entry().parse("let x: u32 = 1;"),
+        Ok((Entry::Statement(_), _))
+    ));
+    assert!(matches!(
+        entry().parse(" f(1) "),
+        Ok((Entry::Expression(_), _))
+    ));
+    assert!(entry().parse("let x").is_err());
+}
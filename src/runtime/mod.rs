@@ -0,0 +1,29 @@
+/// The prefix of the name of every function of the runtime. Programs can not declare items whose
+/// names start with it, so that the runtime, and the names generated C gives its own helpers,
+/// never clash with them.
+pub const PREFIX: &str = "pal_";
+
+/// The functions of the runtime, which every program can call without declaring them, as they
+/// are part of the [`crate::sema::prelude`]. Each backend provides them itself: codegen defines
+/// those a module calls as private functions, see
+/// [`crate::codegen::runtime::define_runtime_function`], and cgen writes [`C_SOURCE`].
+pub const DECLARATIONS: &str = "
+ext fn pal_alloc(size: u32) -> *char;
+ext fn pal_free(ptr: *char) -> u32;
+ext fn pal_print_int(value: u32) -> u32;
+ext fn pal_print_str(s: str) -> u32;
+ext fn pal_print_u64(value: u64) -> u32;
+ext fn pal_print_isize(value: isize) -> u32;
+ext fn pal_print_char(c: char) -> u32;
+ext fn pal_print_cstr(s: *char) -> u32;
+ext fn pal_panic(message: *char) -> !;
+";
+
+/// The runtime as C, which expects `pal_str` to have been declared before it.
+pub const C_SOURCE: &str = include_str!("runtime.c");
+
+/// Returns whether a function is one of the runtime's. Programs can not declare functions with
+/// its [`PREFIX`], so every such function that sema has accepted a call to is.
+pub fn is_runtime(name: &str) -> bool {
+    name.starts_with(PREFIX)
+}
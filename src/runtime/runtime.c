@@ -0,0 +1,43 @@
+/* The runtime of pal, written into every translation unit that calls it. `pal_panic` is written
+ * separately, as what it does depends on the panic strategy. */
+int dprintf(int fd, const char* format, ...);
+
+static inline char* pal_alloc(uint32_t size) {
+    return malloc(size);
+}
+
+static inline uint32_t pal_free(char* ptr) {
+    free(ptr);
+    return 0;
+}
+
+/* What `new` is written as: copies a value of the given size into a new allocation. */
+static inline void* pal_new(size_t size, const void* value) {
+    char* ptr = malloc(size);
+    for (size_t i = 0; i < size; i++) ptr[i] = ((const char*)value)[i];
+    return ptr;
+}
+
+static inline uint32_t pal_print_int(uint32_t value) {
+    return (uint32_t)dprintf(1, "%u", (unsigned)value);
+}
+
+static inline uint32_t pal_print_str(pal_str s) {
+    return (uint32_t)dprintf(1, "%.*s", (int)s.len, s.ptr);
+}
+
+static inline uint32_t pal_print_u64(uint64_t value) {
+    return (uint32_t)dprintf(1, "%llu", (unsigned long long)value);
+}
+
+static inline uint32_t pal_print_isize(intptr_t value) {
+    return (uint32_t)dprintf(1, "%lld", (long long)value);
+}
+
+static inline uint32_t pal_print_char(char c) {
+    return (uint32_t)dprintf(1, "%c", c);
+}
+
+static inline uint32_t pal_print_cstr(char* s) {
+    return (uint32_t)dprintf(1, "%s", s);
+}
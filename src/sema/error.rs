@@ -0,0 +1,256 @@
+use thiserror::Error;
+
+use crate::{diagnostic::Diagnostic, parser::Span};
+
+/// Represents any error found while semantically checking a module.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SemaError {
+    #[error("not all paths in function `{function}` return a value")]
+    MissingReturn { function: String },
+    #[error(
+        "no `main` function found; declare `fn main() -> u32`, or `_start` to run without libc"
+    )]
+    MissingMain,
+    #[error(
+        "`main` must be `fn() -> u32` or `fn(argc: u32, argv: **char) -> u32`, found `{found}`"
+    )]
+    InvalidMainSignature { found: String },
+    #[error("`{name}` starts with `{prefix}`, which is reserved for the runtime")]
+    ReservedName { name: String, prefix: &'static str },
+    #[error("`main` cannot be generic, as nothing calls it from pal to infer its types")]
+    GenericMain,
+    #[error("scripts with top-level statements cannot also declare `main`")]
+    ScriptDeclaresMain,
+    #[error("function `{name}` not found{}", did_you_mean(.suggestion))]
+    FunctionNotFound {
+        name: String,
+        suggestion: Option<String>,
+    },
+    #[error("variable `{name}` not found{}", did_you_mean(.suggestion))]
+    VariableNotFound {
+        name: String,
+        suggestion: Option<String>,
+    },
+    #[error("function `{function}` takes {expected} argument(s), but {found} were given")]
+    ArgumentCountMismatch {
+        function: String,
+        expected: usize,
+        found: usize,
+    },
+    #[error("argument {} of `{function}` should be `{expected}`, found `{found}`", .index + 1)]
+    ArgumentTypeMismatch {
+        function: String,
+        index: usize,
+        expected: String,
+        found: String,
+    },
+    #[error(
+        "type parameter `{name}` of `{function}` is not used by any argument, so it cannot be inferred"
+    )]
+    UninferableGeneric { name: String, function: String },
+    #[error("type `{name}` not found{}", did_you_mean(.suggestion))]
+    TypeNotFound {
+        name: String,
+        suggestion: Option<String>,
+    },
+    #[error("`{name}` is private to module `{module}`; declare it with `pub` to use it")]
+    PrivateItem { name: String, module: String },
+    #[error("`{name}` is {kind}, not a function")]
+    NotAFunction { name: String, kind: &'static str },
+    #[error("`{name}` is not a constant array")]
+    NotAnArray { name: String },
+    #[error("constant `{name}` must have an array type")]
+    ConstNotArray { name: String },
+    #[error("arrays can only be declared with `const`")]
+    ArrayNotConst,
+    #[error("constant `{name}` should have {expected} element(s), but {found} were given")]
+    ArrayLengthMismatch {
+        name: String,
+        expected: u64,
+        found: usize,
+    },
+    #[error("element {} of `{name}` should be `{expected}`, found `{found}`", .index + 1)]
+    ArrayElementMismatch {
+        name: String,
+        index: usize,
+        expected: String,
+        found: String,
+    },
+    #[error("cannot apply `{op}` to a value of type `{found}`")]
+    UnaryTypeMismatch { op: String, found: String },
+    #[error("cannot apply `{op}` to values of type `{left}` and `{right}`")]
+    BinaryTypeMismatch {
+        op: String,
+        left: String,
+        right: String,
+    },
+    #[error(
+        "cannot apply `{op}` to integers of different types, `{left}` and `{right}`; convert one with `as`"
+    )]
+    IntegerMismatch {
+        op: String,
+        left: String,
+        right: String,
+    },
+    #[error("cannot convert a value of type `{from}` to `{to}`, as only integers can be converted")]
+    InvalidCast { from: String, to: String },
+    #[error("the branches of `if` have different types, `{then}` and `{otherwise}`")]
+    BranchTypeMismatch { then: String, otherwise: String },
+    #[error("expected a value of type `{expected}`, found `{found}`; convert it with `as`")]
+    TypeMismatch { expected: String, found: String },
+    #[error("cannot `return` from inside a block expression, which must give its value instead")]
+    ReturnInBlock,
+    #[error("cannot take the address of generic function `{name}`, as its types are not known")]
+    GenericAddress { name: String },
+    #[error("`&` can only take the address of a variable")]
+    AddressOfValue,
+    #[error("cannot return the address of local variable `{name}`, which is freed on return")]
+    ReturnsLocalAddress { name: String },
+    #[error("literal `{value}` does not fit in `{ty}`, which holds values from 0 to {max}")]
+    LiteralOutOfRange { value: u64, ty: String, max: u64 },
+    #[error("enum `{name}` has no variant `{variant}`{}", did_you_mean(.suggestion))]
+    VariantNotFound {
+        name: String,
+        variant: String,
+        suggestion: Option<String>,
+    },
+    #[error("`{name}` is not an enum")]
+    NotAnEnum { name: String },
+    #[error("variant `{variant}` is declared more than once in enum `{name}`")]
+    DuplicateVariant { name: String, variant: String },
+    #[error("cannot match a value of type `{found}`; only `u32`, `char` and enums can be matched")]
+    UnmatchableType { found: String },
+    #[error("pattern `{pattern}` cannot match a value of type `{expected}`")]
+    PatternTypeMismatch { pattern: String, expected: String },
+    #[error("pattern `{pattern}` binds {found} field(s), but its variant has {expected}")]
+    BindingCountMismatch {
+        pattern: String,
+        expected: usize,
+        found: usize,
+    },
+    #[error("a field of `{variant}` cannot be `{found}`, which is an enum with fields itself")]
+    NestedFields { variant: String, found: String },
+    #[error("a field of `{variant}` cannot be `str`, which is wider than a field can be")]
+    StrField { variant: String },
+    #[error("pattern `{pattern}` is matched by an earlier arm")]
+    DuplicatePattern { pattern: String },
+    #[error("match is not exhaustive; add arms for {missing} or a `_` arm")]
+    NonExhaustiveMatch { missing: String },
+    #[error("index {index} is out of bounds for `{name}`, which has {length} element(s)")]
+    IndexOutOfBounds {
+        name: String,
+        index: u64,
+        length: u64,
+    },
+    #[error("the first argument of `{function}` must be a string literal, which is its format")]
+    FormatNotLiteral { function: String },
+    #[error(
+        "format string \"{format}\" has a brace that is not part of `{{}}`; write `{{{{` or `}}}}` for a literal brace"
+    )]
+    InvalidFormat { format: String },
+    #[error("format string has {placeholders} `{{}}`, but {found} argument(s) were given")]
+    FormatArgumentCount { placeholders: usize, found: usize },
+    #[error(
+        "a value of type `{ty}` cannot be printed; only integers, `char`, `str` and `*char` can"
+    )]
+    NotPrintable { ty: String },
+    #[error("`{function}` gives no value, so it can only be called as a statement")]
+    FormatAsValue { function: String },
+}
+
+impl SemaError {
+    /// Returns the code that identifies the error in diagnostics, which is the name of its
+    /// variant in kebab case.
+    pub fn code(&self) -> &'static str {
+        match self {
+            SemaError::MissingReturn { .. } => "missing-return",
+            SemaError::MissingMain => "missing-main",
+            SemaError::InvalidMainSignature { .. } => "invalid-main-signature",
+            SemaError::ReservedName { .. } => "reserved-name",
+            SemaError::GenericMain => "generic-main",
+            SemaError::ScriptDeclaresMain => "script-declares-main",
+            SemaError::FunctionNotFound { .. } => "function-not-found",
+            SemaError::VariableNotFound { .. } => "variable-not-found",
+            SemaError::ArgumentCountMismatch { .. } => "argument-count-mismatch",
+            SemaError::ArgumentTypeMismatch { .. } => "argument-type-mismatch",
+            SemaError::UninferableGeneric { .. } => "uninferable-generic",
+            SemaError::TypeNotFound { .. } => "type-not-found",
+            SemaError::PrivateItem { .. } => "private-item",
+            SemaError::NotAFunction { .. } => "not-a-function",
+            SemaError::NotAnArray { .. } => "not-an-array",
+            SemaError::ConstNotArray { .. } => "const-not-array",
+            SemaError::ArrayNotConst => "array-not-const",
+            SemaError::ArrayLengthMismatch { .. } => "array-length-mismatch",
+            SemaError::ArrayElementMismatch { .. } => "array-element-mismatch",
+            SemaError::UnaryTypeMismatch { .. } => "unary-type-mismatch",
+            SemaError::BinaryTypeMismatch { .. } => "binary-type-mismatch",
+            SemaError::IntegerMismatch { .. } => "integer-mismatch",
+            SemaError::InvalidCast { .. } => "invalid-cast",
+            SemaError::BranchTypeMismatch { .. } => "branch-type-mismatch",
+            SemaError::TypeMismatch { .. } => "type-mismatch",
+            SemaError::ReturnInBlock => "return-in-block",
+            SemaError::GenericAddress { .. } => "generic-address",
+            SemaError::AddressOfValue => "address-of-value",
+            SemaError::ReturnsLocalAddress { .. } => "returns-local-address",
+            SemaError::LiteralOutOfRange { .. } => "literal-out-of-range",
+            SemaError::VariantNotFound { .. } => "variant-not-found",
+            SemaError::NotAnEnum { .. } => "not-an-enum",
+            SemaError::DuplicateVariant { .. } => "duplicate-variant",
+            SemaError::UnmatchableType { .. } => "unmatchable-type",
+            SemaError::PatternTypeMismatch { .. } => "pattern-type-mismatch",
+            SemaError::BindingCountMismatch { .. } => "binding-count-mismatch",
+            SemaError::NestedFields { .. } => "nested-fields",
+            SemaError::StrField { .. } => "str-field",
+            SemaError::DuplicatePattern { .. } => "duplicate-pattern",
+            SemaError::NonExhaustiveMatch { .. } => "non-exhaustive-match",
+            SemaError::IndexOutOfBounds { .. } => "index-out-of-bounds",
+            SemaError::FormatNotLiteral { .. } => "format-not-literal",
+            SemaError::InvalidFormat { .. } => "invalid-format",
+            SemaError::FormatArgumentCount { .. } => "format-argument-count",
+            SemaError::NotPrintable { .. } => "not-printable",
+            SemaError::FormatAsValue { .. } => "format-as-value",
+        }
+    }
+
+    /// Turns the error into a [`Diagnostic`] pointing at the given span.
+    pub fn at(self, span: Span) -> Diagnostic {
+        Diagnostic::from(self).with_span(span)
+    }
+
+    /// Turns the error into a [`Diagnostic`] pointing at the given span, like [`SemaError::at`].
+    /// When the error suggests a name for one it did not find, the [`Diagnostic`] has a fix that
+    /// replaces the name, which `name` is the span of, with the one it suggests.
+    pub fn at_name(self, span: Span, name: Span) -> Diagnostic {
+        let fix = match &self {
+            SemaError::FunctionNotFound {
+                name: found,
+                suggestion: Some(suggestion),
+            }
+            | SemaError::VariableNotFound {
+                name: found,
+                suggestion: Some(suggestion),
+            }
+            | SemaError::TypeNotFound {
+                name: found,
+                suggestion: Some(suggestion),
+            } => Some((
+                format!("replace `{found}` with `{suggestion}`"),
+                suggestion.clone(),
+            )),
+            _ => None,
+        };
+
+        match fix {
+            Some((message, suggestion)) => self.at(span).with_fix(message, name, suggestion),
+            None => self.at(span),
+        }
+    }
+}
+
+/// Formats an optional suggestion as a hint to append to a message.
+fn did_you_mean(suggestion: &Option<String>) -> String {
+    match suggestion {
+        Some(suggestion) => format!("; did you mean `{suggestion}`?"),
+        None => String::new(),
+    }
+}
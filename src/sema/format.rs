@@ -0,0 +1,159 @@
+use crate::{
+    diagnostic::Diagnostic,
+    parser::{Span, Spanned},
+    sema::{Globals, error::SemaError, scope::Scopes, ty::Ty, type_of},
+    spec::ast::*,
+};
+
+/// The functions that print a formatted string, such as `println("x = {}", x)`. They are part of
+/// the language rather than of the prelude, as they take any number of arguments, which no
+/// function declared in pal can. Functions and variables of the same name shadow them.
+pub const FORMAT_FUNCTIONS: [&str; 2] = ["print", "println"];
+
+/// A piece of a format string.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Piece {
+    /// Text that is printed as it is.
+    Text(String),
+    /// A `{}`, which the next argument is printed in place of.
+    Argument,
+}
+
+/// Splits a format string into its pieces, returning `None` when it has a `{` or `}` that is
+/// neither part of a `{}` nor doubled, as `{{` and `}}` are how literal braces are written.
+pub fn parse_format(format: &str) -> Option<Vec<Piece>> {
+    let mut pieces = vec![];
+    let mut text = String::new();
+    let mut chars = format.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                text.push(c);
+            }
+            ('{', Some('}')) => {
+                chars.next();
+
+                if !text.is_empty() {
+                    pieces.push(Piece::Text(std::mem::take(&mut text)));
+                }
+
+                pieces.push(Piece::Argument);
+            }
+            ('{' | '}', _) => return None,
+            _ => text.push(c),
+        }
+    }
+
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+
+    Some(pieces)
+}
+
+/// Returns whether a call to `name` is a call to one of the [`FORMAT_FUNCTIONS`], which it is
+/// unless a variable or function of the same name shadows them.
+pub fn is_format_call(name: &str, globals: &Globals, scopes: &Scopes) -> bool {
+    FORMAT_FUNCTIONS.contains(&name)
+        && scopes.get(name).is_none()
+        && !globals.functions.contains_key(name)
+}
+
+/// Returns whether a value of the given type can be printed by the [`FORMAT_FUNCTIONS`]: integers,
+/// characters and strings, whether a `str` or a `*char`.
+pub fn is_printable(ty: Ty, globals: &Globals) -> bool {
+    let tcx = &globals.tcx;
+
+    tcx.kind(ty).is_integer() || ty == tcx.str() || ty == tcx.pointer(tcx.char())
+}
+
+/// Checks a call to one of the [`FORMAT_FUNCTIONS`]. Its first argument must be a string literal,
+/// with as many `{}` as there are arguments after it, each of which must be printable.
+pub fn check_format(
+    name: &str,
+    args: &[Spanned<Expression>],
+    span: Span,
+    globals: &Globals,
+    scopes: &Scopes,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let tcx = &globals.tcx;
+
+    let format_span = args.first().map_or(span, |format| format.span);
+
+    let placeholders = match args.first().map(|format| &format.node) {
+        Some(Expression::StringLiteral(format)) => match parse_format(format) {
+            Some(pieces) => Some(
+                pieces
+                    .iter()
+                    .filter(|piece| **piece == Piece::Argument)
+                    .count(),
+            ),
+            None => {
+                diagnostics.push(
+                    SemaError::InvalidFormat {
+                        format: format.to_string(),
+                    }
+                    .at(format_span),
+                );
+                None
+            }
+        },
+        _ => {
+            diagnostics.push(
+                SemaError::FormatNotLiteral {
+                    function: name.to_string(),
+                }
+                .at(format_span),
+            );
+            None
+        }
+    };
+
+    let args = args.get(1..).unwrap_or_default();
+
+    if let Some(placeholders) = placeholders
+        && placeholders != args.len()
+    {
+        diagnostics.push(
+            SemaError::FormatArgumentCount {
+                placeholders,
+                found: args.len(),
+            }
+            .at(span),
+        );
+    }
+
+    for arg in args {
+        if let Some(ty) = type_of(arg, globals, scopes, diagnostics)
+            && !is_printable(ty, globals)
+        {
+            diagnostics.push(
+                SemaError::NotPrintable {
+                    ty: tcx.display(ty).to_string(),
+                }
+                .at(arg.span),
+            );
+        }
+    }
+}
+
+#[test]
+fn format_strings_are_parsed() {
+    assert_eq!(
+        parse_format("x = {}, {{y}} = {}!"),
+        Some(vec![
+            Piece::Text("x = ".to_string()),
+            Piece::Argument,
+            Piece::Text(", {y} = ".to_string()),
+            Piece::Argument,
+            Piece::Text("!".to_string()),
+        ])
+    );
+    assert_eq!(parse_format(""), Some(vec![]));
+    assert_eq!(parse_format("{x}"), None);
+    assert_eq!(parse_format("}"), None);
+    assert_eq!(parse_format("{"), None);
+}
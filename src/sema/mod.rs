@@ -0,0 +1,2516 @@
+pub mod error;
+pub mod format;
+pub mod scope;
+pub mod suggest;
+pub mod ty;
+
+use std::{collections::HashMap, sync::LazyLock};
+
+use crate::{
+    diagnostic::Diagnostic,
+    parser::{Span, Spanned},
+    runtime,
+    sema::{
+        error::SemaError,
+        format::{check_format, is_format_call},
+        scope::Scopes,
+        suggest::closest,
+        ty::{Ty, TyCtxt, TyKind},
+    },
+    spec::ast::*,
+};
+
+/// The functions that every module can call without declaring them. They are declared like
+/// external functions, but every backend provides them itself, see [`crate::lower::strings::lower_strings`].
+pub const PRELUDE: &str = "ext fn len(s: str) -> u32; ext fn concat(a: str, b: str) -> str;";
+
+/// Returns the items declared by [`PRELUDE`], followed by the functions of the runtime, see
+/// [`runtime::DECLARATIONS`].
+pub fn prelude() -> &'static [Spanned<Item>] {
+    static ITEMS: LazyLock<Module> = LazyLock::new(|| {
+        crate::spec::module("prelude".to_string())
+            .parse(format!("{PRELUDE}{}", runtime::DECLARATIONS))
+            .expect("the prelude parses")
+            .0
+    });
+
+    &ITEMS.1
+}
+
+/// Identifies a top-level item by its content rather than by its position in the source, so that
+/// tooling can correlate items across edits and reparses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ItemId(pub u64);
+
+impl ItemId {
+    /// Derives the [`ItemId`] of an item from its kind and name. The hash is FNV-1a, which,
+    /// unlike the standard library's hasher, is guaranteed to be the same across builds.
+    pub fn of(item: &Item) -> ItemId {
+        let kind = match item {
+            Item::ExternFunctionDefinition(..) => "ext fn",
+            Item::FunctionDeclaration(..) => "fn",
+            Item::Const(..) => "const",
+            Item::Enum(..) => "enum",
+        };
+        let name = item.name();
+
+        let hash = [kind.as_bytes(), &[0], name.as_bytes()]
+            .concat()
+            .into_iter()
+            .fold(0xcbf29ce484222325u64, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+            });
+
+        ItemId(hash)
+    }
+}
+
+/// Every item declared at the top level of a module. These are collected before any function body
+/// is checked, so that items may be used before they are declared. The table doubles as the
+/// resolution of every call in the module: a call resolves to the signature of the item found
+/// under its name, so the AST itself is never rewritten. The types used throughout the module are
+/// interned alongside.
+#[derive(Debug, Default)]
+pub struct Globals<'a> {
+    pub functions: HashMap<&'a str, &'a Spanned<Item>>,
+    /// The private items of imported modules, which can not be used, along with the name of the
+    /// module each is declared in.
+    pub private: HashMap<&'a str, &'a str>,
+    pub tcx: TyCtxt,
+}
+
+impl<'a> Globals<'a> {
+    /// Collects the items declared in a module.
+    pub fn collect(module: &'a Module) -> Globals<'a> {
+        Globals::collect_with_imports(module, &[])
+    }
+
+    /// Collects the items declared in a module along with the public items of the modules it
+    /// imports and the [`prelude`]. Items declared in the module itself shadow imported items of
+    /// the same name, which shadow those of the prelude.
+    pub fn collect_with_imports(module: &'a Module, imports: &[&'a Module]) -> Globals<'a> {
+        let imported = imports
+            .iter()
+            .flat_map(|import| import.1.iter().map(move |item| (&import.0[..], item)));
+
+        let (public, private): (Vec<_>, Vec<_>) =
+            imported.partition(|(_, item)| item.visibility() == Visibility::Public);
+
+        let globals = Globals {
+            functions: prelude()
+                .iter()
+                .chain(public.into_iter().map(|(_, item)| item))
+                .chain(&module.1)
+                .map(|item| (item.name(), item))
+                .collect(),
+            private: private
+                .into_iter()
+                .map(|(module, item)| (item.name(), module))
+                .collect(),
+            tcx: TyCtxt::new(),
+        };
+
+        for item in globals.functions.values() {
+            if let Item::Enum(name, ..) = &item.node {
+                globals.tcx.declare_enum(*name);
+            }
+        }
+
+        globals
+    }
+
+    /// Resolves the constant array an index expression reads from, reporting it at the span of the
+    /// expression when there is none.
+    pub fn resolve_const(
+        &self,
+        name: &str,
+        span: Span,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Option<&'a Spanned<Item>> {
+        let error = match (self.functions.get(name), self.private.get(name)) {
+            (Some(item), _) if matches!(item.node, Item::Const(..)) => return Some(item),
+            (None, Some(module)) => SemaError::PrivateItem {
+                name: name.to_string(),
+                module: module.to_string(),
+            },
+            _ => SemaError::NotAnArray {
+                name: name.to_string(),
+            },
+        };
+
+        diagnostics.push(error.at(span));
+
+        None
+    }
+
+    /// Interns a type written in the source for a value, such as an argument or a binding,
+    /// reporting it when it does not exist or when it is an array, which only constants may be.
+    pub fn lower_value_type(
+        &self,
+        typ: &Spanned<Type>,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Option<Ty> {
+        let ty = self.lower_type(typ, diagnostics)?;
+
+        if let TyKind::Array(..) = self.tcx.kind(ty) {
+            diagnostics.push(SemaError::ArrayNotConst.at(typ.span));
+        }
+
+        Some(ty)
+    }
+
+    /// Resolves a call target to the function it refers to, reporting it at the span of the call
+    /// when there is none.
+    pub fn resolve_function(
+        &self,
+        name: &str,
+        span: Span,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> Option<&'a Spanned<Item>> {
+        let function = self.functions.get(name).copied();
+
+        if function.is_none()
+            && let Some(module) = self.private.get(name)
+        {
+            diagnostics.push(
+                SemaError::PrivateItem {
+                    name: name.to_string(),
+                    module: module.to_string(),
+                }
+                .at(span),
+            );
+        } else if function.is_none() {
+            let suggestion = closest(name, self.functions.keys().copied());
+
+            diagnostics.push(
+                SemaError::FunctionNotFound {
+                    name: name.to_string(),
+                    suggestion: suggestion.map(str::to_string),
+                }
+                .at_name(span, Span::new(span.start, span.start + name.len())),
+            );
+        }
+
+        function
+    }
+
+    /// Interns a type written in the source, reporting it when it does not exist.
+    pub fn lower_type(&self, typ: &Spanned<Type>, diagnostics: &mut Vec<Diagnostic>) -> Option<Ty> {
+        match self.tcx.lower(typ) {
+            Ok(ty) => Some(ty),
+            Err(name) => {
+                let names = self.tcx.names();
+                let suggestion = closest(name, names.iter().map(|name| &name[..]));
+
+                // Pointers are written before the type they point to, so the name of the type
+                // ends what is written, unless it is part of an array or a function type.
+                let mut pointee = &typ.node;
+
+                while let Type::Pointer(inner) = pointee {
+                    pointee = inner;
+                }
+
+                let error = SemaError::TypeNotFound {
+                    name: name.to_string(),
+                    suggestion: suggestion.map(str::to_string),
+                };
+
+                diagnostics.push(match pointee {
+                    Type::Atomic(_) => {
+                        error.at_name(typ.span, Span::new(typ.span.end - name.len(), typ.span.end))
+                    }
+                    _ => error.at(typ.span),
+                });
+
+                None
+            }
+        }
+    }
+
+    /// Returns whether the function with the given name never returns.
+    pub fn diverges(&self, name: &str) -> bool {
+        self.functions.get(name).is_some_and(|item| item.diverges())
+    }
+
+    /// Returns the variants of the enum declared under a name, if there is one.
+    pub fn variants(&self, name: &str) -> Option<&'a [Variant]> {
+        match self.item(name)? {
+            Item::Enum(_, variants, _) => Some(variants),
+            _ => None,
+        }
+    }
+
+    /// Returns the item declared under a name, if there is one.
+    pub fn item(&self, name: &str) -> Option<&'a Item> {
+        self.functions.get(name).map(|item| &item.node)
+    }
+}
+
+/// Returns whether evaluating an expression always calls a function that never returns, given
+/// which functions those are.
+pub fn expression_diverges(expression: &Expression, diverges: &dyn Fn(&str) -> bool) -> bool {
+    match expression {
+        Expression::Call(name, args) => {
+            diverges(name) || args.iter().any(|arg| expression_diverges(arg, diverges))
+        }
+        Expression::Index(_, operand)
+        | Expression::Unary(_, operand)
+        | Expression::New(_, operand)
+        | Expression::Alloc(_, operand)
+        | Expression::Cast(operand, _) => expression_diverges(operand, diverges),
+        Expression::Binary(_, left, right) => {
+            expression_diverges(left, diverges) || expression_diverges(right, diverges)
+        }
+        Expression::If(condition, then, otherwise) => {
+            expression_diverges(condition, diverges)
+                || expression_diverges(then, diverges) && expression_diverges(otherwise, diverges)
+        }
+        Expression::Variant(_, _, fields) => fields
+            .iter()
+            .any(|field| expression_diverges(field, diverges)),
+        // Only the statements that run every time the block does are looked at, as working out
+        // whether a `match` is exhaustive needs the items of the program.
+        Expression::Block(body, value) => {
+            body.iter().any(|statement| match &statement.node {
+                Statement::Unreachable => true,
+                Statement::FunctionCall(name, args) => {
+                    diverges(name) || args.iter().any(|arg| expression_diverges(arg, diverges))
+                }
+                Statement::Let(_, _, value)
+                | Statement::Free(value)
+                | Statement::Assert(value, _) => expression_diverges(value, diverges),
+                _ => false,
+            }) || expression_diverges(value, diverges)
+        }
+        Expression::StringLiteral(_)
+        | Expression::NumericLiteral(_)
+        | Expression::CharLiteral(_)
+        | Expression::Variable(_) => false,
+    }
+}
+
+/// Returns the variants of an enum that no arm of a `match` over it matches. A `match` with a
+/// wildcard arm misses nothing.
+pub fn missing_variants(arms: &[Arm], name: Symbol, variants: &[Variant]) -> Vec<Symbol> {
+    if arms
+        .iter()
+        .any(|(pattern, _)| pattern.node == Pattern::Wildcard)
+    {
+        return vec![];
+    }
+
+    variants
+        .iter()
+        .map(|(variant, _)| variant.node)
+        .filter(|variant| {
+            let pattern = Pattern::Variant(name, *variant, vec![]);
+            !arms.iter().any(|(other, _)| other.node.overlaps(&pattern))
+        })
+        .collect()
+}
+
+/// Returns whether some arm of a `match` runs whatever the value is, given the items that names
+/// refer to. That is the case when there is a wildcard arm, or when the arms match every variant
+/// of an enum.
+pub fn is_exhaustive<'a>(arms: &[Arm], items: &dyn Fn(&str) -> Option<&'a Item>) -> bool {
+    if arms
+        .iter()
+        .any(|(pattern, _)| pattern.node == Pattern::Wildcard)
+    {
+        return true;
+    }
+
+    let enumeration = arms.iter().find_map(|(pattern, _)| match pattern.node {
+        Pattern::Variant(name, ..) => Some(name),
+        _ => None,
+    });
+
+    match enumeration.map(|name| (name, items(&name))) {
+        Some((name, Some(Item::Enum(_, variants, _)))) => {
+            missing_variants(arms, name, variants).is_empty()
+        }
+        _ => false,
+    }
+}
+
+/// Returns whether every path through the given statements ends in a `return` or in a call to a
+/// function that never returns, given the items that names refer to.
+pub fn always_returns<'a>(
+    statements: &[Spanned<Statement>],
+    items: &dyn Fn(&str) -> Option<&'a Item>,
+) -> bool {
+    let diverges = &|name: &str| items(name).is_some_and(Item::diverges);
+
+    statements.iter().any(|statement| match &statement.node {
+        Statement::Return(_) | Statement::Unreachable => true,
+        Statement::Block(body) => always_returns(body, items),
+        Statement::FunctionCall(name, args) => {
+            diverges(name) || args.iter().any(|arg| expression_diverges(arg, diverges))
+        }
+        Statement::Let(_, _, value) | Statement::Free(value) | Statement::Assert(value, _) => {
+            expression_diverges(value, diverges)
+        }
+        // The body of a loop may never run.
+        Statement::For(_, start, end, _) => {
+            expression_diverges(start, diverges) || expression_diverges(end, diverges)
+        }
+        Statement::Match(value, arms) => {
+            expression_diverges(value, diverges)
+                || is_exhaustive(arms, items)
+                    && arms.iter().all(|(_, body)| always_returns(body, items))
+        }
+        // The statement may have returned had it parsed, and its error is reported already.
+        Statement::Error(_) => true,
+        Statement::Asm(_) => false,
+    })
+}
+
+/// Infers the type parameters of a generic function from the type of an argument, given the type
+/// of the parameter it is passed for. A type parameter is inferred to be the type of the first
+/// argument it appears in. Returns whether the argument can be passed for the parameter.
+pub fn unify(tcx: &TyCtxt, typ: &Type, found: Ty, subst: &mut HashMap<Symbol, Option<Ty>>) -> bool {
+    match typ {
+        Type::Atomic(name) if subst.contains_key(name) => match subst[name] {
+            Some(bound) => bound == found,
+            None => {
+                subst.insert(*name, Some(found));
+                true
+            }
+        },
+        Type::Pointer(pointee) => match tcx.kind(found) {
+            TyKind::Pointer(found) => unify(tcx, pointee, found, subst),
+            _ => false,
+        },
+        Type::Function(arguments, returns) => match tcx.kind(found) {
+            TyKind::Function(found, found_returns) if found.len() == arguments.len() => {
+                arguments
+                    .iter()
+                    .zip(found)
+                    .all(|(argument, found)| unify(tcx, argument, found, subst))
+                    && unify(tcx, returns, found_returns, subst)
+            }
+            _ => false,
+        },
+        _ => match tcx.lower_with(typ, subst) {
+            Ok(ty) => ty == found,
+            // Unknown parameter types are reported with the declaration of the function.
+            Err(name) => !subst.contains_key(name),
+        },
+    }
+}
+
+/// Returns whether an argument of type `found` is passed as a `*char` to an external function
+/// that expects one, which is how a `str` is passed to C.
+fn is_c_string(function: &Item, expected: Ty, found: Ty, tcx: &TyCtxt) -> bool {
+    matches!(function, Item::ExternFunctionDefinition(..))
+        && expected == tcx.pointer(tcx.char())
+        && found == tcx.str()
+}
+
+/// Resolves a call and checks its arguments against the signature of the function it calls,
+/// returning the type of the value the call produces. The type parameters of a generic function
+/// are inferred from the arguments, see [`unify`].
+pub fn check_call(
+    name: &str,
+    args: &[Spanned<Expression>],
+    span: Span,
+    globals: &Globals,
+    scopes: &Scopes,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Option<Ty> {
+    let tcx = &globals.tcx;
+
+    // A variable holding a function pointer shadows any function of the same name.
+    if let Some(ty) = scopes.get(name)
+        && let TyKind::Function(parameters, returns) = tcx.kind(ty)
+    {
+        check_indirect_call(name, &parameters, args, span, globals, scopes, diagnostics);
+
+        return Some(returns);
+    }
+
+    let callee = globals
+        .functions
+        .get(name)
+        .filter(|function| function.is_function());
+    let parameters = callee
+        .map(|function| function.arguments())
+        .unwrap_or_default();
+    let mut subst: HashMap<Symbol, Option<Ty>> = callee
+        .map(|function| function.generics())
+        .unwrap_or_default()
+        .iter()
+        .map(|generic| (*generic, None))
+        .collect();
+
+    // Arguments are checked even when the call does not resolve, so that their own problems are
+    // reported too. Each one is checked knowing what the arguments before it have inferred.
+    let mut found = vec![];
+
+    for (index, arg) in args.iter().enumerate() {
+        let parameter = parameters.get(index).map(|(_, typ)| &typ.node);
+        let expected = parameter.and_then(|typ| tcx.lower_with(typ, &subst).ok());
+        let ty = type_of_expected(arg, expected, globals, scopes, diagnostics);
+
+        if let (Some(typ), Some(ty)) = (parameter, ty) {
+            unify(tcx, typ, ty, &mut subst);
+        }
+
+        found.push(ty);
+    }
+
+    let function = globals.resolve_function(name, span, diagnostics)?;
+
+    if !function.is_function() {
+        diagnostics.push(
+            SemaError::NotAFunction {
+                name: name.to_string(),
+                kind: match function.node {
+                    Item::Enum(..) => "an enum",
+                    _ => "a constant",
+                },
+            }
+            .at(span),
+        );
+
+        return None;
+    }
+
+    let expected = function.arguments();
+
+    if expected.len() != found.len() {
+        diagnostics.push(
+            SemaError::ArgumentCountMismatch {
+                function: name.to_string(),
+                expected: expected.len(),
+                found: found.len(),
+            }
+            .at(span),
+        );
+    } else {
+        for (index, ((_, expected), (arg, found))) in
+            expected.iter().zip(args.iter().zip(found)).enumerate()
+        {
+            let Some(found) = found else {
+                continue;
+            };
+
+            // Unknown parameter types are reported with the declaration of the function, while a
+            // type parameter that is still not inferred could not be from this argument.
+            let expected = match tcx.lower_with(expected, &subst) {
+                Ok(ty) if ty == found || is_c_string(function, ty, found, tcx) => continue,
+                Ok(ty) => tcx.display(ty).to_string(),
+                Err(name) if subst.contains_key(name) => expected.to_string(),
+                Err(_) => continue,
+            };
+
+            diagnostics.push(
+                SemaError::ArgumentTypeMismatch {
+                    function: name.to_string(),
+                    index,
+                    expected,
+                    found: tcx.display(found).to_string(),
+                }
+                .at(arg.span),
+            );
+        }
+    }
+
+    tcx.lower_with(function.return_type()?, &subst).ok()
+}
+
+/// Checks the arguments of a call through a function pointer against the types of the parameters
+/// of the function it points to.
+fn check_indirect_call(
+    name: &str,
+    parameters: &[Ty],
+    args: &[Spanned<Expression>],
+    span: Span,
+    globals: &Globals,
+    scopes: &Scopes,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let tcx = &globals.tcx;
+
+    if parameters.len() != args.len() {
+        diagnostics.push(
+            SemaError::ArgumentCountMismatch {
+                function: name.to_string(),
+                expected: parameters.len(),
+                found: args.len(),
+            }
+            .at(span),
+        );
+    }
+
+    for (index, arg) in args.iter().enumerate() {
+        let expected = parameters.get(index).copied();
+
+        if let Some(found) = type_of_expected(arg, expected, globals, scopes, diagnostics)
+            && let Some(expected) = expected
+            && found != expected
+        {
+            diagnostics.push(
+                SemaError::ArgumentTypeMismatch {
+                    function: name.to_string(),
+                    index,
+                    expected: tcx.display(expected).to_string(),
+                    found: tcx.display(found).to_string(),
+                }
+                .at(arg.span),
+            );
+        }
+    }
+}
+
+/// Works out the type of an expression, reporting every problem found in it. Returns [`None`]
+/// when the type cannot be known because of an error that has already been reported.
+pub fn type_of(
+    expression: &Spanned<Expression>,
+    globals: &Globals,
+    scopes: &Scopes,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Option<Ty> {
+    let tcx = &globals.tcx;
+
+    match &expression.node {
+        Expression::StringLiteral(_) => Some(tcx.pointer(tcx.char())),
+        Expression::NumericLiteral(_) => {
+            type_of_expected(expression, None, globals, scopes, diagnostics)
+        }
+        Expression::CharLiteral(_) => Some(tcx.char()),
+        Expression::Variable(name) => {
+            let ty = scopes.get(name);
+
+            if ty.is_none() {
+                diagnostics.push(
+                    SemaError::VariableNotFound {
+                        name: name.to_string(),
+                        suggestion: closest(name, scopes.names()).map(str::to_string),
+                    }
+                    .at_name(expression.span, expression.span),
+                );
+            }
+
+            ty
+        }
+        Expression::Call(name, args) if is_format_call(name, globals, scopes) => {
+            check_format(name, args, expression.span, globals, scopes, diagnostics);
+            diagnostics.push(
+                SemaError::FormatAsValue {
+                    function: name.to_string(),
+                }
+                .at(expression.span),
+            );
+
+            None
+        }
+        Expression::Call(name, args) => {
+            check_call(name, args, expression.span, globals, scopes, diagnostics)
+        }
+        Expression::Index(name, index) => {
+            type_of(index, globals, scopes, diagnostics);
+
+            // A local pointer, such as `argv`, is indexed like a C array, without a length to
+            // check against.
+            if let Some(ty) = scopes.get(name) {
+                return match tcx.kind(ty) {
+                    TyKind::Pointer(pointee) => Some(pointee),
+                    _ => {
+                        diagnostics.push(
+                            SemaError::NotAnArray {
+                                name: name.to_string(),
+                            }
+                            .at(expression.span),
+                        );
+
+                        None
+                    }
+                };
+            }
+
+            let item = globals.resolve_const(name, expression.span, diagnostics)?;
+            let TyKind::Array(element, length) = tcx.kind(tcx.lower(item.return_type()?).ok()?)
+            else {
+                return None;
+            };
+
+            // Indices known while checking are checked against the length right away.
+            if let Expression::NumericLiteral(value) = index.node
+                && value >= length
+            {
+                diagnostics.push(
+                    SemaError::IndexOutOfBounds {
+                        name: name.to_string(),
+                        index: value,
+                        length,
+                    }
+                    .at(index.span),
+                );
+            }
+
+            Some(element)
+        }
+        // Taking the address of a function, rather than of a variable, gives a pointer to it.
+        Expression::Unary(UnaryOp::AddressOf, operand)
+            if let Expression::Variable(name) = &operand.node
+                && scopes.get(name).is_none()
+                && let Some(function) = globals.item(name).filter(|item| item.is_function()) =>
+        {
+            // A generic function has no address until a call instantiates it.
+            if !function.generics().is_empty() {
+                diagnostics.push(
+                    SemaError::GenericAddress {
+                        name: name.to_string(),
+                    }
+                    .at(expression.span),
+                );
+
+                return None;
+            }
+
+            let arguments = function
+                .arguments()
+                .iter()
+                .map(|(_, typ)| tcx.lower(typ).ok())
+                .collect::<Option<_>>()?;
+            let returns = tcx.lower(function.return_type()?).ok()?;
+
+            Some(tcx.intern(TyKind::Function(arguments, returns)))
+        }
+        Expression::Unary(..) => type_of_unary(expression, None, globals, scopes, diagnostics),
+        Expression::Binary(..) => type_of_binary(expression, None, globals, scopes, diagnostics),
+        Expression::Variant(name, variant, args) => {
+            let resolved = resolve_variant(*name, *variant, expression.span, globals, diagnostics);
+            let fields = resolved.map(|(_, fields)| fields).unwrap_or_default();
+
+            let found: Vec<_> = args
+                .iter()
+                .enumerate()
+                .map(|(index, arg)| {
+                    let expected = fields
+                        .get(index)
+                        .and_then(|typ| globals.tcx.lower(typ).ok());
+
+                    type_of_expected(arg, expected, globals, scopes, diagnostics)
+                })
+                .collect();
+
+            let (ty, fields) = resolved?;
+            let constructor = format!("{name}::{variant}");
+
+            if fields.len() != found.len() {
+                diagnostics.push(
+                    SemaError::ArgumentCountMismatch {
+                        function: constructor,
+                        expected: fields.len(),
+                        found: found.len(),
+                    }
+                    .at(expression.span),
+                );
+
+                return Some(ty);
+            }
+
+            for (index, (expected, (arg, found))) in
+                fields.iter().zip(args.iter().zip(found)).enumerate()
+            {
+                // Unknown field types are reported with the declaration of the enum.
+                if let Ok(expected) = tcx.lower(expected)
+                    && let Some(found) = found
+                    && found != expected
+                {
+                    diagnostics.push(
+                        SemaError::ArgumentTypeMismatch {
+                            function: constructor.clone(),
+                            index,
+                            expected: tcx.display(expected).to_string(),
+                            found: tcx.display(found).to_string(),
+                        }
+                        .at(arg.span),
+                    );
+                }
+            }
+
+            Some(ty)
+        }
+        // Both allocations are checked like calls, `new T(value)` to a function taking a `T` and
+        // `alloc::<T>(count)` to one taking a `u32`.
+        Expression::New(typ, operand) | Expression::Alloc(typ, operand) => {
+            let ty = globals.lower_value_type(typ, diagnostics);
+            let (function, expected) = match &expression.node {
+                Expression::New(..) => (format!("new {}", typ.node), ty),
+                _ => (format!("alloc::<{}>", typ.node), Some(tcx.u32())),
+            };
+
+            if let Some(found) = type_of_expected(operand, expected, globals, scopes, diagnostics)
+                && let Some(expected) = expected
+                && found != expected
+            {
+                diagnostics.push(
+                    SemaError::ArgumentTypeMismatch {
+                        function,
+                        index: 0,
+                        expected: tcx.display(expected).to_string(),
+                        found: tcx.display(found).to_string(),
+                    }
+                    .at(operand.span),
+                );
+            }
+
+            Some(tcx.pointer(ty?))
+        }
+        Expression::If(..) => type_of_if(expression, None, globals, scopes, diagnostics),
+        Expression::Block(..) => type_of_block(expression, None, globals, scopes, diagnostics),
+        Expression::Cast(value, typ) => {
+            let found = type_of(value, globals, scopes, diagnostics);
+            let ty = globals.lower_value_type(typ, diagnostics)?;
+
+            if let Some(found) = found
+                && !(tcx.kind(found).is_integer() && tcx.kind(ty).is_integer())
+            {
+                diagnostics.push(
+                    SemaError::InvalidCast {
+                        from: tcx.display(found).to_string(),
+                        to: tcx.display(ty).to_string(),
+                    }
+                    .at(expression.span),
+                );
+            }
+
+            Some(ty)
+        }
+    }
+}
+
+/// Works out the type of an operator written in front of its operand, given the type its context
+/// expects.
+fn type_of_unary(
+    expression: &Spanned<Expression>,
+    expected: Option<Ty>,
+    globals: &Globals,
+    scopes: &Scopes,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Option<Ty> {
+    let Expression::Unary(op, operand) = &expression.node else {
+        return type_of_expected(expression, expected, globals, scopes, diagnostics);
+    };
+
+    let tcx = &globals.tcx;
+
+    // Only the operators that give a value of the type of their operand pass on what is expected.
+    let expected = expected.filter(|_| !matches!(op, UnaryOp::Deref | UnaryOp::AddressOf));
+    let found = type_of_expected(operand, expected, globals, scopes, diagnostics)?;
+
+    let ty = match (op, tcx.kind(found)) {
+        (UnaryOp::Neg | UnaryOp::Not | UnaryOp::BitNot, kind) if kind.is_integer() => Some(found),
+        (UnaryOp::Deref, TyKind::Pointer(pointee)) => Some(pointee),
+        (UnaryOp::AddressOf, _) => match operand.node {
+            Expression::Variable(_) => Some(tcx.pointer(found)),
+            _ => {
+                diagnostics.push(SemaError::AddressOfValue.at(expression.span));
+                return None;
+            }
+        },
+        _ => None,
+    };
+
+    if ty.is_none() {
+        diagnostics.push(
+            SemaError::UnaryTypeMismatch {
+                op: op.to_string(),
+                found: tcx.display(found).to_string(),
+            }
+            .at(expression.span),
+        );
+    }
+
+    ty
+}
+
+/// Works out the type of an operator between two operands, given the type its context expects.
+fn type_of_binary(
+    expression: &Spanned<Expression>,
+    expected: Option<Ty>,
+    globals: &Globals,
+    scopes: &Scopes,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Option<Ty> {
+    let Expression::Binary(op, left, right) = &expression.node else {
+        return type_of_expected(expression, expected, globals, scopes, diagnostics);
+    };
+
+    let tcx = &globals.tcx;
+
+    // A numeric literal takes the type of the other operand, so that `n & 1` works whatever
+    // integer `n` is, or else the type the context expects. The amount to shift by is a `u32`
+    // unless it says otherwise, and comparisons give a `u32` whatever their operands are.
+    let expected = expected.filter(|_| !matches!(op, BinaryOp::Eq | BinaryOp::Ne));
+    let is_literal =
+        |operand: &Spanned<Expression>| matches!(operand.node, Expression::NumericLiteral(_));
+
+    let (left_ty, right_ty) = match op {
+        BinaryOp::Shl | BinaryOp::Shr => (
+            type_of_expected(left, expected, globals, scopes, diagnostics),
+            type_of(right, globals, scopes, diagnostics),
+        ),
+        _ if is_literal(left) && !is_literal(right) => {
+            let right_ty = type_of_expected(right, expected, globals, scopes, diagnostics);
+            let left_ty = type_of_expected(left, right_ty, globals, scopes, diagnostics);
+
+            (left_ty, right_ty)
+        }
+        _ => {
+            let left_ty = type_of_expected(left, expected, globals, scopes, diagnostics);
+            let right_ty = type_of_expected(right, left_ty, globals, scopes, diagnostics);
+
+            (left_ty, right_ty)
+        }
+    };
+    let (left_ty, right_ty) = (left_ty?, right_ty?);
+
+    // The amount to shift by can be any integer, but the other operators need both
+    // operands to have the same type, so integers of different widths are converted with
+    // `as` first. Enums can only be compared, which gives a `u32` like every other
+    // comparison, and only when none of their variants have fields.
+    let ty = match (op, tcx.kind(left_ty), tcx.kind(right_ty)) {
+        (BinaryOp::Shl | BinaryOp::Shr, left, right) if left.is_integer() && right.is_integer() => {
+            Some(left_ty)
+        }
+        (BinaryOp::Eq | BinaryOp::Ne, kind, _) if kind.is_integer() && left_ty == right_ty => {
+            Some(tcx.u32())
+        }
+        (BinaryOp::Eq | BinaryOp::Ne, TyKind::Enum(name), _)
+            if left_ty == right_ty && !globals.item(&name).is_some_and(Item::has_fields) =>
+        {
+            Some(tcx.u32())
+        }
+        (_, kind, _) if kind.is_integer() && left_ty == right_ty => Some(left_ty),
+        (_, left, right) if left.is_integer() && right.is_integer() => {
+            diagnostics.push(
+                SemaError::IntegerMismatch {
+                    op: op.to_string(),
+                    left: tcx.display(left_ty).to_string(),
+                    right: tcx.display(right_ty).to_string(),
+                }
+                .at(expression.span),
+            );
+
+            return None;
+        }
+        _ => None,
+    };
+
+    if ty.is_none() {
+        diagnostics.push(
+            SemaError::BinaryTypeMismatch {
+                op: op.to_string(),
+                left: tcx.display(left_ty).to_string(),
+                right: tcx.display(right_ty).to_string(),
+            }
+            .at(expression.span),
+        );
+    }
+
+    ty
+}
+
+/// Works out the type of a block expression, which is the type of the expression that ends it,
+/// checked against the type its context expects. The statements of the block are checked in a
+/// scope of their own, and can not `return`.
+fn type_of_block(
+    expression: &Spanned<Expression>,
+    expected: Option<Ty>,
+    globals: &Globals,
+    scopes: &Scopes,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Option<Ty> {
+    let Expression::Block(body, value) = &expression.node else {
+        return type_of_expected(expression, expected, globals, scopes, diagnostics);
+    };
+
+    if let Some(span) = find_return(body) {
+        diagnostics.push(SemaError::ReturnInBlock.at(span));
+    }
+
+    let mut scopes = scopes.clone();
+    scopes.push();
+
+    for statement in body {
+        check_statement(statement, globals, &mut scopes, diagnostics);
+    }
+
+    type_of_expected(value, expected, globals, &scopes, diagnostics)
+}
+
+/// Returns where the first `return` of some statements is, looking inside the blocks they contain
+/// but not inside block expressions, which are checked on their own.
+fn find_return(statements: &[Spanned<Statement>]) -> Option<Span> {
+    statements
+        .iter()
+        .find_map(|statement| match &statement.node {
+            Statement::Return(_) => Some(statement.span),
+            Statement::Block(body) | Statement::For(.., body) => find_return(body),
+            Statement::Match(_, arms) => arms.iter().find_map(|(_, body)| find_return(body)),
+            _ => None,
+        })
+}
+
+/// Works out the type of an `if` expression, given the type its context expects, which both
+/// branches are checked against like the expression itself would be. A branch that never returns
+/// takes the type of the other.
+fn type_of_if(
+    expression: &Spanned<Expression>,
+    expected: Option<Ty>,
+    globals: &Globals,
+    scopes: &Scopes,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Option<Ty> {
+    let Expression::If(condition, then, otherwise) = &expression.node else {
+        return type_of_expected(expression, expected, globals, scopes, diagnostics);
+    };
+
+    let tcx = &globals.tcx;
+
+    if let Some(found) = type_of(condition, globals, scopes, diagnostics)
+        && found != tcx.u32()
+    {
+        diagnostics.push(
+            SemaError::UnaryTypeMismatch {
+                op: "if".to_string(),
+                found: tcx.display(found).to_string(),
+            }
+            .at(condition.span),
+        );
+    }
+
+    let then_ty = type_of_expected(then, expected, globals, scopes, diagnostics);
+    let otherwise_ty = type_of_expected(otherwise, expected, globals, scopes, diagnostics);
+    let (then_ty, otherwise_ty) = (then_ty?, otherwise_ty?);
+
+    match (tcx.kind(then_ty), tcx.kind(otherwise_ty)) {
+        (TyKind::Never, _) => Some(otherwise_ty),
+        (_, TyKind::Never) => Some(then_ty),
+        _ if then_ty == otherwise_ty => Some(then_ty),
+        _ => {
+            diagnostics.push(
+                SemaError::BranchTypeMismatch {
+                    then: tcx.display(then_ty).to_string(),
+                    otherwise: tcx.display(otherwise_ty).to_string(),
+                }
+                .at(expression.span),
+            );
+
+            None
+        }
+    }
+}
+
+/// Resolves a variant of an enum to the type of the enum and the types of the fields of the
+/// variant, reporting it at the given span when there is no such variant.
+fn resolve_variant<'a>(
+    name: Symbol,
+    variant: Symbol,
+    span: Span,
+    globals: &Globals<'a>,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Option<(Ty, &'a [Spanned<Type>])> {
+    let error = match globals.item(&name) {
+        Some(Item::Enum(_, variants, _)) => {
+            if let Some((_, fields)) = variants.iter().find(|(other, _)| other.node == variant) {
+                return Some((globals.tcx.lower(&Type::Atomic(name)).ok()?, fields));
+            }
+
+            SemaError::VariantNotFound {
+                name: name.to_string(),
+                variant: variant.to_string(),
+                suggestion: closest(&variant, variants.iter().map(|(v, _)| v.as_str()))
+                    .map(str::to_string),
+            }
+        }
+        Some(_) => SemaError::NotAnEnum {
+            name: name.to_string(),
+        },
+        None => {
+            let names = globals.tcx.names();
+
+            SemaError::TypeNotFound {
+                name: name.to_string(),
+                suggestion: closest(&name, names.iter().map(|name| &name[..])).map(str::to_string),
+            }
+        }
+    };
+
+    diagnostics.push(error.at_name(span, Span::new(span.start, span.start + name.len())));
+
+    None
+}
+
+/// Works out the type of an expression like [`type_of`], given the type its context expects.
+/// Numeric literals take the expected type when it is an integer type, and are `u32`s otherwise,
+/// and are checked to fit the type they take.
+pub fn type_of_expected(
+    expression: &Spanned<Expression>,
+    expected: Option<Ty>,
+    globals: &Globals,
+    scopes: &Scopes,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Option<Ty> {
+    let tcx = &globals.tcx;
+
+    // String literals are `*char`s, unless a `str` is expected.
+    if let Expression::StringLiteral(_) = expression.node
+        && expected == Some(tcx.str())
+    {
+        return expected;
+    }
+
+    match expression.node {
+        Expression::If(..) => {
+            return type_of_if(expression, expected, globals, scopes, diagnostics);
+        }
+        Expression::Block(..) => {
+            return type_of_block(expression, expected, globals, scopes, diagnostics);
+        }
+        Expression::Unary(UnaryOp::Neg | UnaryOp::Not | UnaryOp::BitNot, _) => {
+            return type_of_unary(expression, expected, globals, scopes, diagnostics);
+        }
+        Expression::Binary(..) => {
+            return type_of_binary(expression, expected, globals, scopes, diagnostics);
+        }
+        _ => {}
+    }
+
+    let Expression::NumericLiteral(value) = expression.node else {
+        return type_of(expression, globals, scopes, diagnostics);
+    };
+
+    let ty = expected
+        .filter(|ty| tcx.max_value(*ty).is_some())
+        .unwrap_or(tcx.u32());
+    let max = tcx.max_value(ty).unwrap_or(u32::MAX as u64);
+
+    if value > max {
+        diagnostics.push(
+            SemaError::LiteralOutOfRange {
+                value,
+                ty: tcx.display(ty).to_string(),
+                max,
+            }
+            .at(expression.span),
+        );
+    }
+
+    Some(ty)
+}
+
+/// Checks a single statement, adding every problem found to `diagnostics`. Bindings declared by
+/// the statement are added to the innermost scope.
+pub fn check_statement(
+    statement: &Spanned<Statement>,
+    globals: &Globals,
+    scopes: &mut Scopes,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    match &statement.node {
+        Statement::FunctionCall(name, args) if is_format_call(name, globals, scopes) => {
+            check_format(name, args, statement.span, globals, scopes, diagnostics);
+        }
+        Statement::FunctionCall(name, args) => {
+            check_call(name, args, statement.span, globals, scopes, diagnostics);
+        }
+        Statement::Return(expression) => {
+            let found = type_of_expected(
+                expression,
+                scopes.return_type(),
+                globals,
+                scopes,
+                diagnostics,
+            );
+
+            check_type(
+                expression,
+                scopes.return_type(),
+                found,
+                globals,
+                diagnostics,
+            );
+
+            // Every variable lives on the stack of the function declaring it, so a pointer to one
+            // dangles as soon as the function returns.
+            if let Expression::Unary(UnaryOp::AddressOf, operand) = &expression.node
+                && let Expression::Variable(name) = &operand.node
+                && scopes.get(name).is_some()
+            {
+                diagnostics.push(
+                    SemaError::ReturnsLocalAddress {
+                        name: name.to_string(),
+                    }
+                    .at(expression.span),
+                );
+            }
+        }
+        Statement::Let(name, typ, expression) => {
+            let ty = globals.lower_value_type(typ, diagnostics);
+
+            let found = type_of_expected(expression, ty, globals, scopes, diagnostics);
+
+            check_type(expression, ty, found, globals, diagnostics);
+
+            if let Some(ty) = ty {
+                scopes.insert(name, ty);
+            }
+        }
+        Statement::Block(body) => check_block(body, globals, scopes, diagnostics),
+        Statement::For(name, start, end, body) => {
+            type_of(start, globals, scopes, diagnostics);
+            type_of(end, globals, scopes, diagnostics);
+
+            scopes.push();
+            scopes.insert(name, globals.tcx.u32());
+            check_block(body, globals, scopes, diagnostics);
+            scopes.pop();
+        }
+        Statement::Match(value, arms) => {
+            check_match(value, arms, statement.span, globals, scopes, diagnostics);
+        }
+        Statement::Free(pointer) => {
+            if let Some(found) = type_of(pointer, globals, scopes, diagnostics)
+                && !matches!(globals.tcx.kind(found), TyKind::Pointer(_))
+            {
+                diagnostics.push(
+                    SemaError::UnaryTypeMismatch {
+                        op: "free".to_string(),
+                        found: globals.tcx.display(found).to_string(),
+                    }
+                    .at(pointer.span),
+                );
+            }
+        }
+        Statement::Assert(condition, _) => {
+            if let Some(found) = type_of(condition, globals, scopes, diagnostics)
+                && found != globals.tcx.u32()
+            {
+                diagnostics.push(
+                    SemaError::UnaryTypeMismatch {
+                        op: "assert".to_string(),
+                        found: globals.tcx.display(found).to_string(),
+                    }
+                    .at(condition.span),
+                );
+            }
+        }
+        Statement::Unreachable | Statement::Asm(_) | Statement::Error(_) => {}
+    }
+}
+
+/// Reports a value whose type is not the one it is expected to have, such as the value of a `let`
+/// of another type. Values of integers of different widths are never converted implicitly, and a
+/// value that never comes to be, like a call to a function that never returns, has every type.
+fn check_type(
+    expression: &Spanned<Expression>,
+    expected: Option<Ty>,
+    found: Option<Ty>,
+    globals: &Globals,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let tcx = &globals.tcx;
+
+    if let (Some(expected), Some(found)) = (expected, found)
+        && found != expected
+        && tcx.kind(found) != TyKind::Never
+    {
+        diagnostics.push(
+            SemaError::TypeMismatch {
+                expected: tcx.display(expected).to_string(),
+                found: tcx.display(found).to_string(),
+            }
+            .at(expression.span),
+        );
+    }
+}
+
+/// Checks a `match` statement: every pattern must be able to match the value, no two arms may
+/// match the same thing, and a `match` over an enum must match all of its variants or have a
+/// wildcard arm.
+fn check_match(
+    value: &Spanned<Expression>,
+    arms: &[Arm],
+    span: Span,
+    globals: &Globals,
+    scopes: &mut Scopes,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let tcx = &globals.tcx;
+    let ty = type_of(value, globals, scopes, diagnostics);
+
+    if let Some(ty) = ty
+        && !matches!(tcx.kind(ty), TyKind::U32 | TyKind::Char | TyKind::Enum(_))
+    {
+        diagnostics.push(
+            SemaError::UnmatchableType {
+                found: tcx.display(ty).to_string(),
+            }
+            .at(value.span),
+        );
+    }
+
+    for (index, (pattern, body)) in arms.iter().enumerate() {
+        // Fields bound by the pattern live in a scope of their own around the arm.
+        scopes.push();
+
+        let found = match &pattern.node {
+            Pattern::Number(number) => {
+                let literal = Spanned::new(Expression::NumericLiteral(*number), pattern.span);
+                type_of_expected(&literal, ty, globals, scopes, diagnostics)
+            }
+            Pattern::Char(_) => Some(tcx.char()),
+            Pattern::Variant(name, variant, bindings) => {
+                let resolved = resolve_variant(*name, *variant, pattern.span, globals, diagnostics);
+
+                if let Some((_, fields)) = resolved {
+                    check_bindings(pattern, bindings, fields, globals, scopes, diagnostics);
+                }
+
+                resolved.map(|(ty, _)| ty)
+            }
+            Pattern::Wildcard => ty,
+        };
+
+        if let (Some(expected), Some(found)) = (ty, found)
+            && expected != found
+        {
+            diagnostics.push(
+                SemaError::PatternTypeMismatch {
+                    pattern: pattern.to_string(),
+                    expected: tcx.display(expected).to_string(),
+                }
+                .at(pattern.span),
+            );
+        }
+
+        // Backends lower a `match` to a switch, which cannot have the same case twice.
+        if arms[..index]
+            .iter()
+            .any(|(other, _)| other.node.overlaps(&pattern.node))
+        {
+            diagnostics.push(
+                SemaError::DuplicatePattern {
+                    pattern: pattern.to_string(),
+                }
+                .at(pattern.span),
+            );
+        }
+
+        check_block(body, globals, scopes, diagnostics);
+        scopes.pop();
+    }
+
+    if let Some(ty) = ty
+        && let TyKind::Enum(name) = tcx.kind(ty)
+        && let Some(variants) = globals.variants(&name)
+    {
+        let missing = missing_variants(arms, name, variants);
+
+        if !missing.is_empty() {
+            let missing = missing
+                .iter()
+                .map(|variant| format!("`{name}::{variant}`"))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            diagnostics.push(SemaError::NonExhaustiveMatch { missing }.at(span));
+        }
+    }
+}
+
+/// Checks that a variant pattern binds every field of its variant, binding each to its type in
+/// the innermost scope.
+fn check_bindings(
+    pattern: &Spanned<Pattern>,
+    bindings: &[Symbol],
+    fields: &[Spanned<Type>],
+    globals: &Globals,
+    scopes: &mut Scopes,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    if bindings.len() != fields.len() {
+        diagnostics.push(
+            SemaError::BindingCountMismatch {
+                pattern: pattern.to_string(),
+                expected: fields.len(),
+                found: bindings.len(),
+            }
+            .at(pattern.span),
+        );
+    }
+
+    for (binding, field) in bindings.iter().zip(fields) {
+        if &binding[..] != "_"
+            && let Ok(ty) = globals.tcx.lower(field)
+        {
+            scopes.insert(binding, ty);
+        }
+    }
+}
+
+/// Checks every statement of a block inside of its own scope.
+pub fn check_block(
+    body: &[Spanned<Statement>],
+    globals: &Globals,
+    scopes: &mut Scopes,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    scopes.push();
+
+    for statement in body {
+        check_statement(statement, globals, scopes, diagnostics);
+    }
+
+    scopes.pop();
+}
+
+/// Checks a constant array against its declared type.
+fn check_const(
+    name: &str,
+    typ: &Spanned<Type>,
+    elements: &[Spanned<Expression>],
+    span: Span,
+    globals: &Globals,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let Some(ty) = globals.lower_type(typ, diagnostics) else {
+        return;
+    };
+
+    let TyKind::Array(element, length) = globals.tcx.kind(ty) else {
+        diagnostics.push(
+            SemaError::ConstNotArray {
+                name: name.to_string(),
+            }
+            .at(typ.span),
+        );
+
+        return;
+    };
+
+    if elements.len() as u64 != length {
+        diagnostics.push(
+            SemaError::ArrayLengthMismatch {
+                name: name.to_string(),
+                expected: length,
+                found: elements.len(),
+            }
+            .at(span),
+        );
+    }
+
+    for (index, value) in elements.iter().enumerate() {
+        if let Some(found) =
+            type_of_expected(value, Some(element), globals, &Scopes::new(), diagnostics)
+            && found != element
+        {
+            diagnostics.push(
+                SemaError::ArrayElementMismatch {
+                    name: name.to_string(),
+                    index,
+                    expected: globals.tcx.display(element).to_string(),
+                    found: globals.tcx.display(found).to_string(),
+                }
+                .at(value.span),
+            );
+        }
+    }
+}
+
+/// Checks that no variant of an enum is declared twice, and that the fields of its variants have
+/// types that values can have. A field can not itself be an enum with fields, which keeps every
+/// field a single scalar, and rules out enums that contain themselves.
+fn check_enum(
+    name: &str,
+    variants: &[Variant],
+    globals: &Globals,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    for (index, (variant, fields)) in variants.iter().enumerate() {
+        if variants[..index]
+            .iter()
+            .any(|(other, _)| other.node == variant.node)
+        {
+            diagnostics.push(
+                SemaError::DuplicateVariant {
+                    name: name.to_string(),
+                    variant: variant.to_string(),
+                }
+                .at(variant.span),
+            );
+        }
+
+        for field in fields {
+            let ty = globals.lower_value_type(field, diagnostics);
+
+            // Every field is kept in a slot as wide as a pointer.
+            if ty == Some(globals.tcx.str()) {
+                diagnostics.push(
+                    SemaError::StrField {
+                        variant: format!("{name}::{variant}"),
+                    }
+                    .at(field.span),
+                );
+            }
+
+            if let Some(ty) = ty
+                && let TyKind::Enum(field_enum) = globals.tcx.kind(ty)
+                && globals.item(&field_enum).is_some_and(Item::has_fields)
+            {
+                diagnostics.push(
+                    SemaError::NestedFields {
+                        variant: format!("{name}::{variant}"),
+                        found: field_enum.to_string(),
+                    }
+                    .at(field.span),
+                );
+            }
+        }
+    }
+}
+
+/// Checks a single top-level item, adding every problem found to `diagnostics`.
+pub fn check_item(item: &Spanned<Item>, globals: &Globals, diagnostics: &mut Vec<Diagnostic>) {
+    if runtime::is_runtime(item.name()) {
+        diagnostics.push(
+            SemaError::ReservedName {
+                name: item.name().to_string(),
+                prefix: runtime::PREFIX,
+            }
+            .at(item.span),
+        );
+    }
+
+    let returns = match &item.node {
+        Item::Const(name, typ, elements, _) => {
+            return check_const(name, typ, elements, item.span, globals, diagnostics);
+        }
+        Item::Enum(name, variants, _) => {
+            return check_enum(name, variants, globals, diagnostics);
+        }
+        Item::ExternFunctionDefinition(_, _, returns, ..)
+        | Item::FunctionDeclaration(_, _, _, returns, ..) => returns,
+    };
+
+    // The type parameters of a generic function can only be inferred from its arguments.
+    for generic in item.generics() {
+        if !item
+            .arguments()
+            .iter()
+            .any(|(_, typ)| mentions(&typ.node, *generic))
+        {
+            diagnostics.push(
+                SemaError::UninferableGeneric {
+                    name: generic.to_string(),
+                    function: item.name().to_string(),
+                }
+                .at(item.span),
+            );
+        }
+    }
+
+    globals.tcx.set_generics(item.generics());
+
+    for (_, typ) in item.arguments() {
+        globals.lower_value_type(typ, diagnostics);
+    }
+
+    globals.lower_value_type(returns, diagnostics);
+
+    match &item.node {
+        Item::ExternFunctionDefinition(..) | Item::Const(..) | Item::Enum(..) => {}
+        Item::FunctionDeclaration(name, _, args, _, body, ..) => {
+            let mut scopes = Scopes::returning(globals.tcx.lower(returns).ok());
+
+            for (arg, typ) in args {
+                if let Ok(ty) = globals.tcx.lower(typ) {
+                    scopes.insert(arg, ty);
+                }
+            }
+
+            for statement in body {
+                check_statement(statement, globals, &mut scopes, diagnostics);
+            }
+
+            if !always_returns(body, &|name| globals.item(name)) {
+                diagnostics.push(
+                    SemaError::MissingReturn {
+                        function: name.to_string(),
+                    }
+                    .at(item.span),
+                );
+            }
+        }
+    }
+
+    globals.tcx.set_generics(&[]);
+}
+
+/// Returns whether a type written in the source refers to a type parameter.
+fn mentions(typ: &Type, generic: Symbol) -> bool {
+    match typ {
+        Type::Atomic(name) => *name == generic,
+        Type::Pointer(inner) | Type::Array(inner, _) => mentions(inner, generic),
+        Type::Function(arguments, returns) => {
+            arguments.iter().any(|argument| mentions(argument, generic))
+                || mentions(returns, generic)
+        }
+        Type::Never => false,
+    }
+}
+
+/// Builds the module for a script. Items are kept as they are, while bare top-level statements
+/// are gathered, in order, into the body of an implicit `fn main() -> u32` that returns 0 unless
+/// the statements return on their own.
+pub fn synthesize_main(
+    name: String,
+    elements: Vec<TopLevel>,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Module {
+    let mut items = vec![];
+    let mut body = vec![];
+
+    for element in elements {
+        match element {
+            TopLevel::Item(item) => items.push(item),
+            TopLevel::Statement(statement) => body.push(statement),
+        }
+    }
+
+    if body.is_empty() {
+        return Module(name, items, vec![]);
+    }
+
+    if let Some(main) = items.iter().find(|item| item.name() == "main") {
+        diagnostics.push(SemaError::ScriptDeclaresMain.at(main.span));
+    }
+
+    // The implicit function spans every top-level statement, from the first to the last.
+    let span = Span::new(body[0].span.start, body[body.len() - 1].span.end);
+
+    let item = |name: &str| {
+        items
+            .iter()
+            .find(|item| item.name() == name)
+            .map(|item| &item.node)
+    };
+
+    if !always_returns(&body, &item) {
+        body.push(Statement::Return(Expression::NumericLiteral(0).into()).into());
+    }
+
+    items.push(Spanned::new(
+        Item::FunctionDeclaration(
+            "main".into(),
+            vec![],
+            vec![],
+            Type::Atomic("u32".into()).into(),
+            body,
+            vec![],
+            Visibility::Private,
+        ),
+        span,
+    ));
+
+    Module(name, items, vec![])
+}
+
+/// Checks an AST module node before it is handed over to codegen, returning every problem found
+/// rather than stopping at the first.
+pub fn check_module(module: &Module) -> Vec<Diagnostic> {
+    check_module_with_imports(module, &[])
+}
+
+/// Checks a module like [`check_module`], resolving names that are not declared in the module
+/// itself against the modules it imports.
+pub fn check_module_with_imports(module: &Module, imports: &[&Module]) -> Vec<Diagnostic> {
+    let mut diagnostics = vec![];
+    let globals = Globals::collect_with_imports(module, imports);
+
+    for item in &module.1 {
+        check_item(item, &globals, &mut diagnostics);
+    }
+
+    diagnostics
+}
+
+/// Checks that a module can be the entry point of a program: it must declare a `main` function
+/// that the C runtime can call, unless it provides its own `_start`.
+pub fn check_entry(module: &Module) -> Vec<Diagnostic> {
+    let functions: Vec<_> = module
+        .1
+        .iter()
+        .filter(|item| matches!(item.node, Item::FunctionDeclaration(..)))
+        .collect();
+
+    let Some(main) = functions.iter().find(|item| item.name() == "main") else {
+        return match functions.iter().any(|item| item.name() == "_start") {
+            true => vec![],
+            false => vec![SemaError::MissingMain.into()],
+        };
+    };
+
+    // Both forms have the same signature as the C entry point, as `u32` and `int` are passed the
+    // same way.
+    let arguments: Vec<_> = main
+        .arguments()
+        .iter()
+        .map(|(_, typ)| typ.to_string())
+        .collect();
+    let returns = main
+        .return_type()
+        .map_or(String::new(), |returns| returns.to_string());
+
+    match (&arguments[..], &returns[..]) {
+        _ if !main.generics().is_empty() => vec![SemaError::GenericMain.at(main.span)],
+        ([], "u32") => vec![],
+        ([argc, argv], "u32") if argc == "u32" && argv == "**char" => vec![],
+        _ => vec![
+            SemaError::InvalidMainSignature {
+                found: format!("fn({}) -> {returns}", arguments.join(", ")),
+            }
+            .at(main.span),
+        ],
+    }
+}
+
+#[cfg(test)]
+fn check_source(source: &str) -> Vec<Diagnostic> {
+    let (module, _) = crate::spec::module("main".to_string())
+        .parse(source)
+        .unwrap();
+
+    check_module(&module)
+}
+
+#[test]
+fn functions_that_return_pass() {
+    assert!(check_source("fn main() -> u32 { return 1; };").is_empty());
+    assert!(check_source("fn main() -> u32 { { return 1; } };").is_empty());
+}
+
+#[test]
+fn functions_without_return_fail() {
+    assert_eq!(
+        check_source("fn broken() -> u32 { let x: u32 = 1; };"),
+        vec![
+            SemaError::MissingReturn {
+                function: "broken".to_string()
+            }
+            .at(Span::new(0, 38))
+        ]
+    );
+}
+
+#[test]
+fn every_problem_is_reported() {
+    assert_eq!(check_source("fn a() -> u32 {}; fn b() -> u32 {};").len(), 2);
+}
+
+#[test]
+fn scripts_get_an_implicit_main() {
+    let ((elements, _), _) = crate::spec::recovering_script()
+        .parse("ext fn puts(s: *char) -> u32; puts(\"hi\");")
+        .unwrap();
+    let mut diagnostics = vec![];
+    let module = synthesize_main("main".to_string(), elements, &mut diagnostics);
+
+    assert!(diagnostics.is_empty());
+    assert_eq!(
+        module.1[1].node,
+        Item::FunctionDeclaration(
+            "main".into(),
+            vec![],
+            vec![],
+            Type::Atomic("u32".into()).into(),
+            vec![
+                Statement::FunctionCall(
+                    "puts".into(),
+                    vec![Expression::StringLiteral("hi".to_string()).into()]
+                )
+                .into(),
+                Statement::Return(Expression::NumericLiteral(0).into()).into(),
+            ],
+            vec![],
+            Visibility::Private,
+        )
+    );
+}
+
+#[test]
+fn item_ids_are_stable_across_reparses() {
+    let parse = |source: &str| {
+        crate::spec::module("main".to_string())
+            .parse(source)
+            .unwrap()
+            .0
+            .1
+    };
+
+    let before = parse("fn main() -> u32 { return 1; };");
+    let after = parse("\n\nfn main(argc: u32) -> u32 {\n    return 2;\n};");
+    let external = parse("ext fn main() -> u32;");
+
+    assert_eq!(ItemId::of(&before[0]), ItemId::of(&after[0]));
+    assert_ne!(ItemId::of(&before[0]), ItemId::of(&external[0]));
+}
+
+#[test]
+fn functions_may_be_called_before_they_are_declared() {
+    assert!(
+        check_source("fn main() -> u32 { later(); return 0; }; fn later() -> u32 { return 1; };")
+            .is_empty()
+    );
+}
+
+#[test]
+fn calls_to_undeclared_functions_fail() {
+    assert_eq!(
+        check_source("fn foo() -> u32 { return 0; }; fn main() -> u32 { { fooo(); } return 0; };"),
+        vec![
+            SemaError::FunctionNotFound {
+                name: "fooo".to_string(),
+                suggestion: Some("foo".to_string()),
+            }
+            .at_name(Span::new(52, 59), Span::new(52, 56))
+        ]
+    );
+
+    assert_eq!(
+        check_source("fn main() -> u32 { printf(); return 0; };")[0].message,
+        "function `printf` not found"
+    );
+}
+
+#[test]
+fn calls_are_checked_against_signatures() {
+    let source = |call: &str| {
+        format!(
+            "ext fn printf(ptr: *char) -> u32;
+            fn twice(x: u32) -> u32 {{ return x; }};
+            fn main() -> u32 {{ {call}; return 0; }};"
+        )
+    };
+
+    assert!(check_source(&source("printf(\"hi\")")).is_empty());
+    assert!(check_source(&source("let x: u32 = twice(twice(1))")).is_empty());
+
+    assert_eq!(
+        check_source(&source("printf()")),
+        vec![
+            SemaError::ArgumentCountMismatch {
+                function: "printf".to_string(),
+                expected: 1,
+                found: 0,
+            }
+            .at(Span::new(116, 125))
+        ]
+    );
+
+    assert_eq!(
+        check_source(&source("return twice(printf(\"hi\"), 'c')"))[0].message,
+        "function `twice` takes 1 argument(s), but 2 were given"
+    );
+
+    assert_eq!(
+        check_source(&source("let x: u32 = twice('c')")),
+        vec![
+            SemaError::ArgumentTypeMismatch {
+                function: "twice".to_string(),
+                index: 0,
+                expected: "u32".to_string(),
+                found: "char".to_string(),
+            }
+            .at(Span::new(135, 138))
+        ]
+    );
+}
+
+#[test]
+fn variables_must_be_in_scope() {
+    assert!(
+        check_source(
+            "fn main(argc: u32) -> u32 { for i in 0..argc { let x: u32 = i; } return argc; };"
+        )
+        .is_empty()
+    );
+
+    assert_eq!(
+        check_source("fn main() -> u32 { { let count: u32 = 1; } return count; };"),
+        vec![
+            SemaError::VariableNotFound {
+                name: "count".to_string(),
+                suggestion: None,
+            }
+            .at(Span::new(50, 55))
+        ]
+    );
+
+    assert_eq!(
+        check_source("fn main(count: u32) -> u32 { return cont; };")[0].message,
+        "variable `cont` not found; did you mean `count`?"
+    );
+}
+
+#[test]
+fn unknown_types_are_reported() {
+    assert_eq!(
+        check_source("fn main(s: *i16) -> u32 { let x: i64 = 1; return 0; };"),
+        vec![
+            SemaError::TypeNotFound {
+                name: "i16".to_string(),
+                suggestion: Some("u16".to_string()),
+            }
+            .at_name(Span::new(11, 15), Span::new(12, 15)),
+            SemaError::TypeNotFound {
+                name: "i64".to_string(),
+                suggestion: Some("u64".to_string()),
+            }
+            .at_name(Span::new(33, 36), Span::new(33, 36)),
+        ]
+    );
+}
+
+#[test]
+fn suggested_names_are_fixes() {
+    let source = "fn main(s: *u3) -> u32 { let count: u32 = 0; return coun; };";
+    let diagnostics = check_source(source);
+
+    assert_eq!(
+        crate::diagnostic::apply_fixes(source, &diagnostics),
+        (
+            "fn main(s: *u32) -> u32 { let count: u32 = 0; return count; };".to_string(),
+            2
+        )
+    );
+}
+
+#[test]
+fn calls_to_diverging_functions_end_a_path() {
+    assert!(
+        check_source(
+            "ext fn exit(code: u32) -> !;
+            fn fail() -> ! { exit(1); };
+            fn main() -> u32 { { fail(); } };"
+        )
+        .is_empty()
+    );
+
+    assert_eq!(
+        check_source("ext fn exit(code: u32) -> u32; fn main() -> u32 { exit(1); };").len(),
+        1
+    );
+}
+
+#[test]
+fn unreachable_ends_a_path() {
+    assert!(
+        check_source("fn pick(x: u32) -> u32 { for i in 0..x { return i; } unreachable(); };")
+            .is_empty()
+    );
+}
+
+#[test]
+fn imported_items_are_resolved() {
+    let parse = |name: &str, source: &str| {
+        crate::spec::module(name.to_string())
+            .parse(source)
+            .unwrap()
+            .0
+    };
+
+    let math = parse(
+        "math",
+        "pub fn twice(x: u32) -> u32 { return half(x); }; fn half(x: u32) -> u32 { return x; };",
+    );
+    let main = parse(
+        "main",
+        "import math; fn main() -> u32 { return twice(1); };",
+    );
+
+    assert!(check_module_with_imports(&main, &[&math]).is_empty());
+    assert_eq!(check_module(&main).len(), 1);
+
+    let private = parse("main", "import math; fn main() -> u32 { return half(1); };");
+
+    assert_eq!(
+        check_module_with_imports(&private, &[&math]),
+        vec![
+            SemaError::PrivateItem {
+                name: "half".to_string(),
+                module: "math".to_string(),
+            }
+            .at(Span::new(39, 46))
+        ]
+    );
+}
+
+#[test]
+fn constant_arrays_are_checked() {
+    assert!(
+        check_source(
+            "const TABLE: [u32; 2] = [1, 2];
+            fn main() -> u32 { for i in 0..2 { return TABLE[i]; } return TABLE[1]; };"
+        )
+        .is_empty()
+    );
+
+    let messages = |source| {
+        check_source(source)
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        messages(
+            "const TABLE: [u32; 2] = [1, 'a', 3];
+            fn main() -> u32 { let t: [u32; 2] = 0; TABLE(); return TABLE[2]; };"
+        ),
+        vec![
+            "constant `TABLE` should have 2 element(s), but 3 were given",
+            "element 2 of `TABLE` should be `u32`, found `char`",
+            "arrays can only be declared with `const`",
+            "expected a value of type `[u32; 2]`, found `u32`; convert it with `as`",
+            "`TABLE` is a constant, not a function",
+            "index 2 is out of bounds for `TABLE`, which has 2 element(s)",
+        ]
+    );
+    assert_eq!(
+        messages("const N: u32 = [1]; fn main() -> u32 { return main[0]; };"),
+        vec![
+            "constant `N` must have an array type",
+            "`main` is not a constant array",
+        ]
+    );
+}
+
+#[test]
+fn unary_operators_are_checked() {
+    assert!(
+        check_source("fn main() -> u32 { let x: u32 = 1; let p: *u32 = &x; return -~!*p; };")
+            .is_empty()
+    );
+
+    let messages: Vec<_> = check_source("fn main() -> u32 { let c: u32 = *1; return -&0; };")
+        .into_iter()
+        .map(|diagnostic| diagnostic.message)
+        .collect();
+
+    assert_eq!(
+        messages,
+        vec![
+            "cannot apply `*` to a value of type `u32`",
+            "`&` can only take the address of a variable",
+        ]
+    );
+}
+
+#[test]
+fn binary_operators_are_checked() {
+    assert!(
+        check_source(
+            "fn main() -> u32 { let c: char = 'a' ^ ' '; let x: u32 = 1 << c; return x & 3; };"
+        )
+        .is_empty()
+    );
+
+    let messages: Vec<_> = check_source("fn main(n: u32) -> u32 { return n | 'a'; };")
+        .into_iter()
+        .map(|diagnostic| diagnostic.message)
+        .collect();
+
+    assert_eq!(
+        messages,
+        vec![
+            "cannot apply `|` to integers of different types, `u32` and `char`; convert one with `as`"
+        ]
+    );
+}
+
+#[test]
+fn prints_are_checked() {
+    assert!(
+        check_source(
+            "fn main(n: u64, s: str) -> u32 { println(\"{} {} {}\", n, s, 'a'); print(\"{{}}\"); return 0; };"
+        )
+        .is_empty()
+    );
+    assert!(
+        check_source(
+            "fn println(x: u32) -> u32 { return x; }; fn main() -> u32 { println(1); return 0; };"
+        )
+        .is_empty()
+    );
+
+    let messages: Vec<_> = check_source(
+        "fn main(f: fn() -> u32, s: *char) -> u32 { println(s); print(\"{\"); println(\"{} {}\", 1); println(\"{}\", f); let x: u32 = println(\"\"); return 0; };",
+    )
+    .into_iter()
+    .map(|diagnostic| diagnostic.message)
+    .collect();
+
+    assert_eq!(
+        messages,
+        vec![
+            "the first argument of `println` must be a string literal, which is its format",
+            "format string \"{\" has a brace that is not part of `{}`; write `{{` or `}}` for a literal brace",
+            "format string has 2 `{}`, but 1 argument(s) were given",
+            "a value of type `fn() -> u32` cannot be printed; only integers, `char`, `str` and `*char` can",
+            "`println` gives no value, so it can only be called as a statement",
+        ]
+    );
+}
+
+#[test]
+fn returning_the_address_of_a_local_is_rejected() {
+    let messages: Vec<_> = check_source(
+        "fn f(n: u32) -> *u32 { let x: u32 = n; return &n; }; fn main() -> u32 { return 0; };",
+    )
+    .into_iter()
+    .map(|diagnostic| diagnostic.message)
+    .collect();
+
+    assert_eq!(
+        messages,
+        vec!["cannot return the address of local variable `n`, which is freed on return"]
+    );
+}
+
+#[test]
+fn literals_must_fit_their_expected_type() {
+    assert!(check_source("fn main() -> u32 { return 4294967295; };").is_empty());
+
+    let diagnostics = check_source(
+        "ext fn sleep(seconds: u32) -> u32;
+        fn main() -> u32 { let c: char = 200; sleep(5000000000); return 0x1_0000_0000; };",
+    );
+    let messages: Vec<_> = diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.message.as_str())
+        .collect();
+
+    assert_eq!(
+        messages,
+        vec![
+            "literal `200` does not fit in `char`, which holds values from 0 to 127",
+            "literal `5000000000` does not fit in `u32`, which holds values from 0 to 4294967295",
+            "literal `4294967296` does not fit in `u32`, which holds values from 0 to 4294967295",
+        ]
+    );
+}
+
+#[test]
+fn entry_modules_need_a_valid_main() {
+    let messages = |source: &str| -> Vec<_> {
+        let (module, _) = crate::spec::module("main".to_string())
+            .parse(source)
+            .unwrap();
+
+        check_entry(&module)
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect()
+    };
+
+    assert!(messages("fn main() -> u32 { return 0; };").is_empty());
+    assert!(messages("fn main(argc: u32, argv: **char) -> u32 { return argc; };").is_empty());
+    assert!(messages("fn _start() -> u32 { return 0; };").is_empty());
+
+    assert_eq!(
+        messages("ext fn main() -> u32; fn helper() -> u32 { return 0; };"),
+        vec![
+            "no `main` function found; declare `fn main() -> u32`, or `_start` to run without libc"
+        ]
+    );
+    assert_eq!(
+        messages("fn main(argc: u32) -> char { return 'a'; };"),
+        vec![
+            "`main` must be `fn() -> u32` or `fn(argc: u32, argv: **char) -> u32`, found \
+             `fn(u32) -> char`"
+        ]
+    );
+}
+
+#[test]
+fn enums_are_checked() {
+    assert!(
+        check_source(
+            "enum Color { Red, Green };
+            fn is_red(c: Color) -> u32 { return c == Color::Red; };
+            fn main() -> u32 { return is_red(Color::Green); };"
+        )
+        .is_empty()
+    );
+
+    let messages: Vec<_> = check_source(
+        "enum Color { Red, Green, Red };
+        fn main() -> u32 { let c: Color = Color::Gren; Color(); return Color::Red == 0; };",
+    )
+    .into_iter()
+    .map(|diagnostic| diagnostic.message)
+    .collect();
+
+    assert_eq!(
+        messages,
+        vec![
+            "variant `Red` is declared more than once in enum `Color`",
+            "enum `Color` has no variant `Gren`; did you mean `Green`?",
+            "`Color` is an enum, not a function",
+            "cannot apply `==` to values of type `Color` and `u32`",
+        ]
+    );
+}
+
+#[test]
+fn matches_are_checked() {
+    assert!(
+        check_source(
+            "enum Color { Red, Green };
+            fn code(c: Color) -> u32 { match c { Color::Red => { return 1; } Color::Green => { return 2; } } };
+            fn main() -> u32 { match code(Color::Red) { 1 => { return 0; } _ => { return 1; } } };"
+        )
+        .is_empty()
+    );
+
+    let messages: Vec<_> = check_source(
+        "enum Color { Red, Green, Blue };
+        fn main() -> u32 {
+            match Color::Red { Color::Red => {} 'a' => {} Color::Red => {} }
+            let c: char = 'a';
+            match c { 300 => {} }
+            return 0;
+        };",
+    )
+    .into_iter()
+    .map(|diagnostic| diagnostic.message)
+    .collect();
+
+    assert_eq!(
+        messages,
+        vec![
+            "pattern `'a'` cannot match a value of type `Color`",
+            "pattern `Color::Red` is matched by an earlier arm",
+            "match is not exhaustive; add arms for `Color::Green`, `Color::Blue` or a `_` arm",
+            "literal `300` does not fit in `char`, which holds values from 0 to 127",
+        ]
+    );
+}
+
+#[test]
+fn enum_fields_are_checked() {
+    assert!(
+        check_source(
+            "enum Shape { Circle(u32), Rect(u32, char) };
+            fn width(s: Shape) -> u32 { match s { Shape::Circle(r) => { return r; } Shape::Rect(w, _) => { return w; } } };
+            fn main() -> u32 { return width(Shape::Rect(3, 'x')); };"
+        )
+        .is_empty()
+    );
+
+    let messages: Vec<_> = check_source(
+        "enum Shape { Circle(u32), Rect(u32, char) };
+        enum Scene { One(Shape) };
+        fn main() -> u32 {
+            let s: Shape = Shape::Rect(1);
+            let t: Shape = Shape::Circle('a');
+            match s { Shape::Circle => {} Shape::Rect(w, h) => { return h; } }
+            return s == t;
+        };",
+    )
+    .into_iter()
+    .map(|diagnostic| diagnostic.message)
+    .collect();
+
+    assert_eq!(
+        messages,
+        vec![
+            "a field of `Scene::One` cannot be `Shape`, which is an enum with fields itself",
+            "function `Shape::Rect` takes 2 argument(s), but 1 were given",
+            "argument 1 of `Shape::Circle` should be `u32`, found `char`",
+            "pattern `Shape::Circle` binds 0 field(s), but its variant has 1",
+            "expected a value of type `u32`, found `char`; convert it with `as`",
+            "cannot apply `==` to values of type `Shape` and `Shape`",
+        ]
+    );
+}
+
+#[test]
+fn generic_calls_are_inferred_from_their_arguments() {
+    assert!(
+        check_source(
+            "fn id<T>(x: T) -> T { let y: T = x; return y; };
+            fn first<T>(p: *T, q: *T) -> T { return id(*p); };
+            fn main() -> u32 { let c: char = id('a'); let d: char = first(&c, &c); return id(7); };"
+        )
+        .is_empty()
+    );
+
+    let messages: Vec<_> = check_source(
+        "fn id<T>(x: T) -> T { return x ^ x; };
+        fn same<T>(a: T, b: *T) -> u32 { return 0; };
+        fn make<T>() -> T { unreachable(); };
+        fn main() -> u32 { let c: char = 'c'; same('a', 'b'); same(1, &c); return 0; };",
+    )
+    .into_iter()
+    .map(|diagnostic| diagnostic.message)
+    .collect();
+
+    assert_eq!(
+        messages,
+        vec![
+            "cannot apply `^` to values of type `T` and `T`",
+            "type parameter `T` of `make` is not used by any argument, so it cannot be inferred",
+            "argument 2 of `same` should be `*char`, found `char`",
+            "argument 2 of `same` should be `*u32`, found `*char`",
+        ]
+    );
+}
+
+#[test]
+fn calls_through_function_pointers_are_checked() {
+    assert!(
+        check_source(
+            "fn double(x: u32) -> u32 { return x << 1; };
+            fn apply(f: fn(u32) -> u32, x: u32) -> u32 { return f(x); };
+            fn pick() -> fn(u32) -> u32 { return &double; };
+            fn main() -> u32 { let f: fn(u32) -> u32 = pick(); return apply(&double, f(1)); };"
+        )
+        .is_empty()
+    );
+
+    let messages: Vec<_> = check_source(
+        "fn id<T>(x: T) -> T { return x; };
+        fn first(c: char) -> u32 { return 0; };
+        fn main() -> u32 { let f: fn(char) -> u32 = &first; f('a', 'b'); f(&first); let g: u32 = &id; return 0; };",
+    )
+    .into_iter()
+    .map(|diagnostic| diagnostic.message)
+    .collect();
+
+    assert_eq!(
+        messages,
+        vec![
+            "function `f` takes 1 argument(s), but 2 were given",
+            "argument 1 of `f` should be `char`, found `fn(char) -> u32`",
+            "cannot take the address of generic function `id`, as its types are not known",
+        ]
+    );
+}
+
+#[test]
+fn strings_are_passed_to_c_as_pointers() {
+    assert!(
+        check_source(
+            "ext fn puts(s: *char) -> u32;
+            fn greet(name: str) -> str { return concat(\"hi\", name); };
+            fn main() -> u32 { let s: str = greet(\"pal\"); puts(s); return len(s); };"
+        )
+        .is_empty()
+    );
+
+    let messages: Vec<_> = check_source(
+        "enum Name { Full(str) };
+        fn print(s: *char) -> u32 { return 0; };
+        fn main() -> u32 { let s: str = \"pal\"; print(s); return len(&s); };",
+    )
+    .into_iter()
+    .map(|diagnostic| diagnostic.message)
+    .collect();
+
+    assert_eq!(
+        messages,
+        vec![
+            "a field of `Name::Full` cannot be `str`, which is wider than a field can be",
+            "argument 1 of `print` should be `*char`, found `str`",
+            "argument 1 of `len` should be `str`, found `*str`",
+        ]
+    );
+}
+
+#[test]
+fn the_runtime_is_declared_for_every_module() {
+    assert!(
+        check_source(
+            "fn main() -> u32 { let p: *char = pal_alloc(4); pal_print_int(len(\"pal\")); pal_free(p); pal_panic(\"done\"); };"
+        )
+        .is_empty()
+    );
+
+    assert_eq!(
+        check_source("fn pal_print(x: u32) -> u32 { return x; };"),
+        vec![
+            SemaError::ReservedName {
+                name: "pal_print".to_string(),
+                prefix: "pal_",
+            }
+            .at(Span::new(0, 41))
+        ]
+    );
+}
+
+#[test]
+fn heap_allocations_are_checked() {
+    assert!(
+        check_source(
+            "fn main() -> u32 { let p: *u32 = new u32(7); let a: *char = alloc::<char>(*p); free(a); return *p; };"
+        )
+        .is_empty()
+    );
+
+    let messages: Vec<_> = check_source(
+        "fn main() -> u32 { let p: *u32 = new u32('a'); let a: *char = alloc::<char>('b'); free(*p); return 0; };",
+    )
+    .into_iter()
+    .map(|diagnostic| diagnostic.message)
+    .collect();
+
+    assert_eq!(
+        messages,
+        vec![
+            "argument 1 of `new u32` should be `u32`, found `char`",
+            "argument 1 of `alloc::<char>` should be `u32`, found `char`",
+            "cannot apply `free` to a value of type `u32`",
+        ]
+    );
+}
+
+#[test]
+fn assertions_take_a_u32() {
+    assert!(check_source("fn main() -> u32 { assert(1 == 1, \"holds\"); return 0; };").is_empty());
+    assert_eq!(
+        check_source("fn main() -> u32 { assert('a', \"char\"); return 0; };").len(),
+        1
+    );
+}
+
+#[test]
+fn if_branches_are_joined() {
+    assert!(
+        check_source(
+            "ext fn exit(code: u32) -> !;
+            fn main(n: u32) -> u32 {
+                let s: str = if n { \"one\" } else { \"other\" };
+                let c: char = if n == 1 { exit(1) } else { 'c' };
+                return if n { 1 } else { len(s) };
+            };"
+        )
+        .is_empty()
+    );
+
+    let messages: Vec<_> =
+        check_source("fn main() -> u32 { let x: u32 = if 'a' { 1 } else { 'b' }; return x; };")
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect();
+
+    assert_eq!(
+        messages,
+        [
+            "cannot apply `if` to a value of type `char`",
+            "the branches of `if` have different types, `u32` and `char`",
+        ]
+    );
+}
+
+#[test]
+fn blocks_take_the_type_of_their_value() {
+    assert!(
+        check_source(
+            "fn main(n: u32) -> u32 {
+                let s: str = { let t: str = \"a\"; t };
+                let m: u32 = { let n: char = 'a'; n == 'b' };
+                m | n | len(s)
+            };"
+        )
+        .is_empty()
+    );
+
+    let messages: Vec<_> = check_source(
+        "fn main() -> u32 { let x: u32 = { let y: u32 = 1; y }; let z: u32 = { return 1; 2 }; return y; };",
+    )
+    .into_iter()
+    .map(|diagnostic| diagnostic.message)
+    .collect();
+
+    assert_eq!(messages.len(), 2);
+    assert_eq!(
+        messages[0],
+        "cannot `return` from inside a block expression, which must give its value instead"
+    );
+    assert_eq!(messages[1], "variable `y` not found; did you mean `x`?");
+}
+
+#[test]
+fn integers_of_different_types_are_cast() {
+    assert!(
+        check_source(
+            "fn main(n: u64, i: isize) -> u32 { let b: u8 = n as u8 ^ 1 as u8 >> n; let s: usize = i as usize; return b as u32; };"
+        )
+        .is_empty()
+    );
+
+    let messages: Vec<_> = check_source(
+        "fn main(n: u64, m: u32, s: *char) -> u32 { return (n & m) as u32 | s as u32; };",
+    )
+    .into_iter()
+    .map(|diagnostic| diagnostic.message)
+    .collect();
+
+    assert_eq!(
+        messages,
+        vec![
+            "cannot apply `&` to integers of different types, `u64` and `u32`; convert one with `as`",
+            "cannot convert a value of type `*char` to `u32`, as only integers can be converted",
+        ]
+    );
+
+    let messages: Vec<_> = check_source(
+        "fn f(n: u64) -> u64 { let b: u8 = n; let c: char = 5000; return 'a'; };
+        fn main() -> u32 { return f(1); };",
+    )
+    .into_iter()
+    .map(|diagnostic| diagnostic.message)
+    .collect();
+
+    assert_eq!(
+        messages,
+        vec![
+            "expected a value of type `u8`, found `u64`; convert it with `as`",
+            "literal `5000` does not fit in `char`, which holds values from 0 to 127",
+            "expected a value of type `u64`, found `char`; convert it with `as`",
+            "expected a value of type `u32`, found `u64`; convert it with `as`",
+        ]
+    );
+}
+
+#[test]
+fn literals_take_the_type_they_are_expected_to_have() {
+    assert!(
+        check_source(
+            "fn main(n: u64) -> u32 { let big: u64 = 5000000000; let b: u8 = ~0; return (1 & n | big << 2) as u32 | b as u32 == 3; };"
+        )
+        .is_empty()
+    );
+
+    let messages: Vec<_> = check_source(
+        "fn f(b: u8) -> u8 { return b; }; fn main() -> u32 { let b: u8 = f(1 | 256); return 5000000000; };",
+    )
+    .into_iter()
+    .map(|diagnostic| diagnostic.message)
+    .collect();
+
+    assert_eq!(
+        messages,
+        vec![
+            "literal `256` does not fit in `u8`, which holds values from 0 to 255",
+            "literal `5000000000` does not fit in `u32`, which holds values from 0 to 4294967295",
+        ]
+    );
+}
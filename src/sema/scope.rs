@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+
+use crate::sema::ty::Ty;
+
+/// The types of the local bindings that are visible at some point in a function body. Like the
+/// symbol table used by codegen, every block pushes a new scope on entry and pops it on exit.
+#[derive(Clone, Debug, Default)]
+pub struct Scopes {
+    scopes: Vec<HashMap<String, Ty>>,
+    returns: Option<Ty>,
+}
+
+impl Scopes {
+    /// Creates a set of scopes with a single, empty scope.
+    pub fn new() -> Scopes {
+        Scopes {
+            scopes: vec![HashMap::new()],
+            returns: None,
+        }
+    }
+
+    /// Creates a set of scopes for the body of a function returning the given type.
+    pub fn returning(ty: Option<Ty>) -> Scopes {
+        Scopes {
+            returns: ty,
+            ..Scopes::new()
+        }
+    }
+
+    /// Returns the type the function these scopes belong to returns, if it is known.
+    pub fn return_type(&self) -> Option<Ty> {
+        self.returns
+    }
+
+    /// Enters a new innermost scope.
+    pub fn push(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    /// Leaves the innermost scope, dropping every binding declared in it.
+    pub fn pop(&mut self) {
+        self.scopes.pop();
+    }
+
+    /// Binds a name in the innermost scope, shadowing any binding of the same name.
+    pub fn insert(&mut self, name: impl ToString, ty: Ty) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), ty);
+        }
+    }
+
+    /// Looks the type of a name up, starting from the innermost scope and working outwards.
+    pub fn get(&self, name: &str) -> Option<Ty> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name).copied())
+    }
+
+    /// Returns every name that is currently visible.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.scopes
+            .iter()
+            .flat_map(|scope| scope.keys().map(String::as_str))
+    }
+}
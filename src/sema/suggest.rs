@@ -0,0 +1,45 @@
+/// Returns the Levenshtein distance between two strings: the least amount of single character
+/// insertions, deletions and substitutions needed to turn one into the other.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// Picks the candidate that is most similar to `name`, if any is similar enough to plausibly be a
+/// typo of it.
+pub fn closest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let threshold = (name.chars().count() / 3).max(1);
+
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= threshold)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+#[test]
+fn edit_distances_are_computed() {
+    assert_eq!(edit_distance("", ""), 0);
+    assert_eq!(edit_distance("foo", "fooo"), 1);
+    assert_eq!(edit_distance("kitten", "sitting"), 3);
+}
+
+#[test]
+fn closest_candidates_are_suggested() {
+    assert_eq!(closest("fooo", ["foo", "bar"]), Some("foo"));
+    assert_eq!(closest("printf", ["main", "exit"]), None);
+}
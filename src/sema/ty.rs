@@ -0,0 +1,314 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    fmt::{Display, Formatter, Result},
+};
+
+use crate::spec::ast::{Symbol, Type};
+
+/// A type that has been interned into a [`TyCtxt`]. Two [`Ty`]s from the same context are equal
+/// exactly when the types they stand for are, so comparing and hashing them is as cheap as
+/// comparing and hashing an integer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Ty(u32);
+
+/// Describes the structure of a type. Nested types are themselves interned.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TyKind {
+    U8,
+    U16,
+    U32,
+    U64,
+    /// An unsigned integer as wide as a pointer of the target.
+    Usize,
+    /// A signed integer as wide as a pointer of the target.
+    Isize,
+    Char,
+    /// A string that knows its length, made of a pointer to its bytes and their number.
+    Str,
+    Pointer(Ty),
+    Array(Ty, u64),
+    /// An enum, by the name it is declared under.
+    Enum(Symbol),
+    /// A type parameter of the generic function being checked, such as the `T` in
+    /// `fn id<T>(x: T) -> T`. It stands for any type, so values of it can only be passed around.
+    Param(Symbol),
+    Never,
+    /// A pointer to a function taking arguments of the given types and returning the last one.
+    Function(Vec<Ty>, Ty),
+}
+
+impl TyKind {
+    /// Returns whether values of the type are integers, which `char`s are as well.
+    pub fn is_integer(&self) -> bool {
+        self.integer_name().is_some()
+    }
+
+    /// Returns how an integer type is written in the source.
+    fn integer_name(&self) -> Option<&'static str> {
+        INTEGERS
+            .iter()
+            .find(|(_, integer)| integer == self)
+            .map(|(name, _)| *name)
+    }
+}
+
+/// The names of the integer types along with the types they stand for.
+const INTEGERS: [(&str, TyKind); 7] = [
+    ("u8", TyKind::U8),
+    ("u16", TyKind::U16),
+    ("u32", TyKind::U32),
+    ("u64", TyKind::U64),
+    ("usize", TyKind::Usize),
+    ("isize", TyKind::Isize),
+    ("char", TyKind::Char),
+];
+
+/// Interns every type used while checking a module. Interning only ever adds types, so it works
+/// through a shared reference.
+#[derive(Debug)]
+pub struct TyCtxt {
+    kinds: RefCell<Vec<TyKind>>,
+    ids: RefCell<HashMap<TyKind, Ty>>,
+    /// The names of every enum that types may refer to.
+    enums: RefCell<HashSet<Symbol>>,
+    /// The type parameters of the generic function being checked, which shadow enums.
+    generics: RefCell<Vec<Symbol>>,
+}
+
+impl Default for TyCtxt {
+    fn default() -> TyCtxt {
+        TyCtxt::new()
+    }
+}
+
+impl TyCtxt {
+    /// Creates a context in which the primitive types are already interned.
+    pub fn new() -> TyCtxt {
+        let tcx = TyCtxt {
+            kinds: RefCell::new(vec![]),
+            ids: RefCell::new(HashMap::new()),
+            enums: RefCell::new(HashSet::new()),
+            generics: RefCell::new(vec![]),
+        };
+
+        tcx.intern(TyKind::U32);
+        tcx.intern(TyKind::Char);
+
+        tcx
+    }
+
+    /// Returns the [`Ty`] standing for the given structure, interning it if it is new.
+    pub fn intern(&self, kind: TyKind) -> Ty {
+        if let Some(ty) = self.ids.borrow().get(&kind) {
+            return *ty;
+        }
+
+        let mut kinds = self.kinds.borrow_mut();
+        let ty = Ty(kinds.len() as u32);
+
+        kinds.push(kind.clone());
+        self.ids.borrow_mut().insert(kind, ty);
+
+        ty
+    }
+
+    /// Returns the structure of an interned type.
+    pub fn kind(&self, ty: Ty) -> TyKind {
+        self.kinds.borrow()[ty.0 as usize].clone()
+    }
+
+    /// Makes an enum available to types written in the source under its name.
+    pub fn declare_enum(&self, name: Symbol) {
+        self.enums.borrow_mut().insert(name);
+    }
+
+    /// Makes the type parameters of a generic function available to types written in the source,
+    /// replacing those of the previous one.
+    pub fn set_generics(&self, generics: &[Symbol]) {
+        *self.generics.borrow_mut() = generics.to_vec();
+    }
+
+    /// Returns the name of every type that types written in the source can refer to, in no
+    /// particular order.
+    pub fn names(&self) -> Vec<Symbol> {
+        INTEGERS
+            .iter()
+            .map(|(name, _)| Symbol::intern(name))
+            .chain([Symbol::intern("str")])
+            .chain(self.enums.borrow().iter().copied())
+            .chain(self.generics.borrow().iter().copied())
+            .collect()
+    }
+
+    /// Returns the `u32` type.
+    pub fn u32(&self) -> Ty {
+        self.intern(TyKind::U32)
+    }
+
+    /// Returns the `char` type.
+    pub fn char(&self) -> Ty {
+        self.intern(TyKind::Char)
+    }
+
+    /// Returns the `str` type.
+    pub fn str(&self) -> Ty {
+        self.intern(TyKind::Str)
+    }
+
+    /// Returns the type of pointers to `pointee`.
+    pub fn pointer(&self, pointee: Ty) -> Ty {
+        self.intern(TyKind::Pointer(pointee))
+    }
+
+    /// Returns the largest value of an integer type, or [`None`] when the type is not one. A
+    /// `char` holds any ASCII character. Pointers are at least 32 bits wide on every target, so
+    /// `usize` and `isize` are only known to hold what 32 bits can.
+    pub fn max_value(&self, ty: Ty) -> Option<u64> {
+        match self.kind(ty) {
+            TyKind::U8 => Some(u8::MAX as u64),
+            TyKind::U16 => Some(u16::MAX as u64),
+            TyKind::U32 | TyKind::Usize => Some(u32::MAX as u64),
+            TyKind::U64 => Some(u64::MAX),
+            TyKind::Isize => Some(i32::MAX as u64),
+            TyKind::Char => Some(0x7f),
+            _ => None,
+        }
+    }
+
+    /// Interns a type written in the source. Fails with the name of the first type that does not
+    /// exist.
+    pub fn lower<'t>(&self, typ: &'t Type) -> std::result::Result<Ty, &'t str> {
+        self.lower_with(typ, &HashMap::new())
+    }
+
+    /// Interns a type written in the source like [`TyCtxt::lower`], replacing the type
+    /// parameters of a generic function with the types they have been inferred to be. A type
+    /// parameter that has not been inferred yet fails with its name.
+    pub fn lower_with<'t>(
+        &self,
+        typ: &'t Type,
+        subst: &HashMap<Symbol, Option<Ty>>,
+    ) -> std::result::Result<Ty, &'t str> {
+        match typ {
+            Type::Atomic(name) => match &name[..] {
+                _ if subst.contains_key(name) => subst[name].ok_or(&name[..]),
+                _ if let Some((_, kind)) =
+                    INTEGERS.iter().find(|(integer, _)| *integer == &name[..]) =>
+                {
+                    Ok(self.intern(kind.clone()))
+                }
+                "str" => Ok(self.str()),
+                _ if self.generics.borrow().contains(name) => Ok(self.intern(TyKind::Param(*name))),
+                _ if self.enums.borrow().contains(name) => Ok(self.intern(TyKind::Enum(*name))),
+                name => Err(name),
+            },
+            Type::Pointer(pointee) => Ok(self.pointer(self.lower_with(pointee, subst)?)),
+            Type::Array(element, length) => {
+                Ok(self.intern(TyKind::Array(self.lower_with(element, subst)?, *length)))
+            }
+            Type::Never => Ok(self.intern(TyKind::Never)),
+            Type::Function(arguments, returns) => {
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| self.lower_with(argument, subst))
+                    .collect::<std::result::Result<_, _>>()?;
+
+                Ok(self.intern(TyKind::Function(
+                    arguments,
+                    self.lower_with(returns, subst)?,
+                )))
+            }
+        }
+    }
+
+    /// Returns how an interned type is written in the source, the opposite of
+    /// [`TyCtxt::lower`].
+    pub fn to_type(&self, ty: Ty) -> Type {
+        match self.kind(ty) {
+            kind @ (TyKind::U8
+            | TyKind::U16
+            | TyKind::U32
+            | TyKind::U64
+            | TyKind::Usize
+            | TyKind::Isize
+            | TyKind::Char) => Type::Atomic(kind.integer_name().unwrap_or_default().into()),
+            TyKind::Str => Type::Atomic("str".into()),
+            TyKind::Pointer(pointee) => Type::Pointer(Box::new(self.to_type(pointee))),
+            TyKind::Array(element, length) => Type::Array(Box::new(self.to_type(element)), length),
+            TyKind::Enum(name) | TyKind::Param(name) => Type::Atomic(name),
+            TyKind::Never => Type::Never,
+            TyKind::Function(arguments, returns) => Type::Function(
+                arguments
+                    .iter()
+                    .map(|argument| self.to_type(*argument))
+                    .collect(),
+                Box::new(self.to_type(returns)),
+            ),
+        }
+    }
+
+    /// Returns a value that displays the type the way it is written in the source. Every
+    /// diagnostic that mentions a type formats it through this.
+    pub fn display(&self, ty: Ty) -> TyDisplay<'_> {
+        TyDisplay { tcx: self, ty }
+    }
+}
+
+/// Displays an interned type, see [`TyCtxt::display`].
+pub struct TyDisplay<'tcx> {
+    tcx: &'tcx TyCtxt,
+    ty: Ty,
+}
+
+impl Display for TyDisplay<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self.tcx.kind(self.ty) {
+            kind @ (TyKind::U8
+            | TyKind::U16
+            | TyKind::U32
+            | TyKind::U64
+            | TyKind::Usize
+            | TyKind::Isize
+            | TyKind::Char) => write!(f, "{}", kind.integer_name().unwrap_or_default()),
+            TyKind::Str => write!(f, "str"),
+            TyKind::Pointer(pointee) => write!(f, "*{}", self.tcx.display(pointee)),
+            TyKind::Array(element, length) => {
+                write!(f, "[{}; {length}]", self.tcx.display(element))
+            }
+            TyKind::Enum(name) | TyKind::Param(name) => write!(f, "{name}"),
+            TyKind::Never => write!(f, "!"),
+            TyKind::Function(arguments, returns) => {
+                let arguments: Vec<_> = arguments
+                    .iter()
+                    .map(|argument| self.tcx.display(*argument).to_string())
+                    .collect();
+
+                write!(
+                    f,
+                    "fn({}) -> {}",
+                    arguments.join(", "),
+                    self.tcx.display(returns)
+                )
+            }
+        }
+    }
+}
+
+#[test]
+fn equal_types_are_interned_once() {
+    let tcx = TyCtxt::new();
+    let pointer = Type::Pointer(Box::new(Type::Atomic("char".into())));
+
+    assert_eq!(tcx.lower(&pointer), Ok(tcx.pointer(tcx.char())));
+    assert_ne!(tcx.pointer(tcx.char()), tcx.pointer(tcx.u32()));
+    assert_eq!(tcx.display(tcx.pointer(tcx.char())).to_string(), "*char");
+    assert_eq!(tcx.lower(&Type::Atomic("str".into())), Ok(tcx.str()));
+    assert_eq!(tcx.lower(&Type::Atomic("i16".into())), Err("i16"));
+    assert_eq!(
+        tcx.display(tcx.lower(&Type::Atomic("usize".into())).unwrap())
+            .to_string(),
+        "usize"
+    );
+    assert_ne!(tcx.lower(&Type::Atomic("u8".into())), Ok(tcx.char()));
+}
@@ -0,0 +1,27 @@
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+/// Represents any error in compiling a test case or comparing it against its snapshots.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotError {
+    #[error("failed to read or write {}: {message}", .path.display())]
+    Io { path: PathBuf, message: String },
+    #[error("{} does not compile:\n{messages}", .path.display())]
+    Compile { path: PathBuf, messages: String },
+    #[error("failed to run {}: {message}", .path.display())]
+    Run { path: PathBuf, message: String },
+    #[error(
+        "{} differs from the output at line {line}\n  expected: {expected}\n     found: {found}\nrerun with {}=1 to accept the output",
+        .path.display(),
+        super::BLESS
+    )]
+    Mismatch {
+        path: PathBuf,
+        line: usize,
+        expected: String,
+        found: String,
+    },
+    #[error("missing snapshot {}; run with {}=1 to write it", .path.display(), super::BLESS)]
+    Missing { path: PathBuf },
+}
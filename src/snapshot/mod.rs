@@ -0,0 +1,243 @@
+pub mod error;
+
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{
+    backend::{Backend, CBackend, CodegenOptions, PanicStrategy},
+    diagnostic::Diagnostic,
+    emit::Emit,
+    sema::{check_entry, check_module_with_imports},
+    snapshot::error::SnapshotError,
+    spec::{ast::Module, recovering_module},
+};
+
+// Snapshot tests compile every program under a directory and compare what the compiler made of
+// it against files checked in next to it, named after the program, so that any change to the
+// grammar or to code generation shows up as a change to those files. Programs are also compiled
+// to native executables and run, so that what they do is compared as well.
+
+/// The environment variable that makes [`assert_snapshot`] write the output it is given instead
+/// of comparing against it, to accept an intended change.
+pub const BLESS: &str = "PAL_BLESS";
+
+/// The code programs exit with when they panic while they are run by [`run_case`].
+pub const PANIC_EXIT_CODE: i32 = 101;
+
+/// A program compiled for a snapshot test.
+#[derive(Clone, Debug)]
+pub struct Case {
+    pub path: PathBuf,
+    pub source: String,
+    pub module: Module,
+}
+
+impl Case {
+    /// Returns where the snapshot with the given extension is kept, next to the program.
+    pub fn snapshot_path(&self, extension: &str) -> PathBuf {
+        self.path.with_extension(extension)
+    }
+}
+
+fn io_error(path: &Path, error: std::io::Error) -> SnapshotError {
+    SnapshotError::Io {
+        path: path.to_path_buf(),
+        message: error.to_string(),
+    }
+}
+
+/// Returns the path of every program in a directory, in order.
+pub fn case_paths(dir: &Path) -> Result<Vec<PathBuf>, SnapshotError> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map_err(|error| io_error(dir, error))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "pal"))
+        .collect();
+
+    paths.sort();
+
+    Ok(paths)
+}
+
+/// Reads, parses and checks a program like the compiler does with an input that imports nothing,
+/// failing with every diagnostic it reports.
+pub fn load_case(path: &Path) -> Result<Case, SnapshotError> {
+    let source = std::fs::read_to_string(path).map_err(|error| io_error(path, error))?;
+
+    let compile_error = |diagnostics: Vec<Diagnostic>| SnapshotError::Compile {
+        path: path.to_path_buf(),
+        messages: diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.render(path, &source))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+
+    let ((module, parse_errors), _) = recovering_module("main".to_string())
+        .parse_memoized(&source)
+        .map_err(|error| compile_error(vec![error.into()]))?;
+
+    let diagnostics: Vec<Diagnostic> = parse_errors
+        .into_iter()
+        .map(Diagnostic::from)
+        .chain(check_module_with_imports(&module, &[]))
+        .chain(check_entry(&module))
+        .collect();
+
+    if !diagnostics.is_empty() {
+        return Err(compile_error(diagnostics));
+    }
+
+    Ok(Case {
+        path: path.to_path_buf(),
+        source,
+        module,
+    })
+}
+
+/// What a program printed to stdout when it was run, and the code it exited with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Run {
+    pub stdout: String,
+    pub code: i32,
+}
+
+impl Display for Run {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.stdout)?;
+
+        if !self.stdout.is_empty() && !self.stdout.ends_with('\n') {
+            writeln!(f)?;
+        }
+
+        writeln!(f, "exit code: {}", self.code)
+    }
+}
+
+/// Compiles a program to C with the C backend, builds it into an executable in `dir` with `cc`,
+/// and runs it. Programs that panic exit with [`PANIC_EXIT_CODE`], and those killed by a signal
+/// are given a code of -1.
+pub fn run_case(case: &Case, dir: &Path) -> Result<Run, SnapshotError> {
+    let stem = case.path.file_stem().unwrap_or_default();
+    let source = dir.join(stem).with_extension("c");
+    let executable = dir.join(stem);
+
+    let failed = |message: String| SnapshotError::Run {
+        path: case.path.clone(),
+        message,
+    };
+
+    std::fs::create_dir_all(dir).map_err(|error| io_error(dir, error))?;
+
+    let opts = CodegenOptions {
+        outputs: vec![(Emit::C, source.clone())],
+        panic: PanicStrategy::Exit(PANIC_EXIT_CODE),
+        ..CodegenOptions::default()
+    };
+
+    CBackend
+        .compile(&case.module, &opts)
+        .map_err(|error| failed(error.to_string()))?;
+
+    let output = Command::new("cc")
+        .args(["-std=c99", "-w"])
+        .arg(&source)
+        .arg("-o")
+        .arg(&executable)
+        .output()
+        .map_err(|error| failed(error.to_string()))?;
+
+    if !output.status.success() {
+        return Err(failed(String::from_utf8_lossy(&output.stderr).to_string()));
+    }
+
+    let output = Command::new(&executable)
+        .output()
+        .map_err(|error| failed(error.to_string()))?;
+
+    Ok(Run {
+        stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+        code: output.status.code().unwrap_or(-1),
+    })
+}
+
+/// Removes what LLVM IR says about where it was generated, such as the name of the module and the
+/// target, so that the same program gives the same IR on every machine.
+pub fn normalize_ir(ir: &str) -> String {
+    ir.lines()
+        .filter(|line| {
+            ![
+                "; ModuleID",
+                "source_filename",
+                "target datalayout",
+                "target triple",
+            ]
+            .iter()
+            .any(|prefix| line.starts_with(prefix))
+        })
+        .map(|line| format!("{}\n", line.trim_end()))
+        .collect::<String>()
+        .trim_start()
+        .to_string()
+}
+
+/// Compares output against the snapshot at `path`, failing at the first line that differs, or
+/// when there is no snapshot. The output is written to the snapshot instead when [`BLESS`] is set.
+pub fn assert_snapshot(path: &Path, actual: &str) -> Result<(), SnapshotError> {
+    if std::env::var_os(BLESS).is_some() {
+        return std::fs::write(path, actual).map_err(|error| io_error(path, error));
+    }
+
+    if !path.exists() {
+        return Err(SnapshotError::Missing {
+            path: path.to_path_buf(),
+        });
+    }
+
+    let expected = std::fs::read_to_string(path).map_err(|error| io_error(path, error))?;
+
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+
+    for line in 1.. {
+        match (expected_lines.next(), actual_lines.next()) {
+            (None, None) => break,
+            (expected, found) if expected != found => {
+                return Err(SnapshotError::Mismatch {
+                    path: path.to_path_buf(),
+                    line,
+                    expected: expected.unwrap_or("<end of file>").to_string(),
+                    found: found.unwrap_or("<end of file>").to_string(),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn missing_snapshots_fail() {
+    let path = std::env::temp_dir().join("pal-missing-snapshot.ast");
+    let _ = std::fs::remove_file(&path);
+
+    assert_eq!(
+        assert_snapshot(&path, "x"),
+        Err(SnapshotError::Missing { path: path.clone() })
+    );
+    assert!(!path.exists());
+}
+
+#[test]
+fn ir_is_normalized() {
+    let ir = "; ModuleID = 'main'\nsource_filename = \"main\"\ntarget datalayout = \"e-m:e\"\ntarget triple = \"x86_64-pc-linux-gnu\"\n\ndefine i32 @main() {  \nentry:\n  ret i32 0\n}\n";
+
+    assert_eq!(
+        normalize_ir(ir),
+        "define i32 @main() {\nentry:\n  ret i32 0\n}\n"
+    );
+}
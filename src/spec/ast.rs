@@ -1,33 +1,360 @@
+use crate::parser::{Spanned, error::ParseError};
+pub use crate::spec::intern::Symbol;
+
+// Every node that is nested in another is [`Spanned`], recording the part of the source it was
+// parsed from for diagnostics and tooling.
+
 /// Describes any possible type expression.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Type {
-    Atomic(String),
+    Atomic(Symbol),
     Pointer(Box<Type>),
+    /// A fixed number of elements of the same type, written `[T; N]`.
+    Array(Box<Type>, u64),
+    /// The return type of functions that never return, written `!`.
+    Never,
+    /// A pointer to a function taking arguments of the given types, written `fn(u32) -> u32`.
+    Function(Vec<Type>, Box<Type>),
+}
+
+/// An operator written in front of its operand.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnaryOp {
+    /// Wrapping negation, written `-x`.
+    Neg,
+    /// Logical negation, written `!x`: 1 when the operand is 0, and 0 otherwise.
+    Not,
+    /// Bitwise negation, written `~x`.
+    BitNot,
+    /// Reads the value a pointer points to, written `*p`.
+    Deref,
+    /// Takes the address of a variable, written `&v`.
+    AddressOf,
+}
+
+/// An operator written between its two operands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryOp {
+    /// Bitwise and, written `a & b`.
+    BitAnd,
+    /// Bitwise or, written `a | b`.
+    BitOr,
+    /// Bitwise exclusive or, written `a ^ b`.
+    BitXor,
+    /// Shifts the bits of the left operand left, written `a << b`.
+    Shl,
+    /// Shifts the bits of the left operand right, written `a >> b`.
+    Shr,
+    /// Equality, written `a == b`: 1 when the operands are equal, and 0 otherwise.
+    Eq,
+    /// Inequality, written `a != b`: 1 when the operands differ, and 0 otherwise.
+    Ne,
 }
 
 /// Describes any possible expression, including left-recursive ones. There is no distinction in
 /// the AST.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Expression {
     StringLiteral(String),
     NumericLiteral(u64),
+    CharLiteral(char),
+    Variable(Symbol),
+    Call(Symbol, Vec<Spanned<Expression>>),
+    /// Reads an element of a constant array, written `NAME[index]`.
+    Index(Symbol, Box<Spanned<Expression>>),
+    Unary(UnaryOp, Box<Spanned<Expression>>),
+    Binary(BinaryOp, Box<Spanned<Expression>>, Box<Spanned<Expression>>),
+    /// A variant of an enum, written `Enum::Variant`, or `Enum::Variant(fields)` when the variant
+    /// has fields.
+    Variant(Symbol, Symbol, Vec<Spanned<Expression>>),
+    /// Allocates a value on the heap, written `new T(value)`, evaluating to a pointer to it.
+    New(Spanned<Type>, Box<Spanned<Expression>>),
+    /// Allocates room for a number of values on the heap without initializing them, written
+    /// `alloc::<T>(count)`, evaluating to a pointer to the first of them.
+    Alloc(Spanned<Type>, Box<Spanned<Expression>>),
+    /// Evaluates to one of two values, written `if cond { a } else { b }`: the first when the
+    /// condition is not 0, and the second otherwise. Only the branch that is taken is evaluated.
+    If(
+        Box<Spanned<Expression>>,
+        Box<Spanned<Expression>>,
+        Box<Spanned<Expression>>,
+    ),
+    /// Runs statements in a scope of their own and evaluates to the expression that ends them,
+    /// written `{ let y: u32 = x << 1; y | 1 }`. The statements can not `return`.
+    Block(Vec<Spanned<Statement>>, Box<Spanned<Expression>>),
+    /// Converts an integer to another integer type, written `x as u8`. Converting to a narrower
+    /// type keeps the low bits, and to a wider one extends the value, with its sign if it is an
+    /// `isize`.
+    Cast(Box<Spanned<Expression>>, Spanned<Type>),
+}
+
+/// A pattern that the value of a `match` is compared against.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Pattern {
+    /// Matches a number, such as `1`.
+    Number(u64),
+    /// Matches a character, such as `'a'`.
+    Char(char),
+    /// Matches a variant of an enum, such as `Color::Red`, binding each of its fields to a name,
+    /// such as in `Shape::Rect(w, h)`. Fields bound to `_` are ignored.
+    Variant(Symbol, Symbol, Vec<Symbol>),
+    /// Matches any value, written `_`.
+    Wildcard,
+}
+
+impl Pattern {
+    /// Returns whether two patterns match the same values, whatever names they bind.
+    pub fn overlaps(&self, other: &Pattern) -> bool {
+        match (self, other) {
+            (Pattern::Variant(name, variant, _), Pattern::Variant(other, other_variant, _)) => {
+                name == other && variant == other_variant
+            }
+            _ => self == other,
+        }
+    }
 }
 
+/// An arm of a `match`: a pattern, and the block that runs when it is the first to match.
+pub type Arm = (Spanned<Pattern>, Vec<Spanned<Statement>>);
+
 /// Describes any possible statement.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Statement {
-    FunctionCall(String, Vec<Expression>),
-    Return(Expression),
+    FunctionCall(Symbol, Vec<Spanned<Expression>>),
+    Return(Spanned<Expression>),
+    Let(Symbol, Spanned<Type>, Spanned<Expression>),
+    Block(Vec<Spanned<Statement>>),
+    For(
+        Symbol,
+        Spanned<Expression>,
+        Spanned<Expression>,
+        Vec<Spanned<Statement>>,
+    ),
+    /// Marks a point that control can never reach, written `unreachable()`.
+    Unreachable,
+    /// Runs the arm whose pattern matches a value, written `match x { 1 => { ... } _ => { ... } }`.
+    /// No arm runs when none of them matches.
+    Match(Spanned<Expression>, Vec<Arm>),
+    /// Frees memory allocated with `new` or `alloc`, written `free(ptr)`.
+    Free(Spanned<Expression>),
+    /// Panics with a message when a condition is 0, written `assert(cond, "message")`.
+    Assert(Spanned<Expression>, String),
+    /// Embeds inline assembly in the function, written `asm!("nop")`, in the syntax of the
+    /// assembler of the target. The assembly takes no operands and is never optimized away.
+    Asm(String),
+    /// Stands in for a statement that failed to parse, so that the rest of its block could still
+    /// be parsed. Only parsers that recover from errors produce it, and they report its error with
+    /// the others, so every later phase skips it.
+    Error(ParseError),
+}
+
+/// A variant of an enum: its name, and the types of its fields, if it has any.
+pub type Variant = (Spanned<Symbol>, Vec<Spanned<Type>>);
+
+/// Whether an item can be used from the modules that import the module it is declared in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Visibility {
+    #[default]
+    Private,
+    /// Written `pub` in front of the item.
+    Public,
+}
+
+/// An attribute written in front of a function, such as `#[no_mangle]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Attribute {
+    /// Keeps the name of a function as it is written instead of mangling it, so that C code, or
+    /// an `ext fn` in a module that does not import the one defining it, can call it.
+    NoMangle,
+}
+
+/// The calling convention of a function declared with `ext fn`, written as a string after `ext`,
+/// such as `ext "stdcall" fn`. Calls through function pointers always use the C convention, so
+/// functions declared with any other one should only be called directly.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Abi {
+    /// The convention of C functions on the target, written `"C"`, which is used when none is
+    /// written.
+    #[default]
+    C,
+    /// The convention of most of the Win32 API on 32-bit x86, written `"stdcall"`.
+    Stdcall,
+    /// The 32-bit x86 convention passing the first two arguments in registers, written
+    /// `"fastcall"`.
+    Fastcall,
+    /// The convention of Windows on x86-64, written `"win64"`.
+    Win64,
+    /// The convention of every other system on x86-64, written `"sysv64"`.
+    Sysv64,
+}
+
+impl Abi {
+    /// Every calling convention, in the order they are tried by the parser.
+    pub const ALL: [Abi; 5] = [Abi::C, Abi::Stdcall, Abi::Fastcall, Abi::Win64, Abi::Sysv64];
+
+    /// Returns the name the calling convention is written with, without the quotes.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Abi::C => "C",
+            Abi::Stdcall => "stdcall",
+            Abi::Fastcall => "fastcall",
+            Abi::Win64 => "win64",
+            Abi::Sysv64 => "sysv64",
+        }
+    }
 }
 
 /// Describes any top-level item. That is, any item that is defined at the top level of a module,
 /// such as a function declaration or an extern function definition.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Item {
-    ExternFunctionDefinition(String, Vec<(String, Type)>, Type),
-    FunctionDeclaration(String, Vec<(String, Type)>, Type, Vec<Statement>),
+    ExternFunctionDefinition(
+        Symbol,
+        Vec<(Symbol, Spanned<Type>)>,
+        Spanned<Type>,
+        Abi,
+        Vec<Attribute>,
+        Visibility,
+    ),
+    /// A function with a body. A generic function, such as `fn id<T>(x: T) -> T`, lists the names
+    /// of its type parameters after its own.
+    FunctionDeclaration(
+        Symbol,
+        Vec<Symbol>,
+        Vec<(Symbol, Spanned<Type>)>,
+        Spanned<Type>,
+        Vec<Spanned<Statement>>,
+        Vec<Attribute>,
+        Visibility,
+    ),
+    /// A constant array, whose elements are all literals.
+    Const(Symbol, Spanned<Type>, Vec<Spanned<Expression>>, Visibility),
+    /// A type whose values are one of a list of named variants, such as
+    /// `enum Color { Red, Green, Blue }`. Variants are numbered from 0 in the order they are
+    /// declared in, and may carry fields, such as in `enum Shape { Circle(u32), Rect(u32, u32) }`.
+    Enum(Symbol, Vec<Variant>, Visibility),
+}
+
+impl Item {
+    /// Returns the name that the item is declared under.
+    pub fn name(&self) -> &str {
+        match self {
+            Item::ExternFunctionDefinition(name, ..)
+            | Item::FunctionDeclaration(name, ..)
+            | Item::Const(name, ..)
+            | Item::Enum(name, ..) => name,
+        }
+    }
+
+    /// Returns the names and types of the arguments the item takes.
+    pub fn arguments(&self) -> &[(Symbol, Spanned<Type>)] {
+        match self {
+            Item::ExternFunctionDefinition(_, args, ..)
+            | Item::FunctionDeclaration(_, _, args, ..) => args,
+            Item::Const(..) | Item::Enum(..) => &[],
+        }
+    }
+
+    /// Returns the names of the type parameters of the item, which only generic functions have.
+    pub fn generics(&self) -> &[Symbol] {
+        match self {
+            Item::FunctionDeclaration(_, generics, ..) => generics,
+            _ => &[],
+        }
+    }
+
+    /// Returns whether the item is an enum with at least one variant that has fields.
+    pub fn has_fields(&self) -> bool {
+        match self {
+            Item::Enum(_, variants, _) => variants.iter().any(|(_, fields)| !fields.is_empty()),
+            _ => false,
+        }
+    }
+
+    /// Returns whether the item is a function that never returns, such as `exit`.
+    pub fn diverges(&self) -> bool {
+        self.return_type()
+            .is_some_and(|typ| typ.node == Type::Never)
+    }
+
+    /// Returns the type of the value the item returns. For a constant, this is its own type, and
+    /// an enum has none, as it is a type itself.
+    pub fn return_type(&self) -> Option<&Spanned<Type>> {
+        match self {
+            Item::ExternFunctionDefinition(_, _, typ, ..)
+            | Item::FunctionDeclaration(_, _, _, typ, ..)
+            | Item::Const(_, typ, _, _) => Some(typ),
+            Item::Enum(..) => None,
+        }
+    }
+
+    /// Returns whether the item can be called.
+    pub fn is_function(&self) -> bool {
+        !matches!(self, Item::Const(..) | Item::Enum(..))
+    }
+
+    /// Returns whether the item can be used from other modules.
+    pub fn visibility(&self) -> Visibility {
+        match self {
+            Item::ExternFunctionDefinition(.., visibility)
+            | Item::FunctionDeclaration(.., visibility)
+            | Item::Const(.., visibility)
+            | Item::Enum(.., visibility) => *visibility,
+        }
+    }
+
+    /// Returns the calling convention of the item, which is always [`Abi::C`] unless it is
+    /// declared with `ext fn`.
+    pub fn abi(&self) -> Abi {
+        match self {
+            Item::ExternFunctionDefinition(_, _, _, abi, ..) => *abi,
+            _ => Abi::C,
+        }
+    }
+
+    /// Returns the attributes written in front of the item, which only functions can have.
+    pub fn attributes(&self) -> &[Attribute] {
+        match self {
+            Item::ExternFunctionDefinition(.., attributes, _)
+            | Item::FunctionDeclaration(.., attributes, _) => attributes,
+            Item::Const(..) | Item::Enum(..) => &[],
+        }
+    }
+
+    /// Returns the function with its attributes replaced. Other items are returned unchanged, as
+    /// they can not have any.
+    pub fn with_attributes(mut self, attributes: Vec<Attribute>) -> Item {
+        if let Item::ExternFunctionDefinition(.., slot, _)
+        | Item::FunctionDeclaration(.., slot, _) = &mut self
+        {
+            *slot = attributes;
+        }
+
+        self
+    }
+
+    /// Returns the item with its visibility replaced.
+    pub fn with_visibility(mut self, visibility: Visibility) -> Item {
+        match &mut self {
+            Item::ExternFunctionDefinition(.., slot)
+            | Item::FunctionDeclaration(.., slot)
+            | Item::Const(.., slot)
+            | Item::Enum(.., slot) => *slot = visibility,
+        }
+
+        self
+    }
+}
+
+/// Describes anything that may appear at the top level of a script: either an item, or a bare
+/// statement that belongs to the implicit `main` function.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TopLevel {
+    Item(Spanned<Item>),
+    Statement(Spanned<Statement>),
 }
 
-/// Describes an individual code module.
-#[derive(Clone, Debug)]
-pub struct Module(pub String, pub Vec<Item>);
+/// Describes an individual code module: its name, its items and the names of the modules it
+/// imports, in that order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Module(pub String, pub Vec<Spanned<Item>>, pub Vec<Spanned<String>>);
@@ -0,0 +1,253 @@
+use crate::{parser::Spanned, spec::ast::*};
+
+/// A small, seedable pseudo-random number generator (xorshift64*). Generated programs only need
+/// to be reproducible from their seed, not cryptographically random.
+#[derive(Clone, Debug)]
+pub struct Rng(u64);
+
+impl Rng {
+    /// Creates a generator from a seed. Every seed, including 0, gives a usable sequence.
+    pub fn new(seed: u64) -> Rng {
+        Rng((seed ^ 0x9e3779b97f4a7c15) | 1)
+    }
+
+    /// Returns the next number in the sequence.
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+
+        self.0.wrapping_mul(0x2545f4914f6cdd1d)
+    }
+
+    /// Returns a number from `0` up to, but not including, `bound`.
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound.max(1) as u64) as usize
+    }
+}
+
+/// Controls the size and the language features of generated programs.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// The amount of functions to generate, not counting `main`.
+    pub functions: usize,
+    /// The amount of statements in every block, not counting its `return`.
+    pub statements: usize,
+    /// How deep blocks, loops and calls may be nested.
+    pub depth: usize,
+    /// Whether to generate `for` loops.
+    pub loops: bool,
+    /// Whether to generate calls between functions.
+    pub calls: bool,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            functions: 4,
+            statements: 4,
+            depth: 2,
+            loops: true,
+            calls: true,
+        }
+    }
+}
+
+/// Generates random programs that are accepted by both the parser and sema. Functions only ever
+/// call functions generated before them, so generated programs always terminate.
+pub struct Generator {
+    rng: Rng,
+    config: Config,
+    /// The names and argument counts of the functions generated so far.
+    functions: Vec<(Symbol, usize)>,
+    /// The variables in scope, innermost scope last.
+    scopes: Vec<Vec<Symbol>>,
+    /// A counter used to give every binding a fresh name.
+    names: usize,
+}
+
+impl Generator {
+    /// Creates a generator for the given seed and configuration.
+    pub fn new(seed: u64, config: Config) -> Generator {
+        Generator {
+            rng: Rng::new(seed),
+            config,
+            functions: vec![],
+            scopes: vec![],
+            names: 0,
+        }
+    }
+
+    /// Generates a whole module, ending with a `main` function.
+    pub fn module(&mut self, name: impl ToString) -> Module {
+        let mut items = vec![
+            Item::ExternFunctionDefinition(
+                "printf".into(),
+                vec![("ptr".into(), char_pointer())],
+                u32_type(),
+                Abi::C,
+                vec![],
+                Visibility::Private,
+            )
+            .into(),
+        ];
+
+        for index in 0..self.config.functions {
+            let args = self.rng.below(4);
+            items.push(self.function(format!("f{index}").into(), args));
+        }
+
+        items.push(self.function("main".into(), 0));
+
+        Module(name.to_string(), items, vec![])
+    }
+
+    /// Generates a function taking `args` arguments, all of which are `u32`.
+    fn function(&mut self, name: Symbol, args: usize) -> Spanned<Item> {
+        let args: Vec<_> = (0..args)
+            .map(|index| (format!("a{index}").into(), u32_type()))
+            .collect();
+
+        self.scopes
+            .push(args.iter().map(|(name, _)| *name).collect());
+
+        let mut body = self.statements(0);
+        body.push(Statement::Return(self.expression(0)).into());
+
+        self.scopes.pop();
+        self.functions.push((name, args.len()));
+
+        Item::FunctionDeclaration(
+            name,
+            vec![],
+            args,
+            u32_type(),
+            body,
+            vec![],
+            Visibility::Private,
+        )
+        .into()
+    }
+
+    /// Generates the statements of a block nested `depth` levels deep.
+    fn statements(&mut self, depth: usize) -> Vec<Spanned<Statement>> {
+        (0..self.rng.below(self.config.statements + 1))
+            .map(|_| self.statement(depth))
+            .collect()
+    }
+
+    fn statement(&mut self, depth: usize) -> Spanned<Statement> {
+        let nested = depth < self.config.depth;
+
+        let statement = match self.rng.below(5) {
+            0 if nested && self.config.loops => {
+                let start = self.expression(depth + 1);
+                let end = self.expression(depth + 1);
+                let name = self.fresh("i");
+
+                self.scopes.push(vec![name]);
+                let body = self.block(depth + 1);
+                self.scopes.pop();
+
+                Statement::For(name, start, end, body)
+            }
+            1 if nested => Statement::Block(self.block(depth + 1)),
+            2 if self.config.calls && !self.functions.is_empty() => {
+                let (name, args) = self.call(depth);
+                Statement::FunctionCall(name, args)
+            }
+            3 => {
+                let message = self.fresh("s");
+                Statement::FunctionCall(
+                    "printf".into(),
+                    vec![Expression::StringLiteral(message.to_string()).into()],
+                )
+            }
+            _ => {
+                let value = self.expression(depth);
+                let name = self.fresh("v");
+
+                if let Some(scope) = self.scopes.last_mut() {
+                    scope.push(name);
+                }
+
+                Statement::Let(name, u32_type(), value)
+            }
+        };
+
+        statement.into()
+    }
+
+    /// Generates a block inside of its own scope.
+    fn block(&mut self, depth: usize) -> Vec<Spanned<Statement>> {
+        self.scopes.push(vec![]);
+        let body = self.statements(depth);
+        self.scopes.pop();
+
+        body
+    }
+
+    /// Generates a `u32` expression.
+    fn expression(&mut self, depth: usize) -> Spanned<Expression> {
+        let variables: Vec<_> = self.scopes.iter().flatten().cloned().collect();
+
+        let expression = match self.rng.below(3) {
+            0 if !variables.is_empty() => {
+                Expression::Variable(variables[self.rng.below(variables.len())])
+            }
+            1 if self.config.calls && !self.functions.is_empty() && depth < self.config.depth => {
+                let (name, args) = self.call(depth + 1);
+                Expression::Call(name, args)
+            }
+            _ => Expression::NumericLiteral(self.rng.below(100) as u64),
+        };
+
+        expression.into()
+    }
+
+    /// Generates the target and arguments of a call to one of the functions generated so far.
+    fn call(&mut self, depth: usize) -> (Symbol, Vec<Spanned<Expression>>) {
+        let (name, args) = self.functions[self.rng.below(self.functions.len())];
+        let args = (0..args).map(|_| self.expression(depth)).collect();
+
+        (name, args)
+    }
+
+    /// Returns a name that has not been used before.
+    fn fresh(&mut self, prefix: &str) -> Symbol {
+        self.names += 1;
+
+        format!("{prefix}{}", self.names).into()
+    }
+}
+
+fn u32_type() -> Spanned<Type> {
+    Type::Atomic("u32".into()).into()
+}
+
+fn char_pointer() -> Spanned<Type> {
+    Type::Pointer(Box::new(Type::Atomic("char".into()))).into()
+}
+
+#[test]
+fn generated_programs_round_trip() {
+    for seed in 0..32 {
+        let module = Generator::new(seed, Config::default()).module("main");
+        let source = module.to_string();
+
+        assert_eq!(
+            crate::spec::module("main".to_string()).parse(&source),
+            Ok((module.clone(), "".to_string())),
+            "{source}"
+        );
+        assert!(crate::sema::check_module(&module).is_empty(), "{source}");
+    }
+}
+
+#[test]
+fn generation_is_reproducible() {
+    let generate = |seed| Generator::new(seed, Config::default()).module("main");
+
+    assert_eq!(generate(7), generate(7));
+    assert_ne!(generate(7), generate(8));
+}
@@ -0,0 +1,131 @@
+use std::{
+    borrow::Borrow,
+    collections::HashSet,
+    fmt::{Debug, Display, Formatter},
+    hash::{Hash, Hasher},
+    ops::Deref,
+    sync::{LazyLock, Mutex},
+};
+
+/// Every name that has been interned so far. Names are never freed, as a compilation refers to
+/// the same few names over and over until it exits.
+static INTERNER: LazyLock<Mutex<HashSet<&'static str>>> =
+    LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// An interned identifier. Every distinct name is stored only once, so a [`Symbol`] is a single
+/// reference that is free to copy, and comparing two of them does not look at their contents.
+#[derive(Clone, Copy)]
+pub struct Symbol(&'static str);
+
+impl Symbol {
+    /// Returns the symbol for a name, storing the name the first time it is seen.
+    pub fn intern(name: &str) -> Symbol {
+        let mut interner = INTERNER.lock().unwrap();
+
+        match interner.get(name) {
+            Some(interned) => Symbol(interned),
+            None => {
+                let interned: &'static str = Box::leak(name.into());
+                interner.insert(interned);
+
+                Symbol(interned)
+            }
+        }
+    }
+
+    /// Returns the name the symbol was interned from.
+    pub fn as_str(&self) -> &'static str {
+        self.0
+    }
+}
+
+/// Two symbols are the same name exactly when they share storage.
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Symbol) -> bool {
+        std::ptr::eq(self.0, other.0)
+    }
+}
+
+impl Eq for Symbol {}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+/// Symbols are ordered and hashed by their name, like the [`str`] they can be borrowed as.
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Symbol) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Symbol {
+    fn cmp(&self, other: &Symbol) -> std::cmp::Ordering {
+        self.0.cmp(other.0)
+    }
+}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl Borrow<str> for Symbol {
+    fn borrow(&self) -> &str {
+        self.0
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.0
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Symbol {
+        Symbol::intern(name)
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(name: String) -> Symbol {
+        Symbol::intern(&name)
+    }
+}
+
+impl Debug for Symbol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self.0, f)
+    }
+}
+
+impl Display for Symbol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self.0, f)
+    }
+}
+
+#[test]
+fn equal_names_share_a_symbol() {
+    let name = String::from("main");
+
+    assert_eq!(Symbol::intern("main"), Symbol::from(name.clone()));
+    assert!(std::ptr::eq(
+        Symbol::intern("main").as_str(),
+        Symbol::intern(&name).as_str()
+    ));
+    assert_ne!(Symbol::intern("main"), Symbol::intern("mains"));
+    assert_eq!(Symbol::intern("main"), "main");
+}
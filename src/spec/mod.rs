@@ -1,7 +1,36 @@
 pub mod ast;
+pub mod generate;
+pub mod intern;
+pub mod pretty;
+pub mod visit;
 
 use crate::parser::*;
 use ast::*;
+use visit::{Visit, walk_statement};
+
+/// Words that are reserved by the language and can never be used as identifiers.
+pub const KEYWORDS: &[&str] = &[
+    "alloc",
+    "as",
+    "asm",
+    "assert",
+    "const",
+    "else",
+    "enum",
+    "ext",
+    "fn",
+    "for",
+    "free",
+    "if",
+    "import",
+    "in",
+    "let",
+    "match",
+    "new",
+    "pub",
+    "return",
+    "unreachable",
+];
 
 pub fn atomic() -> Parser<Type> {
     identifier().map(Type::Atomic)
@@ -11,98 +40,1692 @@ pub fn pointer() -> Parser<Type> {
     symbol("*").right(typ()).map(Box::new).map(Type::Pointer)
 }
 
+pub fn never() -> Parser<Type> {
+    symbol("!").map(|_| Type::Never)
+}
+
+/// Parses an array type, written `[T; N]`.
+pub fn array() -> Parser<Type> {
+    between(
+        symbol("["),
+        typ().left(symbol(";")).chain(number()),
+        symbol("]"),
+    )
+    .map(|(element, length)| Type::Array(Box::new(element), length))
+}
+
+/// Parses a function pointer type, such as `fn(u32, *char) -> u32`.
+pub fn function_type() -> Parser<Type> {
+    let arguments = typ()
+        .maybe()
+        .chain(symbol(",").right(typ()).many())
+        .left(symbol(",").maybe())
+        .map(|(head, rest)| head.into_iter().chain(rest).collect());
+
+    keyword("fn")
+        .right(between(symbol("("), arguments, symbol(")")))
+        .left(symbol("->"))
+        .chain(typ())
+        .map(|(arguments, returns)| Type::Function(arguments, Box::new(returns)))
+}
+
 pub fn typ() -> Parser<Type> {
-    Parser::lazy(|| atomic().or(pointer()))
+    Parser::lazy(|| {
+        function_type()
+            .or(atomic())
+            .or(pointer())
+            .or(array())
+            .or(never())
+    })
+    .label("type")
+    .memoized("type")
+    .named("type")
+}
+
+/// Parses a string literal, which may contain any ASCII character, escaped like in character
+/// literals, returning the text between its quotes.
+pub fn string() -> Parser<String> {
+    strip(between(
+        char('"'),
+        quoted_char('"').many(),
+        char('"').label("'\"'"),
+    ))
+    .map(|text| text.into_iter().collect())
 }
 
 pub fn str_literal() -> Parser<Expression> {
+    string().map(Expression::StringLiteral)
+}
+
+/// Parses an unsigned integer literal. Literals are decimal by default, or hexadecimal, octal or
+/// binary when prefixed with `0x`, `0o` or `0b`, and may contain `_` digit separators anywhere
+/// after their first digit.
+pub fn number() -> Parser<u64> {
+    strip(Parser::new(|input: Input| {
+        let rest = input.rest();
+
+        if !rest.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(error::ParseError::Unit);
+        }
+
+        let (radix, prefix) = match rest.get(..2) {
+            Some("0x") => (16, 2),
+            Some("0o") => (8, 2),
+            Some("0b") => (2, 2),
+            _ => (10, 0),
+        };
+
+        // Everything that could belong to the literal is consumed, so that `0b102` is reported
+        // as a malformed literal rather than as `0b10` followed by `2`.
+        let len = rest[prefix..]
+            .find(|c| !is_alphanum(c))
+            .map_or(rest.len(), |len| prefix + len);
+
+        let mut value = None;
+
+        for (index, c) in rest[prefix..len].char_indices() {
+            if c == '_' {
+                continue;
+            }
+
+            let start = input.offset() + prefix + index;
+            let digit = c.to_digit(radix).ok_or(error::ParseError::InvalidDigit {
+                digit: c,
+                radix,
+                span: Span::new(start, start + c.len_utf8()),
+            })?;
+
+            value = Some(
+                value
+                    .unwrap_or(0u64)
+                    .checked_mul(radix as u64)
+                    .and_then(|value| value.checked_add(digit as u64))
+                    .ok_or(error::ParseError::NumberOverflow {
+                        span: Span::new(input.offset(), input.offset() + len),
+                    })?,
+            );
+        }
+
+        let rest = input.advance(len);
+
+        match value {
+            Some(value) => Ok((value, rest)),
+            None => Err(error::ParseError::Expected {
+                expected: vec![format!("base {radix} digit")],
+                found: rest.next_char(),
+                span: rest.next_span(),
+            }),
+        }
+    }))
+}
+
+pub fn num_literal() -> Parser<Expression> {
+    number().map(Expression::NumericLiteral)
+}
+
+/// Parses the character after a `\` in a character literal.
+pub fn escape() -> Parser<char> {
+    Parser::new(|input: Input| {
+        let escaped = match input.next_char() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('0') => '\0',
+            Some(c @ ('\\' | '\'' | '"')) => c,
+            Some(escape) => {
+                return Err(error::ParseError::InvalidEscape {
+                    escape,
+                    span: input.next_span(),
+                });
+            }
+            None => return Err(error::ParseError::Unit),
+        };
+
+        Ok((escaped, input.advance(1)))
+    })
+    .label("escape character")
+}
+
+/// Parses a single ASCII character inside of a character literal, which may be escaped.
+pub fn literal_char() -> Parser<char> {
+    quoted_char('\'')
+}
+
+/// Parses a single ASCII character inside of a literal delimited by `quote`, which may be escaped
+/// and must be when it is `quote` itself.
+fn quoted_char(quote: char) -> Parser<char> {
+    let plain = Parser::new(move |input: Input| match input.next_char() {
+        Some('\\' | '\n') | None => Err(error::ParseError::Unit),
+        Some(c) if c == quote => Err(error::ParseError::Unit),
+        Some(found) if !found.is_ascii() => Err(error::ParseError::NonAsciiCharacter {
+            found,
+            span: input.next_span(),
+        }),
+        Some(c) => Ok((c, input.advance(1))),
+    });
+
+    char('\\').right(escape()).or(plain).label("character")
+}
+
+/// Parses a character literal, returning the character between its quotes.
+pub fn character() -> Parser<char> {
+    strip(between(
+        char('\''),
+        literal_char(),
+        char('\'').label("'\\''"),
+    ))
+}
+
+pub fn char_literal() -> Parser<Expression> {
+    character().map(Expression::CharLiteral)
+}
+
+pub fn variable() -> Parser<Expression> {
+    identifier().map(Expression::Variable)
+}
+
+/// Parses an expression that is not made up of operators, or any expression in parentheses.
+pub fn primary() -> Parser<Expression> {
+    Parser::lazy(|| {
+        between(symbol("("), expression(), symbol(")"))
+            .or(str_literal())
+            .or(num_literal())
+            .or(char_literal())
+            .or(new_expression())
+            .or(alloc())
+            .or(if_expression())
+            .or(block_expression())
+            .or(variant())
+            .or(call())
+            .or(index())
+            .or(variable())
+    })
+}
+
+/// Parses an operator that is written in front of its operand.
+pub fn unary_op() -> Parser<UnaryOp> {
+    symbol("-")
+        .map(|_| UnaryOp::Neg)
+        .or(symbol("!").map(|_| UnaryOp::Not))
+        .or(symbol("~").map(|_| UnaryOp::BitNot))
+        .or(symbol("*").map(|_| UnaryOp::Deref))
+        .or(symbol("&").map(|_| UnaryOp::AddressOf))
+}
+
+/// Parses any number of prefix operators applied to a primary expression. Prefix operators bind
+/// tighter than any other operator.
+pub fn unary() -> Parser<Expression> {
+    Parser::lazy(|| {
+        unary_op()
+            .chain(unary().spanned())
+            .map(|(op, operand)| Expression::Unary(op, Box::new(operand)))
+            .or(primary())
+    })
+}
+
+/// Combines two operands with a binary operator, into an expression spanning both.
+fn join(
+    op: BinaryOp,
+    left: Spanned<Expression>,
+    right: Spanned<Expression>,
+) -> Spanned<Expression> {
+    let span = Span::new(left.span.start, right.span.end);
+
+    Spanned::new(
+        Expression::Binary(op, Box::new(left), Box::new(right)),
+        span,
+    )
+}
+
+/// The function a binary operator parses to, combining the operands on either side of it.
+type Join = fn(Spanned<Expression>, Spanned<Expression>) -> Spanned<Expression>;
+
+/// Parses a binary operator, for use with [`Parser::chainl1`].
+fn binary_op(op: BinaryOp) -> Parser<Join> {
+    // Function pointers can not capture the operator, so each gets a function of its own.
+    let combine: Join = match op {
+        BinaryOp::BitAnd => |left, right| join(BinaryOp::BitAnd, left, right),
+        BinaryOp::BitOr => |left, right| join(BinaryOp::BitOr, left, right),
+        BinaryOp::BitXor => |left, right| join(BinaryOp::BitXor, left, right),
+        BinaryOp::Shl => |left, right| join(BinaryOp::Shl, left, right),
+        BinaryOp::Shr => |left, right| join(BinaryOp::Shr, left, right),
+        BinaryOp::Eq => |left, right| join(BinaryOp::Eq, left, right),
+        BinaryOp::Ne => |left, right| join(BinaryOp::Ne, left, right),
+    };
+
+    symbol(op.to_string()).map(move |_| combine)
+}
+
+/// Parses any number of conversions applied to a unary expression, such as `x as u8`. Like in
+/// Rust, `as` binds looser than prefix operators but tighter than any binary operator.
+pub fn cast() -> Parser<Spanned<Expression>> {
+    unary()
+        .spanned()
+        .chain(keyword("as").right(typ().spanned()).many_nested())
+        .map(|(value, types)| {
+            types.into_iter().fold(value, |value, typ| {
+                let span = Span::new(value.span.start, typ.span.end);
+                Spanned::new(Expression::Cast(Box::new(value), typ), span)
+            })
+        })
+}
+
+/// Parses the shift operators, which bind tighter than the bitwise ones.
+pub fn shift() -> Parser<Spanned<Expression>> {
+    cast().chainl1(binary_op(BinaryOp::Shl).or(binary_op(BinaryOp::Shr)))
+}
+
+/// Parses the equality operators, which bind looser than shifts but tighter than the bitwise
+/// operators, like in C.
+pub fn equality() -> Parser<Spanned<Expression>> {
+    shift().chainl1(binary_op(BinaryOp::Eq).or(binary_op(BinaryOp::Ne)))
+}
+
+/// Parses the bitwise operators. Like in C, `&` binds tighter than `^`, which binds tighter than
+/// `|`.
+pub fn bitwise() -> Parser<Spanned<Expression>> {
+    equality()
+        .chainl1(binary_op(BinaryOp::BitAnd))
+        .chainl1(binary_op(BinaryOp::BitXor))
+        .chainl1(binary_op(BinaryOp::BitOr))
+}
+
+pub fn expression() -> Parser<Expression> {
+    Parser::lazy(bitwise)
+        .map(|expression| expression.node)
+        .label("expression")
+        .memoized("expression")
+        .named("expression")
+}
+
+/// Parses a variant of an enum, such as `Color::Red`, or `Shape::Rect(1, 2)` with its fields.
+pub fn variant() -> Parser<Expression> {
+    identifier()
+        .left(symbol("::"))
+        .chain(identifier())
+        .chain(arguments().maybe())
+        .map(|((name, variant), fields)| {
+            Expression::Variant(name, variant, fields.unwrap_or_default())
+        })
+}
+
+/// Parses the allocation of a single value on the heap, such as `new u32(1)`.
+pub fn new_expression() -> Parser<Expression> {
+    keyword("new")
+        .right(typ().spanned())
+        .chain(between(symbol("("), expression().spanned(), symbol(")")))
+        .map(|(typ, value)| Expression::New(typ, Box::new(value)))
+}
+
+/// Parses the allocation of a number of values on the heap, such as `alloc::<u32>(4)`.
+pub fn alloc() -> Parser<Expression> {
+    keyword("alloc")
+        .left(symbol("::"))
+        .right(between(symbol("<"), typ().spanned(), symbol(">")))
+        .chain(between(symbol("("), expression().spanned(), symbol(")")))
+        .map(|(typ, count)| Expression::Alloc(typ, Box::new(count)))
+}
+
+/// Parses an `if` expression, such as `if x { 1 } else { 2 }`. The `else` branch may be another
+/// `if` expression without braces around it, as in `if x { 1 } else if y { 2 } else { 3 }`.
+pub fn if_expression() -> Parser<Expression> {
+    keyword("if")
+        .right(expression().spanned())
+        .chain(block_expression().spanned())
+        .left(keyword("else"))
+        .chain(
+            block_expression()
+                .spanned()
+                .or(Parser::lazy(if_expression).spanned()),
+        )
+        .map(|((condition, then), otherwise)| {
+            Expression::If(Box::new(condition), Box::new(then), Box::new(otherwise))
+        })
+}
+
+/// Parses a block that ends with an expression without a `;`, which is its value, such as
+/// `{ let y: u32 = x << 1; y | 1 }`. A block of nothing but an expression is that expression.
+pub fn block_expression() -> Parser<Expression> {
     between(
-        symbol("\""),
-        alphanum().many().qualify().map(Expression::StringLiteral),
-        symbol("\""),
+        symbol("{"),
+        recovering_statement()
+            .spanned()
+            .many()
+            .chain(expression().spanned()),
+        symbol("}"),
     )
+    .map(|(body, value)| match body.is_empty() {
+        true => value.node,
+        false => Expression::Block(body, Box::new(value)),
+    })
 }
 
-pub fn num_literal() -> Parser<Expression> {
-    // TODO: make this parser use an "and_then"/flatmap
-    Parser::new(move |input| {
-        match digit()
+/// Parses an element access of a constant array, such as `TABLE[i]`.
+pub fn index() -> Parser<Expression> {
+    identifier()
+        .chain(between(symbol("["), expression().spanned(), symbol("]")))
+        .map(|(name, index)| Expression::Index(name, Box::new(index)))
+}
+
+/// Parses a parenthesized, comma separated list of call arguments.
+pub fn arguments() -> Parser<Vec<Spanned<Expression>>> {
+    between(
+        symbol("("),
+        expression()
+            .spanned()
+            .maybe()
+            .chain(symbol(",").right(expression().spanned()).many()),
+        symbol(")"),
+    )
+    .map(|(head, rest)| head.into_iter().chain(rest).collect())
+    .memoized("arguments")
+}
+
+pub fn call() -> Parser<Expression> {
+    identifier()
+        .chain(arguments())
+        .map(|(name, args)| Expression::Call(name, args))
+}
+
+pub fn function_call() -> Parser<Statement> {
+    identifier()
+        .chain(arguments())
+        .map(|(name, args)| Statement::FunctionCall(name, args))
+}
+
+pub fn ret() -> Parser<Statement> {
+    keyword("return")
+        .right(expression().spanned())
+        .map(Statement::Return)
+}
+
+pub fn let_binding() -> Parser<Statement> {
+    keyword("let")
+        .right(identifier())
+        .left(symbol(":"))
+        .chain(typ().spanned())
+        .left(symbol("="))
+        .chain(expression().spanned())
+        .map(|((name, typ), value)| Statement::Let(name, typ, value))
+}
+
+pub fn block() -> Parser<Vec<Spanned<Statement>>> {
+    between(
+        symbol("{"),
+        recovering_statement().spanned().many(),
+        symbol("}"),
+    )
+    .named("block")
+}
+
+/// Parses the body of a function. Like a block expression, it can end with an expression without
+/// a `;`, which the function returns: `fn f() -> u32 { 42 }` is `fn f() -> u32 { return 42; }`.
+pub fn function_body() -> Parser<Vec<Spanned<Statement>>> {
+    between(
+        symbol("{"),
+        recovering_statement()
+            .spanned()
             .many()
-            .qualify()
-            .map(|str| str.parse::<u64>())
-            .parse(input)
-        {
-            Ok((Ok(num), remaining)) => Ok((num, remaining)),
-            _ => Err(error::ParseError::Unit),
+            .chain(expression().spanned().maybe()),
+        symbol("}"),
+    )
+    .map(|(mut body, value)| {
+        if let Some(value) = value {
+            let span = value.span;
+            body.push(Spanned::new(Statement::Return(value), span));
         }
+
+        body
     })
-    .map(Expression::NumericLiteral)
+    .named("function body")
 }
 
-pub fn expression() -> Parser<Expression> {
-    str_literal().or(num_literal())
+pub fn for_loop() -> Parser<Statement> {
+    keyword("for")
+        .right(identifier())
+        .left(keyword("in"))
+        .chain(expression().spanned())
+        .left(symbol(".."))
+        .chain(expression().spanned())
+        .chain(block())
+        .map(|(((name, start), end), body)| Statement::For(name, start, end, body))
 }
 
-pub fn function_call() -> Parser<Statement> {
+/// Parses the pattern of a `match` arm.
+pub fn pattern() -> Parser<Pattern> {
+    let literal = number()
+        .map(Pattern::Number)
+        .or(character().map(Pattern::Char));
+
+    let binding = identifier().or(keyword("_").map(|_| Symbol::from("_")));
+    let bindings = between(
+        symbol("("),
+        binding
+            .clone()
+            .chain(symbol(",").right(binding).many())
+            .left(symbol(",").maybe()),
+        symbol(")"),
+    )
+    .map(|(head, rest)| std::iter::once(head).chain(rest).collect());
+
     identifier()
-        .chain(between(
+        .left(symbol("::"))
+        .chain(identifier())
+        .chain(bindings.maybe())
+        .map(|((name, variant), bindings)| {
+            Pattern::Variant(name, variant, bindings.unwrap_or_default())
+        })
+        .or(literal)
+        .or(keyword("_").map(|_| Pattern::Wildcard))
+        .label("pattern")
+}
+
+/// Parses a `match` statement, whose arms may be separated by commas.
+pub fn match_statement() -> Parser<Statement> {
+    let arm = pattern()
+        .spanned()
+        .left(symbol("=>"))
+        .chain(block())
+        .left(symbol(",").maybe());
+
+    keyword("match")
+        .right(expression().spanned())
+        .chain(between(symbol("{"), arm.many(), symbol("}")))
+        .map(|(value, arms)| Statement::Match(value, arms))
+}
+
+pub fn unreachable() -> Parser<Statement> {
+    keyword("unreachable")
+        .left(symbol("("))
+        .left(symbol(")"))
+        .map(|_| Statement::Unreachable)
+}
+
+pub fn free() -> Parser<Statement> {
+    keyword("free")
+        .right(between(symbol("("), expression().spanned(), symbol(")")))
+        .map(Statement::Free)
+}
+
+pub fn assert() -> Parser<Statement> {
+    keyword("assert")
+        .right(between(
             symbol("("),
-            expression()
-                .maybe()
-                .chain(symbol(",").right(expression()).many()),
+            expression().spanned().left(symbol(",")).chain(string()),
             symbol(")"),
         ))
-        .map(|(name, (head, rest))| {
-            Statement::FunctionCall(name, head.into_iter().chain(rest.into_iter()).collect())
-        })
+        .map(|(condition, message)| Statement::Assert(condition, message))
 }
 
-pub fn ret() -> Parser<Statement> {
-    symbol("return").right(expression()).map(Statement::Return)
+/// Parses inline assembly, such as `asm!("nop")`, whose template is written as a string literal.
+pub fn asm() -> Parser<Statement> {
+    keyword("asm")
+        .left(symbol("!"))
+        .right(between(symbol("("), string(), symbol(")")))
+        .map(Statement::Asm)
 }
 
 pub fn statement() -> Parser<Statement> {
-    function_call().or(ret()).left(symbol(";"))
+    Parser::lazy(|| {
+        function_call()
+            .or(ret())
+            .or(unreachable())
+            .or(free())
+            .or(assert())
+            .or(asm())
+            .or(let_binding())
+            .left(symbol(";"))
+            .or(block()
+                .map(Statement::Block)
+                .or(for_loop())
+                .or(match_statement())
+                .left(symbol(";").maybe()))
+    })
+    .label("statement")
+    .named("statement")
+}
+
+/// Returns the characters of a source, with their byte indices, that are not part of a string or
+/// character literal, so that braces and semicolons in a literal are not mistaken for structure.
+fn unquoted(source: &str) -> impl Iterator<Item = (usize, char)> + '_ {
+    let mut quote = None;
+    let mut escaped = false;
+
+    source.char_indices().filter(move |&(_, c)| {
+        match (quote, c) {
+            (Some(_), _) if escaped => escaped = false,
+            (Some(_), '\\') => escaped = true,
+            (Some(open), _) if c == open => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, _) => return true,
+        }
+
+        false
+    })
 }
 
-pub fn argument_parser() -> Parser<Vec<(String, Type)>> {
-    let argument = identifier().left(symbol(":")).chain(typ());
+/// Skips input up to and including the next `;` that is not nested inside of braces or a string
+/// or character literal, or up to the `}` that closes the enclosing block, which is where the next
+/// statement starts. Fails when there is nothing to skip.
+pub fn skip_statement() -> Parser<()> {
+    Parser::new(|input: Input| {
+        let mut depth = 0usize;
+        let mut end = input.rest().len();
+
+        for (index, c) in unquoted(input.rest()) {
+            match c {
+                '{' => depth += 1,
+                '}' if depth == 0 => {
+                    end = index;
+                    break;
+                }
+                '}' => depth -= 1,
+                ';' if depth == 0 => {
+                    end = index + 1;
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        match end {
+            0 => Err(error::ParseError::Unit),
+            len => Ok(((), input.advance(len))),
+        }
+    })
+}
+
+/// Parses a [`statement`], unless the input is recovering from errors, see
+/// [`Parser::with_recovery`], in which case a statement that fails to parse is skipped with
+/// [`skip_statement`] and parsed as a [`Statement::Error`] holding its error. This lets the rest
+/// of a block parse, so that every broken statement in it is reported. The expression that a
+/// block can end with is not skipped.
+pub fn recovering_statement() -> Parser<Statement> {
+    Parser::new(|input: Input| {
+        let error = match statement().parse_input(input.clone()) {
+            Err(error) if input.recovers() && !error.is_fatal() => error,
+            result => return result,
+        };
+
+        if expression()
+            .left(symbol("}"))
+            .parse_input(input.clone())
+            .is_ok()
+        {
+            return Err(error);
+        }
+
+        match strip(skip_statement()).parse_input(input) {
+            Ok((_, rest)) => Ok((Statement::Error(error), rest)),
+            Err(_) => Err(error),
+        }
+    })
+}
+
+pub fn argument_parser() -> Parser<Vec<(Symbol, Spanned<Type>)>> {
+    let argument = identifier().left(symbol(":")).chain(typ().spanned());
 
     argument
         .clone()
         .maybe()
         .chain(symbol(",").right(argument).many())
         .left(symbol(",").maybe())
-        .map(|(head, rest)| head.into_iter().chain(rest.into_iter()).collect())
+        .map(|(head, rest)| head.into_iter().chain(rest).collect())
+        .memoized("parameters")
+}
+
+/// Parses the calling convention of an external function, such as the `"stdcall"` in
+/// `ext "stdcall" fn`.
+pub fn abi() -> Parser<Abi> {
+    alt(Abi::ALL
+        .into_iter()
+        .map(|abi| symbol(format!("\"{}\"", abi.name())).map(move |_| abi)))
+    .label("calling convention")
 }
 
 pub fn extern_function_definition() -> Parser<Item> {
-    symbol("ext")
-        .chain(symbol("fn"))
-        .right(identifier())
+    keyword("ext")
+        .right(abi().maybe())
+        .left(keyword("fn"))
+        .chain(identifier())
         .chain(between(symbol("("), argument_parser(), symbol(")")))
         .left(symbol("->"))
-        .chain(typ())
-        .map(|((a, b), c)| Item::ExternFunctionDefinition(a, b, c))
+        .chain(typ().spanned())
+        .map(|(((abi, a), b), c)| {
+            Item::ExternFunctionDefinition(
+                a,
+                b,
+                c,
+                abi.unwrap_or_default(),
+                vec![],
+                Visibility::Private,
+            )
+        })
+}
+
+/// Parses the type parameters of a generic function, such as the `<T, U>` in
+/// `fn pair<T, U>(a: T, b: U) -> u32`.
+pub fn generics() -> Parser<Vec<Symbol>> {
+    between(
+        symbol("<"),
+        identifier()
+            .chain(symbol(",").right(identifier()).many())
+            .left(symbol(",").maybe()),
+        symbol(">"),
+    )
+    .map(|(head, rest)| std::iter::once(head).chain(rest).collect())
 }
 
 pub fn function_declaration() -> Parser<Item> {
-    symbol("fn")
+    keyword("fn")
         .right(identifier())
+        .chain(generics().maybe())
         .chain(between(symbol("("), argument_parser(), symbol(")")))
         .left(symbol("->"))
-        .chain(typ())
-        .chain(between(symbol("{"), statement().many(), symbol("}")))
-        .map(|(((a, b), c), d)| Item::FunctionDeclaration(a, b, c, d))
+        .chain(typ().spanned())
+        .chain(function_body())
+        .map(|((((a, b), c), d), e)| {
+            Item::FunctionDeclaration(
+                a,
+                b.unwrap_or_default(),
+                c,
+                d,
+                e,
+                vec![],
+                Visibility::Private,
+            )
+        })
+}
+
+/// Parses the `pub` in front of an item, if there is one.
+pub fn visibility() -> Parser<Visibility> {
+    keyword("pub")
+        .map(|_| Visibility::Public)
+        .or(Parser::pure(Visibility::Private))
+}
+
+/// Parses a constant array, such as `const TABLE: [u32; 2] = [1, 2]`. Only literals may be used as
+/// elements.
+pub fn constant() -> Parser<Item> {
+    let literal = str_literal()
+        .or(num_literal())
+        .or(char_literal())
+        .label("literal")
+        .spanned();
+
+    let elements = between(
+        symbol("["),
+        literal
+            .clone()
+            .maybe()
+            .chain(symbol(",").right(literal).many())
+            .left(symbol(",").maybe()),
+        symbol("]"),
+    )
+    .map(|(head, rest)| head.into_iter().chain(rest).collect());
+
+    keyword("const")
+        .right(identifier())
+        .left(symbol(":"))
+        .chain(typ().spanned())
+        .left(symbol("="))
+        .chain(elements)
+        .map(|((name, typ), elements)| Item::Const(name, typ, elements, Visibility::Private))
+}
+
+/// Parses an enum, such as `enum Color { Red, Green, Blue }`. Variants may list the types of
+/// their fields, such as in `enum Shape { Circle(u32), Rect(u32, u32) }`.
+pub fn enumeration() -> Parser<Item> {
+    let fields = between(
+        symbol("("),
+        typ()
+            .spanned()
+            .chain(symbol(",").right(typ().spanned()).many())
+            .left(symbol(",").maybe()),
+        symbol(")"),
+    )
+    .map(|(head, rest)| std::iter::once(head).chain(rest).collect());
+    let variant = identifier()
+        .spanned()
+        .chain(fields.maybe())
+        .map(|(name, fields)| (name, fields.unwrap_or_default()));
+
+    let variants = between(
+        symbol("{"),
+        variant
+            .clone()
+            .maybe()
+            .chain(symbol(",").right(variant).many())
+            .left(symbol(",").maybe()),
+        symbol("}"),
+    )
+    .map(|(head, rest)| head.into_iter().chain(rest).collect());
+
+    keyword("enum")
+        .right(identifier())
+        .chain(variants)
+        .map(|(name, variants)| Item::Enum(name, variants, Visibility::Private))
+}
+
+/// Parses an attribute, such as `#[no_mangle]`.
+pub fn attribute() -> Parser<Attribute> {
+    between(
+        symbol("#["),
+        keyword("no_mangle")
+            .map(|_| Attribute::NoMangle)
+            .label("attribute"),
+        symbol("]"),
+    )
 }
 
+/// Parses any top-level item. Only functions may have attributes in front of them.
 pub fn item() -> Parser<Item> {
-    extern_function_definition().or(function_declaration())
+    let function = attribute()
+        .many()
+        .chain(visibility())
+        .chain(extern_function_definition().or(function_declaration()))
+        .map(|((attributes, visibility), item)| {
+            item.with_attributes(attributes).with_visibility(visibility)
+        });
+
+    let other = visibility()
+        .chain(constant().or(enumeration()))
+        .map(|(visibility, item)| item.with_visibility(visibility));
+
+    function.or(other).label("item").named("item")
 }
 
-pub fn module(name: String) -> Parser<Module> {
-    item()
+/// Skips a `#!` interpreter line at the very start of the input, so that pal scripts can be made
+/// executable on Unix. Always succeeds, consuming nothing when there is no such line.
+pub fn shebang() -> Parser<()> {
+    Parser::new(|input: Input| {
+        if input.offset() != 0 || !input.rest().starts_with("#!") {
+            return Ok(((), input));
+        }
+
+        let len = input
+            .rest()
+            .find('\n')
+            .map_or(input.rest().len(), |newline| newline + 1);
+
+        Ok(((), input.advance(len)))
+    })
+}
+
+/// Parses `import name;`, which makes the items of `name.pal`, next to the importing file,
+/// available to it.
+pub fn import() -> Parser<String> {
+    keyword("import")
+        .right(identifier())
         .left(symbol(";"))
-        .many()
-        .map(move |items| Module(name.clone(), items))
+        .map(|name| name.to_string())
+}
+
+pub fn module(name: String) -> Parser<Module> {
+    shebang()
+        .right(strip(
+            import()
+                .spanned()
+                .many()
+                .chain(item().spanned().left(symbol(";")).many()),
+        ))
+        .left(eof())
+        .map(move |(imports, items)| Module(name.clone(), items, imports))
+}
+
+/// Skips input up to and including the next `;` that is not nested inside of braces or a string
+/// or character literal, which is where the next item starts. Fails only at the end of the input.
+pub fn skip_item() -> Parser<()> {
+    Parser::new(|input: Input| {
+        let mut depth = 0usize;
+
+        for (index, c) in unquoted(input.rest()) {
+            match c {
+                '{' => depth += 1,
+                '}' => depth = depth.saturating_sub(1),
+                ';' if depth == 0 => return Ok(((), input.advance(index + 1))),
+                _ => {}
+            }
+        }
+
+        match input.rest().len() {
+            0 => Err(error::ParseError::Unit),
+            len => Ok(((), input.advance(len))),
+        }
+    })
+}
+
+/// Parses many `T`s, skipping over input that fails to parse with [`skip_item`] instead of
+/// stopping at the first failure. Returns everything that parsed along with every error that was
+/// encountered along the way.
+pub fn recovering<T: Clone + 'static>(p: Parser<T>) -> Parser<(Vec<T>, Vec<error::ParseError>)> {
+    p.recover(skip_item()).many().map(|results| {
+        let mut parsed = vec![];
+        let mut errors = vec![];
+
+        for result in results {
+            match result {
+                Ok(value) => parsed.push(value),
+                Err(error) => errors.push(error),
+            }
+        }
+
+        (parsed, errors)
+    })
+}
+
+/// Parses a module like [`module`], but skips over items that fail to parse instead of stopping
+/// at the first one, returning every error that was encountered along the way.
+pub fn recovering_module(name: String) -> Parser<(Module, Vec<error::ParseError>)> {
+    shebang()
+        .right(strip(
+            import()
+                .spanned()
+                .many()
+                .chain(recovering(item().spanned().left(symbol(";")))),
+        ))
+        .left(eof())
+        .with_recovery()
+        .map(move |(imports, (items, mut errors))| {
+            let module = Module(name.clone(), items, imports);
+
+            let mut collector = StatementErrors(&mut errors);
+            collector.visit_module(&module);
+            errors.sort_by_key(|error| error.span().map(|span| span.start));
+
+            (module, errors)
+        })
+}
+
+/// Parses a script, in which bare statements may appear at the top level in between items.
+/// Recovers from errors like [`recovering_module`].
+pub fn recovering_script() -> Parser<(Vec<TopLevel>, Vec<error::ParseError>)> {
+    shebang()
+        .right(strip(recovering(
+            item()
+                .spanned()
+                .left(symbol(";"))
+                .map(TopLevel::Item)
+                .or(recovering_statement().spanned().map(TopLevel::Statement)),
+        )))
+        .left(eof())
+        .with_recovery()
+        .map(|(elements, mut errors)| {
+            let mut collector = StatementErrors(&mut errors);
+
+            for element in &elements {
+                match element {
+                    TopLevel::Item(item) => collector.visit_item(item),
+                    TopLevel::Statement(statement) => collector.visit_statement(statement),
+                }
+            }
+
+            errors.sort_by_key(|error| error.span().map(|span| span.start));
+
+            (elements, errors)
+        })
+}
+
+/// Collects the errors of the statements that were skipped by [`recovering_statement`].
+struct StatementErrors<'e>(&'e mut Vec<error::ParseError>);
+
+impl Visit<'_> for StatementErrors<'_> {
+    fn visit_statement(&mut self, statement: &Spanned<Statement>) {
+        if let Statement::Error(error) = &statement.node {
+            self.0.push(error.clone());
+        }
+
+        walk_statement(self, statement);
+    }
+}
+
+/// Parses arbitrary bytes with every entry point of the grammar, as a fuzzer would, discarding the
+/// results. Bytes that are not UTF-8 are replaced, as source is always read as text. Every rule
+/// is depth limited and none of them panics, so this returns for any input, without overflowing
+/// the stack.
+pub fn fuzz_parse(bytes: &[u8]) {
+    let source = String::from_utf8_lossy(bytes);
+
+    let _ = module("fuzz".to_string()).parse(&*source);
+    let _ = item().parse(&*source);
+    let _ = recovering_module("fuzz".to_string()).parse_memoized(&*source);
+    let _ = recovering_script().parse_memoized(&*source);
+}
+
+#[test]
+fn blocks_parse_as_statements() {
+    assert_eq!(
+        statement().parse("{ let x: u32 = 1; { let x: u32 = x; } }"),
+        Ok((
+            Statement::Block(vec![
+                Statement::Let(
+                    "x".into(),
+                    Type::Atomic("u32".into()).into(),
+                    Expression::NumericLiteral(1).into()
+                )
+                .into(),
+                Statement::Block(vec![
+                    Statement::Let(
+                        "x".into(),
+                        Type::Atomic("u32".into()).into(),
+                        Expression::Variable("x".into()).into()
+                    )
+                    .into()
+                ])
+                .into(),
+            ]),
+            "".to_string()
+        ))
+    );
+}
+
+#[test]
+fn for_loops_parse() {
+    assert_eq!(
+        statement().parse("for i in 0..n { f(i); }"),
+        Ok((
+            Statement::For(
+                "i".into(),
+                Expression::NumericLiteral(0).into(),
+                Expression::Variable("n".into()).into(),
+                vec![
+                    Statement::FunctionCall(
+                        "f".into(),
+                        vec![Expression::Variable("i".into()).into()]
+                    )
+                    .into()
+                ]
+            ),
+            "".to_string()
+        ))
+    );
+}
+
+#[test]
+fn failed_alternatives_report_labels() {
+    assert_eq!(
+        statement().parse("let x: u32 = ;").unwrap_err().to_string(),
+        "expected expression, found ';'"
+    );
+}
+
+#[test]
+fn recovering_modules_report_every_broken_item() {
+    let (parsed, errors) = recovering_module("main".to_string())
+        .parse("fn a() -> u32 { return ; }; fn b() -> u32 { return 1; }; fn c( -> u32 {};")
+        .unwrap()
+        .0;
+
+    assert_eq!(parsed.1.len(), 2);
+    assert_eq!(errors.len(), 2);
+}
+
+#[test]
+fn braces_in_literals_of_broken_items_are_skipped() {
+    let (parsed, errors) = recovering_module("main".to_string())
+        .parse(
+            r#"fn a( -> u32 { f("{"); return 0; };
+            fn b( -> u8 { return '}'; };
+            fn main() -> u32 { return 0; };"#,
+        )
+        .unwrap()
+        .0;
+
+    assert_eq!(errors.len(), 2);
+    assert_eq!(
+        parsed.to_string(),
+        "fn main() -> u32 {\n    return 0;\n};\n"
+    );
+}
+
+#[test]
+fn broken_statements_are_skipped() {
+    let ((parsed, errors), _) = recovering_module("main".to_string())
+        .parse(
+            r#"fn main() -> u32 {
+                let x: u32 = ;
+                f("a;b" ;
+                g('}' ;
+                for i in 0..x { f(i; g(i); }
+                let y: u32 = { h(; x };
+                y
+            };"#,
+        )
+        .unwrap();
+
+    assert_eq!(
+        errors.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        [
+            "expected expression, found ';'",
+            "expected ')', found ';'",
+            "expected ')', found ';'",
+            "expected ')', found ';'",
+            "expected ')', found ';'",
+        ]
+    );
+    assert_eq!(
+        parsed.to_string(),
+        "fn main() -> u32 {\n    <error>;\n    <error>;\n    <error>;\n    for i in 0..x {\n        <error>;\n        g(i);\n    }\n    let y: u32 = { <error>; x };\n    return y;\n};\n"
+    );
+    assert!(
+        module("main".to_string())
+            .parse("fn main() -> u32 { let x: u32 = ; return 0; };")
+            .is_err()
+    );
+}
+
+#[test]
+fn scripts_mix_items_and_statements() {
+    let ((elements, errors), _) = recovering_script()
+        .parse("ext fn puts(s: *char) -> u32; puts(\"hi\"); { puts(\"there\"); }")
+        .unwrap();
+
+    assert!(errors.is_empty());
+    assert!(matches!(elements[0], TopLevel::Item(_)));
+    assert!(matches!(
+        elements[1],
+        TopLevel::Statement(Spanned {
+            node: Statement::FunctionCall(..),
+            ..
+        })
+    ));
+    assert!(matches!(
+        elements[2],
+        TopLevel::Statement(Spanned {
+            node: Statement::Block(_),
+            ..
+        })
+    ));
+}
+
+#[test]
+fn shebang_lines_are_skipped() {
+    let source = "#!/usr/bin/env pal\nfn main() -> u32 { return 0; };";
+    let (parsed, remaining) = module("main".to_string()).parse(source).unwrap();
+
+    assert_eq!(parsed.1.len(), 1);
+    assert_eq!(remaining, "");
+    assert!(
+        module("main".to_string())
+            .parse("fn main() -> u32 { return 0; }; #!")
+            .is_err()
+    );
+}
+
+#[test]
+fn keywords_are_not_identifiers() {
+    assert_eq!(
+        statement().parse("returnx(1);"),
+        Ok((
+            Statement::FunctionCall("returnx".into(), vec![Expression::NumericLiteral(1).into()]),
+            "".to_string()
+        ))
+    );
+
+    assert_eq!(
+        statement().parse("return x;"),
+        Ok((
+            Statement::Return(Expression::Variable("x".into()).into()),
+            "".to_string()
+        ))
+    );
+
+    assert!(statement().parse("let return: u32 = 1;").is_err());
+}
+
+#[test]
+fn modules_reject_trailing_input() {
+    assert_eq!(
+        module("main".to_string())
+            .parse("fn main() -> u32 { return 0; }; lol")
+            .unwrap_err()
+            .to_string(),
+        "expected end of input, found 'l'"
+    );
+
+    assert!(module("main".to_string()).parse(" \n ").is_ok());
+
+    let ((_, errors), _) = recovering_module("main".to_string()).parse(" \n ").unwrap();
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn prefixed_numeric_literals_parse() {
+    for (source, value) in [
+        ("42", 42),
+        ("1_000", 1000),
+        ("0x1F", 31),
+        ("0o755", 493),
+        ("0b1010", 10),
+        ("0xffff_ffff", 0xffff_ffff),
+        ("18446744073709551615", u64::MAX),
+        ("0xFFFF_FFFF_FFFF_FFFF", u64::MAX),
+    ] {
+        assert_eq!(
+            num_literal().parse(source),
+            Ok((Expression::NumericLiteral(value), "".to_string()))
+        );
+    }
+}
+
+#[test]
+fn malformed_numeric_literals_fail() {
+    assert_eq!(
+        num_literal().parse("0b102"),
+        Err(error::ParseError::InvalidDigit {
+            digit: '2',
+            radix: 2,
+            span: Span::new(4, 5)
+        })
+    );
+
+    assert_eq!(
+        expression().parse("0o8").unwrap_err().to_string(),
+        "invalid digit '8' in base 8 literal"
+    );
+
+    assert_eq!(
+        expression().parse("0x;").unwrap_err().to_string(),
+        "expected base 16 digit, found ';'"
+    );
+
+    assert_eq!(
+        num_literal().parse("18446744073709551616"),
+        Err(error::ParseError::NumberOverflow {
+            span: Span::new(0, 20)
+        })
+    );
+
+    assert!(num_literal().parse("0x1_0000_0000_0000_0000").is_err());
+}
+
+#[test]
+fn char_literals_parse() {
+    for (source, value) in [
+        ("'a'", 'a'),
+        ("'\\n'", '\n'),
+        ("'\\''", '\''),
+        ("'\\0'", '\0'),
+    ] {
+        assert_eq!(
+            expression().parse(source),
+            Ok((Expression::CharLiteral(value), "".to_string()))
+        );
+    }
+
+    assert_eq!(
+        expression().parse("'\\q'").unwrap_err().to_string(),
+        "unknown character escape 'q'"
+    );
+    assert!(expression().parse("'ab'").is_err());
+    assert!(expression().parse("'é'").is_err());
+}
+
+#[test]
+fn string_literals_parse() {
+    let literal = Expression::StringLiteral("x = {}\n\"y\"".to_string());
+
+    assert_eq!(
+        expression().parse("\"x = {}\\n\\\"y\\\"\""),
+        Ok((literal.clone(), "".to_string()))
+    );
+    assert_eq!(literal.to_string(), "\"x = {}\\n\\\"y\\\"\"");
+    assert!(expression().parse("\"unterminated").is_err());
+}
+
+#[test]
+fn calls_parse_as_expressions() {
+    assert_eq!(
+        statement().parse("return add(1, twice(x));"),
+        Ok((
+            Statement::Return(
+                Expression::Call(
+                    "add".into(),
+                    vec![
+                        Expression::NumericLiteral(1).into(),
+                        Expression::Call(
+                            "twice".into(),
+                            vec![Expression::Variable("x".into()).into()]
+                        )
+                        .into(),
+                    ]
+                )
+                .into()
+            ),
+            "".to_string()
+        ))
+    );
+}
+
+#[test]
+fn nodes_record_their_spans() {
+    let source = "fn main(n: u32) -> u32 {\n    return twice(n);\n};";
+    let (parsed, _) = module("main".to_string()).parse(source).unwrap();
+    let slice = |span: Span| &source[span.start..span.end];
+
+    let Item::FunctionDeclaration(_, _, args, typ, body, ..) = &parsed.1[0].node else {
+        panic!("expected a function declaration");
+    };
+    let Statement::Return(value) = &body[0].node else {
+        panic!("expected a return statement");
+    };
+
+    assert_eq!(slice(parsed.1[0].span), &source[..source.len() - 1]);
+    assert_eq!(slice(args[0].1.span), "u32");
+    assert_eq!(slice(typ.span), "u32");
+    assert_eq!(slice(body[0].span), "return twice(n);");
+    assert_eq!(slice(value.span), "twice(n)");
+}
+
+#[test]
+fn imports_precede_items() {
+    let source = "import math;\nimport io;\n\npub fn main() -> u32 {\n    return 0;\n};\n";
+    let (module, _) = module("main".to_string()).parse(source).unwrap();
+
+    assert_eq!(
+        module.2,
+        vec!["math".to_string().into(), "io".to_string().into()]
+    );
+    assert_eq!(module.to_string(), source);
+    assert_eq!(module.1[0].visibility(), Visibility::Public);
+    assert!(
+        crate::spec::module("main".to_string())
+            .parse("fn main() -> u32 { return 0; }; import math;")
+            .is_err()
+    );
+}
+
+#[test]
+fn unary_operators_nest() {
+    let (parsed, rest) = expression().parse("-~*&x").unwrap();
+    let variable = Expression::Variable("x".into());
+    let nest = |op, operand| Expression::Unary(op, Box::new(Spanned::from(operand)));
+
+    assert_eq!(rest, "");
+    assert_eq!(
+        parsed,
+        nest(
+            UnaryOp::Neg,
+            nest(
+                UnaryOp::BitNot,
+                nest(UnaryOp::Deref, nest(UnaryOp::AddressOf, variable))
+            )
+        )
+    );
+    assert_eq!(parsed.to_string(), "-~*&x");
+}
+
+#[test]
+fn constant_arrays_parse() {
+    let source =
+        "pub const TABLE: [u32; 3] = [1, 2, 3];\n\nfn main() -> u32 {\n    return TABLE[2];\n};\n";
+    let (parsed, _) = module("main".to_string()).parse(source).unwrap();
+
+    assert_eq!(
+        parsed.1[0].node,
+        Item::Const(
+            "TABLE".into(),
+            Type::Array(Box::new(Type::Atomic("u32".into())), 3).into(),
+            vec![
+                Expression::NumericLiteral(1).into(),
+                Expression::NumericLiteral(2).into(),
+                Expression::NumericLiteral(3).into(),
+            ],
+            Visibility::Public,
+        )
+    );
+    assert_eq!(parsed.to_string(), source);
+    assert!(
+        module("main".to_string())
+            .parse("const TABLE: [u32; 1] = [x];")
+            .is_err()
+    );
+}
+
+#[test]
+fn bitwise_operators_follow_precedence() {
+    let (parsed, rest) = expression().parse("a | b ^ c & d << 1 | (e | f)").unwrap();
+    let variable = |name: &str| Expression::Variable(name.into());
+    let binary = |op, left, right| {
+        Expression::Binary(
+            op,
+            Box::new(Spanned::from(left)),
+            Box::new(Spanned::from(right)),
+        )
+    };
+
+    assert_eq!(rest, "");
+    assert_eq!(
+        parsed,
+        binary(
+            BinaryOp::BitOr,
+            binary(
+                BinaryOp::BitOr,
+                variable("a"),
+                binary(
+                    BinaryOp::BitXor,
+                    variable("b"),
+                    binary(
+                        BinaryOp::BitAnd,
+                        variable("c"),
+                        binary(BinaryOp::Shl, variable("d"), Expression::NumericLiteral(1))
+                    )
+                )
+            ),
+            binary(BinaryOp::BitOr, variable("e"), variable("f"))
+        )
+    );
+    assert_eq!(parsed.to_string(), "(a | (b ^ (c & (d << 1)))) | (e | f)");
+    assert_eq!(expression().parse(parsed.to_string()).unwrap().0, parsed);
+}
+
+#[test]
+fn parentheses_group_operands() {
+    let (parsed, rest) = expression()
+        .parse("*(p) & -(f((a | b), (c)) ^ 1) << (2)")
+        .unwrap();
+
+    assert_eq!(rest, "");
+    assert_eq!(parsed.to_string(), "*p & (-(f(a | b, c) ^ 1) << 2)");
+    assert_eq!(expression().parse(parsed.to_string()).unwrap().0, parsed);
+    assert_eq!(
+        expression().parse("(*p)").unwrap().0,
+        expression().parse("*p").unwrap().0
+    );
+
+    // Only names can be called, so a call does not follow a parenthesized expression.
+    assert_eq!(expression().parse("(f)(1)").unwrap().1, "(1)");
+}
+
+#[test]
+fn enums_and_comparisons_parse() {
+    let (item, _) = item()
+        .parse("pub enum Color { Red, Green, Blue, }")
+        .unwrap();
+
+    assert_eq!(
+        item,
+        Item::Enum(
+            "Color".into(),
+            ["Red", "Green", "Blue"]
+                .map(|variant| (Symbol::from(variant).into(), vec![]))
+                .to_vec(),
+            Visibility::Public
+        )
+    );
+    assert_eq!(item.to_string(), "pub enum Color { Red, Green, Blue };");
+
+    let (parsed, _) = expression().parse("c == Color::Red & x != 1 << 2").unwrap();
+
+    assert_eq!(parsed.to_string(), "(c == Color::Red) & (x != (1 << 2))");
+    assert_eq!(expression().parse(parsed.to_string()).unwrap().0, parsed);
+}
+
+#[test]
+fn match_statements_parse() {
+    let (parsed, _) = statement()
+        .parse("match c { Color::Red => { return 1; }, 'a' => {} 7 => { f(); } _ => {} }")
+        .unwrap();
+
+    let Statement::Match(_, arms) = &parsed else {
+        panic!("expected a match, found {parsed:?}");
+    };
+
+    assert_eq!(
+        arms.iter()
+            .map(|(pattern, _)| pattern.node.clone())
+            .collect::<Vec<_>>(),
+        vec![
+            Pattern::Variant("Color".into(), "Red".into(), vec![]),
+            Pattern::Char('a'),
+            Pattern::Number(7),
+            Pattern::Wildcard,
+        ]
+    );
+    assert_eq!(statement().parse(parsed.to_string()).unwrap().0, parsed);
+}
+
+#[test]
+fn enum_fields_parse() {
+    let (item, _) = item()
+        .parse("enum Shape { Circle(u32), Rect(u32, *char,), Empty }")
+        .unwrap();
+
+    assert_eq!(
+        item.to_string(),
+        "enum Shape { Circle(u32), Rect(u32, *char), Empty };"
+    );
+    assert!(item.has_fields());
+
+    let (parsed, _) = statement()
+        .parse("match Shape::Rect(1, s) { Shape::Rect(w, _) => { return w; } _ => {} }")
+        .unwrap();
+
+    let Statement::Match(value, arms) = &parsed else {
+        panic!("expected a match, found {parsed:?}");
+    };
+
+    assert_eq!(value.to_string(), "Shape::Rect(1, s)");
+    assert_eq!(
+        arms[0].0.node,
+        Pattern::Variant("Shape".into(), "Rect".into(), vec!["w".into(), "_".into()])
+    );
+    assert_eq!(statement().parse(parsed.to_string()).unwrap().0, parsed);
+}
+
+#[test]
+fn generic_functions_parse() {
+    let (parsed, _) = item()
+        .parse("fn pair<T, U,>(a: T, b: *U) -> T { return a; }")
+        .unwrap();
+
+    assert_eq!(parsed.generics(), &[Symbol::from("T"), Symbol::from("U")]);
+    assert_eq!(
+        parsed.to_string(),
+        "fn pair<T, U>(a: T, b: *U) -> T {\n    return a;\n};"
+    );
+    assert_eq!(item().parse(parsed.to_string()).unwrap().0, parsed);
+}
+
+#[test]
+fn function_pointer_types_parse() {
+    let (parsed, _) = typ().parse("fn(u32, fn() -> !,) -> *char").unwrap();
+
+    assert_eq!(
+        parsed,
+        Type::Function(
+            vec![
+                Type::Atomic("u32".into()),
+                Type::Function(vec![], Box::new(Type::Never)),
+            ],
+            Box::new(Type::Pointer(Box::new(Type::Atomic("char".into())))),
+        )
+    );
+    assert_eq!(parsed.to_string(), "fn(u32, fn() -> !) -> *char");
+}
+
+#[test]
+fn heap_allocations_parse() {
+    let (parsed, _) = module("main".to_string())
+        .parse(
+            "fn main() -> u32 { let p: *u32 = new u32(7); let a: *char = alloc::<char>(*p); free(a); return *p; };",
+        )
+        .unwrap();
+
+    assert_eq!(
+        parsed.to_string(),
+        "fn main() -> u32 {
+    let p: *u32 = new u32(7);
+    let a: *char = alloc::<char>(*p);
+    free(a);
+    return *p;
+};
+"
+    );
+}
+
+#[test]
+fn assertions_parse() {
+    let (parsed, _) = module("main".to_string())
+        .parse("fn main(x: u32) -> u32 { assert(x == 1, \"xisone\"); return x; };")
+        .unwrap();
+
+    assert_eq!(
+        parsed.to_string(),
+        "fn main(x: u32) -> u32 {
+    assert(x == 1, \"xisone\");
+    return x;
+};
+"
+    );
+    assert!(
+        module("main".to_string())
+            .parse("fn main() -> u32 { assert(1); return 0; };")
+            .is_err()
+    );
+}
+
+#[test]
+fn attributes_parse() {
+    let (parsed, _) = item()
+        .parse("#[no_mangle] pub fn add(a: u32) -> u32 { return a; }")
+        .unwrap();
+
+    assert_eq!(parsed.attributes(), &[Attribute::NoMangle]);
+    assert_eq!(parsed.visibility(), Visibility::Public);
+    assert_eq!(item().parse(parsed.to_string()).unwrap().0, parsed);
+
+    assert!(
+        item()
+            .parse("#[no_mangle] const A: [u32; 1] = [1]")
+            .is_err()
+    );
+    assert!(
+        item()
+            .parse("#[inline] fn f() -> u32 { return 0; }")
+            .is_err()
+    );
+}
+
+#[test]
+fn calling_conventions_parse() {
+    let (parsed, _) = item()
+        .parse("ext \"fastcall\" fn f(a: u32) -> u32")
+        .unwrap();
+
+    assert_eq!(parsed.abi(), Abi::Fastcall);
+    assert_eq!(parsed.to_string(), "ext \"fastcall\" fn f(a: u32) -> u32;");
+
+    assert_eq!(
+        item()
+            .parse("ext \"C\" fn f() -> u32")
+            .unwrap()
+            .0
+            .to_string(),
+        "ext fn f() -> u32;"
+    );
+    assert!(item().parse("ext \"pascal\" fn f() -> u32").is_err());
+}
+
+#[test]
+fn inline_assembly_parses() {
+    let (parsed, _) = statement()
+        .parse("asm!(\"mov $1, %eax\\n\\tnop # \\\"done\\\"\");")
+        .unwrap();
+
+    assert_eq!(
+        parsed,
+        Statement::Asm("mov $1, %eax\n\tnop # \"done\"".to_string())
+    );
+    assert_eq!(statement().parse(parsed.to_string()).unwrap().0, parsed);
+    assert!(statement().parse("asm!(nop);").is_err());
+}
+
+#[test]
+fn if_expressions_parse() {
+    let (parsed, _) = statement()
+        .parse("let x: u32 = if a { 1 } else if b == 2 { f(2) } else { 3 } | 4;")
+        .unwrap();
+
+    assert_eq!(
+        parsed.to_string(),
+        "let x: u32 = (if a { 1 } else if b == 2 { f(2) } else { 3 }) | 4;"
+    );
+    assert_eq!(statement().parse(parsed.to_string()).unwrap().0, parsed);
+    assert!(expression().parse("if a { 1 }").is_err());
+}
+
+#[test]
+fn blocks_end_with_their_value() {
+    let (parsed, _) = statement()
+        .parse("let x: u32 = { let y: u32 = 1; if y { f(y); y } else { 2 } };")
+        .unwrap();
+
+    assert_eq!(
+        parsed.to_string(),
+        "let x: u32 = { let y: u32 = 1; if y { f(y); y } else { 2 } };"
+    );
+    assert_eq!(statement().parse(parsed.to_string()).unwrap().0, parsed);
+    assert_eq!(
+        expression().parse("{ 1 }").unwrap().0,
+        Expression::NumericLiteral(1)
+    );
+
+    let (parsed, _) = function_declaration()
+        .parse("fn f() -> u32 { g(); 42 }")
+        .unwrap();
+
+    assert_eq!(
+        parsed.to_string(),
+        "fn f() -> u32 {\n    g();\n    return 42;\n};"
+    );
+}
+
+#[test]
+fn deep_nesting_is_an_error() {
+    let source = format!("{}1{}", "(".repeat(10_000), ")".repeat(10_000));
+
+    assert!(matches!(
+        statement().parse(format!("return {source};")),
+        Err(error::ParseError::TooDeep { .. })
+    ));
+    assert!(expression().parse("((((1))))").is_ok());
+    assert!(matches!(
+        typ().depth_limit(4).parse("*****u32"),
+        Err(error::ParseError::TooDeep { limit: 4, .. })
+    ));
+}
+
+#[test]
+fn malformed_input_does_not_panic() {
+    let nested = |open: &str, close: &str| format!("{}{}", open.repeat(5_000), close.repeat(5_000));
+
+    for source in [
+        nested("fn f() -> u32 { ", "}"),
+        nested("fn f() -> u32 { return -", "1; }"),
+        nested("const C: [", "u32; 1]"),
+        nested("enum E { A(*", "u8) }"),
+        format!("fn f() -> u32 {{ return {}1; }};", "~!-*&".repeat(5_000)),
+        format!("fn f() -> u32 {{ return 1{}; }};", " as u8".repeat(5_000)),
+        "fn \u{1F600}() -> \u{e9} { '\\q' \"\\".to_string(),
+    ] {
+        fuzz_parse(source.as_bytes());
+    }
+
+    fuzz_parse(&[0xff, 0xfe, b'f', b'n', 0x80]);
+}
+
+#[test]
+fn casts_parse() {
+    let (parsed, _) = statement()
+        .parse("let x: u8 = ~a as u8 & *p as u8 << 1 as u16;")
+        .unwrap();
+
+    assert_eq!(
+        parsed.to_string(),
+        "let x: u8 = (~a as u8) & ((*p as u8) << (1 as u16));"
+    );
+    assert_eq!(statement().parse(parsed.to_string()).unwrap().0, parsed);
+    assert!(expression().parse("(x as u64) as usize").is_ok());
 }
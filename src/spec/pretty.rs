@@ -0,0 +1,381 @@
+use std::fmt::{Display, Formatter, Result, Write};
+
+use crate::{parser::Spanned, spec::ast::*};
+
+/// The string a nested block is indented with.
+const INDENT: &str = "    ";
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Type::Atomic(name) => write!(f, "{name}"),
+            Type::Pointer(inner) => write!(f, "*{inner}"),
+            Type::Array(element, length) => write!(f, "[{element}; {length}]"),
+            Type::Never => write!(f, "!"),
+            Type::Function(arguments, returns) => {
+                let arguments: Vec<_> = arguments.iter().map(ToString::to_string).collect();
+                write!(f, "fn({}) -> {returns}", arguments.join(", "))
+            }
+        }
+    }
+}
+
+impl Display for UnaryOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            UnaryOp::Neg => write!(f, "-"),
+            UnaryOp::Not => write!(f, "!"),
+            UnaryOp::BitNot => write!(f, "~"),
+            UnaryOp::Deref => write!(f, "*"),
+            UnaryOp::AddressOf => write!(f, "&"),
+        }
+    }
+}
+
+impl Display for BinaryOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            BinaryOp::BitAnd => write!(f, "&"),
+            BinaryOp::BitOr => write!(f, "|"),
+            BinaryOp::BitXor => write!(f, "^"),
+            BinaryOp::Shl => write!(f, "<<"),
+            BinaryOp::Shr => write!(f, ">>"),
+            BinaryOp::Eq => write!(f, "=="),
+            BinaryOp::Ne => write!(f, "!="),
+        }
+    }
+}
+
+/// Writes the operand of an operator, parenthesizing it when it is itself a binary expression so
+/// that it parses back the same way regardless of precedence.
+fn write_operand(f: &mut Formatter<'_>, operand: &Expression) -> Result {
+    match operand {
+        Expression::Binary(..) | Expression::If(..) | Expression::Cast(..) => {
+            write!(f, "({operand})")
+        }
+        _ => write!(f, "{operand}"),
+    }
+}
+
+/// Writes text between double quotes, escaped like it is in a string literal. The escapes are
+/// those of C as well.
+fn write_quoted(f: &mut impl Write, text: &str) -> Result {
+    write!(f, "\"")?;
+
+    for c in text.chars() {
+        match c {
+            '\n' => write!(f, "\\n")?,
+            '\t' => write!(f, "\\t")?,
+            '\r' => write!(f, "\\r")?,
+            '\0' => write!(f, "\\0")?,
+            '\\' | '"' => write!(f, "\\{c}")?,
+            _ => write!(f, "{c}")?,
+        }
+    }
+
+    write!(f, "\"")
+}
+
+/// Writes a branch of an `if`, which is braced unless it is a block already.
+fn write_branch(f: &mut Formatter<'_>, branch: &Expression) -> Result {
+    match branch {
+        Expression::Block(..) => write!(f, "{branch}"),
+        _ => write!(f, "{{ {branch} }}"),
+    }
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Expression::StringLiteral(value) => write_quoted(f, value),
+            Expression::NumericLiteral(value) => write!(f, "{value}"),
+            Expression::CharLiteral(value) => match value {
+                '\n' => write!(f, "'\\n'"),
+                '\t' => write!(f, "'\\t'"),
+                '\r' => write!(f, "'\\r'"),
+                '\0' => write!(f, "'\\0'"),
+                '\\' | '\'' => write!(f, "'\\{value}'"),
+                _ => write!(f, "'{value}'"),
+            },
+            Expression::Variable(name) => write!(f, "{name}"),
+            Expression::Call(name, args) => {
+                write!(f, "{name}(")?;
+                write_expressions(f, args)?;
+                write!(f, ")")
+            }
+            Expression::Index(name, index) => write!(f, "{name}[{index}]"),
+            Expression::Unary(op, operand) => {
+                write!(f, "{op}")?;
+                write_operand(f, operand)
+            }
+            Expression::Binary(op, left, right) => {
+                write_operand(f, left)?;
+                write!(f, " {op} ")?;
+                write_operand(f, right)
+            }
+            Expression::Variant(name, variant, fields) => {
+                write!(f, "{name}::{variant}")?;
+
+                if !fields.is_empty() {
+                    write!(f, "(")?;
+                    write_expressions(f, fields)?;
+                    write!(f, ")")?;
+                }
+
+                Ok(())
+            }
+            Expression::New(typ, value) => write!(f, "new {typ}({value})"),
+            Expression::Alloc(typ, count) => write!(f, "alloc::<{typ}>({count})"),
+            Expression::If(condition, then, otherwise) => {
+                write!(f, "if {condition} ")?;
+                write_branch(f, then)?;
+                write!(f, " else ")?;
+
+                match otherwise.node {
+                    Expression::If(..) => write!(f, "{otherwise}"),
+                    _ => write_branch(f, otherwise),
+                }
+            }
+            Expression::Cast(value, typ) => {
+                write_operand(f, value)?;
+                write!(f, " as {typ}")
+            }
+            Expression::Block(body, value) => {
+                write!(f, "{{ ")?;
+
+                for statement in body {
+                    write!(f, "{} ", statement.node)?;
+                }
+
+                write!(f, "{value} }}")
+            }
+        }
+    }
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Pattern::Number(value) => write!(f, "{value}"),
+            Pattern::Char(value) => write!(f, "{}", Expression::CharLiteral(*value)),
+            Pattern::Variant(name, variant, bindings) => {
+                write!(f, "{name}::{variant}")?;
+
+                if !bindings.is_empty() {
+                    let bindings: Vec<_> =
+                        bindings.iter().map(|binding| binding.as_str()).collect();
+                    write!(f, "({})", bindings.join(", "))?;
+                }
+
+                Ok(())
+            }
+            Pattern::Wildcard => write!(f, "_"),
+        }
+    }
+}
+
+impl Display for Statement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write_statement(f, self, 0)
+    }
+}
+
+impl Display for Attribute {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Attribute::NoMangle => write!(f, "#[no_mangle]"),
+        }
+    }
+}
+
+impl Display for Item {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        for attribute in self.attributes() {
+            writeln!(f, "{attribute}")?;
+        }
+
+        if self.visibility() == Visibility::Public {
+            write!(f, "pub ")?;
+        }
+
+        match self {
+            Item::ExternFunctionDefinition(name, args, typ, abi, ..) => {
+                match abi {
+                    Abi::C => write!(f, "ext fn {name}(")?,
+                    abi => write!(f, "ext \"{}\" fn {name}(", abi.name())?,
+                }
+
+                write_arguments(f, args)?;
+                write!(f, ") -> {typ};")
+            }
+            Item::FunctionDeclaration(name, generics, args, typ, body, ..) => {
+                write!(f, "fn {name}")?;
+
+                if !generics.is_empty() {
+                    let generics: Vec<_> = generics.iter().map(ToString::to_string).collect();
+                    write!(f, "<{}>", generics.join(", "))?;
+                }
+
+                write!(f, "(")?;
+                write_arguments(f, args)?;
+                write!(f, ") -> {typ} ")?;
+                write_block(f, body, 0)?;
+                write!(f, ";")
+            }
+            Item::Const(name, typ, elements, _) => {
+                write!(f, "const {name}: {typ} = [")?;
+                write_expressions(f, elements)?;
+                write!(f, "];")
+            }
+            Item::Enum(name, variants, _) => {
+                write!(f, "enum {name} {{ ")?;
+
+                for (index, (variant, fields)) in variants.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+
+                    write!(f, "{variant}")?;
+
+                    if !fields.is_empty() {
+                        let fields: Vec<_> = fields.iter().map(ToString::to_string).collect();
+                        write!(f, "({})", fields.join(", "))?;
+                    }
+                }
+
+                write!(f, " }};")
+            }
+        }
+    }
+}
+
+impl Display for Module {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        for import in &self.2 {
+            writeln!(f, "import {import};")?;
+        }
+
+        if !self.2.is_empty() && !self.1.is_empty() {
+            writeln!(f)?;
+        }
+
+        for (index, item) in self.1.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+
+            writeln!(f, "{item}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes a comma separated argument list, without the surrounding parentheses.
+fn write_arguments(f: &mut impl Write, args: &[(Symbol, Spanned<Type>)]) -> Result {
+    for (index, (name, typ)) in args.iter().enumerate() {
+        if index > 0 {
+            write!(f, ", ")?;
+        }
+
+        write!(f, "{name}: {typ}")?;
+    }
+
+    Ok(())
+}
+
+/// Writes a comma separated list of expressions, without the surrounding parentheses.
+fn write_expressions(f: &mut impl Write, expressions: &[Spanned<Expression>]) -> Result {
+    for (index, expression) in expressions.iter().enumerate() {
+        if index > 0 {
+            write!(f, ", ")?;
+        }
+
+        write!(f, "{expression}")?;
+    }
+
+    Ok(())
+}
+
+/// Writes a braced block whose statements are indented one level deeper than `depth`. The
+/// opening brace is written at the current position and the closing brace at `depth`.
+fn write_block(f: &mut impl Write, body: &[Spanned<Statement>], depth: usize) -> Result {
+    writeln!(f, "{{")?;
+
+    for statement in body {
+        write!(f, "{}", INDENT.repeat(depth + 1))?;
+        write_statement(f, statement, depth + 1)?;
+        writeln!(f)?;
+    }
+
+    write!(f, "{}}}", INDENT.repeat(depth))
+}
+
+/// Writes a single statement that is nested `depth` levels deep. The caller is responsible for
+/// the indentation of the first line.
+fn write_statement(f: &mut impl Write, statement: &Statement, depth: usize) -> Result {
+    match statement {
+        Statement::FunctionCall(name, args) => {
+            write!(f, "{name}(")?;
+            write_expressions(f, args)?;
+            write!(f, ");")
+        }
+        Statement::Return(expression) => write!(f, "return {expression};"),
+        Statement::Let(name, typ, expression) => write!(f, "let {name}: {typ} = {expression};"),
+        Statement::Block(body) => write_block(f, body, depth),
+        Statement::For(name, start, end, body) => {
+            write!(f, "for {name} in {start}..{end} ")?;
+            write_block(f, body, depth)
+        }
+        Statement::Unreachable => write!(f, "unreachable();"),
+        Statement::Error(_) => write!(f, "<error>;"),
+        Statement::Free(pointer) => write!(f, "free({pointer});"),
+        Statement::Assert(condition, message) => {
+            write!(f, "assert({condition}, ")?;
+            write_quoted(f, message)?;
+            write!(f, ");")
+        }
+        Statement::Asm(template) => {
+            write!(f, "asm!(")?;
+            write_quoted(f, template)?;
+            write!(f, ");")
+        }
+        Statement::Match(value, arms) => {
+            writeln!(f, "match {value} {{")?;
+
+            for (pattern, body) in arms {
+                write!(f, "{}{pattern} => ", INDENT.repeat(depth + 1))?;
+                write_block(f, body, depth + 1)?;
+                writeln!(f)?;
+            }
+
+            write!(f, "{}}}", INDENT.repeat(depth))
+        }
+    }
+}
+
+#[test]
+fn pretty_printing_round_trips() {
+    let source = "ext fn printf(ptr: *char) -> u32;
+
+ext fn exit(code: u32) -> !;
+
+fn main(n: u32) -> u32 {
+    let message: *char = \"hi\";
+    {
+        printf(message);
+        putchar('\\n');
+    }
+    for i in 0..n {
+        printf(\"tick\");
+    }
+    return twice(n);
+    unreachable();
+};
+";
+    let (module, _) = crate::spec::module("main".to_string())
+        .parse(source)
+        .unwrap();
+
+    assert_eq!(module.to_string(), source);
+}
@@ -0,0 +1,350 @@
+use crate::{parser::Spanned, spec::ast::*};
+
+// A pass that only cares about some nodes implements the methods for those alone, and calls the
+// `walk_` function of the same name from them to keep going into the nodes they contain. Every
+// method walks by default, so a visitor implementing none visits the whole tree and does nothing.
+
+/// Visits the nodes of the AST by reference, from the outside in.
+pub trait Visit<'ast> {
+    fn visit_module(&mut self, module: &'ast Module) {
+        walk_module(self, module);
+    }
+
+    fn visit_item(&mut self, item: &'ast Spanned<Item>) {
+        walk_item(self, item);
+    }
+
+    /// Visits the statements of the body of a function, a `for` loop, an arm of a `match` or a
+    /// block, whether it is a statement or an expression.
+    fn visit_block(&mut self, body: &'ast [Spanned<Statement>]) {
+        walk_block(self, body);
+    }
+
+    fn visit_statement(&mut self, statement: &'ast Spanned<Statement>) {
+        walk_statement(self, statement);
+    }
+
+    fn visit_expression(&mut self, expression: &'ast Spanned<Expression>) {
+        walk_expression(self, expression);
+    }
+
+    fn visit_pattern(&mut self, _pattern: &'ast Spanned<Pattern>) {}
+
+    fn visit_type(&mut self, _typ: &'ast Spanned<Type>) {}
+}
+
+pub fn walk_module<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, module: &'ast Module) {
+    for item in &module.1 {
+        visitor.visit_item(item);
+    }
+}
+
+pub fn walk_item<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, item: &'ast Spanned<Item>) {
+    match &item.node {
+        Item::ExternFunctionDefinition(_, args, returns, ..) => {
+            for (_, typ) in args {
+                visitor.visit_type(typ);
+            }
+
+            visitor.visit_type(returns);
+        }
+        Item::FunctionDeclaration(_, _, args, returns, body, ..) => {
+            for (_, typ) in args {
+                visitor.visit_type(typ);
+            }
+
+            visitor.visit_type(returns);
+            visitor.visit_block(body);
+        }
+        Item::Const(_, typ, elements, _) => {
+            visitor.visit_type(typ);
+
+            for element in elements {
+                visitor.visit_expression(element);
+            }
+        }
+        Item::Enum(_, variants, _) => {
+            for typ in variants.iter().flat_map(|(_, fields)| fields) {
+                visitor.visit_type(typ);
+            }
+        }
+    }
+}
+
+pub fn walk_block<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    body: &'ast [Spanned<Statement>],
+) {
+    for statement in body {
+        visitor.visit_statement(statement);
+    }
+}
+
+pub fn walk_statement<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    statement: &'ast Spanned<Statement>,
+) {
+    match &statement.node {
+        Statement::FunctionCall(_, args) => {
+            for arg in args {
+                visitor.visit_expression(arg);
+            }
+        }
+        Statement::Return(value) | Statement::Free(value) | Statement::Assert(value, _) => {
+            visitor.visit_expression(value)
+        }
+        Statement::Let(_, typ, value) => {
+            visitor.visit_type(typ);
+            visitor.visit_expression(value);
+        }
+        Statement::Block(body) => visitor.visit_block(body),
+        Statement::For(_, start, end, body) => {
+            visitor.visit_expression(start);
+            visitor.visit_expression(end);
+            visitor.visit_block(body);
+        }
+        Statement::Match(value, arms) => {
+            visitor.visit_expression(value);
+
+            for (pattern, body) in arms {
+                visitor.visit_pattern(pattern);
+                visitor.visit_block(body);
+            }
+        }
+        Statement::Unreachable | Statement::Asm(_) | Statement::Error(_) => {}
+    }
+}
+
+pub fn walk_expression<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    expression: &'ast Spanned<Expression>,
+) {
+    match &expression.node {
+        Expression::Call(_, args) | Expression::Variant(_, _, args) => {
+            for arg in args {
+                visitor.visit_expression(arg);
+            }
+        }
+        Expression::Index(_, operand) | Expression::Unary(_, operand) => {
+            visitor.visit_expression(operand)
+        }
+        Expression::Binary(_, left, right) => {
+            visitor.visit_expression(left);
+            visitor.visit_expression(right);
+        }
+        Expression::If(condition, then, otherwise) => {
+            visitor.visit_expression(condition);
+            visitor.visit_expression(then);
+            visitor.visit_expression(otherwise);
+        }
+        Expression::Block(body, value) => {
+            visitor.visit_block(body);
+            visitor.visit_expression(value);
+        }
+        Expression::New(typ, operand) | Expression::Alloc(typ, operand) => {
+            visitor.visit_type(typ);
+            visitor.visit_expression(operand);
+        }
+        Expression::Cast(operand, typ) => {
+            visitor.visit_expression(operand);
+            visitor.visit_type(typ);
+        }
+        Expression::StringLiteral(_)
+        | Expression::NumericLiteral(_)
+        | Expression::CharLiteral(_)
+        | Expression::Variable(_) => {}
+    }
+}
+
+/// Visits the nodes of the AST by mutable reference, from the outside in, so that a pass can
+/// rewrite them in place. Blocks are visited as the vectors they are, so that statements can be
+/// added to or removed from them.
+pub trait VisitMut {
+    fn visit_module_mut(&mut self, module: &mut Module) {
+        walk_module_mut(self, module);
+    }
+
+    fn visit_item_mut(&mut self, item: &mut Spanned<Item>) {
+        walk_item_mut(self, item);
+    }
+
+    fn visit_block_mut(&mut self, body: &mut Vec<Spanned<Statement>>) {
+        walk_block_mut(self, body);
+    }
+
+    fn visit_statement_mut(&mut self, statement: &mut Spanned<Statement>) {
+        walk_statement_mut(self, statement);
+    }
+
+    fn visit_expression_mut(&mut self, expression: &mut Spanned<Expression>) {
+        walk_expression_mut(self, expression);
+    }
+
+    fn visit_pattern_mut(&mut self, _pattern: &mut Spanned<Pattern>) {}
+
+    fn visit_type_mut(&mut self, _typ: &mut Spanned<Type>) {}
+}
+
+pub fn walk_module_mut<V: VisitMut + ?Sized>(visitor: &mut V, module: &mut Module) {
+    for item in &mut module.1 {
+        visitor.visit_item_mut(item);
+    }
+}
+
+pub fn walk_item_mut<V: VisitMut + ?Sized>(visitor: &mut V, item: &mut Spanned<Item>) {
+    match &mut item.node {
+        Item::ExternFunctionDefinition(_, args, returns, ..) => {
+            for (_, typ) in args {
+                visitor.visit_type_mut(typ);
+            }
+
+            visitor.visit_type_mut(returns);
+        }
+        Item::FunctionDeclaration(_, _, args, returns, body, ..) => {
+            for (_, typ) in args {
+                visitor.visit_type_mut(typ);
+            }
+
+            visitor.visit_type_mut(returns);
+            visitor.visit_block_mut(body);
+        }
+        Item::Const(_, typ, elements, _) => {
+            visitor.visit_type_mut(typ);
+
+            for element in elements {
+                visitor.visit_expression_mut(element);
+            }
+        }
+        Item::Enum(_, variants, _) => {
+            for typ in variants.iter_mut().flat_map(|(_, fields)| fields) {
+                visitor.visit_type_mut(typ);
+            }
+        }
+    }
+}
+
+pub fn walk_block_mut<V: VisitMut + ?Sized>(visitor: &mut V, body: &mut Vec<Spanned<Statement>>) {
+    for statement in body {
+        visitor.visit_statement_mut(statement);
+    }
+}
+
+pub fn walk_statement_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    statement: &mut Spanned<Statement>,
+) {
+    match &mut statement.node {
+        Statement::FunctionCall(_, args) => {
+            for arg in args {
+                visitor.visit_expression_mut(arg);
+            }
+        }
+        Statement::Return(value) | Statement::Free(value) | Statement::Assert(value, _) => {
+            visitor.visit_expression_mut(value)
+        }
+        Statement::Let(_, typ, value) => {
+            visitor.visit_type_mut(typ);
+            visitor.visit_expression_mut(value);
+        }
+        Statement::Block(body) => visitor.visit_block_mut(body),
+        Statement::For(_, start, end, body) => {
+            visitor.visit_expression_mut(start);
+            visitor.visit_expression_mut(end);
+            visitor.visit_block_mut(body);
+        }
+        Statement::Match(value, arms) => {
+            visitor.visit_expression_mut(value);
+
+            for (pattern, body) in arms {
+                visitor.visit_pattern_mut(pattern);
+                visitor.visit_block_mut(body);
+            }
+        }
+        Statement::Unreachable | Statement::Asm(_) | Statement::Error(_) => {}
+    }
+}
+
+pub fn walk_expression_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    expression: &mut Spanned<Expression>,
+) {
+    match &mut expression.node {
+        Expression::Call(_, args) | Expression::Variant(_, _, args) => {
+            for arg in args {
+                visitor.visit_expression_mut(arg);
+            }
+        }
+        Expression::Index(_, operand) | Expression::Unary(_, operand) => {
+            visitor.visit_expression_mut(operand)
+        }
+        Expression::Binary(_, left, right) => {
+            visitor.visit_expression_mut(left);
+            visitor.visit_expression_mut(right);
+        }
+        Expression::If(condition, then, otherwise) => {
+            visitor.visit_expression_mut(condition);
+            visitor.visit_expression_mut(then);
+            visitor.visit_expression_mut(otherwise);
+        }
+        Expression::Block(body, value) => {
+            visitor.visit_block_mut(body);
+            visitor.visit_expression_mut(value);
+        }
+        Expression::New(typ, operand) | Expression::Alloc(typ, operand) => {
+            visitor.visit_type_mut(typ);
+            visitor.visit_expression_mut(operand);
+        }
+        Expression::Cast(operand, typ) => {
+            visitor.visit_expression_mut(operand);
+            visitor.visit_type_mut(typ);
+        }
+        Expression::StringLiteral(_)
+        | Expression::NumericLiteral(_)
+        | Expression::CharLiteral(_)
+        | Expression::Variable(_) => {}
+    }
+}
+
+#[test]
+fn visitors_reach_every_nested_node() {
+    /// Counts the variables an AST refers to, and renames them all to `v`.
+    #[derive(Default)]
+    struct Variables(usize);
+
+    impl Visit<'_> for Variables {
+        fn visit_expression(&mut self, expression: &Spanned<Expression>) {
+            self.0 += matches!(expression.node, Expression::Variable(_)) as usize;
+            walk_expression(self, expression);
+        }
+    }
+
+    impl VisitMut for Variables {
+        fn visit_expression_mut(&mut self, expression: &mut Spanned<Expression>) {
+            if let Expression::Variable(name) = &mut expression.node {
+                *name = Symbol::from("v");
+            }
+
+            walk_expression_mut(self, expression);
+        }
+    }
+
+    let (mut module, _) = crate::spec::module("main".to_string())
+        .parse(
+            "fn main(a: u32) -> u32 {
+                for i in 0..a { f(i); }
+                match a { _ => { let b: u32 = { let c: u32 = a; if c { c } else { new u32(a) as u32 } }; } }
+                return ~a;
+            };",
+        )
+        .unwrap();
+
+    let mut variables = Variables::default();
+    variables.visit_module(&module);
+    assert_eq!(variables.0, 8);
+
+    variables.visit_module_mut(&mut module);
+    let renamed = module.to_string();
+    assert!(renamed.contains("for i in 0..v"));
+    assert!(renamed.contains("if v { v } else { new u32(v) as u32 }"));
+    assert!(renamed.contains("return ~v;"));
+}
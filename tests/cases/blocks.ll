@@ -0,0 +1,135 @@
+@0 = private unnamed_addr constant [3 x i8] c"%u\00", align 1
+@1 = private unnamed_addr constant [2 x i8] c" \00", align 1
+@2 = private unnamed_addr constant [3 x i8] c"%s\00", align 1
+@3 = private unnamed_addr constant [2 x i8] c" \00", align 1
+@4 = private unnamed_addr constant [2 x i8] c"\0A\00", align 1
+
+define i32 @_P4main5twice3u32(i32 %0) {
+_P4main5twice3u32:
+  %x = alloca i32, align 4
+  %1 = alloca i32, align 4
+  store i32 %0, ptr %x, align 4
+  br label %bb0
+
+bb0:                                              ; preds = %_P4main5twice3u32
+  %2 = load i32, ptr %x, align 4
+  %3 = shl i32 %2, 1
+  store i32 %3, ptr %1, align 4
+  %4 = load i32, ptr %1, align 4
+  ret i32 %4
+}
+
+define i32 @_P4main5applyF3u323u32E3u32(ptr %0, i32 %1) {
+_P4main5applyF3u323u32E3u32:
+  %f = alloca ptr, align 8
+  %x = alloca i32, align 4
+  %2 = alloca i32, align 4
+  store ptr %0, ptr %f, align 8
+  store i32 %1, ptr %x, align 4
+  br label %bb0
+
+bb0:                                              ; preds = %_P4main5applyF3u323u32E3u32
+  %3 = load i32, ptr %x, align 4
+  %4 = load ptr, ptr %f, align 8
+  %5 = call i32 %4(i32 %3)
+  store i32 %5, ptr %2, align 4
+  %6 = load i32, ptr %2, align 4
+  ret i32 %6
+}
+
+define i32 @main() {
+main:
+  %p = alloca ptr, align 8
+  %0 = alloca i32, align 4
+  %1 = alloca i32, align 4
+  %doubled = alloca i32, align 4
+  %2 = alloca i32, align 4
+  %3 = alloca i32, align 4
+  %4 = alloca i32, align 4
+  %value = alloca i32, align 4
+  %5 = alloca i32, align 4
+  %6 = alloca i32, align 4
+  %7 = alloca i32, align 4
+  %8 = alloca i32, align 4
+  br label %bb0
+
+bb0:                                              ; preds = %main
+  %9 = call ptr @malloc(i64 ptrtoint (ptr getelementptr (i32, ptr null, i32 1) to i64))
+  store i32 21, ptr %9, align 4
+  store ptr %9, ptr %p, align 8
+  %10 = load ptr, ptr %p, align 8
+  %11 = load i32, ptr %10, align 4
+  store i32 %11, ptr %0, align 4
+  %12 = load i32, ptr %0, align 4
+  %13 = call i32 @_P4main5applyF3u323u32E3u32(ptr @_P4main5twice3u32, i32 %12)
+  store i32 %13, ptr %1, align 4
+  %14 = load i32, ptr %1, align 4
+  store i32 %14, ptr %doubled, align 4
+  %15 = load i32, ptr %doubled, align 4
+  %16 = icmp eq i32 %15, 42
+  %17 = zext i1 %16 to i32
+  store i32 %17, ptr %2, align 4
+  %18 = load i32, ptr %2, align 4
+  switch i32 %18, label %bb1 [
+    i32 0, label %bb2
+  ]
+
+bb1:                                              ; preds = %bb0
+  %19 = load i32, ptr %doubled, align 4
+  %20 = xor i32 %19, 1
+  store i32 %20, ptr %3, align 4
+  %21 = load i32, ptr %3, align 4
+  store i32 %21, ptr %4, align 4
+  br label %bb3
+
+bb2:                                              ; preds = %bb0
+  store i32 0, ptr %4, align 4
+  br label %bb3
+
+bb3:                                              ; preds = %bb2, %bb1
+  %22 = load i32, ptr %4, align 4
+  store i32 %22, ptr %value, align 4
+  %23 = load ptr, ptr %p, align 8
+  call void @free(ptr %23)
+  %24 = load i32, ptr %value, align 4
+  %25 = call i32 @pal_print_int(i32 %24)
+  %26 = call i32 @pal_print_cstr(ptr @1)
+  store i32 -1, ptr %5, align 4
+  %27 = load i32, ptr %5, align 4
+  %28 = icmp eq i32 %27, -1
+  %29 = zext i1 %28 to i32
+  store i32 %29, ptr %6, align 4
+  %30 = load i32, ptr %6, align 4
+  %31 = call i32 @pal_print_int(i32 %30)
+  %32 = call i32 @pal_print_cstr(ptr @3)
+  %33 = load i32, ptr %value, align 4
+  %34 = icmp eq i32 %33, 0
+  %35 = zext i1 %34 to i32
+  store i32 %35, ptr %7, align 4
+  %36 = load i32, ptr %7, align 4
+  %37 = call i32 @pal_print_int(i32 %36)
+  %38 = call i32 @pal_print_cstr(ptr @4)
+  %39 = load i32, ptr %value, align 4
+  %40 = and i32 %39, 7
+  store i32 %40, ptr %8, align 4
+  %41 = load i32, ptr %8, align 4
+  ret i32 %41
+}
+
+declare ptr @malloc(i64)
+
+declare void @free(ptr)
+
+define private i32 @pal_print_int(i32 %0) {
+entry:
+  %1 = call i32 (i32, ptr, ...) @dprintf(i32 1, ptr @0, i32 %0)
+  ret i32 %1
+}
+
+declare i32 @dprintf(i32, ptr, ...)
+
+define private i32 @pal_print_cstr(ptr %0) {
+entry:
+  %1 = call i32 (i32, ptr, ...) @dprintf(i32 1, ptr @2, ptr %0)
+  ret i32 %1
+}
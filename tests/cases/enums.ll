@@ -0,0 +1,170 @@
+%Shape = type { i32, [2 x i64] }
+
+@0 = private unnamed_addr constant [3 x i8] c"%u\00", align 1
+@1 = private unnamed_addr constant [2 x i8] c"\0A\00", align 1
+@2 = private unnamed_addr constant [3 x i8] c"%s\00", align 1
+@3 = private unnamed_addr constant [2 x i8] c"\0A\00", align 1
+@4 = private unnamed_addr constant [2 x i8] c"\0A\00", align 1
+
+define private i32 @"Shape::match"(%Shape %0) {
+entry:
+  %1 = extractvalue %Shape %0, 0
+  ret i32 %1
+}
+
+define private %Shape @"Shape::Circle"(i32 %0) {
+entry:
+  %1 = zext i32 %0 to i64
+  %2 = insertvalue [2 x i64] undef, i64 %1, 0
+  %3 = insertvalue %Shape { i32 0, [2 x i64] undef }, [2 x i64] %2, 1
+  ret %Shape %3
+}
+
+define private i32 @"Shape::Circle::0"(%Shape %0) {
+entry:
+  %1 = extractvalue %Shape %0, 1
+  %2 = extractvalue [2 x i64] %1, 0
+  %3 = trunc i64 %2 to i32
+  ret i32 %3
+}
+
+define private %Shape @"Shape::Rect"(i32 %0, i32 %1) {
+entry:
+  %2 = zext i32 %0 to i64
+  %3 = insertvalue [2 x i64] undef, i64 %2, 0
+  %4 = zext i32 %1 to i64
+  %5 = insertvalue [2 x i64] %3, i64 %4, 1
+  %6 = insertvalue %Shape { i32 1, [2 x i64] undef }, [2 x i64] %5, 1
+  ret %Shape %6
+}
+
+define private i32 @"Shape::Rect::0"(%Shape %0) {
+entry:
+  %1 = extractvalue %Shape %0, 1
+  %2 = extractvalue [2 x i64] %1, 0
+  %3 = trunc i64 %2 to i32
+  ret i32 %3
+}
+
+define private i32 @"Shape::Rect::1"(%Shape %0) {
+entry:
+  %1 = extractvalue %Shape %0, 1
+  %2 = extractvalue [2 x i64] %1, 1
+  %3 = trunc i64 %2 to i32
+  ret i32 %3
+}
+
+define private %Shape @"Shape::Empty"() {
+entry:
+  ret %Shape { i32 2, [2 x i64] undef }
+}
+
+define i32 @_P4main5width5Shape(%Shape %0) {
+_P4main5width5Shape:
+  %shape = alloca %Shape, align 8
+  %match = alloca %Shape, align 8
+  %1 = alloca i32, align 4
+  %2 = alloca i32, align 4
+  %r = alloca i32, align 4
+  %3 = alloca i32, align 4
+  %4 = alloca i32, align 4
+  %w = alloca i32, align 4
+  store %Shape %0, ptr %shape, align 8
+  br label %bb0
+
+bb0:                                              ; preds = %_P4main5width5Shape
+  %5 = load %Shape, ptr %shape, align 8
+  store %Shape %5, ptr %match, align 8
+  %6 = load %Shape, ptr %match, align 8
+  %7 = call i32 @"Shape::match"(%Shape %6)
+  store i32 %7, ptr %1, align 4
+  %8 = load i32, ptr %1, align 4
+  switch i32 %8, label %bb3 [
+    i32 0, label %bb1
+    i32 1, label %bb2
+  ]
+
+bb1:                                              ; preds = %bb0
+  %9 = load %Shape, ptr %match, align 8
+  %10 = call i32 @"Shape::Circle::0"(%Shape %9)
+  store i32 %10, ptr %2, align 4
+  %11 = load i32, ptr %2, align 4
+  store i32 %11, ptr %r, align 4
+  %12 = load i32, ptr %r, align 4
+  %13 = shl i32 %12, 1
+  store i32 %13, ptr %3, align 4
+  %14 = load i32, ptr %3, align 4
+  ret i32 %14
+
+bb2:                                              ; preds = %bb0
+  %15 = load %Shape, ptr %match, align 8
+  %16 = call i32 @"Shape::Rect::0"(%Shape %15)
+  store i32 %16, ptr %4, align 4
+  %17 = load i32, ptr %4, align 4
+  store i32 %17, ptr %w, align 4
+  %18 = load i32, ptr %w, align 4
+  ret i32 %18
+
+bb3:                                              ; preds = %bb0
+  ret i32 0
+}
+
+define i32 @main() {
+main:
+  %0 = alloca %Shape, align 8
+  %1 = alloca i32, align 4
+  %2 = alloca %Shape, align 8
+  %3 = alloca i32, align 4
+  %4 = alloca %Shape, align 8
+  %5 = alloca i32, align 4
+  %6 = alloca %Shape, align 8
+  %7 = alloca i32, align 4
+  br label %bb0
+
+bb0:                                              ; preds = %main
+  %8 = call %Shape @"Shape::Circle"(i32 2)
+  store %Shape %8, ptr %0, align 8
+  %9 = load %Shape, ptr %0, align 8
+  %10 = call i32 @_P4main5width5Shape(%Shape %9)
+  store i32 %10, ptr %1, align 4
+  %11 = load i32, ptr %1, align 4
+  %12 = call i32 @pal_print_int(i32 %11)
+  %13 = call i32 @pal_print_cstr(ptr @1)
+  %14 = call %Shape @"Shape::Rect"(i32 3, i32 4)
+  store %Shape %14, ptr %2, align 8
+  %15 = load %Shape, ptr %2, align 8
+  %16 = call i32 @_P4main5width5Shape(%Shape %15)
+  store i32 %16, ptr %3, align 4
+  %17 = load i32, ptr %3, align 4
+  %18 = call i32 @pal_print_int(i32 %17)
+  %19 = call i32 @pal_print_cstr(ptr @3)
+  %20 = call %Shape @"Shape::Empty"()
+  store %Shape %20, ptr %4, align 8
+  %21 = load %Shape, ptr %4, align 8
+  %22 = call i32 @_P4main5width5Shape(%Shape %21)
+  store i32 %22, ptr %5, align 4
+  %23 = load i32, ptr %5, align 4
+  %24 = call i32 @pal_print_int(i32 %23)
+  %25 = call i32 @pal_print_cstr(ptr @4)
+  %26 = call %Shape @"Shape::Rect"(i32 5, i32 1)
+  store %Shape %26, ptr %6, align 8
+  %27 = load %Shape, ptr %6, align 8
+  %28 = call i32 @_P4main5width5Shape(%Shape %27)
+  store i32 %28, ptr %7, align 4
+  %29 = load i32, ptr %7, align 4
+  ret i32 %29
+}
+
+define private i32 @pal_print_int(i32 %0) {
+entry:
+  %1 = call i32 (i32, ptr, ...) @dprintf(i32 1, ptr @0, i32 %0)
+  ret i32 %1
+}
+
+declare i32 @dprintf(i32, ptr, ...)
+
+define private i32 @pal_print_cstr(ptr %0) {
+entry:
+  %1 = call i32 (i32, ptr, ...) @dprintf(i32 1, ptr @2, ptr %0)
+  ret i32 %1
+}
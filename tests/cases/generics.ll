@@ -0,0 +1,111 @@
+@PRIMES = constant [4 x i32] [i32 2, i32 3, i32 5, i32 7]
+@0 = private unnamed_addr constant [3 x i8] c"%u\00", align 1
+@1 = private unnamed_addr constant [2 x i8] c"\0A\00", align 1
+@2 = private unnamed_addr constant [3 x i8] c"%s\00", align 1
+@3 = private unnamed_addr constant [2 x i8] c" \00", align 1
+@4 = private unnamed_addr constant [5 x i8] c"%llu\00", align 1
+@5 = private unnamed_addr constant [2 x i8] c"\0A\00", align 1
+
+define i32 @main() {
+main:
+  %i = alloca i32, align 4
+  %0 = alloca i32, align 4
+  %1 = alloca i32, align 4
+  %2 = alloca i32, align 4
+  %small = alloca i8, align 1
+  %3 = alloca i8, align 1
+  %wide = alloca i64, align 8
+  %4 = alloca i32, align 4
+  br label %bb0
+
+bb0:                                              ; preds = %main
+  store i32 0, ptr %i, align 4
+  br label %bb1
+
+bb1:                                              ; preds = %bb2, %bb0
+  %5 = load i32, ptr %i, align 4
+  %6 = icmp ult i32 %5, 4
+  %7 = zext i1 %6 to i32
+  store i32 %7, ptr %0, align 4
+  %8 = load i32, ptr %0, align 4
+  switch i32 %8, label %bb2 [
+    i32 0, label %bb3
+  ]
+
+bb2:                                              ; preds = %bb1
+  %9 = load i32, ptr %i, align 4
+  %10 = getelementptr inbounds [4 x i32], ptr @PRIMES, i32 0, i32 %9
+  %PRIMES = load i32, ptr %10, align 4
+  store i32 %PRIMES, ptr %1, align 4
+  %11 = load i32, ptr %1, align 4
+  %12 = call i32 @"_P4main2id1T$u32"(i32 %11)
+  store i32 %12, ptr %2, align 4
+  %13 = load i32, ptr %2, align 4
+  %14 = call i32 @pal_print_int(i32 %13)
+  %15 = call i32 @pal_print_cstr(ptr @1)
+  %16 = load i32, ptr %i, align 4
+  %17 = add i32 %16, 1
+  store i32 %17, ptr %i, align 4
+  br label %bb1
+
+bb3:                                              ; preds = %bb1
+  store i8 44, ptr %small, align 1
+  %18 = load i8, ptr %small, align 1
+  %19 = call i8 @"_P4main2id1T$u8"(i8 %18)
+  store i8 %19, ptr %3, align 1
+  %20 = load i8, ptr %3, align 1
+  %21 = zext i8 %20 to i64
+  store i64 %21, ptr %wide, align 8
+  %22 = load i8, ptr %small, align 1
+  %23 = zext i8 %22 to i32
+  store i32 %23, ptr %4, align 4
+  %24 = load i32, ptr %4, align 4
+  %25 = call i32 @pal_print_int(i32 %24)
+  %26 = call i32 @pal_print_cstr(ptr @3)
+  %27 = load i64, ptr %wide, align 8
+  %28 = call i32 @pal_print_u64(i64 %27)
+  %29 = call i32 @pal_print_cstr(ptr @5)
+  ret i32 0
+}
+
+define private i32 @"_P4main2id1T$u32"(i32 %0) {
+"_P4main2id1T$u32":
+  %x = alloca i32, align 4
+  store i32 %0, ptr %x, align 4
+  br label %bb0
+
+bb0:                                              ; preds = %"_P4main2id1T$u32"
+  %1 = load i32, ptr %x, align 4
+  ret i32 %1
+}
+
+define private i8 @"_P4main2id1T$u8"(i8 %0) {
+"_P4main2id1T$u8":
+  %x = alloca i8, align 1
+  store i8 %0, ptr %x, align 1
+  br label %bb0
+
+bb0:                                              ; preds = %"_P4main2id1T$u8"
+  %1 = load i8, ptr %x, align 1
+  ret i8 %1
+}
+
+define private i32 @pal_print_int(i32 %0) {
+entry:
+  %1 = call i32 (i32, ptr, ...) @dprintf(i32 1, ptr @0, i32 %0)
+  ret i32 %1
+}
+
+declare i32 @dprintf(i32, ptr, ...)
+
+define private i32 @pal_print_cstr(ptr %0) {
+entry:
+  %1 = call i32 (i32, ptr, ...) @dprintf(i32 1, ptr @2, ptr %0)
+  ret i32 %1
+}
+
+define private i32 @pal_print_u64(i64 %0) {
+entry:
+  %1 = call i32 (i32, ptr, ...) @dprintf(i32 1, ptr @4, i64 %0)
+  ret i32 %1
+}
@@ -0,0 +1,84 @@
+@0 = private unnamed_addr constant [4 x i8] c"pal\00", align 1
+@1 = private unnamed_addr constant [8 x i8] c"hello, \00", align 1
+@2 = private unnamed_addr constant [3 x i8] c"%s\00", align 1
+@3 = private unnamed_addr constant [5 x i8] c"%.*s\00", align 1
+@4 = private unnamed_addr constant [3 x i8] c"!\0A\00", align 1
+@5 = private unnamed_addr constant [3 x i8] c"%u\00", align 1
+@6 = private unnamed_addr constant [2 x i8] c" \00", align 1
+@7 = private unnamed_addr constant [5 x i8] c"%llu\00", align 1
+@8 = private unnamed_addr constant [2 x i8] c" \00", align 1
+@9 = private unnamed_addr constant [3 x i8] c"%c\00", align 1
+@10 = private unnamed_addr constant [2 x i8] c"\0A\00", align 1
+@11 = private unnamed_addr constant [13 x i8] c"{no newline}\00", align 1
+@12 = private unnamed_addr constant [2 x i8] c"\0A\00", align 1
+
+define i32 @main() {
+main:
+  %0 = alloca { ptr, i32 }, align 8
+  %name = alloca { ptr, i32 }, align 8
+  %big = alloca i64, align 8
+  br label %bb0
+
+bb0:                                              ; preds = %main
+  %1 = call { ptr, i32 } @"str::new"(ptr @0, i32 3)
+  store { ptr, i32 } %1, ptr %0, align 8
+  %2 = load { ptr, i32 }, ptr %0, align 8
+  store { ptr, i32 } %2, ptr %name, align 8
+  store i64 1099511627776, ptr %big, align 8
+  %3 = call i32 @pal_print_cstr(ptr @1)
+  %4 = load { ptr, i32 }, ptr %name, align 8
+  %5 = call i32 @pal_print_str({ ptr, i32 } %4)
+  %6 = call i32 @pal_print_cstr(ptr @4)
+  %7 = call i32 @pal_print_int(i32 7)
+  %8 = call i32 @pal_print_cstr(ptr @6)
+  %9 = load i64, ptr %big, align 8
+  %10 = call i32 @pal_print_u64(i64 %9)
+  %11 = call i32 @pal_print_cstr(ptr @8)
+  %12 = call i32 @pal_print_char(i8 120)
+  %13 = call i32 @pal_print_cstr(ptr @10)
+  %14 = call i32 @pal_print_cstr(ptr @11)
+  %15 = call i32 @pal_print_cstr(ptr @12)
+  ret i32 0
+}
+
+define private { ptr, i32 } @"str::new"(ptr %0, i32 %1) {
+entry:
+  %2 = insertvalue { ptr, i32 } undef, ptr %0, 0
+  %3 = insertvalue { ptr, i32 } %2, i32 %1, 1
+  ret { ptr, i32 } %3
+}
+
+define private i32 @pal_print_cstr(ptr %0) {
+entry:
+  %1 = call i32 (i32, ptr, ...) @dprintf(i32 1, ptr @2, ptr %0)
+  ret i32 %1
+}
+
+declare i32 @dprintf(i32, ptr, ...)
+
+define private i32 @pal_print_str({ ptr, i32 } %0) {
+entry:
+  %1 = extractvalue { ptr, i32 } %0, 1
+  %2 = extractvalue { ptr, i32 } %0, 0
+  %3 = call i32 (i32, ptr, ...) @dprintf(i32 1, ptr @3, i32 %1, ptr %2)
+  ret i32 %3
+}
+
+define private i32 @pal_print_int(i32 %0) {
+entry:
+  %1 = call i32 (i32, ptr, ...) @dprintf(i32 1, ptr @5, i32 %0)
+  ret i32 %1
+}
+
+define private i32 @pal_print_u64(i64 %0) {
+entry:
+  %1 = call i32 (i32, ptr, ...) @dprintf(i32 1, ptr @7, i64 %0)
+  ret i32 %1
+}
+
+define private i32 @pal_print_char(i8 %0) {
+entry:
+  %1 = zext i8 %0 to i32
+  %2 = call i32 (i32, ptr, ...) @dprintf(i32 1, ptr @9, i32 %1)
+  ret i32 %2
+}
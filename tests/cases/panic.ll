@@ -0,0 +1,76 @@
+@0 = private unnamed_addr constant [27 x i8] c"assertion failed: x is one\00", align 1
+@1 = private unnamed_addr constant [3 x i8] c"%u\00", align 1
+@2 = private unnamed_addr constant [2 x i8] c"\0A\00", align 1
+@3 = private unnamed_addr constant [3 x i8] c"%s\00", align 1
+@4 = private unnamed_addr constant [2 x i8] c"\0A\00", align 1
+
+define i32 @_P4main5check3u32(i32 %0) {
+_P4main5check3u32:
+  %x = alloca i32, align 4
+  %1 = alloca i32, align 4
+  store i32 %0, ptr %x, align 4
+  br label %bb0
+
+bb0:                                              ; preds = %_P4main5check3u32
+  %2 = load i32, ptr %x, align 4
+  %3 = icmp eq i32 %2, 1
+  %4 = zext i1 %3 to i32
+  store i32 %4, ptr %1, align 4
+  %5 = load i32, ptr %1, align 4
+  switch i32 %5, label %bb2 [
+    i32 0, label %bb1
+  ]
+
+bb1:                                              ; preds = %bb0
+  call void @pal.panic(ptr @0)
+  unreachable
+
+bb2:                                              ; preds = %bb0
+  %6 = load i32, ptr %x, align 4
+  ret i32 %6
+}
+
+define i32 @main() {
+main:
+  %0 = alloca i32, align 4
+  %1 = alloca i32, align 4
+  br label %bb0
+
+bb0:                                              ; preds = %main
+  %2 = call i32 @_P4main5check3u32(i32 1)
+  store i32 %2, ptr %0, align 4
+  %3 = load i32, ptr %0, align 4
+  %4 = call i32 @pal_print_int(i32 %3)
+  %5 = call i32 @pal_print_cstr(ptr @2)
+  %6 = call i32 @_P4main5check3u32(i32 2)
+  store i32 %6, ptr %1, align 4
+  %7 = load i32, ptr %1, align 4
+  %8 = call i32 @pal_print_int(i32 %7)
+  %9 = call i32 @pal_print_cstr(ptr @4)
+  ret i32 0
+}
+
+; Function Attrs: noreturn
+define internal void @pal.panic(ptr %0) #0 {
+entry:
+  call void @abort()
+  unreachable
+}
+
+define private i32 @pal_print_int(i32 %0) {
+entry:
+  %1 = call i32 (i32, ptr, ...) @dprintf(i32 1, ptr @1, i32 %0)
+  ret i32 %1
+}
+
+declare i32 @dprintf(i32, ptr, ...)
+
+define private i32 @pal_print_cstr(ptr %0) {
+entry:
+  %1 = call i32 (i32, ptr, ...) @dprintf(i32 1, ptr @3, ptr %0)
+  ret i32 %1
+}
+
+declare void @abort()
+
+attributes #0 = { noreturn }
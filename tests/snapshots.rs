@@ -0,0 +1,66 @@
+//! Compiles every program under `tests/cases` and compares what the compiler made of it against
+//! the snapshots checked in next to it: `.ast` for the AST as `--dump-ast` prints it, and `.ll`
+//! for the LLVM IR. Each program is also built into an executable and run, and what it printed to
+//! stdout and the code it exited with are compared against `.expected`. A missing snapshot fails
+//! like one that differs. Run with `PAL_BLESS=1` to write missing snapshots and to accept changes
+//! to the output.
+
+use std::path::Path;
+
+use lang::{
+    backend::{CodegenOptions, generate_llvm_ir},
+    snapshot::{Case, assert_snapshot, case_paths, load_case, normalize_ir, run_case},
+};
+
+/// The target IR is generated for, so that every machine generates the same IR.
+const TARGET: &str = "x86_64-unknown-linux-gnu";
+
+/// Runs a check on every case, failing with every snapshot that differs rather than the first.
+fn check_cases(check: impl Fn(&Case) -> anyhow::Result<()>) {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/cases");
+    let failures: Vec<String> = case_paths(&dir)
+        .unwrap()
+        .iter()
+        .filter_map(|path| {
+            load_case(path)
+                .map_err(anyhow::Error::from)
+                .and_then(|case| check(&case))
+                .err()
+        })
+        .map(|error| error.to_string())
+        .collect();
+
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
+
+#[test]
+fn ast_snapshots() {
+    check_cases(|case| {
+        assert_snapshot(&case.snapshot_path("ast"), &case.module.to_string())?;
+        Ok(())
+    });
+}
+
+#[test]
+fn llvm_ir_snapshots() {
+    check_cases(|case| {
+        let opts = CodegenOptions {
+            target: Some(TARGET.to_string()),
+            ..CodegenOptions::default()
+        };
+        let ir = generate_llvm_ir(&case.module, &opts)?;
+
+        assert_snapshot(&case.snapshot_path("ll"), &normalize_ir(&ir))?;
+        Ok(())
+    });
+}
+
+#[test]
+fn run_snapshots() {
+    check_cases(|case| {
+        let run = run_case(case, Path::new(env!("CARGO_TARGET_TMPDIR")))?;
+
+        assert_snapshot(&case.snapshot_path("expected"), &run.to_string())?;
+        Ok(())
+    });
+}